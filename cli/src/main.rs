@@ -0,0 +1,318 @@
+extern crate rat_in_a_tube;
+
+use rat_in_a_tube::*;
+use std::process::ExitCode;
+
+const USAGE: &str = "Usage: riat [options] <file.hsc>...
+
+Compile Halo: Combat Evolved scripts, printing any diagnostics.
+
+Options:
+  -t, --target <target>      Target engine (default: mcc-cea)
+                             mcc-cea, gbx-retail, gbx-demo, gbx-demo-mac, gbx-custom, xbox, xbox-pal, xbox-jp
+  -e, --encoding <encoding>  Encoding of the script files and string data (default: windows-1252)
+                             windows-1252, utf-8
+      --decode-errors <policy>
+                             What to do with bytes that cannot be decoded as UTF-8 (default: replace)
+                             replace  replace them with U+FFFD and warn
+                             skip     leave them out and warn
+                             error    fail to read the file
+  -f, --format <format>      What to output once compiled (default: nothing)
+                             json     compile report with diagnostics, statistics, and source map
+                             syntax   scenario script syntax data, with the string data in <output>.strings
+                             listing  scripts, globals, and nodes in a human readable table
+                             source   decompiled HSC source
+                             dot      Graphviz DOT graph of which scripts each script and global calls
+                             markdown Markdown documentation of each script, its parameters, and the globals
+  -o, --output <path>        File to write the output to instead of standard output (required for syntax)
+  -d, --definitions <path>   JSON file with additional engine functions and globals, such as ones added by a mod, using the
+                             same schema as the built-in definitions (can be repeated)
+  -s, --simulate <name=value>
+                             Assume an engine global or function always has the value, removing branches that are never
+                             taken, such as game_difficulty_get=impossible (for analysis only; can be repeated). Only
+                             booleans, numbers, difficulties, and teams can be simulated, not strings such as a map name
+  -c, --who-calls <name>     Print where each call to the engine function or script is, and which script or global it is in
+                             (can be repeated)
+  -w, --who-writes <name>    Print where each set of the global is, and which script or global it is in (can be repeated)
+      --dedicated-server     Warn about calls to functions that do nothing when the scripts are run by a dedicated server
+  -h, --help                 Show this message";
+
+#[derive(Copy, Clone, PartialEq)]
+enum OutputFormat {
+    Json,
+    Syntax,
+    Listing,
+    Source,
+    Dot,
+    Markdown
+}
+
+#[derive(Copy, Clone, PartialEq)]
+enum Query {
+    Calls,
+    Writes
+}
+
+struct Options {
+    target: CompileTarget,
+    encoding: CompileEncoding,
+    decode_error_policy: DecodeErrorPolicy,
+    format: Option<OutputFormat>,
+    output: Option<String>,
+    definitions: Vec<String>,
+    simulated_values: Vec<(String, String)>,
+    queries: Vec<(Query, String)>,
+    dedicated_server: bool,
+    files: Vec<String>
+}
+
+fn parse_target(target: &str) -> Option<CompileTarget> {
+    Some(match target {
+        "mcc-cea" => CompileTarget::HaloCEA,
+        "gbx-retail" => CompileTarget::HaloCEGBX,
+        "gbx-demo" => CompileTarget::HaloCEGBXDemo,
+        "gbx-demo-mac" => CompileTarget::HaloCEGBXDemoMac,
+        "gbx-custom" => CompileTarget::HaloCustomEdition,
+        "xbox" => CompileTarget::HaloCEXboxNTSC,
+        "xbox-pal" => CompileTarget::HaloCEXboxPAL,
+        "xbox-jp" => CompileTarget::HaloCEXboxJP,
+        _ => return None
+    })
+}
+
+fn parse_encoding(encoding: &str) -> Option<CompileEncoding> {
+    Some(match encoding {
+        "windows-1252" => CompileEncoding::Windows1252,
+        "utf-8" => CompileEncoding::UTF8,
+        _ => return None
+    })
+}
+
+fn parse_decode_error_policy(policy: &str) -> Option<DecodeErrorPolicy> {
+    Some(match policy {
+        "replace" => DecodeErrorPolicy::Replace,
+        "skip" => DecodeErrorPolicy::Skip,
+        "error" => DecodeErrorPolicy::Error,
+        _ => return None
+    })
+}
+
+fn parse_format(format: &str) -> Option<OutputFormat> {
+    Some(match format {
+        "json" => OutputFormat::Json,
+        "syntax" => OutputFormat::Syntax,
+        "listing" => OutputFormat::Listing,
+        "source" => OutputFormat::Source,
+        "dot" => OutputFormat::Dot,
+        "markdown" => OutputFormat::Markdown,
+        _ => return None
+    })
+}
+
+/// Parse the arguments, returning `None` if help was requested.
+fn parse_arguments(arguments: &[String]) -> Result<Option<Options>, String> {
+    let mut options = Options { target: CompileTarget::HaloCEA, encoding: CompileEncoding::Windows1252, decode_error_policy: DecodeErrorPolicy::default(), format: None, output: None, definitions: Vec::new(), simulated_values: Vec::new(), queries: Vec::new(), dedicated_server: false, files: Vec::new() };
+
+    let mut arguments = arguments.iter();
+    while let Some(argument) = arguments.next() {
+        let mut value = |name: &str| arguments.next().cloned().ok_or_else(|| format!("{name} requires a value"));
+        match argument.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "-t" | "--target" => {
+                let target = value(argument)?;
+                options.target = parse_target(&target).ok_or_else(|| format!("unknown target '{target}'"))?;
+            },
+            "-e" | "--encoding" => {
+                let encoding = value(argument)?;
+                options.encoding = parse_encoding(&encoding).ok_or_else(|| format!("unknown encoding '{encoding}'"))?;
+            },
+            "--decode-errors" => {
+                let policy = value(argument)?;
+                options.decode_error_policy = parse_decode_error_policy(&policy).ok_or_else(|| format!("unknown decode error policy '{policy}'"))?;
+            },
+            "-f" | "--format" => {
+                let format = value(argument)?;
+                options.format = Some(parse_format(&format).ok_or_else(|| format!("unknown format '{format}'"))?);
+            },
+            "-o" | "--output" => options.output = Some(value(argument)?),
+            "-d" | "--definitions" => options.definitions.push(value(argument)?),
+            "-s" | "--simulate" => {
+                let simulated = value(argument)?;
+                let (name, value) = simulated.split_once('=').ok_or_else(|| format!("expected <name>=<value>, got '{simulated}'"))?;
+                options.simulated_values.push((name.to_owned(), value.to_owned()));
+            },
+            "-c" | "--who-calls" => options.queries.push((Query::Calls, value(argument)?)),
+            "-w" | "--who-writes" => options.queries.push((Query::Writes, value(argument)?)),
+            "--dedicated-server" => options.dedicated_server = true,
+            n if n.starts_with('-') => return Err(format!("unknown option '{n}'")),
+            n => options.files.push(n.to_owned())
+        }
+    }
+
+    if options.files.is_empty() {
+        return Err("no script files given".to_owned())
+    }
+    if options.format == Some(OutputFormat::Syntax) && options.output.is_none() {
+        return Err("the syntax format requires an output path".to_owned())
+    }
+    Ok(Some(options))
+}
+
+fn describe_node_type(node_type: NodeType) -> &'static str {
+    match node_type {
+        NodeType::Primitive(PrimitiveType::Static) => "static",
+        NodeType::Primitive(PrimitiveType::Local) => "local",
+        NodeType::Primitive(PrimitiveType::Global) => "global",
+        NodeType::FunctionCall(true) => "call",
+        NodeType::FunctionCall(false) => "script call"
+    }
+}
+
+/// List the scripts, globals, and nodes, one per line.
+fn listing(script_data: &CompiledScriptData) -> String {
+    let mut listing = String::new();
+    for (i, s) in script_data.get_scripts().iter().enumerate() {
+        listing += &format!("script {i} {} {} {} -> node {}\n", s.get_type().as_str(), s.get_value_type().as_str().replace(' ', "_"), s.get_name(), s.get_first_node_index());
+    }
+    for (i, g) in script_data.get_globals().iter().enumerate() {
+        listing += &format!("global {i} {} {} -> node {}\n", g.get_value_type().as_str().replace(' ', "_"), g.get_name(), g.get_first_node_index());
+    }
+
+    let node_base = script_data.get_node_base();
+    for (i, n) in script_data.get_nodes().iter().enumerate() {
+        let data = match n.get_data() {
+            Some(NodeData::Boolean(b)) => b.to_string(),
+            Some(NodeData::Short(v)) => v.to_string(),
+            Some(NodeData::Long(v)) => v.to_string(),
+            Some(NodeData::Real(v)) => v.to_string(),
+            Some(NodeData::NodeOffset(o)) => format!("-> {o}"),
+            None => "-".to_owned()
+        };
+        let index = n.get_index().map(|i| i.to_string()).unwrap_or_else(|| "-".to_owned());
+        let next = n.get_next_node_index().map(|i| i.to_string()).unwrap_or_else(|| "-".to_owned());
+        let string = n.get_string_data().map(|s| format!("{s:?}")).unwrap_or_default();
+        let line = format!("{:>5} {:<11} {:<20} index {:<5} data {:<12} next {:<5} {string}", node_base + i, describe_node_type(n.get_type()), n.get_value_type().as_str().replace(' ', "_"), index, data, next);
+        listing += line.trim_end();
+        listing.push('\n');
+    }
+    listing
+}
+
+fn write_output(output: &Option<String>, data: &[u8]) -> Result<(), String> {
+    match output {
+        Some(path) => std::fs::write(path, data).map_err(|e| format!("failed to write {path}: {e}")),
+        None => {
+            use std::io::Write;
+            std::io::stdout().write_all(data).map_err(|e| format!("failed to write output: {e}"))
+        }
+    }
+}
+
+/// Describe where each node is and which script or global it is in, one per line.
+fn describe_locations(script_data: &CompiledScriptData, nodes: &[usize]) -> String {
+    let mut locations = String::new();
+    for entry in nodes.iter().filter_map(|n| script_data.get_source_map_entry(*n)) {
+        let owner = match entry.owner_type {
+            SymbolType::Script => script_data.get_scripts()[entry.owner_index].get_name(),
+            SymbolType::Global => script_data.get_globals()[entry.owner_index].get_name()
+        };
+        let file = script_data.get_files()[entry.file].to_string_lossy();
+        locations += &format!("{file}:{}:{}: {} {owner}\n", entry.line, entry.column, entry.owner_type.as_str());
+    }
+    locations
+}
+
+/// Compile the files, printing diagnostics and writing the output. Return whether compiling succeeded.
+fn run(options: Options) -> Result<bool, String> {
+    let mut compiler = Compiler::new(options.target, options.encoding);
+    compiler.set_error_recovery(true);
+    compiler.set_decode_error_policy(options.decode_error_policy);
+    compiler.set_dedicated_server(options.dedicated_server);
+    for path in &options.definitions {
+        let json = std::fs::read_to_string(path).map_err(|e| format!("failed to read {path}: {e}"))?;
+        compiler.add_definitions(&json).map_err(|e| format!("failed to load definitions from {path}: {e}"))?;
+    }
+    for (name, value) in &options.simulated_values {
+        compiler.set_simulated_value(name, Some(value))?;
+    }
+
+    for file in &options.files {
+        let script = std::fs::read(file).map_err(|e| format!("failed to read {file}: {e}"))?;
+
+        // Errors that could be recovered from are included in the report, but anything else stops compiling
+        if let Err(e) = compiler.read_script_data(file, &script) {
+            if !compiler.get_errors().iter().any(|r| r.get_position() == e.get_position() && r.get_message() == e.get_message()) {
+                eprintln!("{e}");
+                return Ok(false)
+            }
+        }
+    }
+
+    let report = compiler.compile_with_report();
+    for d in report.get_diagnostics() {
+        eprintln!("{d}");
+    }
+
+    if options.format == Some(OutputFormat::Json) {
+        write_output(&options.output, report.to_json().as_bytes())?;
+    }
+
+    let script_data = match report.get_script_data() {
+        Some(n) => n,
+        None => return Ok(false)
+    };
+
+    for (query, name) in &options.queries {
+        let nodes = match query {
+            Query::Calls => script_data.who_calls(name),
+            Query::Writes => script_data.who_writes(name)
+        };
+        print!("{}", describe_locations(script_data, &nodes));
+    }
+
+    match options.format {
+        Some(OutputFormat::Syntax) => {
+            let syntax_data = match script_data.to_syntax_data(options.target) {
+                Ok(n) => n,
+                Err(e) => {
+                    eprintln!("{e}");
+                    return Ok(false)
+                }
+            };
+            let output = options.output.unwrap();
+            write_output(&Some(output.clone()), &syntax_data.syntax_data)?;
+            write_output(&Some(format!("{output}.strings")), &syntax_data.string_data)?;
+        },
+        Some(OutputFormat::Listing) => write_output(&options.output, listing(script_data).as_bytes())?,
+        Some(OutputFormat::Source) => write_output(&options.output, script_data.to_source().as_bytes())?,
+        Some(OutputFormat::Dot) => write_output(&options.output, script_data.call_graph_to_dot().as_bytes())?,
+        Some(OutputFormat::Markdown) => write_output(&options.output, script_data.to_markdown().as_bytes())?,
+        Some(OutputFormat::Json) | None => ()
+    }
+
+    Ok(true)
+}
+
+fn main() -> ExitCode {
+    let arguments: Vec<String> = std::env::args().skip(1).collect();
+    let options = match parse_arguments(&arguments) {
+        Ok(Some(n)) => n,
+        Ok(None) => {
+            println!("{USAGE}");
+            return ExitCode::SUCCESS
+        },
+        Err(e) => {
+            eprintln!("riat: {e}\n\n{USAGE}");
+            return ExitCode::from(2)
+        }
+    };
+
+    match run(options) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(e) => {
+            eprintln!("riat: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
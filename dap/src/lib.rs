@@ -0,0 +1,250 @@
+extern crate rat_in_a_tube;
+use rat_in_a_tube::*;
+
+#[cfg(test)]
+mod test; // test module for unit testing
+
+/// Connection to a running engine which executes the compiled scripts.
+///
+/// Implementations of this (e.g. Chimera, an MCC mod, or an emulator) only deal with node and script indices. Mapping these to and
+/// from source positions is done by [`DebugSession`].
+pub trait DebugBackend {
+    /// Error type returned by the backend.
+    type Error;
+
+    /// Replace all breakpoints with the given node indices.
+    fn set_breakpoints(&mut self, nodes: &[usize]) -> Result<(), Self::Error>;
+
+    /// Get the call stack of the current thread, innermost frame first.
+    fn get_stack_frames(&mut self) -> Result<Vec<RuntimeFrame>, Self::Error>;
+
+    /// Resume execution until the given node index is evaluated.
+    fn run_to_node(&mut self, node: usize) -> Result<(), Self::Error>;
+
+    /// Resume execution.
+    fn resume(&mut self) -> Result<(), Self::Error>;
+
+    /// Pause execution.
+    fn pause(&mut self) -> Result<(), Self::Error>;
+}
+
+/// Stack frame as reported by a [`DebugBackend`].
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RuntimeFrame {
+    /// Index of the script being executed.
+    ///
+    /// This corresponds to [`CompiledScriptData::get_scripts`].
+    pub script: usize,
+
+    /// Index of the node being evaluated.
+    ///
+    /// This corresponds to [`CompiledScriptData::get_nodes`].
+    pub node: usize
+}
+
+/// Breakpoint as requested by the client.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SourceBreakpoint {
+    /// Line of the breakpoint, starting at 1.
+    pub line: usize,
+
+    /// Column of the breakpoint, starting at 1, if any.
+    pub column: Option<usize>
+}
+
+/// Breakpoint after being resolved to a node.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Breakpoint {
+    /// Index of the node the breakpoint was placed on, or `None` if no node could be found at or after the requested position.
+    pub node: Option<usize>,
+
+    /// Line of the breakpoint, starting at 1.
+    ///
+    /// If the breakpoint was placed, this is the line of the node.
+    pub line: usize,
+
+    /// Column of the breakpoint, starting at 1, if any.
+    ///
+    /// If the breakpoint was placed, this is the column of the node.
+    pub column: Option<usize>
+}
+
+impl Breakpoint {
+    /// Get whether or not the breakpoint was placed on a node.
+    pub fn is_verified(&self) -> bool {
+        self.node.is_some()
+    }
+}
+
+/// Stack frame after being mapped to a source position.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StackFrame {
+    /// Name of the script being executed.
+    pub name: String,
+
+    /// Index of the node being evaluated.
+    pub node: usize,
+
+    /// File index of the node, starting at 0.
+    ///
+    /// This corresponds to [`CompiledScriptData::get_files`].
+    pub file: usize,
+
+    /// Line of the node, starting at 1.
+    pub line: usize,
+
+    /// Column of the node, starting at 1.
+    pub column: usize
+}
+
+/// Debug session which maps requests from a client to a [`DebugBackend`].
+pub struct DebugSession<B: DebugBackend> {
+    script_data: CompiledScriptData,
+    backend: B,
+    breakpoints: Vec<Vec<usize>>
+}
+
+impl<B: DebugBackend> DebugSession<B> {
+    /// Instantiate a new debug session with the given compiled script data and backend.
+    pub fn new(script_data: CompiledScriptData, backend: B) -> DebugSession<B> {
+        let file_count = script_data.get_files().len();
+        DebugSession {
+            script_data,
+            backend,
+            breakpoints: vec![Vec::new(); file_count]
+        }
+    }
+
+    /// Get the compiled script data.
+    pub fn get_script_data(&self) -> &CompiledScriptData {
+        &self.script_data
+    }
+
+    /// Get the backend.
+    pub fn get_backend(&self) -> &B {
+        &self.backend
+    }
+
+    /// Get the backend as mutable.
+    pub fn get_backend_mut(&mut self) -> &mut B {
+        &mut self.backend
+    }
+
+    /// Get the index of a file from its name, if it was compiled.
+    pub fn get_file_index(&self, file: &str) -> Option<usize> {
+        self.script_data.get_files().iter().position(|f| f.to_str() == Ok(file))
+    }
+
+    /// Replace all breakpoints in the given file, returning the resolved breakpoints in the same order as requested.
+    ///
+    /// Each breakpoint is moved to the first position a node is evaluated at or after the requested position. Breakpoints that could not
+    /// be placed are returned unverified.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the backend failed to set the breakpoints.
+    pub fn set_breakpoints(&mut self, file: usize, requested: &[SourceBreakpoint]) -> Result<Vec<Breakpoint>, B::Error> {
+        let positions = self.script_data.valid_breakpoint_positions(file);
+
+        let mut resolved = Vec::with_capacity(requested.len());
+        let mut nodes = Vec::new();
+        for r in requested {
+            let start = (r.line, r.column.unwrap_or(1));
+            let position = positions.iter().find(|p| **p >= start);
+            let node = position.and_then(|&(line, column)| self.find_node(file, line, column));
+
+            match (position, node) {
+                (Some(&(line, column)), Some(node)) => {
+                    resolved.push(Breakpoint { node: Some(node), line, column: Some(column) });
+                    nodes.push(node);
+                },
+                _ => resolved.push(Breakpoint { node: None, line: r.line, column: r.column })
+            }
+        }
+
+        if let Some(b) = self.breakpoints.get_mut(file) {
+            *b = nodes;
+        }
+
+        let mut all_nodes: Vec<usize> = self.breakpoints.iter().flatten().copied().collect();
+        all_nodes.sort_unstable();
+        all_nodes.dedup();
+        self.backend.set_breakpoints(&all_nodes)?;
+
+        Ok(resolved)
+    }
+
+    /// Get the call stack mapped to source positions, innermost frame first.
+    ///
+    /// Frames which refer to scripts or nodes that do not exist are omitted.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the backend failed to get the call stack.
+    pub fn get_stack_trace(&mut self) -> Result<Vec<StackFrame>, B::Error> {
+        let scripts = self.script_data.get_scripts();
+
+        Ok(self.backend.get_stack_frames()?.into_iter().filter_map(|frame| {
+            let script = scripts.get(frame.script)?;
+            let node = self.script_data.get_node(frame.node)?;
+            Some(StackFrame {
+                name: script.get_name().to_owned(),
+                node: frame.node,
+                file: node.get_file(),
+                line: node.get_line(),
+                column: node.get_column()
+            })
+        }).collect())
+    }
+
+    /// Step to the next node evaluated in the innermost frame's script.
+    ///
+    /// Returns `false` without resuming if there is no frame or the node is the last one evaluated in the script.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the backend failed to get the call stack or resume execution.
+    pub fn step(&mut self) -> Result<bool, B::Error> {
+        let frame = match self.backend.get_stack_frames()?.first() {
+            Some(n) => *n,
+            None => return Ok(false)
+        };
+
+        let order = match self.script_data.get_evaluation_order(frame.script) {
+            Some(n) => n,
+            None => return Ok(false)
+        };
+
+        match order.iter().position(|n| *n == frame.node).and_then(|p| order.get(p + 1)) {
+            Some(&next) => {
+                self.backend.run_to_node(next)?;
+                Ok(true)
+            },
+            None => Ok(false)
+        }
+    }
+
+    /// Resume execution.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the backend failed to resume.
+    pub fn resume(&mut self) -> Result<(), B::Error> {
+        self.backend.resume()
+    }
+
+    /// Pause execution.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the backend failed to pause.
+    pub fn pause(&mut self) -> Result<(), B::Error> {
+        self.backend.pause()
+    }
+
+    fn find_node(&self, file: usize, line: usize, column: usize) -> Option<usize> {
+        self.script_data.get_nodes().iter().position(|n| {
+            n.get_file() == file && n.get_line() == line && n.get_column() == column && n.get_value_type() != ValueType::FunctionName
+        }).map(|n| n + self.script_data.get_node_base())
+    }
+}
@@ -0,0 +1,94 @@
+use super::*;
+
+const TEST_SCRIPT_PARAMETERS_HSC : &[u8] = include_bytes!("../../src/test/script/test_script_parameters.hsc");
+
+#[derive(Default)]
+struct TestBackend {
+    breakpoints: Vec<usize>,
+    frames: Vec<RuntimeFrame>,
+    run_to: Option<usize>
+}
+
+impl DebugBackend for TestBackend {
+    type Error = ();
+
+    fn set_breakpoints(&mut self, nodes: &[usize]) -> Result<(), ()> {
+        self.breakpoints = nodes.to_owned();
+        Ok(())
+    }
+
+    fn get_stack_frames(&mut self) -> Result<Vec<RuntimeFrame>, ()> {
+        Ok(self.frames.clone())
+    }
+
+    fn run_to_node(&mut self, node: usize) -> Result<(), ()> {
+        self.run_to = Some(node);
+        Ok(())
+    }
+
+    fn resume(&mut self) -> Result<(), ()> {
+        Ok(())
+    }
+
+    fn pause(&mut self) -> Result<(), ()> {
+        Ok(())
+    }
+}
+
+fn new_session() -> DebugSession<TestBackend> {
+    let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
+    compiler.read_script_data("test_script_parameters.hsc", TEST_SCRIPT_PARAMETERS_HSC).unwrap();
+    DebugSession::new(compiler.compile_script_data().unwrap(), TestBackend::default())
+}
+
+#[test]
+fn test_set_breakpoints() {
+    let mut session = new_session();
+    let file = session.get_file_index("test_script_parameters.hsc").unwrap();
+
+    // Line 9 has no evaluated nodes, so it snaps to line 10, and line 12 is past the end of the file
+    let breakpoints = session.set_breakpoints(file, &[
+        SourceBreakpoint { line: 9, column: None },
+        SourceBreakpoint { line: 10, column: Some(9) },
+        SourceBreakpoint { line: 12, column: None }
+    ]).unwrap();
+
+    assert_eq!((10, Some(5)), (breakpoints[0].line, breakpoints[0].column));
+    assert_eq!((10, Some(11)), (breakpoints[1].line, breakpoints[1].column));
+    assert!(!breakpoints[2].is_verified());
+
+    for b in &breakpoints[..2] {
+        let node = session.get_script_data().get_node(b.node.unwrap()).unwrap();
+        assert_eq!((b.line, b.column), (node.get_line(), Some(node.get_column())));
+    }
+
+    let mut expected = vec![breakpoints[0].node.unwrap(), breakpoints[1].node.unwrap()];
+    expected.sort_unstable();
+    assert_eq!(expected, session.get_backend().breakpoints);
+
+    // Clearing the file's breakpoints clears them from the backend
+    session.set_breakpoints(file, &[]).unwrap();
+    assert!(session.get_backend().breakpoints.is_empty());
+}
+
+#[test]
+fn test_stack_trace_and_step() {
+    let mut session = new_session();
+    let order = session.get_script_data().get_evaluation_order(0).unwrap();
+    session.get_backend_mut().frames = vec![RuntimeFrame { script: 0, node: order[0] }, RuntimeFrame { script: 5, node: 0 }];
+
+    // The nonexistent script is omitted
+    let trace = session.get_stack_trace().unwrap();
+    assert_eq!(1, trace.len());
+    assert_eq!("avg", trace[0].name);
+    assert_eq!((10, 5), (trace[0].line, trace[0].column));
+
+    assert!(session.step().unwrap());
+    assert_eq!(Some(order[1]), session.get_backend().run_to);
+
+    // Nothing comes after the last node
+    session.get_backend_mut().frames = vec![RuntimeFrame { script: 0, node: *order.last().unwrap() }];
+    session.get_backend_mut().run_to = None;
+    assert!(!session.step().unwrap());
+    assert_eq!(None, session.get_backend().run_to);
+}
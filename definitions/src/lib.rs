@@ -54,6 +54,15 @@ struct Function {
     #[serde(default = "default_value")]
     passthrough_last: bool,
 
+    #[serde(default = "default_value")]
+    console_only: bool,
+
+    #[serde(default = "default_value")]
+    expensive: bool,
+
+    #[serde(default = "default_value")]
+    client_only: bool,
+
     engines: BTreeMap<String, Value>
 }
 
@@ -91,12 +100,17 @@ pub fn generate_definitions(_: TokenStream) -> TokenStream {
         format!("ValueType::{}", s.into_iter().collect::<String>())
     }
 
+    // Engines with a base engine have the same availability as it, except where a function or global lists the engine itself
+    let bases: BTreeMap<String, String> = definitions.engines.iter().filter_map(|e| {
+        Some((e.get("identifier")?.as_str()?.to_owned(), e.get("base")?.as_str()?.to_owned()))
+    }).collect();
+
     // Make a Availability struct
-    fn generate_availability(t: &BTreeMap<String, Value>) -> String {
+    fn generate_availability(t: &BTreeMap<String, Value>, bases: &BTreeMap<String, String>) -> String {
         let mut s = String::new();
 
         let mut modify_thing = |from: &str, to: &str| {
-            if let Some(n) = t.get(from) {
+            if let Some(n) = t.get(from).or_else(|| bases.get(from).and_then(|base| t.get(base))) {
                 match n {
                     Value::Null => s += &format!("{to}: Some(u16::MAX),"),
                     Value::Number(n) => {
@@ -120,6 +134,9 @@ pub fn generate_definitions(_: TokenStream) -> TokenStream {
         modify_thing("gbx-custom", "gbx_custom");
         modify_thing("gbx-retail", "gbx_retail");
         modify_thing("gbx-demo", "gbx_demo");
+        modify_thing("gbx-demo-mac", "gbx_demo_mac");
+        modify_thing("xbox-pal", "xbox_pal");
+        modify_thing("xbox-jp", "xbox_jp");
 
         format!("EngineAvailability {{ {s} }}")
     }
@@ -129,9 +146,9 @@ pub fn generate_definitions(_: TokenStream) -> TokenStream {
     for g in &definitions.globals {
         let global_name = &g.name;
         let global_type = snake_to_pascal(&g.r#type);
-        let global_availability = generate_availability(&g.engines);
+        let global_availability = generate_availability(&g.engines, &bases);
 
-        globals_list += &format!("EngineGlobal {{ name: \"{global_name}\", value_type: {global_type}, availability: {global_availability} }},");
+        globals_list += &format!("EngineGlobal {{ name: Cow::Borrowed(\"{global_name}\"), value_type: {global_type}, availability: {global_availability} }},");
     }
 
     // Generate functions
@@ -139,10 +156,13 @@ pub fn generate_definitions(_: TokenStream) -> TokenStream {
     for f in &definitions.functions {
         let function_name = &f.name;
         let function_type = snake_to_pascal(&f.r#type);
-        let function_availability = generate_availability(&f.engines);
+        let function_availability = generate_availability(&f.engines, &bases);
         let function_number_passthrough = &f.number_passthrough;
         let function_passthrough_last = &f.passthrough_last;
         let function_inequality = &f.inequality;
+        let function_console_only = &f.console_only;
+        let function_expensive = &f.expensive;
+        let function_client_only = &f.client_only;
 
         let mut function_parameters = String::new();
         for p in &f.parameters {
@@ -153,8 +173,18 @@ pub fn generate_definitions(_: TokenStream) -> TokenStream {
             function_parameters += &format!("EngineFunctionParameter {{ value_type: {parameter_type}, many: {parameter_many}, allow_uppercase: {parameter_allow_uppercase}, optional: {parameter_optional} }},")
         }
 
-        functions_list += &format!("EngineFunction {{ name: \"{function_name}\", return_type: {function_type}, availability: {function_availability}, number_passthrough: {function_number_passthrough}, inequality: {function_inequality}, passthrough_last: {function_passthrough_last}, parameters: &[{function_parameters}] }},");
+        functions_list += &format!("EngineFunction {{ name: Cow::Borrowed(\"{function_name}\"), return_type: {function_type}, availability: {function_availability}, number_passthrough: {function_number_passthrough}, inequality: {function_inequality}, passthrough_last: {function_passthrough_last}, console_only: {function_console_only}, expensive: {function_expensive}, client_only: {function_client_only}, parameters: Cow::Borrowed(&[{function_parameters}]) }},");
+    }
+
+    // Generate engines
+    let mut engines_list = String::new();
+    for e in &definitions.engines {
+        let engine_identifier = e.get("identifier").and_then(Value::as_str).unwrap();
+        match bases.get(engine_identifier) {
+            Some(base) => engines_list += &format!("(\"{engine_identifier}\", Some(\"{base}\")),"),
+            None => engines_list += &format!("(\"{engine_identifier}\", None),")
+        }
     }
 
-    format!("pub(crate) const ALL_GLOBALS: [EngineGlobal; {}] = [{}]; pub(crate) const ALL_FUNCTIONS: [EngineFunction; {}] = [{}];", definitions.globals.len(), globals_list, definitions.functions.len(), functions_list).parse().unwrap()
+    format!("pub(crate) static ALL_ENGINES: [(&str, Option<&str>); {}] = [{}]; pub(crate) static ALL_GLOBALS: [EngineGlobal; {}] = [{}]; pub(crate) static ALL_FUNCTIONS: [EngineFunction; {}] = [{}];", definitions.engines.len(), engines_list, definitions.globals.len(), globals_list, definitions.functions.len(), functions_list).parse().unwrap()
 }
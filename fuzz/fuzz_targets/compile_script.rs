@@ -0,0 +1,13 @@
+#![no_main]
+
+extern crate libfuzzer_sys;
+extern crate rat_in_a_tube;
+
+use libfuzzer_sys::fuzz_target;
+use rat_in_a_tube::fuzz;
+
+fuzz_target!(|data: &[u8]| {
+    if let Err(e) = fuzz::compile_script_bytes(data) {
+        assert!(!fuzz::is_internal_error(&e), "{}", e);
+    }
+});
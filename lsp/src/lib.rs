@@ -0,0 +1,425 @@
+extern crate rat_in_a_tube;
+use rat_in_a_tube::*;
+use std::collections::BTreeMap;
+
+extern crate serde_json;
+
+#[cfg(test)]
+mod test; // test module for unit testing
+
+pub mod stdio;
+
+/// Position in a document as sent by the client.
+///
+/// Unlike positions reported by the compiler, these start at 0, and the character is counted in UTF-16 code units.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Position {
+    /// Line, starting at 0.
+    pub line: usize,
+
+    /// Character offset in the line in UTF-16 code units, starting at 0.
+    pub character: usize
+}
+
+/// Range in a document as sent by the client.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Range {
+    /// Start of the range.
+    pub start: Position,
+
+    /// End of the range, exclusive.
+    pub end: Position
+}
+
+/// Change to a document as sent by the client.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ContentChange {
+    /// Range being replaced, or `None` to replace the whole document.
+    pub range: Option<Range>,
+
+    /// Text to replace the range with.
+    pub text: String
+}
+
+/// Severity of a [`Diagnostic`].
+///
+/// The numeric values are the ones used by the protocol.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[repr(u8)]
+pub enum DiagnosticSeverity {
+    /// The code was invalid
+    Error = 1,
+
+    /// Potentially bad, but not technically invalid code
+    Warning = 2
+}
+
+/// Error or warning in a document.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    /// Range of what the diagnostic refers to.
+    pub range: Range,
+
+    /// Severity of the diagnostic.
+    pub severity: DiagnosticSeverity,
+
+    /// Code identifying what the diagnostic is about.
+    pub code: CompileErrorCode,
+
+    /// Message of the diagnostic.
+    pub message: String,
+
+    /// Text that the range can be replaced with to fix the diagnostic, if any.
+    pub fix: Option<String>
+}
+
+/// Diagnostics of a document, which are sent to the client in full whenever they change.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PublishDiagnostics {
+    /// URI of the document.
+    pub uri: String,
+
+    /// Version of the document the diagnostics were found in.
+    pub version: i64,
+
+    /// Errors and warnings in the document, sorted by position.
+    pub diagnostics: Vec<Diagnostic>
+}
+
+/// Range in a document.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Location {
+    /// URI of the document.
+    pub uri: String,
+
+    /// Range in the document.
+    pub range: Range
+}
+
+/// Result of [`LanguageServer::hover`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct HoverContents {
+    /// Markdown describing the symbol.
+    pub contents: String,
+
+    /// Range of the token being hovered over.
+    pub range: Range
+}
+
+/// Candidate returned by [`LanguageServer::completions`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct CompletionItem {
+    /// Name being suggested.
+    pub label: String,
+
+    /// Kind of symbol being suggested.
+    pub kind: CompletionKind,
+
+    /// Value type of the symbol, or the return type if it is a function or script, as it is written in scripts.
+    pub detail: String,
+
+    /// Snippet to insert instead of the label, if any.
+    ///
+    /// Engine functions are completed right after the opening parenthesis of the call, so their snippets are the rest of the call with
+    /// a placeholder for each parameter, such as `ai_place ${1:ai})`.
+    pub insert_snippet: Option<String>
+}
+
+/// Replacement of a range of a document.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DocumentEdit {
+    /// Range being replaced.
+    pub range: Range,
+
+    /// Text to replace the range with.
+    pub text: String
+}
+
+/// Result of [`LanguageServer::code_actions`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct DocumentAction {
+    /// Kind of change.
+    pub kind: CodeActionKind,
+
+    /// Title to show to the user.
+    pub title: String,
+
+    /// Edits to the document making the change.
+    pub edits: Vec<DocumentEdit>
+}
+
+/// Document opened by the client.
+struct Document {
+    text: String,
+    version: i64,
+
+    // Tokenized when the document is opened or changed, so other documents don't need to be tokenized again when one changes
+    parsed: ParsedScriptData
+}
+
+/// Language server which keeps track of the documents opened by the client and answers requests about them.
+///
+/// Documents are checked together, so scripts and globals defined in one document can be used in the others. This does not handle
+/// the transport, so the client's messages must be decoded and passed to the corresponding functions, such as by [`stdio::run`].
+pub struct LanguageServer {
+    target: CompileTarget,
+    documents: BTreeMap<String, Document>,
+    diagnostics: Option<Vec<PublishDiagnostics>>
+}
+
+impl LanguageServer {
+    /// Instantiate a new language server for the given target without any documents.
+    pub fn new(target: CompileTarget) -> LanguageServer {
+        LanguageServer { target, documents: BTreeMap::new(), diagnostics: None }
+    }
+
+    /// Open a document, replacing it if it is already open.
+    pub fn open_document(&mut self, uri: &str, version: i64, text: &str) {
+        let parsed = self.new_compiler().parse_script_data(uri, text.as_bytes());
+        self.documents.insert(uri.to_owned(), Document { text: text.to_owned(), version, parsed });
+        self.diagnostics = None;
+    }
+
+    /// Apply changes to a document in order.
+    ///
+    /// Only this document is tokenized again. Returns `false` without doing anything if the document is not open.
+    pub fn change_document(&mut self, uri: &str, version: i64, changes: &[ContentChange]) -> bool {
+        let mut compiler = self.new_compiler();
+        let document = match self.documents.get_mut(uri) {
+            Some(n) => n,
+            None => return false
+        };
+
+        for c in changes {
+            match c.range {
+                Some(range) => {
+                    let start = offset_of_position(&document.text, range.start);
+                    let end = offset_of_position(&document.text, range.end).max(start);
+                    document.text.replace_range(start..end, &c.text);
+                },
+                None => document.text = c.text.clone()
+            }
+        }
+        document.version = version;
+        document.parsed = compiler.parse_script_data(uri, document.text.as_bytes());
+        self.diagnostics = None;
+        true
+    }
+
+    /// Close a document.
+    ///
+    /// Returns `false` if the document was not open.
+    pub fn close_document(&mut self, uri: &str) -> bool {
+        self.diagnostics = None;
+        self.documents.remove(uri).is_some()
+    }
+
+    /// Get the text of a document, if it is open.
+    pub fn get_document(&self, uri: &str) -> Option<&str> {
+        self.documents.get(uri).map(|d| d.text.as_str())
+    }
+
+    /// Get the diagnostics of every open document, sorted by URI.
+    ///
+    /// Every open document is returned, even if it has no diagnostics, so diagnostics of fixed documents are cleared. The documents are
+    /// only checked again after they change.
+    pub fn diagnostics(&mut self) -> &[PublishDiagnostics] {
+        if self.diagnostics.is_none() {
+            self.diagnostics = Some(self.check_documents());
+        }
+        self.diagnostics.as_ref().unwrap()
+    }
+
+    /// Get where the script, global, or parameter at the given position of a document is defined.
+    ///
+    /// Returns `None` if the document is not open, if there is no name at the position, or if it is an engine function or global.
+    pub fn definition(&self, uri: &str, position: Position) -> Option<Location> {
+        let document = self.documents.get(uri)?;
+        let location = self.compiler_without(uri).definition(document.text.as_bytes(), to_script_position(&document.text, position))?;
+
+        let uri = location.file.as_deref().unwrap_or(uri);
+        let text = &self.documents.get(uri)?.text;
+        Some(Location {
+            uri: uri.to_owned(),
+            range: to_range(text, (location.line, location.column), location.end)
+        })
+    }
+
+    /// Describe what the name at the given position of a document refers to, such as the signature of an engine function.
+    ///
+    /// Returns `None` if the document is not open or if there is no name at the position.
+    pub fn hover(&self, uri: &str, position: Position) -> Option<HoverContents> {
+        let document = self.documents.get(uri)?;
+        let hover = self.compiler_without(uri).hover(document.text.as_bytes(), to_script_position(&document.text, position))?;
+
+        let contents = match (hover.kind, hover.signature.as_ref()) {
+            (CompletionKind::Function, Some(s)) => format!("```hsc\n{}\n```\nEngine function returning `{}`", s.to_call_string(), s.return_type),
+            (_, Some(s)) => format!("```hsc\n{}\n```\nScript returning `{}`", s.to_call_string(), s.return_type),
+            (CompletionKind::Parameter, None) => format!("```hsc\n({} {})\n```\nParameter", type_name(hover.value_type), hover.name),
+            (_, None) => format!("```hsc\n(global {} {})\n```", type_name(hover.value_type), hover.name)
+        };
+
+        Some(HoverContents { contents, range: to_range(&document.text, (hover.line, hover.column), hover.end) })
+    }
+
+    /// Get what can be written at the given position of a document.
+    ///
+    /// Returns nothing if the document is not open.
+    pub fn completions(&self, uri: &str, position: Position) -> Vec<CompletionItem> {
+        let document = match self.documents.get(uri) {
+            Some(n) => n,
+            None => return Vec::new()
+        };
+        let compiler = self.compiler_without(uri);
+        compiler.completions(document.text.as_bytes(), to_script_position(&document.text, position)).into_iter().map(|c| CompletionItem {
+            insert_snippet: match c.kind {
+                CompletionKind::Function => compiler.function_snippet(&c.label).map(|s| s[1..].to_owned()),
+                _ => None
+            },
+            label: c.label,
+            kind: c.kind,
+            detail: type_name(c.value_type)
+        }).collect()
+    }
+
+    /// Get the changes that can be made to organize a document, such as moving globals before scripts, given the position of the cursor.
+    ///
+    /// Returns nothing if the document is not open.
+    pub fn code_actions(&self, uri: &str, position: Position) -> Vec<DocumentAction> {
+        let document = match self.documents.get(uri) {
+            Some(n) => n,
+            None => return Vec::new()
+        };
+        let text = &document.text;
+        self.new_compiler().code_actions(text.as_bytes(), to_script_position(text, position)).into_iter().map(|a| DocumentAction {
+            kind: a.kind,
+            title: a.title,
+            edits: a.edits.into_iter().map(|e| DocumentEdit { range: to_range(text, e.start, e.end), text: e.text }).collect()
+        }).collect()
+    }
+
+    /// Instantiate a compiler with every open document read except for the given one.
+    fn compiler_without(&self, uri: &str) -> Compiler {
+        let mut compiler = self.new_compiler();
+        for (_, d) in self.documents.iter().filter(|(u, _)| u.as_str() != uri) {
+            let _ = compiler.read_parsed_script_data(&d.parsed);
+        }
+        compiler
+    }
+
+    fn new_compiler(&self) -> Compiler {
+        // Text from the client is already decoded
+        let mut compiler = Compiler::new(self.target, CompileEncoding::UTF8);
+        compiler.set_tolerant(true);
+        compiler
+    }
+
+    fn check_documents(&self) -> Vec<PublishDiagnostics> {
+        let mut compiler = self.new_compiler();
+
+        // Errors that could not be recovered from leave the document out of the results
+        let mut read_errors = BTreeMap::new();
+        for (uri, d) in &self.documents {
+            if let Err(e) = compiler.read_parsed_script_data(&d.parsed) {
+                read_errors.insert(uri.as_str(), e);
+            }
+        }
+        let mut checked: BTreeMap<String, Vec<CompileError>> = compiler.check_all().into_iter().map(|f| (f.file, f.diagnostics)).collect();
+
+        self.documents.iter().map(|(uri, d)| {
+            let errors = checked.remove(uri).or_else(|| read_errors.remove(uri.as_str()).map(|e| vec![e])).unwrap_or_default();
+            PublishDiagnostics {
+                uri: uri.clone(),
+                version: d.version,
+                diagnostics: errors.iter().map(|e| Diagnostic {
+                    range: to_range(&d.text, e.get_position(), e.get_end_position()),
+                    severity: match e.get_error_type() {
+                        CompileErrorType::Error => DiagnosticSeverity::Error,
+                        CompileErrorType::Warning => DiagnosticSeverity::Warning
+                    },
+                    code: e.get_code(),
+                    message: e.get_message().to_owned(),
+                    fix: e.get_fix().map(str::to_owned)
+                }).collect()
+            }
+        }).collect()
+    }
+}
+
+/// Get the name of a value type as it is written in scripts, such as `game_difficulty`.
+fn type_name(value_type: ValueType) -> String {
+    value_type.as_str().replace(' ', "_")
+}
+
+/// Get the byte offsets of the start and end of each line, excluding the line break.
+///
+/// Lines are broken the same way as the compiler breaks them, so `\r\n`, `\r`, and `\n` all end a line.
+fn line_ranges(text: &str) -> Vec<(usize, usize)> {
+    let bytes = text.as_bytes();
+    let mut lines = Vec::new();
+    let mut start = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\r' if bytes.get(i + 1) == Some(&b'\n') => {
+                lines.push((start, i));
+                i += 1;
+                start = i + 1;
+            },
+            b'\r' | b'\n' => {
+                lines.push((start, i));
+                start = i + 1;
+            },
+            _ => ()
+        }
+        i += 1;
+    }
+    lines.push((start, bytes.len()));
+    lines
+}
+
+/// Get the byte offset of a position in the text, clamped to the end of its line or the end of the text.
+fn offset_of_position(text: &str, position: Position) -> usize {
+    let (start, end) = match line_ranges(text).get(position.line) {
+        Some(n) => *n,
+        None => return text.len()
+    };
+
+    let mut units = 0;
+    for (i, c) in text[start..end].char_indices() {
+        if units >= position.character {
+            return start + i
+        }
+        units += c.len_utf16();
+    }
+    end
+}
+
+/// Convert a position from the client to a line and column of the script data as used by the compiler.
+fn to_script_position(text: &str, position: Position) -> (usize, usize) {
+    let offset = offset_of_position(text, position);
+    let start = line_ranges(text).get(position.line).map(|l| l.0).unwrap_or(offset);
+    (position.line + 1, offset - start + 1)
+}
+
+/// Convert a line and column of the script data to a position for the client, clamped to the end of the text.
+fn to_position(text: &str, (line, column): (usize, usize)) -> Position {
+    let lines = line_ranges(text);
+    let (start, end) = match lines.get(line.max(1) - 1) {
+        Some(n) => *n,
+        None => {
+            let last = lines.len() - 1;
+            return Position { line: last, character: text[lines[last].0..].encode_utf16().count() }
+        }
+    };
+
+    let mut offset = (start + column.max(1) - 1).min(end);
+    while !text.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    Position { line: line.max(1) - 1, character: text[start..offset].encode_utf16().count() }
+}
+
+fn to_range(text: &str, start: (usize, usize), end: (usize, usize)) -> Range {
+    Range { start: to_position(text, start), end: to_position(text, end) }
+}
@@ -0,0 +1,70 @@
+extern crate rat_in_a_tube;
+extern crate riat_lsp;
+
+use rat_in_a_tube::*;
+use std::process::ExitCode;
+
+const USAGE: &str = "Usage: riat-lsp [options]
+
+Run a Language Server Protocol server for Halo: Combat Evolved scripts, talking JSON-RPC over standard input and output.
+
+Options:
+  -t, --target <target>      Target engine (default: mcc-cea)
+                             mcc-cea, gbx-retail, gbx-demo, gbx-demo-mac, gbx-custom, xbox, xbox-pal, xbox-jp
+  -h, --help                 Show this message";
+
+fn parse_target(target: &str) -> Option<CompileTarget> {
+    Some(match target {
+        "mcc-cea" => CompileTarget::HaloCEA,
+        "gbx-retail" => CompileTarget::HaloCEGBX,
+        "gbx-demo" => CompileTarget::HaloCEGBXDemo,
+        "gbx-demo-mac" => CompileTarget::HaloCEGBXDemoMac,
+        "gbx-custom" => CompileTarget::HaloCustomEdition,
+        "xbox" => CompileTarget::HaloCEXboxNTSC,
+        "xbox-pal" => CompileTarget::HaloCEXboxPAL,
+        "xbox-jp" => CompileTarget::HaloCEXboxJP,
+        _ => return None
+    })
+}
+
+/// Parse the arguments, returning `None` if help was requested.
+fn parse_arguments(arguments: &[String]) -> Result<Option<CompileTarget>, String> {
+    let mut target = CompileTarget::HaloCEA;
+    let mut arguments = arguments.iter();
+    while let Some(argument) = arguments.next() {
+        match argument.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "-t" | "--target" => {
+                let value = arguments.next().ok_or_else(|| format!("{argument} requires a value"))?;
+                target = parse_target(value).ok_or_else(|| format!("unknown target '{value}'"))?;
+            },
+            n => return Err(format!("unknown option '{n}'"))
+        }
+    }
+    Ok(Some(target))
+}
+
+fn main() -> ExitCode {
+    let arguments: Vec<String> = std::env::args().skip(1).collect();
+    let target = match parse_arguments(&arguments) {
+        Ok(Some(n)) => n,
+        Ok(None) => {
+            println!("{USAGE}");
+            return ExitCode::SUCCESS
+        },
+        Err(e) => {
+            eprintln!("riat-lsp: {e}\n\n{USAGE}");
+            return ExitCode::from(2)
+        }
+    };
+
+    // Standard output is only for messages to the client, so errors go to standard error
+    match riat_lsp::stdio::run(target, std::io::stdin().lock(), std::io::stdout().lock()) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(e) => {
+            eprintln!("riat-lsp: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
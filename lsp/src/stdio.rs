@@ -0,0 +1,302 @@
+//! JSON-RPC transport for the [`LanguageServer`], as used by language clients that start the server and talk to it over its
+//! standard input and output.
+
+use super::*;
+use serde_json::{json, Map, Value};
+use std::io::{BufRead, Write};
+
+/// Error code for a message that is not valid JSON.
+const PARSE_ERROR: i64 = -32700;
+
+/// Error code for a message that is not a valid request.
+const INVALID_REQUEST: i64 = -32600;
+
+/// Error code for a request of a method that the server doesn't support.
+const METHOD_NOT_FOUND: i64 = -32601;
+
+/// Error code for a request with missing or malformed parameters.
+const INVALID_PARAMS: i64 = -32602;
+
+/// Read messages from the client and write the responses and notifications, until the client sends an `exit` notification or closes
+/// the input.
+///
+/// Returns `true` if the client asked the server to shut down before exiting, in which case the process should exit successfully.
+///
+/// # Errors
+///
+/// Errors if reading or writing fails, or if a message does not have a valid header.
+pub fn run<R: BufRead, W: Write>(target: CompileTarget, mut input: R, mut output: W) -> std::io::Result<bool> {
+    let mut connection = Connection { server: LanguageServer::new(target), published: BTreeMap::new(), shutdown: false };
+
+    while let Some(content) = read_message(&mut input)? {
+        let message = match serde_json::from_slice::<Value>(&content) {
+            Ok(n) => n,
+            Err(e) => {
+                write_message(&mut output, &error_response(Value::Null, PARSE_ERROR, &e.to_string()))?;
+                continue
+            }
+        };
+
+        let method = message.get("method").and_then(Value::as_str).unwrap_or_default();
+        if method == "exit" {
+            return Ok(connection.shutdown)
+        }
+
+        for reply in connection.handle(&message) {
+            write_message(&mut output, &reply)?;
+        }
+    }
+
+    Ok(connection.shutdown)
+}
+
+/// Read the content of the next message, or `None` if the input was closed.
+fn read_message<R: BufRead>(input: &mut R) -> std::io::Result<Option<Vec<u8>>> {
+    let invalid = |message: String| std::io::Error::new(std::io::ErrorKind::InvalidData, message);
+
+    // Headers are terminated by an empty line, and only Content-Length is needed
+    let mut length = None;
+    loop {
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return match length {
+                None => Ok(None),
+                Some(_) => Err(invalid("input ended in the middle of a header".to_owned()))
+            }
+        }
+
+        let line = line.trim_end_matches(['\r', '\n']);
+        if line.is_empty() {
+            break
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                length = Some(value.trim().parse::<usize>().map_err(|_| invalid(format!("invalid Content-Length '{}'", value.trim())))?);
+            }
+        }
+    }
+
+    let length = length.ok_or_else(|| invalid("expected a Content-Length header".to_owned()))?;
+    let mut content = vec![0; length];
+    input.read_exact(&mut content)?;
+    Ok(Some(content))
+}
+
+fn write_message<W: Write>(output: &mut W, message: &Value) -> std::io::Result<()> {
+    let content = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{content}", content.len())?;
+    output.flush()
+}
+
+fn error_response(id: Value, code: i64, message: &str) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
+
+fn notification(method: &str, params: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "method": method, "params": params })
+}
+
+struct Connection {
+    server: LanguageServer,
+
+    // Diagnostics last sent for each document, so only ones that changed are sent again
+    published: BTreeMap<String, Vec<Diagnostic>>,
+
+    shutdown: bool
+}
+
+impl Connection {
+    /// Handle a message, returning the response (if it is a request) and any notifications to send.
+    fn handle(&mut self, message: &Value) -> Vec<Value> {
+        let id = message.get("id").cloned();
+        let method = match message.get("method").and_then(Value::as_str) {
+            Some(n) => n,
+
+            // Responses to requests from the server are not expected, as it never sends any
+            None if id.is_some() && (message.get("result").is_some() || message.get("error").is_some()) => return Vec::new(),
+            None => return vec![error_response(id.unwrap_or(Value::Null), INVALID_REQUEST, "expected a method")]
+        };
+        let params = message.get("params").cloned().unwrap_or(Value::Null);
+
+        let id = match id {
+            Some(n) => n,
+            None => {
+                self.notify(method, &params);
+                return self.publish_diagnostics()
+            }
+        };
+
+        let result = match method {
+            _ if self.shutdown => Err((INVALID_REQUEST, "the server is shutting down".to_owned())),
+            "initialize" => Ok(initialize_result()),
+            "shutdown" => {
+                self.shutdown = true;
+                Ok(Value::Null)
+            },
+            "textDocument/hover" => self.hover(&params),
+            "textDocument/definition" => self.definition(&params),
+            "textDocument/completion" => self.completion(&params),
+            "textDocument/codeAction" => self.code_action(&params),
+            _ => Err((METHOD_NOT_FOUND, format!("unsupported method '{method}'")))
+        };
+
+        vec![match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err((code, message)) => error_response(id, code, &message)
+        }]
+    }
+
+    /// Handle a notification. Malformed and unsupported notifications are ignored, as they can't be responded to.
+    fn notify(&mut self, method: &str, params: &Value) {
+        let document = match params.get("textDocument") {
+            Some(n) => n,
+            None => return
+        };
+        let uri = match document.get("uri").and_then(Value::as_str) {
+            Some(n) => n,
+            None => return
+        };
+        let version = document.get("version").and_then(Value::as_i64).unwrap_or_default();
+
+        match method {
+            "textDocument/didOpen" => {
+                if let Some(text) = document.get("text").and_then(Value::as_str) {
+                    self.server.open_document(uri, version, text);
+                }
+            },
+            "textDocument/didChange" => {
+                let changes: Vec<ContentChange> = params.get("contentChanges").and_then(Value::as_array).into_iter().flatten().filter_map(|c| {
+                    Some(ContentChange { range: c.get("range").and_then(parse_range), text: c.get("text")?.as_str()?.to_owned() })
+                }).collect();
+                self.server.change_document(uri, version, &changes);
+            },
+            "textDocument/didClose" => {
+                self.server.close_document(uri);
+            },
+            _ => ()
+        }
+    }
+
+    /// Get a notification for each document whose diagnostics changed since they were last sent.
+    fn publish_diagnostics(&mut self) -> Vec<Value> {
+        let diagnostics = self.server.diagnostics();
+        let mut notifications = Vec::new();
+
+        // Clear the diagnostics of closed documents
+        let closed: Vec<String> = self.published.keys().filter(|u| diagnostics.iter().all(|d| &d.uri != *u)).cloned().collect();
+        for uri in closed {
+            self.published.remove(&uri);
+            notifications.push(notification("textDocument/publishDiagnostics", json!({ "uri": uri, "diagnostics": [] })));
+        }
+
+        for d in diagnostics {
+            if self.published.get(&d.uri) == Some(&d.diagnostics) {
+                continue
+            }
+            self.published.insert(d.uri.clone(), d.diagnostics.clone());
+            notifications.push(notification("textDocument/publishDiagnostics", json!({
+                "uri": d.uri,
+                "version": d.version,
+                "diagnostics": d.diagnostics.iter().map(|e| json!({
+                    "range": range_json(e.range),
+                    "severity": e.severity as u8,
+                    "code": e.code as u16,
+                    "source": "riat",
+                    "message": e.message
+                })).collect::<Vec<Value>>()
+            })));
+        }
+
+        notifications
+    }
+
+    fn hover(&self, params: &Value) -> Result<Value, (i64, String)> {
+        let (uri, position) = document_position(params)?;
+        Ok(match self.server.hover(uri, position) {
+            Some(h) => json!({ "contents": { "kind": "markdown", "value": h.contents }, "range": range_json(h.range) }),
+            None => Value::Null
+        })
+    }
+
+    fn definition(&self, params: &Value) -> Result<Value, (i64, String)> {
+        let (uri, position) = document_position(params)?;
+        Ok(match self.server.definition(uri, position) {
+            Some(l) => json!({ "uri": l.uri, "range": range_json(l.range) }),
+            None => Value::Null
+        })
+    }
+
+    fn completion(&self, params: &Value) -> Result<Value, (i64, String)> {
+        let (uri, position) = document_position(params)?;
+        Ok(self.server.completions(uri, position).into_iter().map(|c| {
+            let mut item = json!({ "label": c.label, "kind": completion_kind(c.kind), "detail": c.detail });
+            if let Some(snippet) = c.insert_snippet {
+                item["insertText"] = json!(snippet);
+                item["insertTextFormat"] = json!(2);
+            }
+            item
+        }).collect())
+    }
+
+    fn code_action(&self, params: &Value) -> Result<Value, (i64, String)> {
+        let uri = params.pointer("/textDocument/uri").and_then(Value::as_str).ok_or_else(|| (INVALID_PARAMS, "expected a document".to_owned()))?;
+        let position = params.pointer("/range/start").and_then(parse_position).ok_or_else(|| (INVALID_PARAMS, "expected a range".to_owned()))?;
+        Ok(self.server.code_actions(uri, position).into_iter().map(|a| {
+            let mut changes = Map::new();
+            changes.insert(uri.to_owned(), a.edits.iter().map(|e| json!({ "range": range_json(e.range), "newText": e.text })).collect());
+            json!({ "title": a.title, "kind": code_action_kind(a.kind), "edit": { "changes": changes } })
+        }).collect())
+    }
+}
+
+fn initialize_result() -> Value {
+    json!({
+        "capabilities": {
+            // Documents are synchronized incrementally
+            "textDocumentSync": { "openClose": true, "change": 2 },
+            "hoverProvider": true,
+            "definitionProvider": true,
+            "completionProvider": { "triggerCharacters": ["("] },
+            "codeActionProvider": true
+        },
+        "serverInfo": { "name": "riat-lsp", "version": env!("CARGO_PKG_VERSION") }
+    })
+}
+
+/// Get the kind of completion item used by the protocol.
+fn completion_kind(kind: CompletionKind) -> u8 {
+    match kind {
+        CompletionKind::Function | CompletionKind::Script => 3,
+        CompletionKind::Global | CompletionKind::Parameter => 6,
+        CompletionKind::Literal => 12
+    }
+}
+
+/// Get the kind of code action used by the protocol.
+fn code_action_kind(kind: CodeActionKind) -> &'static str {
+    match kind {
+        CodeActionKind::SortGlobalsFirst | CodeActionKind::GroupScriptsByType | CodeActionKind::NormalizeCase => "source",
+        CodeActionKind::ConvertToCond => "refactor.rewrite"
+    }
+}
+
+fn document_position(params: &Value) -> Result<(&str, Position), (i64, String)> {
+    let uri = params.pointer("/textDocument/uri").and_then(Value::as_str).ok_or_else(|| (INVALID_PARAMS, "expected a document".to_owned()))?;
+    let position = params.get("position").and_then(parse_position).ok_or_else(|| (INVALID_PARAMS, "expected a position".to_owned()))?;
+    Ok((uri, position))
+}
+
+fn parse_position(value: &Value) -> Option<Position> {
+    Some(Position { line: value.get("line")?.as_u64()? as usize, character: value.get("character")?.as_u64()? as usize })
+}
+
+fn parse_range(value: &Value) -> Option<Range> {
+    Some(Range { start: parse_position(value.get("start")?)?, end: parse_position(value.get("end")?)? })
+}
+
+fn range_json(range: Range) -> Value {
+    json!({
+        "start": { "line": range.start.line, "character": range.start.character },
+        "end": { "line": range.end.line, "character": range.end.character }
+    })
+}
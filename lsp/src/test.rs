@@ -0,0 +1,174 @@
+use super::*;
+
+fn position(line: usize, character: usize) -> Position {
+    Position { line, character }
+}
+
+fn new_server() -> LanguageServer {
+    let mut server = LanguageServer::new(CompileTarget::HaloCEA);
+    server.open_document("file:///globals.hsc", 1, "(global short counter 0)\r\n(global string greeting \"h\u{E9}llo\")");
+    server.open_document("file:///scripts.hsc", 1, "(script static void greet\n    (print greeting)\n    (set counter (+ counter 1)))");
+    server
+}
+
+#[test]
+fn test_diagnostics() {
+    let mut server = new_server();
+    let diagnostics = server.diagnostics();
+    assert_eq!(2, diagnostics.len());
+    assert!(diagnostics.iter().all(|d| d.diagnostics.is_empty()));
+
+    // Diagnostics are found as the document is edited
+    assert!(server.change_document("file:///scripts.hsc", 2, &[ContentChange {
+        range: Some(Range { start: position(1, 11), end: position(1, 19) }),
+        text: "counter".to_owned()
+    }]));
+    assert_eq!("(script static void greet\n    (print counter)\n    (set counter (+ counter 1)))", server.get_document("file:///scripts.hsc").unwrap());
+
+    let diagnostics = server.diagnostics();
+    assert_eq!(vec![0, 1], diagnostics.iter().map(|d| d.diagnostics.len()).collect::<Vec<usize>>());
+    let scripts = &diagnostics[1];
+    assert_eq!(("file:///scripts.hsc", 2), (scripts.uri.as_str(), scripts.version));
+    assert_eq!(DiagnosticSeverity::Error, scripts.diagnostics[0].severity);
+    assert_eq!(position(1, 11), scripts.diagnostics[0].range.start);
+
+    // Script data that cannot be read at all still reports why
+    server.change_document("file:///scripts.hsc", 3, &[ContentChange { range: None, text: "(script static void greet (print \"unterminated".to_owned() }]);
+    let diagnostics = server.diagnostics();
+    assert_eq!(1, diagnostics[1].diagnostics.len());
+
+    // Closing a document stops checking it
+    assert!(server.close_document("file:///scripts.hsc"));
+    assert!(!server.close_document("file:///scripts.hsc"));
+    assert_eq!(1, server.diagnostics().len());
+    assert!(!server.change_document("file:///scripts.hsc", 4, &[]));
+}
+
+#[test]
+fn test_definition() {
+    let server = new_server();
+
+    // Globals defined in another document
+    let location = server.definition("file:///scripts.hsc", position(1, 13)).unwrap();
+    assert_eq!("file:///globals.hsc", location.uri);
+    assert_eq!(Range { start: position(1, 15), end: position(1, 23) }, location.range);
+
+    let location = server.definition("file:///scripts.hsc", position(2, 27)).unwrap();
+    assert_eq!(Range { start: position(0, 14), end: position(0, 21) }, location.range);
+
+    // Engine functions are not defined in any document
+    assert!(server.definition("file:///scripts.hsc", position(1, 6)).is_none());
+    assert!(server.definition("file:///missing.hsc", position(0, 0)).is_none());
+}
+
+#[test]
+fn test_hover() {
+    let server = new_server();
+
+    let hover = server.hover("file:///scripts.hsc", position(1, 7)).unwrap();
+    assert_eq!("```hsc\n(print <string>)\n```\nEngine function returning `void`", hover.contents);
+    assert_eq!(Range { start: position(1, 5), end: position(1, 10) }, hover.range);
+
+    let hover = server.hover("file:///scripts.hsc", position(2, 10)).unwrap();
+    assert_eq!("```hsc\n(global short counter)\n```", hover.contents);
+
+    // Names where they are defined, but not types or literals
+    let hover = server.hover("file:///globals.hsc", position(1, 16)).unwrap();
+    assert_eq!("```hsc\n(global string greeting)\n```", hover.contents);
+    assert!(server.hover("file:///globals.hsc", position(1, 9)).is_none());
+    assert!(server.hover("file:///globals.hsc", position(1, 28)).is_none());
+}
+
+#[test]
+fn test_completions() {
+    let mut server = new_server();
+    server.open_document("file:///new.hsc", 1, "(script startup begin\n    (ai_alleg\n    (set coun");
+
+    let completions = server.completions("file:///new.hsc", position(1, 13));
+    let allegiance = completions.iter().find(|c| c.label == "ai_allegiance").unwrap();
+    assert_eq!("void", allegiance.detail);
+    assert!(allegiance.insert_snippet.as_ref().unwrap().starts_with("ai_allegiance ${1|default,player,"));
+
+    // Globals from other documents are inserted as is
+    let completions = server.completions("file:///new.hsc", position(2, 13));
+    assert_eq!(vec![CompletionItem { label: "counter".to_owned(), kind: CompletionKind::Global, detail: "short".to_owned(), insert_snippet: None }], completions);
+
+    assert!(server.completions("file:///missing.hsc", position(0, 0)).is_empty());
+}
+
+#[test]
+fn test_code_actions() {
+    let mut server = new_server();
+    server.open_document("file:///mixed.hsc", 1, "(script static void greet (PRINT \"h\u{E9}llo\"))\n(global short later 0)");
+
+    let actions = server.code_actions("file:///mixed.hsc", position(0, 0));
+    assert_eq!(vec![CodeActionKind::SortGlobalsFirst, CodeActionKind::NormalizeCase], actions.iter().map(|a| a.kind).collect::<Vec<CodeActionKind>>());
+    assert_eq!(Range { start: position(0, 0), end: position(1, 22) }, actions[0].edits[0].range);
+    assert_eq!(DocumentEdit { range: Range { start: position(0, 27), end: position(0, 32) }, text: "print".to_owned() }, actions[1].edits[0]);
+
+    assert!(server.code_actions("file:///scripts.hsc", position(0, 0)).is_empty());
+    assert!(server.code_actions("file:///missing.hsc", position(0, 0)).is_empty());
+}
+
+#[test]
+fn test_positions() {
+    let text = "a\r\nb\u{E9}\u{1F400}c\rd";
+    assert_eq!((2, 8), to_script_position(text, position(1, 4)));
+    assert_eq!(position(1, 4), to_position(text, (2, 8)));
+    assert_eq!(position(2, 1), to_position(text, (100, 1)));
+
+    // Positions past the end of a line are clamped to it
+    assert_eq!(position(1, 5), to_position(text, (2, 100)));
+    assert_eq!(11, offset_of_position(text, position(1, 100)));
+}
+
+#[test]
+fn test_stdio() {
+    let messages = [
+        r#"{"jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {"capabilities": {}}}"#,
+        r#"{"jsonrpc": "2.0", "method": "initialized", "params": {}}"#,
+        r#"{"jsonrpc": "2.0", "method": "textDocument/didOpen", "params": {"textDocument": {"uri": "file:///a.hsc", "languageId": "hsc", "version": 1, "text": "(script static void a (sleep b))"}}}"#,
+        r#"{"jsonrpc": "2.0", "id": 2, "method": "textDocument/hover", "params": {"textDocument": {"uri": "file:///a.hsc"}, "position": {"line": 0, "character": 24}}}"#,
+        r#"{"jsonrpc": "2.0", "method": "textDocument/didChange", "params": {"textDocument": {"uri": "file:///a.hsc", "version": 2}, "contentChanges": [{"range": {"start": {"line": 0, "character": 29}, "end": {"line": 0, "character": 30}}, "text": "30"}]}}"#,
+        r#"{"jsonrpc": "2.0", "id": 3, "method": "textDocument/unknown", "params": {}}"#,
+        "not json",
+        r#"{"jsonrpc": "2.0", "id": 4, "method": "shutdown"}"#,
+        r#"{"jsonrpc": "2.0", "method": "exit"}"#,
+        r#"{"jsonrpc": "2.0", "id": 5, "method": "shutdown"}"#
+    ];
+    let input: String = messages.iter().map(|m| format!("Content-Length: {}\r\n\r\n{m}", m.len())).collect();
+    let mut output = Vec::new();
+    assert!(stdio::run(CompileTarget::HaloCEA, input.as_bytes(), &mut output).unwrap());
+
+    // Split the output back into messages
+    let output = String::from_utf8(output).unwrap();
+    let replies: Vec<serde_json::Value> = output.split("Content-Length: ").skip(1).map(|m| {
+        let (length, content) = m.split_once("\r\n\r\n").unwrap();
+        assert_eq!(length.parse::<usize>().unwrap(), content.len());
+        serde_json::from_str(content).unwrap()
+    }).collect();
+    assert_eq!(7, replies.len());
+
+    assert_eq!(1, replies[0]["id"]);
+    assert_eq!(2, replies[0]["result"]["capabilities"]["textDocumentSync"]["change"]);
+
+    // Opening and changing the document publishes its diagnostics
+    assert_eq!("textDocument/publishDiagnostics", replies[1]["method"]);
+    assert_eq!(1, replies[1]["params"]["version"]);
+    assert_eq!(1, replies[1]["params"]["diagnostics"][0]["severity"]);
+    assert_eq!(serde_json::json!({"line": 0, "character": 29}), replies[1]["params"]["diagnostics"][0]["range"]["start"]);
+
+    assert_eq!(2, replies[2]["id"]);
+    assert!(replies[2]["result"]["contents"]["value"].as_str().unwrap().starts_with("```hsc\n(sleep <short>"));
+
+    assert_eq!(2, replies[3]["params"]["version"]);
+    assert_eq!(0, replies[3]["params"]["diagnostics"].as_array().unwrap().len());
+
+    assert_eq!(-32601, replies[4]["error"]["code"]);
+    assert_eq!(-32700, replies[5]["error"]["code"]);
+    assert_eq!((4, serde_json::Value::Null), (replies[6]["id"].as_i64().unwrap(), replies[6]["result"].clone()));
+
+    // Exiting without shutting down first is a failure
+    let exit = r#"{"jsonrpc": "2.0", "method": "exit"}"#;
+    assert!(!stdio::run(CompileTarget::HaloCEA, format!("Content-Length: {}\r\n\r\n{exit}", exit.len()).as_bytes(), Vec::new()).unwrap());
+}
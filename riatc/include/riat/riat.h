@@ -14,6 +14,10 @@ extern "C" {
 typedef struct RIATCompiler RIATCompiler;
 typedef struct RIATCompileError RIATCompileError;
 typedef struct RIATCompiledScriptData RIATCompiledScriptData;
+typedef struct RIATScriptDataStrings RIATScriptDataStrings;
+
+#define RIAT_ABI_VERSION 4
+uint32_t riat_abi_version(void);
 
 typedef enum RIATCompileTarget {
     RIAT_HaloCEA,
@@ -21,92 +25,149 @@ typedef enum RIATCompileTarget {
     RIAT_HaloCEGBX,
     RIAT_HaloCEGBXDemo,
     RIAT_HaloCustomEdition,
+    RIAT_HaloCEGBXDemoMac,
+    RIAT_HaloCEXboxPAL,
+    RIAT_HaloCEXboxJP,
 } RIATCompileTarget;
 
+typedef enum RIATCompileEncoding {
+    RIAT_UTF8,
+    RIAT_Windows1252
+} RIATCompileEncoding;
+
+typedef enum RIATDecodeErrorPolicy {
+    RIAT_DecodeError,
+    RIAT_DecodeReplace,
+    RIAT_DecodeSkip
+} RIATDecodeErrorPolicy;
+
+typedef enum RIATCompileErrorType {
+    RIAT_Warning = 0,
+    RIAT_Error = 1
+} RIATCompileErrorType;
+
+typedef enum RIATCompileErrorCode {
+    RIAT_CodeGeneric = 0,
+    RIAT_CodeEmptyBlock = 1,
+    RIAT_CodeConsoleFunction = 2,
+    RIAT_CodeRealPrecision = 3,
+    RIAT_CodeUninitializedGlobal = 4,
+    RIAT_CodeMissingReturnValue = 5,
+    RIAT_CodeScriptCount = 6,
+    RIAT_CodeStyle = 7,
+    RIAT_CodeBudget = 8,
+    RIAT_CodeUnsupportedByTarget = 9,
+    RIAT_CodeUnresolvedSymbol = 10,
+    RIAT_CodeUnsupportedByBackend = 11,
+    RIAT_CodeDiagnosticLimit = 12,
+    RIAT_CodeShadowedGlobal = 13,
+    RIAT_CodeGlobalUsage = 14,
+    RIAT_CodeTickCost = 15,
+    RIAT_CodeSuspiciousSleep = 16,
+    RIAT_CodeUnusedParameter = 17,
+    RIAT_CodeUnsetGlobal = 18,
+    RIAT_CodeLiteralName = 19,
+    RIAT_CodeUndecodableToken = 20,
+    RIAT_CodeTokenCase = 21,
+    RIAT_CodeClientOnly = 22,
+    RIAT_CodeDeepNesting = 23
+} RIATCompileErrorCode;
+
 typedef struct RIATCompileErrorC {
     const char *file;
     const char *message;
     size_t line;
     size_t column;
+    size_t end_line;
+    size_t end_column;
+    uint16_t error_type;
+    uint16_t code;
     void *_reserved;
 } RIATCompileErrorC;
 
-RIATCompiler *riat_compiler_new(RIATCompileTarget target);
+RIATCompiler *riat_compiler_new(RIATCompileTarget target, RIATCompileEncoding encoding);
+void riat_compiler_set_decode_error_policy(RIATCompiler *compiler, RIATDecodeErrorPolicy policy);
 int riat_compiler_read_script_data(RIATCompiler *compiler, const char *input_filename, const uint8_t *input_data, size_t input_size, RIATCompileErrorC *error);
 RIATCompiledScriptData *riat_compiler_compile_script_data(RIATCompiler *compiler, RIATCompileErrorC *error);
 void riat_compiler_free(RIATCompiler *compiler);
 
+RIATCompileTarget riat_script_data_get_target(const RIATCompiledScriptData *script_data);
+RIATCompileEncoding riat_script_data_get_encoding(const RIATCompiledScriptData *script_data);
 size_t riat_script_data_get_warnings(const RIATCompiledScriptData *script_data, RIATCompileErrorC *warnings);
+size_t riat_script_data_get_warning_count_by_severity(const RIATCompiledScriptData *script_data, uint16_t error_type);
+size_t riat_script_data_get_warnings_by_severity(const RIATCompiledScriptData *script_data, uint16_t error_type, RIATCompileErrorC *warnings);
 void riat_script_data_free(RIATCompiledScriptData *compiler);
 
 void riat_error_free(RIATCompileErrorC *error);
+const RIATCompileErrorC *riat_get_last_error(const RIATCompiler *compiler);
+void riat_clear_last_error(const RIATCompiler *compiler);
 
 typedef enum RIATValueType {
-    RIAT_Unparsed,
-    RIAT_SpecialForm,
-    RIAT_FunctionName,
-    RIAT_Passthrough,
-    RIAT_Void,
-    RIAT_Boolean,
-    RIAT_Real,
-    RIAT_Short,
-    RIAT_Long,
-    RIAT_String,
-    RIAT_Script,
-    RIAT_TriggerVolume,
-    RIAT_CutsceneFlag,
-    RIAT_CutsceneCameraPoint,
-    RIAT_CutsceneTitle,
-    RIAT_CutsceneRecording,
-    RIAT_DeviceGroup,
-    RIAT_Ai,
-    RIAT_AiCommandList,
-    RIAT_StartingProfile,
-    RIAT_Conversation,
-    RIAT_Navpoint,
-    RIAT_HudMessage,
-    RIAT_ObjectList,
-    RIAT_Sound,
-    RIAT_Effect,
-    RIAT_Damage,
-    RIAT_LoopingSound,
-    RIAT_AnimationGraph,
-    RIAT_ActorVariant,
-    RIAT_DamageEffect,
-    RIAT_ObjectDefinition,
-    RIAT_GameDifficulty,
-    RIAT_Team,
-    RIAT_AiDefaultState,
-    RIAT_ActorType,
-    RIAT_HudCorner,
-    RIAT_Object,
-    RIAT_Unit,
-    RIAT_Vehicle,
-    RIAT_Weapon,
-    RIAT_Device,
-    RIAT_Scenery,
-    RIAT_ObjectName,
-    RIAT_UnitName,
-    RIAT_VehicleName,
-    RIAT_WeaponName,
-    RIAT_DeviceName,
-    RIAT_SceneryName
+    RIAT_Unparsed = 0,
+    RIAT_SpecialForm = 1,
+    RIAT_FunctionName = 2,
+    RIAT_Passthrough = 3,
+    RIAT_Void = 4,
+    RIAT_Boolean = 5,
+    RIAT_Real = 6,
+    RIAT_Short = 7,
+    RIAT_Long = 8,
+    RIAT_String = 9,
+    RIAT_Script = 10,
+    RIAT_TriggerVolume = 11,
+    RIAT_CutsceneFlag = 12,
+    RIAT_CutsceneCameraPoint = 13,
+    RIAT_CutsceneTitle = 14,
+    RIAT_CutsceneRecording = 15,
+    RIAT_DeviceGroup = 16,
+    RIAT_Ai = 17,
+    RIAT_AiCommandList = 18,
+    RIAT_StartingProfile = 19,
+    RIAT_Conversation = 20,
+    RIAT_Navpoint = 21,
+    RIAT_HudMessage = 22,
+    RIAT_ObjectList = 23,
+    RIAT_Sound = 24,
+    RIAT_Effect = 25,
+    RIAT_Damage = 26,
+    RIAT_LoopingSound = 27,
+    RIAT_AnimationGraph = 28,
+    RIAT_ActorVariant = 29,
+    RIAT_DamageEffect = 30,
+    RIAT_ObjectDefinition = 31,
+    RIAT_GameDifficulty = 32,
+    RIAT_Team = 33,
+    RIAT_AiDefaultState = 34,
+    RIAT_ActorType = 35,
+    RIAT_HudCorner = 36,
+    RIAT_Object = 37,
+    RIAT_Unit = 38,
+    RIAT_Vehicle = 39,
+    RIAT_Weapon = 40,
+    RIAT_Device = 41,
+    RIAT_Scenery = 42,
+    RIAT_ObjectName = 43,
+    RIAT_UnitName = 44,
+    RIAT_VehicleName = 45,
+    RIAT_WeaponName = 46,
+    RIAT_DeviceName = 47,
+    RIAT_SceneryName = 48
 } RIATValueType;
 
 typedef enum RIATScriptType {
-    RIAT_Startup,
-    RIAT_Dormant,
-    RIAT_Continuous,
-    RIAT_Static,
-    RIAT_Stub
+    RIAT_Startup = 0,
+    RIAT_Dormant = 1,
+    RIAT_Continuous = 2,
+    RIAT_Static = 3,
+    RIAT_Stub = 4
 } RIATScriptType;
 
 typedef enum RIATNodeTypeC {
-    RIAT_StaticValue,
-    RIAT_LocalVariable,
-    RIAT_GlobalVariable,
-    RIAT_FunctionCall,
-    RIAT_ScriptCall
+    RIAT_StaticValue = 0,
+    RIAT_LocalVariable = 1,
+    RIAT_GlobalVariable = 2,
+    RIAT_FunctionCall = 3,
+    RIAT_ScriptCall = 4
 } RIATNodeTypeC;
 
 typedef union RIATScriptNodeDataC {
@@ -123,8 +184,8 @@ typedef struct RIATScriptNodeC {
     size_t column;
     const char *string_data;
     uint16_t index_union;
-    RIATValueType value_type;
-    RIATNodeTypeC node_type;
+    uint16_t value_type;
+    uint16_t node_type;
     RIATScriptNodeDataC node_data;
     size_t next_node;
 } RIATScriptNodeC;
@@ -136,8 +197,8 @@ typedef struct RIATScriptC {
     const char *file;
     size_t line;
     size_t column;
-    RIATScriptType script_type;
-    RIATValueType return_type;
+    uint16_t script_type;
+    uint16_t return_type;
     size_t first_node;
 } RIATScriptC;
 
@@ -146,7 +207,7 @@ typedef struct RIATGlobalC {
     const char *file;
     size_t line;
     size_t column;
-    RIATValueType value_type;
+    uint16_t value_type;
     size_t first_node;
 } RIATGlobalC;
 
@@ -155,11 +216,107 @@ size_t riat_script_data_get_globals(const RIATCompiledScriptData *script_data, R
 
 typedef struct RIATScriptParameterC {
     const char *name;
-    RIATValueType value_type;
+    uint16_t value_type;
 } RIATScriptParameterC;
 
 size_t riat_script_data_get_script_parameters(const RIATCompiledScriptData *script_data, const char *script_name, RIATScriptParameterC *parameters);
 
+typedef struct RIATMemoryUsage {
+    size_t tokens;
+    size_t nodes;
+    size_t strings;
+} RIATMemoryUsage;
+
+typedef struct RIATScriptDataStatistics {
+    size_t script_count;
+    size_t global_count;
+    size_t node_count;
+    size_t string_data_length;
+    size_t maximum_node_count;
+    size_t maximum_string_data_length;
+    size_t optimized_node_count;
+    RIATMemoryUsage memory;
+} RIATScriptDataStatistics;
+
+RIATScriptDataStatistics riat_script_data_get_statistics(const RIATCompiledScriptData *script_data);
+
+typedef enum RIATSymbolType {
+    RIAT_SymbolScript,
+    RIAT_SymbolGlobal
+} RIATSymbolType;
+
+typedef struct RIATSourceMapEntryC {
+    const char *file;
+    size_t line;
+    size_t column;
+    RIATSymbolType owner_type;
+    size_t owner_index;
+} RIATSourceMapEntryC;
+
+bool riat_script_data_get_source_map_entry(const RIATCompiledScriptData *script_data, size_t node_index, RIATSourceMapEntryC *entry);
+
+size_t riat_script_data_who_calls(const RIATCompiledScriptData *script_data, const char *function, size_t *nodes);
+size_t riat_script_data_who_writes(const RIATCompiledScriptData *script_data, const char *global, size_t *nodes);
+
+typedef enum RIATStringKindC {
+    RIAT_StringFile,
+    RIAT_StringScriptName,
+    RIAT_StringGlobalName,
+    RIAT_StringNodeStringData,
+    RIAT_StringWarningFile,
+    RIAT_StringWarningMessage
+} RIATStringKindC;
+
+const RIATScriptDataStrings *riat_script_data_copy_strings(const RIATCompiledScriptData *script_data);
+void riat_strings_retain(const RIATScriptDataStrings *strings);
+void riat_strings_release(const RIATScriptDataStrings *strings);
+const char *riat_strings_get(const RIATScriptDataStrings *strings, RIATStringKindC kind, size_t index);
+
+size_t riat_script_data_get_script_name_utf16(const RIATCompiledScriptData *script_data, size_t index, uint16_t *buffer, size_t buffer_length);
+size_t riat_script_data_get_global_name_utf16(const RIATCompiledScriptData *script_data, size_t index, uint16_t *buffer, size_t buffer_length);
+size_t riat_script_data_get_file_name_utf16(const RIATCompiledScriptData *script_data, size_t index, uint16_t *buffer, size_t buffer_length);
+uint16_t riat_script_data_get_script_type(const RIATCompiledScriptData *script_data, size_t index);
+uint16_t riat_script_data_get_script_return_type(const RIATCompiledScriptData *script_data, size_t index);
+size_t riat_script_data_get_script_first_node(const RIATCompiledScriptData *script_data, size_t index);
+uint16_t riat_script_data_get_global_value_type(const RIATCompiledScriptData *script_data, size_t index);
+size_t riat_script_data_get_global_first_node(const RIATCompiledScriptData *script_data, size_t index);
+size_t riat_error_copy_message_utf16(const RIATCompileErrorC *error, uint16_t *buffer, size_t buffer_length);
+
+typedef enum RIATScriptNodeDataKindC {
+    RIAT_DataNone = 0,
+    RIAT_DataLong = 1,
+    RIAT_DataShort = 2,
+    RIAT_DataBoolean = 3,
+    RIAT_DataReal = 4,
+    RIAT_DataNodeOffset = 5
+} RIATScriptNodeDataKindC;
+
+typedef struct RIATScriptNodeV2C {
+    const char *file;
+    size_t line;
+    size_t column;
+    const char *string_data;
+    bool has_string_data;
+    uint16_t index_union;
+    uint16_t value_type;
+    uint16_t node_type;
+    uint16_t data_kind;
+    RIATScriptNodeDataC node_data;
+    bool has_next_node;
+    size_t next_node;
+} RIATScriptNodeV2C;
+
+size_t riat_script_data_get_nodes_v2(const RIATCompiledScriptData *script_data, RIATScriptNodeV2C *nodes);
+bool riat_script_data_get_node_v2(const RIATCompiledScriptData *script_data, size_t node_index, RIATScriptNodeV2C *node);
+
+typedef struct RIATNodeDataC {
+    uint16_t kind;
+    int64_t integer;
+    double real;
+} RIATNodeDataC;
+
+bool riat_script_data_get_node_data(const RIATCompiledScriptData *script_data, size_t node_index, RIATNodeDataC *data);
+
 #ifdef __cplusplus
 }
 #endif
@@ -176,6 +176,15 @@ namespace RIAT {
             }
         }
 
+        /**
+         * Set what is done with bytes that cannot be decoded with the encoding (by default, they are replaced with U+FFFD)
+         *
+         * @param policy policy to use
+         */
+        void set_decode_error_policy(RIATDecodeErrorPolicy policy) noexcept {
+            ::riat_compiler_set_decode_error_policy(this->get_instance(), policy);
+        }
+
         /**
          * Compile the given script and, if successful, clear all loaded scripts.
          *
@@ -205,8 +214,9 @@ namespace RIAT {
          * Instantiate a compiler instance
          *
          * @param target   target engine
+         * @param encoding encoding of the scripts
          */
-        Compiler(RIATCompileTarget target) : instance(::riat_compiler_new(target), ::riat_compiler_free) {
+        Compiler(RIATCompileTarget target, RIATCompileEncoding encoding = RIATCompileEncoding::RIAT_Windows1252) : instance(::riat_compiler_new(target, encoding), ::riat_compiler_free) {
             if(this->instance.get() == nullptr) {
                 throw std::exception();
             }
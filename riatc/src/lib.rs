@@ -2,9 +2,66 @@ extern crate rat_in_a_tube;
 use rat_in_a_tube::*;
 
 use std::os::raw::*;
-use std::ffi::CStr;
+use std::ffi::{CStr, CString};
+use std::sync::Arc;
+use std::cell::RefCell;
+use std::collections::HashMap;
 
 
+/// Version of the C ABI.
+///
+/// This is incremented whenever a struct layout, function signature, or enum value changes in a way that breaks existing bindings.
+pub const RIAT_ABI_VERSION: u32 = 4;
+
+// Enum values are part of the ABI, so changes to the definitions must never renumber or resize them
+const _: () = {
+    assert!(std::mem::size_of::<ValueType>() == 2);
+    assert!(std::mem::size_of::<ScriptType>() == 2);
+    assert!(std::mem::size_of::<NodeTypeC>() == 2);
+    assert!(std::mem::size_of::<CompileErrorType>() == 2);
+    assert!(std::mem::size_of::<CompileErrorCode>() == 2);
+    assert!(CompileErrorCode::UnsupportedByTarget as u16 == 9);
+    assert!(CompileErrorCode::UnresolvedSymbol as u16 == 10);
+    assert!(CompileErrorCode::UnsupportedByBackend as u16 == 11);
+    assert!(CompileErrorCode::DiagnosticLimit as u16 == 12);
+    assert!(CompileErrorCode::ShadowedGlobal as u16 == 13);
+    assert!(CompileErrorCode::GlobalUsage as u16 == 14);
+    assert!(CompileErrorCode::TickCost as u16 == 15);
+    assert!(CompileErrorCode::SuspiciousSleep as u16 == 16);
+    assert!(CompileErrorCode::UnusedParameter as u16 == 17);
+    assert!(CompileErrorCode::UnsetGlobal as u16 == 18);
+    assert!(CompileErrorCode::LiteralName as u16 == 19);
+    assert!(CompileErrorCode::UndecodableToken as u16 == 20);
+    assert!(CompileErrorCode::TokenCase as u16 == 21);
+    assert!(CompileErrorCode::ClientOnly as u16 == 22);
+    assert!(CompileErrorCode::DeepNesting as u16 == 23);
+    assert!(ValueType::Void as u16 == 4);
+    assert!(ValueType::Script as u16 == 10);
+    assert!(ValueType::GameDifficulty as u16 == 32);
+    assert!(ValueType::Object as u16 == 37);
+    assert!(ValueType::SceneryName as u16 == 48);
+    assert!(ScriptType::Stub as u16 == 4);
+    assert!(std::mem::size_of::<DecodeErrorPolicy>() == std::mem::size_of::<c_int>());
+    assert!(DecodeErrorPolicy::Error as c_int == 0);
+    assert!(DecodeErrorPolicy::Replace as c_int == 1);
+    assert!(DecodeErrorPolicy::Skip as c_int == 2);
+    assert!(NodeTypeC::ScriptCall as u16 == 4);
+    assert!(std::mem::size_of::<ScriptNodeDataKindC>() == 2);
+    assert!(ScriptNodeDataKindC::NodeOffset as u16 == 5);
+
+    // New kinds of node data must fit in NodeDataC without changing its layout
+    assert!(std::mem::size_of::<NodeDataC>() == 24);
+
+    // Adding fields to the statistics changes their layout, which needs RIAT_ABI_VERSION to be incremented
+    assert!(std::mem::size_of::<ScriptDataStatistics>() == 10 * std::mem::size_of::<usize>());
+};
+
+/// Get the version of the C ABI, which bindings can compare against the version they were written for.
+#[no_mangle]
+pub extern "C" fn riat_abi_version() -> u32 {
+    RIAT_ABI_VERSION
+}
+
 /// Compile error C struct.
 #[derive(Copy, Clone)]
 #[repr(C)]
@@ -21,6 +78,18 @@ pub struct CompileErrorC {
     /// Column the error occured on.
     pub column: usize,
 
+    /// Line just past the end of what the error refers to.
+    pub end_line: usize,
+
+    /// Column just past the end of what the error refers to.
+    pub end_column: usize,
+
+    /// Whether this is a warning or an error.
+    pub error_type: CompileErrorType,
+
+    /// Code identifying what the error is about.
+    pub code: CompileErrorCode,
+
     /// Reserved
     pub base: *mut CompileError
 }
@@ -35,12 +104,17 @@ impl CompileErrorC {
 
     fn new(error: &CompileError) -> Self {
         let (line, column) = error.get_position();
+        let (end_line, end_column) = error.get_end_position();
 
         Self {
             file: error.get_file_cstr().as_ptr(),
             message: error.get_message_cstr().as_ptr(),
             line: line,
             column: column,
+            end_line,
+            end_column,
+            error_type: error.get_error_type(),
+            code: error.get_code(),
             base: std::ptr::null_mut()
         }
     }
@@ -53,21 +127,37 @@ impl CompileErrorC {
             self.message = std::ptr::null();
             self.line = 0;
             self.column = 0;
+            self.end_line = 0;
+            self.end_column = 0;
         }
     }
 }
 
 
-/// Allocate a compiler instance with the given target and return a pointer to it.
+/// Allocate a compiler instance with the given target and encoding and return a pointer to it.
 ///
 /// # Requirements
 ///
 /// The resulting pointer must be freed with [`riat_compiler_free`] or else a memory leak will occur.
 ///
-/// The target must be a valid [`CompileTarget`] enum or else **undefined behavior** will occur.
+/// The target must be a valid [`CompileTarget`] enum and the encoding must be a valid [`CompileEncoding`] enum or else **undefined behavior** will occur.
 #[no_mangle]
-pub extern "C" fn riat_compiler_new(target: CompileTarget) -> *mut Compiler {
-    Box::into_raw(Box::<Compiler>::new(Compiler::new(target)))
+pub extern "C" fn riat_compiler_new(target: CompileTarget, encoding: CompileEncoding) -> *mut Compiler {
+    Box::into_raw(Box::<Compiler>::new(Compiler::new(target, encoding)))
+}
+
+/// Set what is done with bytes in script data that cannot be decoded with the compiler's encoding.
+///
+/// By default, they are replaced with U+FFFD and a warning is emitted.
+///
+/// # Requirements
+///
+/// If any of these requirements are not met, **undefined behavior** will occur:
+/// * The `compiler` parameter must point to a valid [`Compiler`].
+/// * The policy must be a valid [`DecodeErrorPolicy`] enum.
+#[no_mangle]
+pub unsafe extern "C" fn riat_compiler_set_decode_error_policy(compiler: *mut Compiler, policy: DecodeErrorPolicy) {
+    (*compiler).set_decode_error_policy(policy)
 }
 
 /// Free a Compiler instance.
@@ -80,6 +170,7 @@ pub extern "C" fn riat_compiler_new(target: CompileTarget) -> *mut Compiler {
 #[no_mangle]
 pub unsafe extern "C" fn riat_compiler_free(compiler: *mut Compiler) {
     if !compiler.is_null() {
+        riat_clear_last_error(compiler);
         drop(Box::from_raw(compiler));
     }
 }
@@ -123,6 +214,7 @@ pub unsafe extern "C" fn riat_compiler_read_script_data(compiler: *mut Compiler,
     match compiler_ref.read_script_data(filename, input_data_slice) {
         Ok(()) => 0,
         Err(e) => {
+            set_last_error(compiler, &e);
             if !error.is_null() {
                 *error = CompileErrorC::new_owned(e);
             }
@@ -149,6 +241,7 @@ pub unsafe extern "C" fn riat_compiler_compile_script_data(compiler: *mut Compil
     match (*compiler).compile_script_data() {
         Ok(n) => Box::into_raw(Box::new(n)),
         Err(e) => {
+            set_last_error(compiler, &e);
             if !error.is_null() {
                 *error = CompileErrorC::new_owned(e);
             }
@@ -172,6 +265,27 @@ pub unsafe extern "C" fn riat_script_data_free(script_data: *mut CompiledScriptD
 }
 
 
+/// Get the target the script data was compiled for.
+///
+/// # Requirements
+///
+/// The `script_data` parameter must point to a valid [`CompiledScriptData`] or else **undefined behavior** will occur.
+#[no_mangle]
+pub unsafe extern "C" fn riat_script_data_get_target(script_data: *const CompiledScriptData) -> CompileTarget {
+    (*script_data).get_target()
+}
+
+/// Get the encoding the script data was compiled with.
+///
+/// # Requirements
+///
+/// The `script_data` parameter must point to a valid [`CompiledScriptData`] or else **undefined behavior** will occur.
+#[no_mangle]
+pub unsafe extern "C" fn riat_script_data_get_encoding(script_data: *const CompiledScriptData) -> CompileEncoding {
+    (*script_data).get_encoding()
+}
+
+
 /// Get all warnings from the script compilation.
 ///
 /// Return the number of warnings. Write this many warnings to an array pointed to by `warnings` if `warnings` is non-null.
@@ -198,15 +312,56 @@ pub unsafe extern "C" fn riat_script_data_get_warnings(script_data: *const Compi
     count
 }
 
+/// Get the number of warnings from the script compilation of the given severity.
+///
+/// This is the same as the number of warnings written by [`riat_script_data_get_warnings_by_severity`].
+///
+/// # Requirements
+///
+/// If any of these requirements are not met, **undefined behavior** will occur:
+/// * The `script_data` parameter must point to a valid [`CompiledScriptData`].
+/// * The `error_type` parameter must be a valid [`CompileErrorType`] value.
+#[no_mangle]
+pub unsafe extern "C" fn riat_script_data_get_warning_count_by_severity(script_data: *const CompiledScriptData, error_type: CompileErrorType) -> usize {
+    (*script_data).get_warnings().iter().filter(|w| w.get_error_type() == error_type).count()
+}
+
+/// Get all warnings from the script compilation of the given severity, in the same order as [`riat_script_data_get_warnings`].
+///
+/// Return the number of warnings. Write this many warnings to an array pointed to by `warnings` if `warnings` is non-null.
+///
+/// These warnings must NOT be freed with [`riat_error_free`], as the resources are owned by the [`CompiledScriptData`], not the [`CompileErrorC`] struct.
+///
+/// # Requirements
+///
+/// If any of these requirements are not met, **undefined behavior** will occur:
+/// * The `script_data` parameter must point to a valid [`CompiledScriptData`].
+/// * The `warnings` parameter must point to a valid array of [`CompileErrorC`] long enough to hold the result of this function or be null. To query the number of warnings, run this function with this parameter set to null.
+/// * The `error_type` parameter must be a valid [`CompileErrorType`] value.
+/// * If [`riat_script_data_free`] is called, the resulting warnings will no longer be valid, thus no pointers may be dereferenced after this.
+#[no_mangle]
+pub unsafe extern "C" fn riat_script_data_get_warnings_by_severity(script_data: *const CompiledScriptData, error_type: CompileErrorType, warnings: *mut CompileErrorC) -> usize {
+    let mut count = 0;
+
+    for w in (*script_data).get_warnings().iter().filter(|w| w.get_error_type() == error_type) {
+        if !warnings.is_null() {
+            *warnings.add(count) = CompileErrorC::new(w)
+        }
+        count += 1;
+    }
+
+    count
+}
+
 /// Node type C enum.
-#[repr(C)]
+#[repr(u16)]
 #[derive(Copy, Clone)]
 pub enum NodeTypeC {
-    StaticValue,
-    LocalVariable,
-    GlobalVariable,
-    FunctionCall,
-    ScriptCall
+    StaticValue = 0,
+    LocalVariable = 1,
+    GlobalVariable = 2,
+    FunctionCall = 3,
+    ScriptCall = 4
 }
 
 impl NodeTypeC {
@@ -232,6 +387,21 @@ pub union ScriptNodeDataC {
     pub boolean: bool
 }
 
+impl ScriptNodeDataC {
+    fn new(data: Option<NodeData>) -> Self {
+        match data {
+            None => ScriptNodeDataC { offset: usize::MAX },
+            Some(n) => match n {
+                NodeData::Long(v) => ScriptNodeDataC { long: v },
+                NodeData::Short(v) => ScriptNodeDataC { short: v },
+                NodeData::Boolean(v) => ScriptNodeDataC { boolean: v },
+                NodeData::Real(v) => ScriptNodeDataC { real: v },
+                NodeData::NodeOffset(v) => ScriptNodeDataC { offset: v }
+            }
+        }
+    }
+}
+
 /// Script node C struct.
 #[repr(C)]
 #[derive(Copy, Clone)]
@@ -297,16 +467,7 @@ pub unsafe extern "C" fn riat_script_data_get_nodes(script_data: *const Compiled
             node_out.value_type = node_in.get_value_type();
             node_out.node_type = NodeTypeC::new(node_in.get_type());
             node_out.index_union = node_in.get_index().unwrap_or(node_out.value_type as u16);
-            node_out.node_data = match node_in.get_data() {
-                None => ScriptNodeDataC { offset: usize::MAX },
-                Some(n) => match n {
-                    NodeData::Long(v) => ScriptNodeDataC { long: v },
-                    NodeData::Short(v) => ScriptNodeDataC { short: v },
-                    NodeData::Boolean(v) => ScriptNodeDataC { boolean: v },
-                    NodeData::Real(v) => ScriptNodeDataC { real: v },
-                    NodeData::NodeOffset(v) => ScriptNodeDataC { offset: v }
-                }
-            };
+            node_out.node_data = ScriptNodeDataC::new(node_in.get_data());
         }
     }
 
@@ -479,3 +640,590 @@ pub unsafe extern "C" fn riat_script_data_get_globals(global_data: *const Compil
 
     count
 }
+
+/// Get statistics on the size of the script data.
+///
+/// # Requirements
+///
+/// The `script_data` parameter must point to a valid [`CompiledScriptData`] or else **undefined behavior** will occur.
+#[no_mangle]
+pub unsafe extern "C" fn riat_script_data_get_statistics(script_data: *const CompiledScriptData) -> ScriptDataStatistics {
+    (*script_data).get_statistics()
+}
+
+/// Source map entry C struct.
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct SourceMapEntryC {
+    /// Pointer to a null terminated string containing the file name for the node.
+    pub file: *const c_char,
+
+    /// Line the node occured on.
+    pub line: usize,
+
+    /// Column the node occured on.
+    pub column: usize,
+
+    /// Whether the node belongs to a script or a global
+    pub owner_type: SymbolType,
+
+    /// Index of the script or global the node belongs to
+    pub owner_index: usize
+}
+
+/// Get where the node at `node_index` came from.
+///
+/// Return `true` and write the entry to `entry` if the node exists. Otherwise, return `false`.
+///
+/// # Requirements
+///
+/// If any of these requirements are not met, **undefined behavior** will occur:
+/// * The `script_data` parameter must point to a valid [`CompiledScriptData`].
+/// * The `entry` parameter must point to a valid [`SourceMapEntryC`].
+/// * If [`riat_script_data_free`] is called, the resulting entry will no longer be valid, thus no pointers may be dereferenced after this.
+#[no_mangle]
+pub unsafe extern "C" fn riat_script_data_get_source_map_entry(script_data: *const CompiledScriptData, node_index: usize, entry: *mut SourceMapEntryC) -> bool {
+    let entry_in = match (*script_data).get_source_map_entry(node_index) {
+        Some(n) => n,
+        None => return false
+    };
+
+    *entry = SourceMapEntryC {
+        file: (*script_data).get_files()[entry_in.file].as_ptr(),
+        line: entry_in.line,
+        column: entry_in.column,
+        owner_type: entry_in.owner_type,
+        owner_index: entry_in.owner_index
+    };
+
+    true
+}
+
+/// Write the query results to `nodes` if it is non-null, returning how many there are.
+unsafe fn write_node_indices(results: Vec<usize>, nodes: *mut usize) -> usize {
+    if !nodes.is_null() {
+        std::ptr::copy_nonoverlapping(results.as_ptr(), nodes, results.len());
+    }
+    results.len()
+}
+
+/// Find each node that calls the engine function or script named `function`, such as `ai_place`.
+///
+/// Return the number of nodes. Write this many node indices to an array pointed to by `nodes` if `nodes` is non-null. Use
+/// [`riat_script_data_get_source_map_entry`] to find where each call is and which script or global it is in.
+///
+/// # Requirements
+///
+/// If any of these requirements are not met, **undefined behavior** will occur:
+/// * The `script_data` parameter must point to a valid [`CompiledScriptData`].
+/// * The `function` parameter must point to a valid null-terminated C string.
+/// * The `nodes` parameter must point to a valid array of `size_t` long enough to hold the result of this function or be null. To query the number of nodes, run this function with this parameter set to null.
+#[no_mangle]
+pub unsafe extern "C" fn riat_script_data_who_calls(script_data: *const CompiledScriptData, function: *const c_char, nodes: *mut usize) -> usize {
+    let function = CStr::from_ptr(function).to_string_lossy();
+    write_node_indices((*script_data).who_calls(&function), nodes)
+}
+
+/// Find each `set` node that writes the global named `global`, such as `mission_state`.
+///
+/// Return the number of nodes. Write this many node indices to an array pointed to by `nodes` if `nodes` is non-null. Use
+/// [`riat_script_data_get_source_map_entry`] to find where each write is and which script or global it is in.
+///
+/// # Requirements
+///
+/// If any of these requirements are not met, **undefined behavior** will occur:
+/// * The `script_data` parameter must point to a valid [`CompiledScriptData`].
+/// * The `global` parameter must point to a valid null-terminated C string.
+/// * The `nodes` parameter must point to a valid array of `size_t` long enough to hold the result of this function or be null. To query the number of nodes, run this function with this parameter set to null.
+#[no_mangle]
+pub unsafe extern "C" fn riat_script_data_who_writes(script_data: *const CompiledScriptData, global: *const c_char, nodes: *mut usize) -> usize {
+    let global = CStr::from_ptr(global).to_string_lossy();
+    write_node_indices((*script_data).who_writes(&global), nodes)
+}
+
+/// Kind of string held by a [`ScriptDataStrings`].
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub enum StringKindC {
+    /// File name, indexed like [`CompiledScriptData::get_files`]
+    File,
+
+    /// Script name, indexed like [`riat_script_data_get_scripts`]
+    ScriptName,
+
+    /// Global name, indexed like [`riat_script_data_get_globals`]
+    GlobalName,
+
+    /// Node string data, indexed like [`riat_script_data_get_nodes`]
+    NodeStringData,
+
+    /// Warning file name, indexed like [`riat_script_data_get_warnings`]
+    WarningFile,
+
+    /// Warning message, indexed like [`riat_script_data_get_warnings`]
+    WarningMessage
+}
+
+/// Copy of all strings in a [`CompiledScriptData`] which is reference counted and does not depend on the script data's lifetime.
+pub struct ScriptDataStrings {
+    files: Vec<CString>,
+    script_names: Vec<CString>,
+    global_names: Vec<CString>,
+    node_string_data: Vec<Option<CString>>,
+    warning_files: Vec<CString>,
+    warning_messages: Vec<CString>
+}
+
+impl ScriptDataStrings {
+    fn new(script_data: &CompiledScriptData) -> Self {
+        Self {
+            files: script_data.get_files().to_owned(),
+            script_names: script_data.get_scripts().iter().map(|s| s.get_name_cstr().to_owned()).collect(),
+            global_names: script_data.get_globals().iter().map(|g| g.get_name_cstr().to_owned()).collect(),
+            node_string_data: script_data.get_nodes().iter().map(|n| n.get_string_data_cstr().map(CStr::to_owned)).collect(),
+            warning_files: script_data.get_warnings().iter().map(|w| w.get_file_cstr().to_owned()).collect(),
+            warning_messages: script_data.get_warnings().iter().map(|w| w.get_message_cstr().to_owned()).collect()
+        }
+    }
+
+    fn get(&self, kind: StringKindC, index: usize) -> Option<&CStr> {
+        match kind {
+            StringKindC::File => self.files.get(index).map(CString::as_c_str),
+            StringKindC::ScriptName => self.script_names.get(index).map(CString::as_c_str),
+            StringKindC::GlobalName => self.global_names.get(index).map(CString::as_c_str),
+            StringKindC::NodeStringData => self.node_string_data.get(index)?.as_deref(),
+            StringKindC::WarningFile => self.warning_files.get(index).map(CString::as_c_str),
+            StringKindC::WarningMessage => self.warning_messages.get(index).map(CString::as_c_str)
+        }
+    }
+}
+
+/// Copy all strings out of the script data and return a pointer to the copy with a reference count of 1.
+///
+/// Unlike the pointers in the structs returned by other functions, the copied strings remain valid after [`riat_script_data_free`] is
+/// called, as long as a reference is held. This makes them safe to hold onto from garbage collected languages.
+///
+/// # Requirements
+///
+/// The resulting pointer must be released with [`riat_strings_release`] or else a memory leak will occur.
+///
+/// The `script_data` parameter must point to a valid [`CompiledScriptData`] or else **undefined behavior** will occur.
+#[no_mangle]
+pub unsafe extern "C" fn riat_script_data_copy_strings(script_data: *const CompiledScriptData) -> *const ScriptDataStrings {
+    Arc::into_raw(Arc::new(ScriptDataStrings::new(&*script_data)))
+}
+
+/// Increment the reference count of the strings.
+///
+/// # Requirements
+///
+/// Each call must be balanced with a call to [`riat_strings_release`] or else a memory leak will occur.
+///
+/// The `strings` parameter must point to a valid [`ScriptDataStrings`] returned by [`riat_script_data_copy_strings`] or else
+/// **undefined behavior** will occur.
+#[no_mangle]
+pub unsafe extern "C" fn riat_strings_retain(strings: *const ScriptDataStrings) {
+    Arc::increment_strong_count(strings)
+}
+
+/// Decrement the reference count of the strings, freeing them once no references remain.
+///
+/// # Requirements
+///
+/// If any of these requirements are not met, **undefined behavior** will occur:
+/// * The `strings` parameter must point to a valid [`ScriptDataStrings`] returned by [`riat_script_data_copy_strings`] or be null.
+/// * If the strings are freed, no pointers returned by [`riat_strings_get`] for them may be dereferenced after this.
+#[no_mangle]
+pub unsafe extern "C" fn riat_strings_release(strings: *const ScriptDataStrings) {
+    if !strings.is_null() {
+        Arc::decrement_strong_count(strings)
+    }
+}
+
+/// Get a null terminated string of the given kind at the given index.
+///
+/// Return null if the index is out of bounds or, for node string data, if the node has no string data.
+///
+/// # Requirements
+///
+/// If any of these requirements are not met, **undefined behavior** will occur:
+/// * The `strings` parameter must point to a valid [`ScriptDataStrings`] returned by [`riat_script_data_copy_strings`].
+/// * The `kind` parameter must be a valid [`StringKindC`].
+/// * The resulting pointer is only valid as long as a reference to the strings is held.
+#[no_mangle]
+pub unsafe extern "C" fn riat_strings_get(strings: *const ScriptDataStrings, kind: StringKindC, index: usize) -> *const c_char {
+    match (*strings).get(kind, index) {
+        Some(n) => n.as_ptr(),
+        None => std::ptr::null()
+    }
+}
+
+/// Write the string as null terminated UTF-16 to `buffer` if it is non-null, truncating it to fit in `buffer_length` code units.
+///
+/// Return the length of the string in UTF-16 code units, not including the null terminator.
+unsafe fn write_utf16(string: &str, buffer: *mut u16, buffer_length: usize) -> usize {
+    let utf16: Vec<u16> = string.encode_utf16().collect();
+
+    if !buffer.is_null() && buffer_length > 0 {
+        let written = utf16.len().min(buffer_length - 1);
+        std::ptr::copy_nonoverlapping(utf16.as_ptr(), buffer, written);
+        *buffer.add(written) = 0;
+    }
+
+    utf16.len()
+}
+
+/// Get the name of the script at `index` as UTF-16.
+///
+/// Return the length of the name in UTF-16 code units, not including the null terminator. If `buffer` is non-null, write the name to it
+/// with a null terminator, truncating it to fit in `buffer_length` code units. To query the length, run this function with `buffer` set
+/// to null.
+///
+/// # Requirements
+///
+/// If any of these requirements are not met, **undefined behavior** will occur:
+/// * The `script_data` parameter must point to a valid [`CompiledScriptData`].
+/// * The `buffer` parameter must point to a valid array of at least `buffer_length` code units or be null.
+/// * The `index` parameter must be less than the number of scripts or else a panic will occur which may result in UB.
+#[no_mangle]
+pub unsafe extern "C" fn riat_script_data_get_script_name_utf16(script_data: *const CompiledScriptData, index: usize, buffer: *mut u16, buffer_length: usize) -> usize {
+    write_utf16((*script_data).get_scripts()[index].get_name(), buffer, buffer_length)
+}
+
+/// Get the name of the global at `index` as UTF-16.
+///
+/// This works the same as [`riat_script_data_get_script_name_utf16`].
+///
+/// # Requirements
+///
+/// If any of these requirements are not met, **undefined behavior** will occur:
+/// * The `script_data` parameter must point to a valid [`CompiledScriptData`].
+/// * The `buffer` parameter must point to a valid array of at least `buffer_length` code units or be null.
+/// * The `index` parameter must be less than the number of globals or else a panic will occur which may result in UB.
+#[no_mangle]
+pub unsafe extern "C" fn riat_script_data_get_global_name_utf16(script_data: *const CompiledScriptData, index: usize, buffer: *mut u16, buffer_length: usize) -> usize {
+    write_utf16((*script_data).get_globals()[index].get_name(), buffer, buffer_length)
+}
+
+/// Get the name of the file at `index` as UTF-16.
+///
+/// This works the same as [`riat_script_data_get_script_name_utf16`].
+///
+/// # Requirements
+///
+/// If any of these requirements are not met, **undefined behavior** will occur:
+/// * The `script_data` parameter must point to a valid [`CompiledScriptData`].
+/// * The `buffer` parameter must point to a valid array of at least `buffer_length` code units or be null.
+/// * The `index` parameter must be less than the number of files or else a panic will occur which may result in UB.
+#[no_mangle]
+pub unsafe extern "C" fn riat_script_data_get_file_name_utf16(script_data: *const CompiledScriptData, index: usize, buffer: *mut u16, buffer_length: usize) -> usize {
+    write_utf16(&(*script_data).get_files()[index].to_string_lossy(), buffer, buffer_length)
+}
+
+/// Get the type of the script at `index`.
+///
+/// # Requirements
+///
+/// If any of these requirements are not met, **undefined behavior** will occur:
+/// * The `script_data` parameter must point to a valid [`CompiledScriptData`].
+/// * The `index` parameter must be less than the number of scripts or else a panic will occur which may result in UB.
+#[no_mangle]
+pub unsafe extern "C" fn riat_script_data_get_script_type(script_data: *const CompiledScriptData, index: usize) -> ScriptType {
+    (*script_data).get_scripts()[index].get_type()
+}
+
+/// Get the return type of the script at `index`.
+///
+/// # Requirements
+///
+/// If any of these requirements are not met, **undefined behavior** will occur:
+/// * The `script_data` parameter must point to a valid [`CompiledScriptData`].
+/// * The `index` parameter must be less than the number of scripts or else a panic will occur which may result in UB.
+#[no_mangle]
+pub unsafe extern "C" fn riat_script_data_get_script_return_type(script_data: *const CompiledScriptData, index: usize) -> ValueType {
+    (*script_data).get_scripts()[index].get_value_type()
+}
+
+/// Get the index of the first node of the script at `index`.
+///
+/// # Requirements
+///
+/// If any of these requirements are not met, **undefined behavior** will occur:
+/// * The `script_data` parameter must point to a valid [`CompiledScriptData`].
+/// * The `index` parameter must be less than the number of scripts or else a panic will occur which may result in UB.
+#[no_mangle]
+pub unsafe extern "C" fn riat_script_data_get_script_first_node(script_data: *const CompiledScriptData, index: usize) -> usize {
+    (*script_data).get_scripts()[index].get_first_node_index()
+}
+
+/// Get the value type of the global at `index`.
+///
+/// # Requirements
+///
+/// If any of these requirements are not met, **undefined behavior** will occur:
+/// * The `script_data` parameter must point to a valid [`CompiledScriptData`].
+/// * The `index` parameter must be less than the number of globals or else a panic will occur which may result in UB.
+#[no_mangle]
+pub unsafe extern "C" fn riat_script_data_get_global_value_type(script_data: *const CompiledScriptData, index: usize) -> ValueType {
+    (*script_data).get_globals()[index].get_value_type()
+}
+
+/// Get the index of the first node of the global at `index`.
+///
+/// # Requirements
+///
+/// If any of these requirements are not met, **undefined behavior** will occur:
+/// * The `script_data` parameter must point to a valid [`CompiledScriptData`].
+/// * The `index` parameter must be less than the number of globals or else a panic will occur which may result in UB.
+#[no_mangle]
+pub unsafe extern "C" fn riat_script_data_get_global_first_node(script_data: *const CompiledScriptData, index: usize) -> usize {
+    (*script_data).get_globals()[index].get_first_node_index()
+}
+
+/// Copy the message of the error as UTF-16.
+///
+/// This works the same as [`riat_script_data_get_script_name_utf16`], but the copy remains valid after the error is freed.
+///
+/// # Requirements
+///
+/// If any of these requirements are not met, **undefined behavior** will occur:
+/// * The `error` parameter must point to a valid [`CompileErrorC`] initialized by a rat_in_a_tubec function which has not been freed.
+/// * The `buffer` parameter must point to a valid array of at least `buffer_length` code units or be null.
+#[no_mangle]
+pub unsafe extern "C" fn riat_error_copy_message_utf16(error: *const CompileErrorC, buffer: *mut u16, buffer_length: usize) -> usize {
+    write_utf16(&CStr::from_ptr((*error).message).to_string_lossy(), buffer, buffer_length)
+}
+
+/// Script node data kind C enum, which is the member of [`ScriptNodeDataC`] that is valid.
+#[repr(u16)]
+#[derive(Copy, Clone)]
+pub enum ScriptNodeDataKindC {
+    None = 0,
+    Long = 1,
+    Short = 2,
+    Boolean = 3,
+    Real = 4,
+    NodeOffset = 5
+}
+
+impl ScriptNodeDataKindC {
+    fn new(data: Option<NodeData>) -> Self {
+        match data {
+            None => Self::None,
+            Some(NodeData::Long(_)) => Self::Long,
+            Some(NodeData::Short(_)) => Self::Short,
+            Some(NodeData::Boolean(_)) => Self::Boolean,
+            Some(NodeData::Real(_)) => Self::Real,
+            Some(NodeData::NodeOffset(_)) => Self::NodeOffset
+        }
+    }
+}
+
+/// Script node C struct with explicit validity flags instead of sentinel values.
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct ScriptNodeV2C {
+    /// Pointer to a null terminated string containing the file name for the node.
+    pub file: *const c_char,
+
+    /// Line the node occured on.
+    pub line: usize,
+
+    /// Column the node occured on.
+    pub column: usize,
+
+    /// Pointer to a null terminated string containing the string data if `has_string_data` is set. Otherwise, this is null.
+    pub string_data: *const c_char,
+
+    /// Whether or not the node has string data
+    pub has_string_data: bool,
+
+    /// Index union (equal to type unless it's a function call or function name)
+    pub index_union: u16,
+
+    /// Value type of the node
+    pub value_type: ValueType,
+
+    /// Type of the node
+    pub node_type: NodeTypeC,
+
+    /// Member of `node_data` that is valid
+    pub data_kind: ScriptNodeDataKindC,
+
+    /// Data of the node, which is zeroed if `data_kind` is [`ScriptNodeDataKindC::None`]
+    pub node_data: ScriptNodeDataC,
+
+    /// Whether or not the node has a next node
+    pub has_next_node: bool,
+
+    /// Offset to the next node if `has_next_node` is set. Otherwise, this is 0.
+    pub next_node: usize
+}
+
+impl ScriptNodeV2C {
+    fn new(script_data: &CompiledScriptData, node: &CompiledNode) -> Self {
+        let data = node.get_data();
+        let value_type = node.get_value_type();
+
+        Self {
+            file: script_data.get_files()[node.get_file()].as_ptr(),
+            line: node.get_line(),
+            column: node.get_column(),
+            string_data: node.get_string_data_cstr().map(CStr::as_ptr).unwrap_or(std::ptr::null()),
+            has_string_data: node.get_string_data_cstr().is_some(),
+            index_union: node.get_index().unwrap_or(value_type as u16),
+            value_type,
+            node_type: NodeTypeC::new(node.get_type()),
+            data_kind: ScriptNodeDataKindC::new(data),
+            node_data: match data {
+                Some(_) => ScriptNodeDataC::new(data),
+                None => ScriptNodeDataC { offset: 0 }
+            },
+            has_next_node: node.get_next_node_index().is_some(),
+            next_node: node.get_next_node_index().unwrap_or(0)
+        }
+    }
+}
+
+/// Get all nodes from the script compilation as [`ScriptNodeV2C`] structs.
+///
+/// This works the same as [`riat_script_data_get_nodes`].
+///
+/// # Requirements
+///
+/// If any of these requirements are not met, **undefined behavior** will occur:
+/// * The `script_data` parameter must point to a valid [`CompiledScriptData`].
+/// * The `nodes` parameter must point to a valid array of [`ScriptNodeV2C`] long enough to hold the result of this function or be null. To query the number of nodes, run this function with this parameter set to null.
+/// * If [`riat_script_data_free`] is called, the resulting nodes will no longer be valid, thus no pointers may be dereferenced after this.
+#[no_mangle]
+pub unsafe extern "C" fn riat_script_data_get_nodes_v2(script_data: *const CompiledScriptData, nodes: *mut ScriptNodeV2C) -> usize {
+    let all_nodes = (*script_data).get_nodes();
+
+    if !nodes.is_null() {
+        for (i, node) in all_nodes.iter().enumerate() {
+            *nodes.add(i) = ScriptNodeV2C::new(&*script_data, node);
+        }
+    }
+
+    all_nodes.len()
+}
+
+/// Get the node at `node_index` as a [`ScriptNodeV2C`] struct.
+///
+/// Return `true` and write the node to `node` if the node exists. Otherwise, return `false`.
+///
+/// # Requirements
+///
+/// If any of these requirements are not met, **undefined behavior** will occur:
+/// * The `script_data` parameter must point to a valid [`CompiledScriptData`].
+/// * The `node` parameter must point to a valid [`ScriptNodeV2C`].
+/// * If [`riat_script_data_free`] is called, the resulting node will no longer be valid, thus no pointers may be dereferenced after this.
+#[no_mangle]
+pub unsafe extern "C" fn riat_script_data_get_node_v2(script_data: *const CompiledScriptData, node_index: usize, node: *mut ScriptNodeV2C) -> bool {
+    match (*script_data).get_nodes().get(node_index) {
+        Some(n) => {
+            *node = ScriptNodeV2C::new(&*script_data, n);
+            true
+        },
+        None => false
+    }
+}
+
+/// Script node data C struct with an explicit kind and storage wide enough for every kind, as returned by
+/// [`riat_script_data_get_node_data`].
+///
+/// More kinds may be added without changing this struct, so unknown kinds should be treated like [`ScriptNodeDataKindC::None`].
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct NodeDataC {
+    /// Kind of data the node has
+    pub kind: ScriptNodeDataKindC,
+
+    /// Value if the kind is long, short, boolean (0 or 1), or node offset. Otherwise, this is 0.
+    pub integer: i64,
+
+    /// Value if the kind is real. Otherwise, this is 0.
+    pub real: f64
+}
+
+impl NodeDataC {
+    fn new(data: Option<NodeData>) -> Self {
+        let (integer, real) = match data {
+            None => (0, 0.0),
+            Some(NodeData::Long(v)) => (v as i64, 0.0),
+            Some(NodeData::Short(v)) => (v as i64, 0.0),
+            Some(NodeData::Boolean(v)) => (v as i64, 0.0),
+            Some(NodeData::Real(v)) => (0, v as f64),
+            Some(NodeData::NodeOffset(v)) => (v as i64, 0.0)
+        };
+        Self { kind: ScriptNodeDataKindC::new(data), integer, real }
+    }
+}
+
+/// Get the data of the node at `node_index` as a [`NodeDataC`] struct.
+///
+/// Return `true` and write the data to `data` if the node exists. Otherwise, return `false`.
+///
+/// # Requirements
+///
+/// If any of these requirements are not met, **undefined behavior** will occur:
+/// * The `script_data` parameter must point to a valid [`CompiledScriptData`].
+/// * The `data` parameter must point to a valid [`NodeDataC`].
+#[no_mangle]
+pub unsafe extern "C" fn riat_script_data_get_node_data(script_data: *const CompiledScriptData, node_index: usize, data: *mut NodeDataC) -> bool {
+    match (*script_data).get_nodes().get(node_index) {
+        Some(n) => {
+            *data = NodeDataC::new(n.get_data());
+            true
+        },
+        None => false
+    }
+}
+
+/// Last error of a compiler, along with the [`CompileErrorC`] that points to it.
+struct LastError {
+    _error: Box<CompileError>,
+    error_c: CompileErrorC
+}
+
+thread_local! {
+    /// Last error of each compiler on this thread, keyed by the compiler's address.
+    static LAST_ERRORS: RefCell<HashMap<usize, Box<LastError>>> = RefCell::new(HashMap::new());
+}
+
+fn set_last_error(compiler: *const Compiler, error: &CompileError) {
+    let error = Box::new(error.clone());
+    let error_c = CompileErrorC::new(&error);
+    LAST_ERRORS.with(|e| e.borrow_mut().insert(compiler as usize, Box::new(LastError { _error: error, error_c })));
+}
+
+/// Get the last error returned by a function called with the compiler on this thread.
+///
+/// Return null if no function called with the compiler on this thread has failed since the compiler was allocated or
+/// [`riat_clear_last_error`] was called. This is useful for bindings that cannot easily pass an error out-parameter.
+///
+/// The error must NOT be freed with [`riat_error_free`], as it is owned by the compiler.
+///
+/// # Requirements
+///
+/// If any of these requirements are not met, **undefined behavior** will occur:
+/// * The `compiler` parameter must point to a valid [`Compiler`].
+/// * The resulting error will no longer be valid once the compiler fails again on this thread, [`riat_clear_last_error`] is called, or
+///   [`riat_compiler_free`] is called, thus no pointers may be dereferenced after this.
+#[no_mangle]
+pub unsafe extern "C" fn riat_get_last_error(compiler: *const Compiler) -> *const CompileErrorC {
+    LAST_ERRORS.with(|e| match e.borrow().get(&(compiler as usize)) {
+        Some(n) => &n.error_c as *const CompileErrorC,
+        None => std::ptr::null()
+    })
+}
+
+/// Clear the last error of the compiler on this thread.
+///
+/// # Requirements
+///
+/// The `compiler` parameter must point to a valid [`Compiler`] or else **undefined behavior** will occur.
+#[no_mangle]
+pub unsafe extern "C" fn riat_clear_last_error(compiler: *const Compiler) {
+    LAST_ERRORS.with(|e| e.borrow_mut().remove(&(compiler as usize)));
+}
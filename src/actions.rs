@@ -0,0 +1,308 @@
+use super::*;
+use super::compile::callable_engine_functions_and_globals;
+use super::editor::offset_of_position;
+use super::token::{build_token_tree_recovering, TokenizedScript, TreeOptions, MAXIMUM_BLOCK_DEPTH};
+use std::collections::BTreeMap;
+
+/// Kind of change made by a [`CodeAction`].
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum CodeActionKind {
+    /// Move every global before the scripts, keeping their order otherwise
+    SortGlobalsFirst,
+
+    /// Reorder scripts so scripts of the same type are together, in the order startup, dormant, continuous, static, then stub
+    GroupScriptsByType,
+
+    /// Lowercase every token that the compiler would lowercase
+    NormalizeCase,
+
+    /// Turn a chain of `if` blocks where each else branch is another `if` into a `cond` block
+    ConvertToCond
+}
+
+/// Replacement of a span of script data.
+#[derive(Clone, PartialEq, Debug)]
+pub struct TextEdit {
+    /// Line and column of the start of the span, starting at 1.
+    pub start: (usize, usize),
+
+    /// Line and column just past the end of the span.
+    pub end: (usize, usize),
+
+    /// Text to replace the span with.
+    pub text: String
+}
+
+/// Change to script data found by [`Compiler::code_actions`].
+#[derive(Clone, PartialEq, Debug)]
+pub struct CodeAction {
+    /// Kind of change.
+    pub kind: CodeActionKind,
+
+    /// Short description of the change to show to the user.
+    pub title: String,
+
+    /// Edits making the change, which do not overlap and are sorted by position.
+    pub edits: Vec<TextEdit>
+}
+
+impl Compiler {
+    /// Get the changes that can be made to the script data to organize it, given the position (line, column) of the cursor.
+    ///
+    /// Reordering globals and scripts and normalizing case apply to the whole script data and are only returned if they would change
+    /// anything. Converting to `cond` applies to the chain of `if` blocks at the position. Globals and scripts are moved along with
+    /// comments on the lines right before them and on the same line after them. Nothing is reordered if a block is unterminated.
+    pub fn code_actions(&self, script: &[u8], position: (usize, usize)) -> Vec<CodeAction> {
+        let TokenizedScript { tokens, end, comments, .. } = match self.tokenize("", self.files.len(), script, self.encoding) {
+            Ok(n) => n,
+            Err(_) => return Vec::new()
+        };
+        let options = TreeOptions { allow_empty_blocks: true, close_unterminated: true, maximum_depth: None };
+        let (tree, errors) = build_token_tree_recovering("", tokens, end, options);
+
+        let mut actions = Vec::new();
+        if errors.is_empty() && !tree.iter().any(|t| t.synthetic) {
+            let comments: Vec<(usize, usize)> = comments.iter().map(|(start, end)| (offset_of_position(script, *start), offset_of_position(script, *end))).collect();
+            let chunks = top_level_chunks(script, &tree, &comments);
+            let kinds: Vec<Option<BlockKind>> = tree.iter().map(block_kind).collect();
+
+            let mut order: Vec<usize> = (0..tree.len()).collect();
+            order.sort_by_key(|&i| kinds[i] != Some(BlockKind::Global));
+            if let Some(edit) = reorder_chunks(script, &chunks, &order) {
+                actions.push(CodeAction { kind: CodeActionKind::SortGlobalsFirst, title: "Move globals before scripts".to_owned(), edits: vec![edit] });
+            }
+
+            // Scripts take the places of other scripts, so globals stay where they are
+            let slots: Vec<usize> = (0..tree.len()).filter(|&i| matches!(kinds[i], Some(BlockKind::Script(_)))).collect();
+            let mut scripts = slots.clone();
+            scripts.sort_by_key(|&i| match kinds[i] {
+                Some(BlockKind::Script(script_type)) => script_type as u16,
+                _ => unreachable!()
+            });
+            let mut order: Vec<usize> = (0..tree.len()).collect();
+            for (slot, script) in slots.iter().zip(scripts) {
+                order[*slot] = script;
+            }
+            if let Some(edit) = reorder_chunks(script, &chunks, &order) {
+                actions.push(CodeAction { kind: CodeActionKind::GroupScriptsByType, title: "Group scripts by type".to_owned(), edits: vec![edit] });
+            }
+        }
+
+        let (functions, _) = callable_engine_functions_and_globals(self.target, &self.engine_definitions);
+        let mut edits = Vec::new();
+        for t in &tree {
+            lowercase_tokens(script, t, &functions, &mut edits);
+        }
+        if !edits.is_empty() {
+            actions.push(CodeAction { kind: CodeActionKind::NormalizeCase, title: "Normalize case".to_owned(), edits });
+        }
+
+        if let Some(edit) = convert_to_cond(script, &tree, position) {
+            actions.push(CodeAction { kind: CodeActionKind::ConvertToCond, title: "Convert to cond".to_owned(), edits: vec![edit] });
+        }
+
+        actions
+    }
+}
+
+/// What a top level block defines.
+#[derive(Copy, Clone, PartialEq)]
+enum BlockKind {
+    Global,
+    Script(ScriptType)
+}
+
+fn block_kind(token: &Token) -> Option<BlockKind> {
+    let children = token.children.as_ref()?;
+    match children.first()?.string.to_ascii_lowercase().as_str() {
+        "global" => Some(BlockKind::Global),
+        "script" => ScriptType::from_str(&children.get(1)?.string.to_ascii_lowercase()).map(BlockKind::Script),
+        _ => None
+    }
+}
+
+/// Byte offsets of a top level token and the comments that are moved along with it.
+struct Chunk {
+    start: usize,
+    end: usize
+}
+
+/// Get the byte offset of the end of the line the offset is in, excluding the line break.
+fn line_end(script: &[u8], offset: usize) -> usize {
+    script[offset..].iter().position(|c| matches!(c, b'\r' | b'\n')).map(|n| offset + n).unwrap_or(script.len())
+}
+
+/// Get the line and column of a byte offset of the script data.
+fn position_of_offset(script: &[u8], offset: usize) -> (usize, usize) {
+    let (mut line, mut column) = (1, 1);
+    for (i, c) in script[..offset].iter().enumerate() {
+        match c {
+            b'\n' if i > 0 && script[i - 1] == b'\r' => (),
+            b'\n' | b'\r' => {
+                line += 1;
+                column = 1;
+            },
+            _ => column += 1
+        }
+    }
+    (line, column)
+}
+
+/// Find the span of each top level token, including comments on their own lines right before it and a comment after it on its last line.
+///
+/// Comments are given as the byte offsets of their start and end.
+fn top_level_chunks(script: &[u8], tree: &[Token], comments: &[(usize, usize)]) -> Vec<Chunk> {
+    let is_blank = |bytes: &[u8]| bytes.iter().all(|c| matches!(c, b' ' | b'\t'));
+
+    // A comment which is the only thing on its line, or the only thing after the token on its line
+    let comment_ending_line = |start: usize| -> Option<usize> {
+        let end_of_line = line_end(script, start);
+        let comment_start = start + script[start..end_of_line].iter().position(|c| !matches!(c, b' ' | b'\t'))?;
+        let comment_end = comments.iter().find(|c| c.0 == comment_start)?.1;
+        (comment_end <= end_of_line && is_blank(&script[comment_end..end_of_line])).then_some(end_of_line)
+    };
+
+    tree.iter().enumerate().map(|(i, t)| {
+        let mut start = offset_of_position(script, (t.line, t.column));
+        let mut end = offset_of_position(script, t.end);
+
+        let previous_line = if i == 0 { 0 } else { tree[i - 1].end.0 };
+        let line_start = offset_of_position(script, (t.line, 1));
+        if previous_line < t.line && is_blank(&script[line_start..start]) {
+            start = line_start;
+            let mut line = t.line - 1;
+            while line > previous_line {
+                let line_start = offset_of_position(script, (line, 1));
+                if comment_ending_line(line_start).is_none() {
+                    break
+                }
+                start = line_start;
+                line -= 1;
+            }
+        }
+
+        if tree.get(i + 1).map(|n| n.line > t.end.0).unwrap_or(true) {
+            if let Some(n) = comment_ending_line(end) {
+                end = n;
+            }
+        }
+
+        Chunk { start, end }
+    }).collect()
+}
+
+/// Get an edit moving the chunks to the given order, or `None` if it does not change anything.
+///
+/// Text between the chunks stays where it is.
+fn reorder_chunks(script: &[u8], chunks: &[Chunk], order: &[usize]) -> Option<TextEdit> {
+    if order.iter().enumerate().all(|(i, o)| i == *o) {
+        return None
+    }
+
+    let mut text = Vec::new();
+    for (i, o) in order.iter().enumerate() {
+        if i > 0 {
+            text.extend_from_slice(&script[chunks[i - 1].end..chunks[i].start]);
+        }
+        text.extend_from_slice(&script[chunks[*o].start..chunks[*o].end]);
+    }
+
+    Some(TextEdit {
+        start: position_of_offset(script, chunks.first()?.start),
+        end: position_of_offset(script, chunks.last()?.end),
+        text: String::from_utf8_lossy(&text).into_owned()
+    })
+}
+
+/// Add an edit for each token in the block that is not lowercase, except for parameters of engine functions that allow uppercase.
+fn lowercase_tokens(script: &[u8], token: &Token, functions: &BTreeMap<&str, &dyn CallableFunction>, edits: &mut Vec<TextEdit>) {
+    let children = match token.children.as_ref() {
+        Some(n) => n,
+        None => {
+            let lowercase = token.string.to_ascii_lowercase();
+            if lowercase != token.string {
+                let quoted = script.get(offset_of_position(script, (token.line, token.column))) == Some(&b'"');
+                edits.push(TextEdit {
+                    start: (token.line, token.column),
+                    end: token.end,
+                    text: if quoted { format!("\"{lowercase}\"") } else { lowercase }
+                });
+            }
+            return
+        }
+    };
+
+    let function = children.first()
+        .filter(|f| f.children.is_none())
+        .and_then(|f| functions.get(f.string.to_ascii_lowercase().as_str()));
+    for (i, c) in children.iter().enumerate() {
+        let uppercase_allowed = i > 0 && c.children.is_none() && function.map(|f| f.is_uppercase_allowed_for_parameter(i - 1)).unwrap_or(false);
+        if !uppercase_allowed {
+            lowercase_tokens(script, c, functions, edits);
+        }
+    }
+}
+
+/// Get the children of the block if it is a complete `if` block with or without an else branch.
+fn if_children(token: &Token) -> Option<&[Token]> {
+    let children = token.children.as_ref().filter(|_| !token.synthetic)?;
+    (matches!(children.len(), 3 | 4) && children[0].children.is_none() && children[0].string.eq_ignore_ascii_case("if")).then_some(children)
+}
+
+/// Get the else branch of the `if` block if it is another `if` block.
+fn else_if(token: &Token) -> Option<&Token> {
+    if_children(token)?.get(3).filter(|e| if_children(e).is_some())
+}
+
+/// Get an edit turning the chain of `if` blocks at the position into a `cond` block.
+///
+/// The innermost chain containing the position is converted from its first `if`.
+fn convert_to_cond(script: &[u8], tree: &[Token], position: (usize, usize)) -> Option<TextEdit> {
+    let mut path = Vec::<&Token>::new();
+    let mut siblings = tree;
+    while let Some(t) = siblings.iter().find(|t| t.children.is_some() && (t.line, t.column) <= position && position <= t.end) {
+        path.push(t);
+        siblings = t.children.as_ref().unwrap();
+    }
+
+    let head = (0..path.len()).rev().filter(|&i| if_children(path[i]).is_some()).find_map(|mut i| {
+        while i > 0 && else_if(path[i - 1]).map(|e| std::ptr::eq(e, path[i])).unwrap_or(false) {
+            i -= 1;
+        }
+        else_if(path[i]).map(|_| path[i])
+    })?;
+
+    let source = |token: &Token| String::from_utf8_lossy(&script[offset_of_position(script, (token.line, token.column))..offset_of_position(script, token.end)]).into_owned();
+
+    // A begin block is unwrapped, since each condition of a cond already takes several expressions
+    let expressions = |token: &Token| match token.children.as_deref() {
+        Some([begin, expressions @ ..]) if !expressions.is_empty() && begin.children.is_none() && begin.string.eq_ignore_ascii_case("begin") => {
+            expressions.iter().map(source).collect::<Vec<String>>().join(" ")
+        },
+        _ => source(token)
+    };
+
+    let mut clauses = Vec::new();
+    let mut current = head;
+    loop {
+        let children = if_children(current).unwrap();
+        clauses.push(format!("({} {})", source(&children[1]), expressions(&children[2])));
+        match children.get(3) {
+            Some(n) if if_children(n).is_some() => current = n,
+            Some(n) => {
+                clauses.push(format!("(true {})", expressions(n)));
+                break
+            },
+            None => break
+        }
+    }
+    if clauses.len() > MAXIMUM_BLOCK_DEPTH {
+        return None
+    }
+
+    let line_start = offset_of_position(script, (head.line, 1));
+    let indentation: String = script[line_start..].iter().take_while(|c| matches!(c, b' ' | b'\t')).map(|c| *c as char).collect();
+    let text = clauses.iter().fold("(cond".to_owned(), |text, c| format!("{text}\n{indentation}    {c}")) + ")";
+
+    Some(TextEdit { start: (head.line, head.column), end: head.end, text })
+}
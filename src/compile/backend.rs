@@ -0,0 +1,361 @@
+use super::*;
+
+/// Consumer of the typed node tree of each script and global, used with [`Compiler::compile_with_backend`].
+///
+/// This allows emitting to formats other than scenario script data (such as another virtual machine or another language) without
+/// reparsing [`CompiledScriptData`]. Scripts and globals are each emitted in index order, but scripts and globals may be interleaved.
+pub trait EmitBackend {
+    /// What the backend produces once everything is emitted.
+    type Output;
+
+    /// Emit a script.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the backend cannot emit the script, which stops compilation.
+    fn emit_script(&mut self, script: ScriptDefinition) -> Result<(), CompileError>;
+
+    /// Emit a global.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the backend cannot emit the global, which stops compilation.
+    fn emit_global(&mut self, global: GlobalDefinition) -> Result<(), CompileError>;
+
+    /// Finish emitting, returning the output.
+    fn finish(self) -> Self::Output;
+}
+
+/// Node of the typed node tree passed to an [`EmitBackend`].
+///
+/// Script, global, and engine function indices are already resolved, and all literals are parsed.
+#[derive(Copy, Clone)]
+pub struct TypedNode<'a> {
+    node: &'a Node,
+    files: &'a [String]
+}
+
+impl<'a> TypedNode<'a> {
+    pub(super) fn new(node: &'a Node, files: &'a [String]) -> TypedNode<'a> {
+        TypedNode { node, files }
+    }
+
+    /// Get the type of node.
+    pub fn get_type(&self) -> NodeType {
+        self.node.node_type
+    }
+
+    /// Get the value type of the node.
+    pub fn get_value_type(&self) -> ValueType {
+        self.node.value_type
+    }
+
+    /// Get the data of the node, if any.
+    ///
+    /// Function calls have no data, as their parameters are retrieved with [`TypedNode::get_parameters`].
+    pub fn get_data(&self) -> Option<NodeData> {
+        self.node.data
+    }
+
+    /// Get the string data of the node, if any.
+    ///
+    /// For function calls, this is the name of the function.
+    pub fn get_string_data(&self) -> Option<&'a str> {
+        self.node.string_data.as_deref()
+    }
+
+    /// Get the string data as it was written in the script data, if it was lowercased.
+    pub fn get_original_string_data(&self) -> Option<&'a str> {
+        self.node.original_string_data.as_deref()
+    }
+
+    /// Get the index of the function, script, or global, if any.
+    pub fn get_index(&self) -> Option<u16> {
+        self.node.index
+    }
+
+    /// Get the parameters of the function call in order. This is empty if the node is not a function call.
+    pub fn get_parameters(&self) -> impl Iterator<Item = TypedNode<'a>> {
+        let files = self.files;
+        self.node.parameters.iter().flatten().map(move |n| TypedNode::new(n, files))
+    }
+
+    /// Get the file index of the node.
+    pub fn get_file(&self) -> usize {
+        self.node.file
+    }
+
+    /// Get the name of the file of the node.
+    pub fn get_file_name(&self) -> &'a str {
+        &self.files[self.node.file]
+    }
+
+    /// Get the line of the node, starting at 1.
+    pub fn get_line(&self) -> usize {
+        self.node.line
+    }
+
+    /// Get the column of the node, starting at 1.
+    pub fn get_column(&self) -> usize {
+        self.node.column
+    }
+}
+
+/// Script passed to an [`EmitBackend`].
+#[derive(Copy, Clone)]
+pub struct ScriptDefinition<'a> {
+    script: &'a Script,
+    index: usize,
+    files: &'a [String]
+}
+
+impl<'a> ScriptDefinition<'a> {
+    /// Get the index of the script, including the script index base.
+    pub fn get_index(&self) -> usize {
+        self.index
+    }
+
+    /// Get the name of the script.
+    pub fn get_name(&self) -> &'a str {
+        &self.script.name
+    }
+
+    /// Get the type of the script.
+    pub fn get_type(&self) -> ScriptType {
+        self.script.script_type
+    }
+
+    /// Get the return type of the script.
+    pub fn get_return_type(&self) -> ValueType {
+        self.script.return_type
+    }
+
+    /// Get the name and value type of each parameter in order.
+    pub fn get_parameters(&self) -> impl Iterator<Item = (&'a str, ValueType)> {
+        self.script.parameters.iter().map(|p| (p.name.as_str(), p.value_type))
+    }
+
+    /// Get the root node of the script.
+    pub fn get_node(&self) -> TypedNode<'a> {
+        TypedNode::new(&self.script.node, self.files)
+    }
+
+    /// Get the file index of the script.
+    pub fn get_file(&self) -> usize {
+        self.script.original_token.file
+    }
+
+    /// Get the line of the script, starting at 1.
+    pub fn get_line(&self) -> usize {
+        self.script.original_token.line
+    }
+
+    /// Get the column of the script, starting at 1.
+    pub fn get_column(&self) -> usize {
+        self.script.original_token.column
+    }
+}
+
+/// Global passed to an [`EmitBackend`].
+#[derive(Copy, Clone)]
+pub struct GlobalDefinition<'a> {
+    global: &'a Global,
+    index: usize,
+    files: &'a [String]
+}
+
+impl<'a> GlobalDefinition<'a> {
+    /// Get the index of the global, including the global index base.
+    pub fn get_index(&self) -> usize {
+        self.index
+    }
+
+    /// Get the name of the global.
+    pub fn get_name(&self) -> &'a str {
+        &self.global.name
+    }
+
+    /// Get the value type of the global.
+    pub fn get_value_type(&self) -> ValueType {
+        self.global.value_type
+    }
+
+    /// Get the root node of the global.
+    pub fn get_node(&self) -> TypedNode<'a> {
+        TypedNode::new(&self.global.node, self.files)
+    }
+
+    /// Get the file index of the global.
+    pub fn get_file(&self) -> usize {
+        self.global.original_token.file
+    }
+
+    /// Get the line of the global, starting at 1.
+    pub fn get_line(&self) -> usize {
+        self.global.original_token.line
+    }
+
+    /// Get the column of the global, starting at 1.
+    pub fn get_column(&self) -> usize {
+        self.global.original_token.column
+    }
+}
+
+/// Backend which emits the scripts, globals, and flattened nodes of scenario script data.
+#[derive(Default)]
+pub(super) struct ScenarioEmitter {
+    scripts: Vec<CompiledScript>,
+    globals: Vec<CompiledGlobal>,
+    nodes: Vec<CompiledNode>
+}
+
+impl ScenarioEmitter {
+    /// Flatten the node into the node array, returning the index of the node.
+    pub(super) fn emit_node(&mut self, node: TypedNode) -> usize {
+        let string_data = node.get_string_data().map(|n| CString::new(n).unwrap());
+
+        // What type of node is it?
+        match node.get_type() {
+            NodeType::Primitive(primitive_type) => {
+                // Globals need to have string data set
+                debug_assert!((primitive_type != PrimitiveType::Global && primitive_type != PrimitiveType::Local) || string_data.is_some());
+
+                let result = self.nodes.len();
+                self.nodes.push(CompiledNode {
+                    node_type: node.get_type(),
+                    value_type: node.get_value_type(),
+                    data: node.get_data(),
+                    string_data,
+                    original_string_data: node.get_original_string_data().map(str::to_owned),
+                    next_node: None,
+                    index: node.get_index(),
+
+                    file: node.get_file(),
+                    column: node.get_column(),
+                    line: node.get_line()
+                });
+                result
+            },
+            NodeType::FunctionCall(_) => {
+                // First let's get this function call done and over with
+                let function_call_node = self.nodes.len();
+                let function_name_node = function_call_node + 1;
+                self.nodes.push(CompiledNode {
+                    node_type: node.get_type(),
+                    value_type: node.get_value_type(),
+                    data: Some(NodeData::NodeOffset(function_name_node)),
+                    string_data: None,
+                    original_string_data: None,
+                    next_node: None,
+                    index: node.get_index(),
+
+                    file: node.get_file(),
+                    column: node.get_column(),
+                    line: node.get_line()
+                });
+
+                // Next get the function name out of the way
+                self.nodes.push(CompiledNode {
+                    node_type: NodeType::Primitive(PrimitiveType::Static),
+                    value_type: ValueType::FunctionName,
+                    data: Some(NodeData::Long(0)),
+                    string_data,
+                    original_string_data: node.get_original_string_data().map(str::to_owned),
+                    next_node: None,
+                    index: node.get_index(),
+
+                    file: node.get_file(),
+                    column: node.get_column(),
+                    line: node.get_line()
+                });
+
+                // Let's get our parameters here now
+                let mut previous_node = function_name_node;
+                for p in node.get_parameters() {
+                    let next_node = self.emit_node(p);
+                    self.nodes[previous_node].next_node = Some(next_node);
+                    previous_node = next_node;
+                }
+
+                // Done
+                function_call_node
+            }
+        }
+    }
+}
+
+impl EmitBackend for ScenarioEmitter {
+    type Output = (Vec<CompiledScript>, Vec<CompiledGlobal>, Vec<CompiledNode>);
+
+    fn emit_script(&mut self, script: ScriptDefinition) -> Result<(), CompileError> {
+        let parameters = script.script.parameters.iter().map(|p| CompiledScriptParameter {
+            name: CString::new(p.name.as_str()).unwrap(),
+            value_type: p.value_type,
+            file: p.original_token.file,
+            column: p.original_token.column,
+            line: p.original_token.line,
+        }).collect();
+
+        let first_node = self.emit_node(script.get_node());
+        self.scripts.push(CompiledScript {
+            name: CString::new(script.get_name()).unwrap(),
+            value_type: script.get_return_type(),
+            script_type: script.get_type(),
+            first_node,
+            parameters,
+
+            file: script.get_file(),
+            column: script.get_column(),
+            line: script.get_line()
+        });
+        Ok(())
+    }
+
+    fn emit_global(&mut self, global: GlobalDefinition) -> Result<(), CompileError> {
+        let first_node = self.emit_node(global.get_node());
+        self.globals.push(CompiledGlobal {
+            name: CString::new(global.get_name()).unwrap(),
+            value_type: global.get_value_type(),
+            first_node,
+
+            file: global.get_file(),
+            column: global.get_column(),
+            line: global.get_line()
+        });
+        Ok(())
+    }
+
+    fn finish(self) -> Self::Output {
+        (self.scripts, self.globals, self.nodes)
+    }
+}
+
+impl Compiler {
+    /// Pass each script and global to the backend in the order their nodes are emitted.
+    ///
+    /// With [`OutputMode::SourceOrder`], this is the order the scripts and globals appear in the source. Otherwise, all scripts come
+    /// first.
+    pub(super) fn emit_definitions<B: EmitBackend>(&self, scripts: &[Script], globals: &[Global], backend: &mut B) -> Result<(), CompileError> {
+        let mut definitions: Vec<(SymbolType, usize)> = (0..scripts.len()).map(|i| (SymbolType::Script, i))
+            .chain((0..globals.len()).map(|i| (SymbolType::Global, i)))
+            .collect();
+        if self.output_mode == OutputMode::SourceOrder {
+            definitions.sort_by_key(|&(symbol_type, i)| {
+                let token = match symbol_type {
+                    SymbolType::Script => &scripts[i].original_token,
+                    SymbolType::Global => &globals[i].original_token
+                };
+                (token.file, token.line, token.column)
+            });
+        }
+
+        for (symbol_type, i) in definitions {
+            match symbol_type {
+                SymbolType::Script => backend.emit_script(ScriptDefinition { script: &scripts[i], index: self.script_index_base + i, files: &self.files })?,
+                SymbolType::Global => backend.emit_global(GlobalDefinition { global: &globals[i], index: self.global_index_base + i, files: &self.files })?
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,64 @@
+use super::*;
+
+/// Number of scripts and globals listed in a budget warning.
+const LISTED_CONSUMER_COUNT: usize = 3;
+
+/// Script or global that uses part of a budget.
+struct BudgetConsumer<'a> {
+    name: &'a str,
+    file: usize,
+    line: usize,
+    column: usize,
+    end: (usize, usize),
+    nodes: &'a [CompiledNode]
+}
+
+/// Get the length of the string data of the node, including the null terminator.
+pub(super) fn string_data_length(node: &CompiledNode) -> usize {
+    node.string_data.as_ref().map(|s| s.as_bytes_with_nul().len()).unwrap_or(0)
+}
+
+impl Compiler {
+    /// Warn if the emitted nodes or string data exceed the budget thresholds, listing the largest consumers.
+    ///
+    /// Each script and global's nodes are expected to be contiguous. Nodes already in the scenario, as set with
+    /// [`Compiler::set_index_bases`], count towards the node budget.
+    pub(super) fn check_budgets(&mut self, scripts: &[CompiledScript], globals: &[CompiledGlobal], nodes: &[CompiledNode]) {
+        let mut entries: Vec<(&str, usize, usize, usize, usize)> = scripts.iter().map(|s| (s.get_name(), s.file, s.line, s.column, s.first_node))
+            .chain(globals.iter().map(|g| (g.get_name(), g.file, g.line, g.column, g.first_node)))
+            .collect();
+        entries.sort_by_key(|e| e.4);
+
+        // Each script or global's nodes end where the next one's begin
+        let mut consumers: Vec<BudgetConsumer> = entries.iter().enumerate().map(|(i, &(name, file, line, column, first_node))| {
+            let end = entries.get(i + 1).map(|e| e.4).unwrap_or(nodes.len());
+            BudgetConsumer { name, file, line, column, end: (line, column), nodes: &nodes[first_node..end] }
+        }).collect();
+
+        let thresholds = self.budget_thresholds;
+        self.check_budget(&mut consumers, "node_budget", thresholds.nodes, self.target_limits().maximum_nodes(), self.node_index_base, |n| n.len());
+        self.check_budget(&mut consumers, "string_data_budget", thresholds.string_data, self.target_limits().maximum_string_data_length(), 0, |n| n.iter().map(string_data_length).sum());
+    }
+
+    fn check_budget(&mut self, consumers: &mut [BudgetConsumer], key: &'static str, threshold: Option<f64>, maximum: usize, existing: usize, usage: fn(&[CompiledNode]) -> usize) {
+        let threshold = match threshold {
+            Some(n) => n,
+            None => return
+        };
+
+        let total: usize = existing + consumers.iter().map(|c| usage(c.nodes)).sum::<usize>();
+        if (total as f64) < (maximum as f64) * threshold {
+            return
+        }
+
+        consumers.sort_by_key(|c| std::cmp::Reverse(usage(c.nodes)));
+        let largest = match consumers.first() {
+            Some(n) => n,
+            None => return
+        };
+
+        let listed: Vec<String> = consumers.iter().take(LISTED_CONSUMER_COUNT).map(|c| format!("'{}' ({})", c.name, usage(c.nodes))).collect();
+        let percentage = total as f64 / maximum as f64 * 100.0;
+        compile_warn!(self, largest, CompileErrorCode::Budget, message!(key, format!("{percentage:.0}"), total, maximum, listed.join(", ")));
+    }
+}
@@ -0,0 +1,71 @@
+use super::*;
+
+/// Estimated cost of evaluating a call to an engine function marked as expensive in the definitions, in addition to its nodes.
+const EXPENSIVE_FUNCTION_COST: usize = 10;
+
+/// Estimate of how much work evaluating a node does.
+#[derive(Default)]
+struct CostEstimate<'a> {
+    cost: usize,
+    expensive_calls: Vec<&'a Node>
+}
+
+/// Estimate the cost of evaluating the node, adding it to the estimate.
+///
+/// Every node evaluated costs 1, and every branch is assumed to be taken. Calls to static scripts include the cost of the script,
+/// unless the script is already being evaluated.
+fn estimate_cost<'a>(node: &'a Node, scripts: &'a [Script], definitions: &EngineDefinitions, calling: &mut Vec<&'a str>, estimate: &mut CostEstimate<'a>) {
+    estimate.cost += 1;
+
+    match node.node_type {
+        NodeType::FunctionCall(true) => {
+            let expensive = definitions.functions().any(|f| f.expensive && Some(&*f.name) == node.string_data.as_deref());
+            if expensive {
+                estimate.cost += EXPENSIVE_FUNCTION_COST;
+                estimate.expensive_calls.push(node);
+            }
+        },
+        NodeType::FunctionCall(false) => {
+            let name = node.string_data.as_deref().unwrap();
+            if let Some(script) = scripts.iter().find(|s| s.name == name && s.script_type == ScriptType::Static) {
+                if !calling.contains(&name) {
+                    calling.push(name);
+                    estimate_cost(&script.node, scripts, definitions, calling, estimate);
+                    calling.pop();
+                }
+            }
+        },
+        _ => ()
+    }
+
+    for p in node.parameters.iter().flatten() {
+        estimate_cost(p, scripts, definitions, calling, estimate);
+    }
+}
+
+impl Compiler {
+    /// Warn about each continuous script whose estimated cost exceeds the tick cost threshold, as it is evaluated every tick.
+    pub(super) fn check_tick_costs(&mut self, scripts: &[Script]) {
+        let threshold = match self.tick_cost_threshold {
+            Some(n) => n,
+            None => return
+        };
+
+        for s in scripts.iter().filter(|s| s.script_type == ScriptType::Continuous) {
+            let mut estimate = CostEstimate::default();
+            estimate_cost(&s.node, scripts, &self.engine_definitions, &mut vec![s.name.as_str()], &mut estimate);
+            if estimate.cost <= threshold {
+                continue
+            }
+
+            let token = &s.original_token;
+            let mut warning = CompileError::from_message(self.files[token.file].as_str(), token.line, token.column, CompileErrorType::Warning, message!("tick_cost", s.name, estimate.cost, threshold))
+                .with_end_position(token.end.0, token.end.1)
+                .with_code(CompileErrorCode::TickCost);
+            for call in estimate.expensive_calls {
+                warning = warning.with_related_location(self.files[call.file].as_str(), call.line, call.column, message!("expensive_call", call.string_data.as_deref().unwrap()));
+            }
+            self.push_warning(warning);
+        }
+    }
+}
@@ -0,0 +1,105 @@
+use super::*;
+
+/// Get the name of the value type as it is written in script data.
+fn type_name(value_type: ValueType) -> String {
+    value_type.as_str().replace(' ', "_")
+}
+
+/// Get the string data of the node as it was written, if known.
+fn source_string(node: &CompiledNode) -> Option<&str> {
+    node.get_original_string_data().or_else(|| node.get_string_data())
+}
+
+/// Get the start of the script's definition up to and including its name and parameters, such as `(script static short (f (short a))`.
+pub(super) fn script_header(script: &CompiledScript) -> String {
+    let mut header = format!("(script {}", script.script_type.as_str());
+    if !script.script_type.always_returns_void() {
+        header += &format!(" {}", type_name(script.value_type));
+    }
+    if script.parameters.is_empty() {
+        header += &format!(" {}", quote_name(script.get_name()));
+    }
+    else {
+        let parameters: Vec<String> = script.parameters.iter().map(|p| format!("({} {})", type_name(p.value_type), quote_name(p.get_name()))).collect();
+        header += &format!(" ({} {})", quote_name(script.get_name()), parameters.join(" "));
+    }
+    header
+}
+
+impl CompiledScriptData {
+    /// Decompile the script data back into HSC source, such as for extracting the scripts of a map.
+    ///
+    /// Globals are written first and then scripts, each in index order, with each expression of a script on its own line. Comments,
+    /// formatting, and anything removed when optimizing (such as `begin` blocks with one expression) can't be recovered, but compiling
+    /// the output for the same target results in the same nodes. Names and literals are written in their original case where it is
+    /// known.
+    pub fn to_source(&self) -> String {
+        let mut source = String::new();
+
+        for g in &self.globals {
+            let value = self.get_node(g.first_node).map(|n| self.unwrap_begin(n)).unwrap_or_default();
+            source += &format!("(global {} {} {})\n", type_name(g.value_type), quote_name(g.get_name()), value.join(" "));
+        }
+        if !self.globals.is_empty() && !self.scripts.is_empty() {
+            source.push('\n');
+        }
+
+        for (i, s) in self.scripts.iter().enumerate() {
+            if i > 0 {
+                source.push('\n');
+            }
+
+            source += &script_header(s);
+
+            for expression in self.get_node(s.first_node).map(|n| self.unwrap_begin(n)).unwrap_or_default() {
+                source += "\n    ";
+                source += &expression;
+            }
+            source += ")\n";
+        }
+
+        source
+    }
+
+    /// Decompile the node, or each of its parameters if it is a `begin` block, as scripts and globals are implicitly in one.
+    fn unwrap_begin(&self, node: &CompiledNode) -> Vec<String> {
+        if let (NodeType::FunctionCall(true), Some(NodeData::NodeOffset(function_name_node))) = (node.node_type, node.data) {
+            if let Some(function_name_node) = self.get_node(function_name_node).filter(|n| n.get_string_data() == Some("begin")) {
+                return self.decompile_parameters(function_name_node)
+            }
+        }
+        vec![self.decompile_node(node)]
+    }
+
+    /// Decompile each node after the function name node.
+    fn decompile_parameters(&self, function_name_node: &CompiledNode) -> Vec<String> {
+        let mut parameters = Vec::new();
+        let mut next_node = function_name_node.next_node;
+        while let Some(node) = next_node.and_then(|n| self.get_node(n)) {
+            parameters.push(self.decompile_node(node));
+            next_node = node.next_node;
+        }
+        parameters
+    }
+
+    fn decompile_node(&self, node: &CompiledNode) -> String {
+        match (node.node_type, node.data) {
+            (NodeType::FunctionCall(_), Some(NodeData::NodeOffset(function_name_node))) => {
+                let function_name_node = match self.get_node(function_name_node) {
+                    Some(n) => n,
+                    None => return "(begin)".to_owned()
+                };
+                let mut call = vec![quote_name(source_string(function_name_node).unwrap_or_default())];
+                call.extend(self.decompile_parameters(function_name_node));
+                format!("({})", call.join(" "))
+            },
+            (_, _) if node.value_type == ValueType::String => format!("\"{}\"", node.get_string_data().unwrap_or_default()),
+            (_, _) if node.string_data.is_some() => quote_name(source_string(node).unwrap()),
+            (_, Some(NodeData::Boolean(b))) => b.to_string(),
+            (_, Some(NodeData::Short(n))) => n.to_string(),
+            (_, Some(NodeData::Long(n))) => n.to_string(),
+            (_, Some(NodeData::Real(n))) => n.to_string(),
+            _ => "none".to_owned()
+        }
+    }
+}
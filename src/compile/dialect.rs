@@ -0,0 +1,198 @@
+use super::*;
+
+/// Script or global declared by a top level block.
+pub(crate) enum Declaration {
+    Script(Script),
+    Global(Global)
+}
+
+/// Grammar of the top level blocks of script data, which declare scripts and globals.
+///
+/// Everything inside of a script or global (tokens, expressions, and value types) as well as emitting is shared by all dialects, so
+/// engine generations that declare scripts and globals differently only need their own dialect. The dialect is chosen by the target
+/// with [`CompileTarget::dialect`].
+pub(crate) trait Dialect: Sync {
+    /// Parse a top level block into the script or global it declares.
+    ///
+    /// The body of the script or global is parsed later, starting at the block's child at the declaration's `body_offset`.
+    fn parse_declaration(&self, compiler: &mut Compiler, token: Token) -> Result<Declaration, CompileError>;
+}
+
+/// Dialect of Halo: Combat Evolved and its ports.
+///
+/// Globals are declared with `(global <type> <name> <expression>)` and scripts with `(script <script type> [return type] <name>
+/// <expression(s)>)`, where the name may be a block of the name followed by `(<type> <name>)` parameters on targets that support them.
+pub(crate) struct HaloCEDialect;
+
+impl Dialect for HaloCEDialect {
+    fn parse_declaration(&self, compiler: &mut Compiler, token: Token) -> Result<Declaration, CompileError> {
+        let children = token.children.as_ref().unwrap();
+
+        // Get the object type
+        let block_type = &children[0];
+        let max_script_parameters = compiler.target_limits().maximum_script_parameters();
+        match compiler.lowercase_token(block_type).as_str() {
+            "global" => {
+                // Make sure we have enough tokens here
+                match children.len() {
+                    n if n < 4 => {
+                        return_compile_error!(compiler, token, message!("incomplete_global"));
+                    },
+                    n if n > 4 => {
+                        return_compile_error!(compiler, children[4], message!("extraneous_global_token"));
+                    },
+                    4 => (),
+                    _ => unreachable!()
+                }
+
+                // Add the global
+                Ok(Declaration::Global(Global {
+                    name: {
+                        let global_name_token = &children[2];
+                        if global_name_token.children.is_some() {
+                            return_compile_error!(compiler, global_name_token, message!("global_name_is_block"))
+                        }
+                        let name = compiler.lowercase_token(global_name_token);
+                        compiler.check_definition_name(global_name_token, &name, "global_name_is_literal")?;
+                        name
+                    },
+                    value_type: {
+                        let value_type_token = &children[1];
+                        let value_type_string = compiler.lowercase_token(value_type_token);
+                        match ValueType::from_str_underscore(&value_type_string) {
+                            Some(ValueType::Passthrough) => return_compile_error!(compiler, value_type_token, message!("invalid_global_type", value_type_string)),
+                            Some(n) => n,
+                            None => return_compile_error!(compiler, value_type_token, message!("expected_global_type", value_type_string))
+                        }
+                    },
+                    body_offset: 3,
+                    original_token: token,
+                    node: Node::default() // we're going to parse this later
+                }))
+            },
+            "script" => {
+                // Get the script type
+                let script_type_token = match children.get(1) {
+                    Some(n) => n,
+                    None => return_compile_error!(compiler, token, message!("incomplete_script_type"))
+                };
+                let script_type_string = compiler.lowercase_token(script_type_token);
+                let script_type = match ScriptType::from_str(&script_type_string) {
+                    Some(n) => n,
+                    None => return_compile_error!(compiler, script_type_token, message!("expected_script_type", script_type_string))
+                };
+                let type_expected = !script_type.always_returns_void();
+
+                // Do we have enough tokens?
+                let minimum_number_of_tokens = script_type.expression_offset() + 1;
+                if children.len() < minimum_number_of_tokens {
+                    return_compile_error!(compiler, token, message!(if type_expected { "incomplete_script_with_return_type" } else { "incomplete_script" }, script_type_string))
+                }
+
+                // A return type would otherwise be taken as the name, with the actual name becoming the first expression
+                if !type_expected && children[2].children.is_none() && children[3].children.is_none() {
+                    let type_string = compiler.lowercase_token(&children[2]);
+                    if ValueType::from_str_underscore(&type_string).is_some() {
+                        return_compile_error!(compiler, children[2], message!("void_script_return_type", script_type_string, type_string))
+                    }
+                }
+
+                // Parameters!
+                let mut parameters = Vec::<ScriptParameter>::new();
+
+                // Add the script
+                Ok(Declaration::Script(Script {
+                    name: {
+                        let name_token = &children[minimum_number_of_tokens - 2];
+
+                        // Get the name. We may need to also get the script parameters.
+                        let name;
+                        match &name_token.children {
+                            // If there are children, then that means script parameters were passed.
+                            Some(c) => {
+                                // Check if the target supports script parameters
+                                if max_script_parameters == 0 {
+                                    return_compile_error!(compiler, name_token, message!("script_parameters_unsupported", compiler.target_limits().get_name()));
+                                }
+
+                                // Can we even use them?
+                                if script_type != ScriptType::Static && script_type != ScriptType::Stub {
+                                    return_compile_error!(compiler, name_token, message!("script_parameters_static_only"))
+                                }
+
+                                // Get the name
+                                let name_token = match c.first() {
+                                    Some(n) => n,
+                                    None => return_compile_error!(compiler, name_token, message!("script_name_empty_block"))
+                                };
+                                if name_token.children.is_some() {
+                                    return_compile_error!(compiler, name_token, message!("script_name_is_block"))
+                                }
+                                name = compiler.lowercase_token(name_token);
+
+                                // Get the parameters
+                                let parameter_tokens = &c[1..];
+                                let parameter_count = parameter_tokens.len() - 1;
+                                if parameter_count > max_script_parameters {
+                                    return_compile_error!(compiler, name_token, message!("too_many_script_parameters", max_script_parameters, compiler.target_limits().get_name()));
+                                }
+
+                                // Reserve it
+                                parameters.reserve_exact(parameter_count);
+
+                                for p in parameter_tokens {
+                                    let children = match &p.children {
+                                        Some(n) => n,
+                                        None => return_compile_error!(compiler, p, message!("expected_script_parameter"))
+                                    };
+
+                                    if children.len() != 2 || children[0].children.is_some() || children[1].children.is_some() {
+                                        return_compile_error!(compiler, p, message!("malformed_script_parameter"))
+                                    }
+
+                                    let parameter_type = match ValueType::from_str_underscore(&children[0].string) {
+                                        Some(n) => n,
+                                        None => return_compile_error!(compiler, p, message!("expected_parameter_type", children[0].string))
+                                    };
+
+                                    let parameter_name = compiler.lowercase_token(&children[1]);
+                                    compiler.check_definition_name(&children[1], &parameter_name, "parameter_name_is_literal")?;
+                                    parameters.push(ScriptParameter { name: parameter_name, value_type: parameter_type, original_token: children[1].clone() });
+                                }
+                            },
+                            None => name = compiler.lowercase_token(name_token)
+                        };
+
+                        match name.as_str() {
+                            "begin" | "if" | "cond" => return_compile_error!(compiler, name_token, message!("function_cannot_be_overridden", name)),
+                            _ => ()
+                        }
+                        compiler.check_definition_name(name_token, &name, "script_name_is_literal")?;
+
+                        name
+                    },
+                    return_type: if type_expected {
+                        let return_type_token = &children[2];
+                        let return_type_token_string = compiler.lowercase_token(return_type_token);
+
+                        match ValueType::from_str_underscore(&return_type_token_string) {
+                            Some(ValueType::Passthrough) => return_compile_error!(compiler, return_type_token, message!("invalid_script_type", return_type_token_string)),
+                            Some(n) => n,
+                            None => return_compile_error!(compiler, return_type_token, message!("expected_return_type", return_type_token_string))
+                        }
+                    }
+                    else {
+                        ValueType::Void
+                    },
+                    script_type,
+                    body_offset: minimum_number_of_tokens - 1,
+                    original_token: token,
+                    parameters,
+
+                    node: Node::default() // we're going to parse this later
+                }))
+            },
+            n => return_compile_error!(compiler, block_type, message!("expected_global_or_script", n))
+        }
+    }
+}
@@ -0,0 +1,148 @@
+use super::*;
+
+/// How a script or global differs between two compilations.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum SymbolChange {
+    /// The symbol only exists in the new script data.
+    Added,
+
+    /// The symbol only exists in the old script data.
+    Removed,
+
+    /// The symbol exists in both, but its signature or nodes differ.
+    Changed
+}
+
+/// Subtree of nodes that differs between two compilations, as part of a [`SymbolDiff`].
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct NodeChange {
+    /// Index of the root node of the subtree in the old script data, or `None` if a parameter was added.
+    pub old_node: Option<usize>,
+
+    /// Index of the root node of the subtree in the new script data, or `None` if a parameter was removed.
+    pub new_node: Option<usize>
+}
+
+/// Script or global that differs between two compilations, as returned by [`CompiledScriptData::diff`].
+#[derive(Clone, PartialEq, Debug)]
+pub struct SymbolDiff {
+    /// Whether the symbol is a script or a global.
+    pub symbol_type: SymbolType,
+
+    /// Name of the symbol.
+    pub name: String,
+
+    /// How the symbol changed.
+    pub change: SymbolChange,
+
+    /// Whether the script type, return type, or parameters of a script, or the value type of a global, changed.
+    pub signature_changed: bool,
+
+    /// Topmost subtrees of nodes that differ, in evaluation order. This is empty unless the symbol changed.
+    pub node_changes: Vec<NodeChange>
+}
+
+/// Compare two nodes, ignoring source positions and indices (which change whenever anything before them is added or removed).
+fn same_node(old_data: &CompiledScriptData, old: &CompiledNode, new_data: &CompiledScriptData, new: &CompiledNode) -> bool {
+    let same_data = match (old.data, new.data) {
+        (Some(NodeData::NodeOffset(o)), Some(NodeData::NodeOffset(n))) => {
+            // Function calls are the same function if their function name nodes are
+            old_data.get_node(o).map(|o| &o.string_data) == new_data.get_node(n).map(|n| &n.string_data)
+        },
+
+        // Global and script references hold the index, so they are compared by name instead
+        _ if old.node_type == NodeType::Primitive(PrimitiveType::Global) || old.value_type == ValueType::Script => true,
+
+        (o, n) => o == n
+    };
+    same_data && old.node_type == new.node_type && old.value_type == new.value_type && old.string_data == new.string_data
+}
+
+/// Get the indices of the parameters of the node, which is empty if it is not a function call.
+pub(super) fn parameter_nodes(script_data: &CompiledScriptData, node: &CompiledNode) -> Vec<usize> {
+    let mut parameters = Vec::new();
+    if let (NodeType::FunctionCall(_), Some(NodeData::NodeOffset(function_name_node))) = (node.node_type, node.data) {
+        let mut parameter = script_data.get_node(function_name_node).and_then(|n| n.next_node);
+        while let Some(p) = parameter {
+            parameters.push(p);
+            parameter = script_data.get_node(p).and_then(|n| n.next_node);
+        }
+    }
+    parameters
+}
+
+/// Find the topmost subtrees that differ between the old and new node.
+fn diff_nodes(old_data: &CompiledScriptData, old_index: usize, new_data: &CompiledScriptData, new_index: usize, changes: &mut Vec<NodeChange>) {
+    let (old, new) = match (old_data.get_node(old_index), new_data.get_node(new_index)) {
+        (Some(o), Some(n)) => (o, n),
+        _ => return
+    };
+
+    if !same_node(old_data, old, new_data, new) {
+        changes.push(NodeChange { old_node: Some(old_index), new_node: Some(new_index) });
+        return
+    }
+
+    let old_parameters = parameter_nodes(old_data, old);
+    let new_parameters = parameter_nodes(new_data, new);
+    for i in 0..old_parameters.len().max(new_parameters.len()) {
+        match (old_parameters.get(i), new_parameters.get(i)) {
+            (Some(&o), Some(&n)) => diff_nodes(old_data, o, new_data, n, changes),
+            (o, n) => changes.push(NodeChange { old_node: o.copied(), new_node: n.copied() })
+        }
+    }
+}
+
+impl CompiledScriptData {
+    /// Compare this script data (the old version) with another compilation (the new version), returning each script and global that
+    /// was added, removed, or changed.
+    ///
+    /// Scripts and globals are matched by name. Source positions and indices are not compared, so moving code around does not count
+    /// as a change. Scripts are listed before globals, in the order they appear in the new script data, followed by those removed.
+    pub fn diff(&self, new: &CompiledScriptData) -> Vec<SymbolDiff> {
+        let mut diffs = Vec::new();
+        let symbol_diff = |symbol_type, name: &str, change, signature_changed, node_changes| SymbolDiff { symbol_type, name: name.to_owned(), change, signature_changed, node_changes };
+
+        for s in &new.scripts {
+            let (change, signature_changed, node_changes) = match self.scripts.iter().find(|o| o.name == s.name) {
+                None => (SymbolChange::Added, false, Vec::new()),
+                Some(o) => {
+                    let same_parameters = o.parameters.len() == s.parameters.len()
+                        && o.parameters.iter().zip(&s.parameters).all(|(op, np)| op.name == np.name && op.value_type == np.value_type);
+                    let signature_changed = o.script_type != s.script_type || o.value_type != s.value_type || !same_parameters;
+                    let mut node_changes = Vec::new();
+                    diff_nodes(self, o.first_node, new, s.first_node, &mut node_changes);
+                    if !signature_changed && node_changes.is_empty() {
+                        continue
+                    }
+                    (SymbolChange::Changed, signature_changed, node_changes)
+                }
+            };
+            diffs.push(symbol_diff(SymbolType::Script, s.get_name(), change, signature_changed, node_changes));
+        }
+        for o in self.scripts.iter().filter(|o| !new.scripts.iter().any(|s| s.name == o.name)) {
+            diffs.push(symbol_diff(SymbolType::Script, o.get_name(), SymbolChange::Removed, false, Vec::new()));
+        }
+
+        for g in &new.globals {
+            let (change, signature_changed, node_changes) = match self.globals.iter().find(|o| o.name == g.name) {
+                None => (SymbolChange::Added, false, Vec::new()),
+                Some(o) => {
+                    let signature_changed = o.value_type != g.value_type;
+                    let mut node_changes = Vec::new();
+                    diff_nodes(self, o.first_node, new, g.first_node, &mut node_changes);
+                    if !signature_changed && node_changes.is_empty() {
+                        continue
+                    }
+                    (SymbolChange::Changed, signature_changed, node_changes)
+                }
+            };
+            diffs.push(symbol_diff(SymbolType::Global, g.get_name(), change, signature_changed, node_changes));
+        }
+        for o in self.globals.iter().filter(|o| !new.globals.iter().any(|g| g.name == o.name)) {
+            diffs.push(symbol_diff(SymbolType::Global, o.get_name(), SymbolChange::Removed, false, Vec::new()));
+        }
+
+        diffs
+    }
+}
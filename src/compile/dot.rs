@@ -0,0 +1,115 @@
+use super::*;
+
+/// Quote the string as a DOT string.
+///
+/// Line breaks are kept as `\n`, which Graphviz shows as a centered line break in labels.
+fn dot_string(string: &str) -> String {
+    let mut quoted = String::with_capacity(string.len() + 2);
+    quoted.push('"');
+    for c in string.chars() {
+        match c {
+            '"' => quoted += "\\\"",
+            '\\' => quoted += "\\\\",
+            '\n' => quoted += "\\n",
+            '\r' => (),
+            c => quoted.push(c)
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+impl CompiledScriptData {
+    /// Render which scripts each script and global calls or references (such as with `wake`) as a Graphviz DOT graph.
+    ///
+    /// Scripts are boxes and globals are ellipses. Scripts that are not in the script data, such as external scripts, are dashed boxes.
+    pub fn call_graph_to_dot(&self) -> String {
+        let mut dot = String::from("digraph calls {\n");
+
+        for (i, s) in self.scripts.iter().enumerate() {
+            let label = format!("{}\n{} {}", s.get_name(), s.get_type().as_str(), s.get_value_type().as_str());
+            dot += &format!("    script_{i} [shape=box, label={}];\n", dot_string(&label));
+        }
+        for (i, g) in self.globals.iter().enumerate() {
+            let label = format!("{}\n{}", g.get_name(), g.get_value_type().as_str());
+            dot += &format!("    global_{i} [shape=ellipse, label={}];\n", dot_string(&label));
+        }
+
+        let mut external_scripts = Vec::<String>::new();
+        for call in find_script_calls(self) {
+            let caller = format!("{}_{}", call.caller_type.as_str(), call.caller_index);
+            let callee = call.script_index
+                .and_then(|i| i.checked_sub(self.script_base))
+                .filter(|&i| self.scripts.get(i).map(|s| s.get_name()) == Some(call.script.as_str()));
+            let callee = match callee {
+                Some(i) => format!("script_{i}"),
+                None => {
+                    let index = match external_scripts.iter().position(|s| *s == call.script) {
+                        Some(n) => n,
+                        None => {
+                            dot += &format!("    external_{} [shape=box, style=dashed, label={}];\n", external_scripts.len(), dot_string(&call.script));
+                            external_scripts.push(call.script.clone());
+                            external_scripts.len() - 1
+                        }
+                    };
+                    format!("external_{index}")
+                }
+            };
+            dot += &format!("    {caller} -> {callee};\n");
+        }
+
+        dot += "}\n";
+        dot
+    }
+
+    /// Render the expression tree of the script at the given index as a Graphviz DOT graph, or `None` if there is no such script.
+    ///
+    /// Function calls are boxes labeled with the function name, with their parameters as children from left to right. Values and
+    /// variables are ellipses. Each node is also labeled with its value type, and its identifier is `node_<index>`, matching the node
+    /// indices of the script data.
+    pub fn script_tree_to_dot(&self, script_index: usize) -> Option<String> {
+        let script = self.scripts.get(script_index)?;
+        let mut dot = format!("digraph {} {{\n    ordering=out;\n", dot_string(script.get_name()));
+        self.write_dot_node(script.first_node, &mut dot);
+        dot += "}\n";
+        Some(dot)
+    }
+
+    fn write_dot_node(&self, node_index: usize, dot: &mut String) {
+        let node = match self.get_node(node_index) {
+            Some(n) => n,
+            None => return
+        };
+        let value_type = node.value_type.as_str();
+
+        let function_name_node = match (node.node_type, node.data) {
+            (NodeType::FunctionCall(_), Some(NodeData::NodeOffset(n))) => self.get_node(n),
+            _ => None
+        };
+        let function_name_node = match function_name_node {
+            Some(n) => n,
+            None => {
+                let value = match (node.get_string_data(), node.data) {
+                    (Some(s), _) => s.to_owned(),
+                    (None, Some(NodeData::Boolean(b))) => b.to_string(),
+                    (None, Some(NodeData::Short(s))) => s.to_string(),
+                    (None, Some(NodeData::Long(l))) => l.to_string(),
+                    (None, Some(NodeData::Real(r))) => r.to_string(),
+                    _ => String::new()
+                };
+                *dot += &format!("    node_{node_index} [shape=ellipse, label={}];\n", dot_string(&format!("{value}\n{value_type}")));
+                return
+            }
+        };
+
+        let name = function_name_node.get_string_data().unwrap_or_default();
+        *dot += &format!("    node_{node_index} [shape=box, label={}];\n", dot_string(&format!("{name}\n{value_type}")));
+
+        let mut parameter = function_name_node.next_node;
+        while let Some(p) = parameter {
+            *dot += &format!("    node_{node_index} -> node_{p};\n");
+            self.write_dot_node(p, dot);
+            parameter = self.get_node(p).and_then(|n| n.next_node);
+        }
+    }
+}
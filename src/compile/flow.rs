@@ -0,0 +1,147 @@
+use super::*;
+
+/// Engine functions which suspend the script, letting other scripts run before it continues.
+const YIELDING_FUNCTIONS: [&str; 4] = ["sleep", "sleep_until", "sleep_forever", "thread_sleep"];
+
+/// Global that a script always sets before reading it, as found by [`find_unconditional_initializations`].
+pub(super) struct Initialization<'a> {
+    pub name: &'a str,
+    pub variable: &'a Node,
+    pub after_yield: bool
+}
+
+/// Record each global that the node always sets before reading it, going through `begin` blocks in evaluation order, and whether the
+/// script may have yielded to other scripts before setting it.
+///
+/// Calls to anything else may not evaluate all of their parameters, so globals they set are not counted, but globals they read are.
+pub(super) fn find_unconditional_initializations<'a>(node: &'a Node, read: &mut Vec<&'a str>, yielded: &mut bool, initialized: &mut Vec<Initialization<'a>>) {
+    match (engine_function_name(node), node.node_type) {
+        (Some("begin"), _) => for p in node.parameters.as_ref().unwrap() {
+            find_unconditional_initializations(p, read, yielded, initialized);
+        },
+        (Some("set"), _) => {
+            let parameters = node.parameters.as_ref().unwrap();
+            find_unconditional_initializations(&parameters[1], read, yielded, initialized);
+            if let Some(name) = parameters[0].string_data.as_deref().filter(|_| parameters[0].node_type == NodeType::Primitive(PrimitiveType::Global)) {
+                if !read.contains(&name) && !initialized.iter().any(|i| i.name == name) {
+                    initialized.push(Initialization { name, variable: &parameters[0], after_yield: *yielded });
+                }
+            }
+        },
+        (_, NodeType::Primitive(PrimitiveType::Global)) => read.push(node.string_data.as_deref().unwrap()),
+        (function, NodeType::FunctionCall(_)) => {
+            let mut usage = BTreeMap::new();
+            find_global_usage(node, &mut usage);
+            read.extend(usage.into_iter().filter(|(_, u)| u.read).map(|(name, _)| name));
+            *yielded |= function.map(|f| YIELDING_FUNCTIONS.contains(&f)).unwrap_or(false);
+        },
+        _ => ()
+    }
+}
+
+/// Result of looking for a read of a global before a script yields.
+enum Flow<'a> {
+    /// The global may be read here
+    Read(&'a Node),
+
+    /// The script always yields or sets the global itself before going any further
+    Stop,
+
+    /// Neither, so keep looking
+    Continue
+}
+
+/// Find the first place the node may read the global before the script yields or sets it, following calls to static scripts.
+///
+/// Every branch is assumed to be taken when looking for reads, but only yields and sets outside of branches stop the search. Calls to
+/// static scripts that lead to the read are added to the path.
+fn find_read_before_yield<'a>(node: &'a Node, name: &str, scripts: &'a [Script], conditional: bool, calling: &mut Vec<&'a str>, path: &mut Vec<&'a Node>) -> Flow<'a> {
+    match node.node_type {
+        NodeType::Primitive(PrimitiveType::Global) if node.string_data.as_deref() == Some(name) => Flow::Read(node),
+        NodeType::FunctionCall(is_engine_function) => {
+            let function = node.string_data.as_deref().unwrap();
+            let parameters = node.parameters.as_ref().unwrap();
+
+            // The variable passed to 'set' is written, not read
+            let first_read = if is_engine_function && function == "set" { 1 } else { 0 };
+            for (i, p) in parameters.iter().enumerate().skip(first_read) {
+                let conditional = conditional || match function {
+                    "if" | "and" | "or" => i > 0,
+                    "begin_random" => true,
+                    _ => false
+                };
+                match find_read_before_yield(p, name, scripts, conditional, calling, path) {
+                    Flow::Continue => (),
+                    n => return n
+                }
+            }
+
+            if is_engine_function {
+                let sets_global = function == "set" && parameters[0].node_type == NodeType::Primitive(PrimitiveType::Global) && parameters[0].string_data.as_deref() == Some(name);
+                return if !conditional && (sets_global || YIELDING_FUNCTIONS.contains(&function)) { Flow::Stop } else { Flow::Continue }
+            }
+
+            let script = match scripts.iter().find(|s| s.name == function && s.script_type == ScriptType::Static) {
+                Some(n) if !calling.contains(&function) => n,
+                _ => return Flow::Continue
+            };
+            calling.push(function);
+            path.push(node);
+            let flow = find_read_before_yield(&script.node, name, scripts, conditional, calling, path);
+            if !matches!(flow, Flow::Read(_)) {
+                path.pop();
+            }
+            calling.pop();
+            flow
+        },
+        _ => Flow::Continue
+    }
+}
+
+impl Compiler {
+    /// Warn about each global that a startup script initializes (by setting it before reading it) but that another startup or
+    /// continuous script may read on the first tick before it is set, following calls to static scripts.
+    ///
+    /// Scripts run in the order they are defined, so a script defined after the startup script can only read the global before it is
+    /// set if the startup script yields first.
+    pub(super) fn check_unset_globals(&mut self, scripts: &[Script]) {
+        let entry_point = |s: &Script| s.script_type == ScriptType::Startup || s.script_type == ScriptType::Continuous;
+
+        let mut initializations: Vec<(usize, Initialization)> = Vec::new();
+        for (i, s) in scripts.iter().enumerate().filter(|(_, s)| s.script_type == ScriptType::Startup) {
+            let mut initialized = Vec::new();
+            find_unconditional_initializations(&s.node, &mut Vec::new(), &mut false, &mut initialized);
+            for n in initialized {
+                // Only the first startup script to initialize a global counts
+                if !initializations.iter().any(|(_, i)| i.name == n.name) {
+                    initializations.push((i, n));
+                }
+            }
+        }
+
+        for (initializer, initialization) in &initializations {
+            for (i, s) in scripts.iter().enumerate().filter(|(i, s)| i != initializer && entry_point(s)) {
+                if i > *initializer && !initialization.after_yield {
+                    continue
+                }
+
+                let mut path = Vec::new();
+                let read = match find_read_before_yield(&s.node, initialization.name, scripts, false, &mut vec![s.name.as_str()], &mut path) {
+                    Flow::Read(n) => n,
+                    _ => continue
+                };
+
+                let initializer = &scripts[*initializer];
+                let mut warning = CompileError::from_message(self.files[read.file].as_str(), read.line, read.column, CompileErrorType::Warning, message!("possibly_unset_global", initialization.name, s.name, initializer.name))
+                    .with_end_position(read.end.0, read.end.1)
+                    .with_code(CompileErrorCode::UnsetGlobal);
+                for call in path {
+                    warning = warning.with_related_location(self.files[call.file].as_str(), call.line, call.column, message!("script_called_here", call.string_data.as_deref().unwrap()));
+                }
+                let variable = initialization.variable;
+                warning = warning.with_related_location(self.files[variable.file].as_str(), variable.line, variable.column, message!("global_set_here", initialization.name));
+                self.push_warning(warning);
+            }
+        }
+    }
+}
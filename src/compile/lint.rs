@@ -0,0 +1,271 @@
+use super::*;
+
+/// Number of ticks that sleeping for longer than is probably a mistake (10 minutes).
+const MAXIMUM_SENSIBLE_SLEEP_TICKS: i16 = 30 * 60 * 10;
+
+/// Get the name of the engine function this node calls, if it calls one.
+pub(super) fn engine_function_name(node: &Node) -> Option<&str> {
+    match node.node_type {
+        NodeType::FunctionCall(true) => node.string_data.as_deref(),
+        _ => None
+    }
+}
+
+/// Get the value of the node if it's a boolean literal.
+pub(super) fn boolean_literal(node: &Node) -> Option<bool> {
+    match (node.node_type, node.data) {
+        (NodeType::Primitive(PrimitiveType::Static), Some(NodeData::Boolean(b))) => Some(b),
+        _ => None
+    }
+}
+
+/// Get the value of the node if it's an integer literal.
+fn integer_literal(node: &Node) -> Option<i32> {
+    match (node.node_type, node.data) {
+        (NodeType::Primitive(PrimitiveType::Static), Some(NodeData::Short(n))) => Some(n as i32),
+        (NodeType::Primitive(PrimitiveType::Static), Some(NodeData::Long(n))) => Some(n),
+        _ => None
+    }
+}
+
+/// Describe a node in a short, human readable way for use in diagnostic messages.
+fn describe_node(node: &Node) -> String {
+    match node.node_type {
+        NodeType::FunctionCall(_) => format!("({} ...)", node.string_data.as_deref().unwrap_or("")),
+        NodeType::Primitive(_) => node.string_data.clone().unwrap_or_else(|| "expression".to_owned())
+    }
+}
+
+/// Find an `if` without an else branch that can end evaluation of the node, leaving it without a value.
+fn find_valueless_path(node: &Node) -> Option<&Node> {
+    let parameters = node.parameters.as_ref()?;
+
+    match engine_function_name(node)? {
+        // The last expression of a begin block is the value
+        "begin" => find_valueless_path(parameters.last()?),
+
+        // Any expression of a begin_random block may be the last one evaluated
+        "begin_random" => parameters.iter().find_map(find_valueless_path),
+
+        // If there is no else branch, there's no value when the condition is false, unless it is always true, like the last
+        // condition of a cond can be
+        "if" => match parameters.len() {
+            2 if boolean_literal(&parameters[0]) == Some(true) => find_valueless_path(&parameters[1]),
+            2 => Some(node),
+            _ => parameters[1..].iter().find_map(find_valueless_path)
+        },
+
+        _ => None
+    }
+}
+
+/// Find the first reference to the script parameter in the node.
+fn find_parameter_reference<'a>(node: &'a Node, name: &str) -> Option<&'a Node> {
+    match node.node_type {
+        NodeType::Primitive(PrimitiveType::Local) if node.string_data.as_deref() == Some(name) => Some(node),
+        NodeType::FunctionCall(_) => node.parameters.as_ref()?.iter().find_map(|p| find_parameter_reference(p, name)),
+        _ => None
+    }
+}
+
+/// How a global is used by the scripts and globals.
+#[derive(Default)]
+pub(super) struct GlobalUsage<'a> {
+    pub read: bool,
+    pub first_write: Option<&'a Node>
+}
+
+/// Record each global that the node reads or writes with `set`.
+pub(super) fn find_global_usage<'a>(node: &'a Node, usage: &mut BTreeMap<&'a str, GlobalUsage<'a>>) {
+    match node.node_type {
+        NodeType::Primitive(PrimitiveType::Global) => usage.entry(node.string_data.as_deref().unwrap()).or_default().read = true,
+        NodeType::FunctionCall(_) => {
+            let parameters = node.parameters.as_ref().unwrap();
+            let mut reads = &parameters[..];
+            if engine_function_name(node) == Some("set") {
+                if let Some(variable) = parameters.first().filter(|p| p.node_type == NodeType::Primitive(PrimitiveType::Global)) {
+                    let global = usage.entry(variable.string_data.as_deref().unwrap()).or_default();
+                    global.first_write = global.first_write.or(Some(variable));
+                }
+                reads = &parameters[1..];
+            }
+            for p in reads {
+                find_global_usage(p, usage);
+            }
+        },
+        _ => ()
+    }
+}
+
+impl Compiler {
+    /// Warn about each global that is set but never read, as it is probably unneeded, and each global that is read but never set, as
+    /// its value never changes from its initial value.
+    pub(super) fn find_unused_global_accesses(&mut self, scripts: &[Script], globals: &[Global]) {
+        let mut usage = BTreeMap::new();
+        for node in scripts.iter().map(|s| &s.node).chain(globals.iter().map(|g| &g.node)) {
+            find_global_usage(node, &mut usage);
+        }
+
+        for g in globals {
+            match usage.get(g.name.as_str()) {
+                Some(GlobalUsage { read: false, first_write: Some(write) }) => {
+                    let token = &g.original_token;
+                    self.push_warning(CompileError::from_message(self.files[token.file].as_str(), token.line, token.column, CompileErrorType::Warning, message!("global_never_read", g.name))
+                        .with_end_position(token.end.0, token.end.1)
+                        .with_code(CompileErrorCode::GlobalUsage)
+                        .with_related_location(self.files[write.file].as_str(), write.line, write.column, message!("global_set_here", g.name)));
+                },
+                Some(GlobalUsage { read: true, first_write: None }) => {
+                    compile_warn!(self, g.original_token, CompileErrorCode::GlobalUsage, message!("global_never_set", g.name));
+                },
+                _ => ()
+            }
+        }
+    }
+
+    /// Warn about each script parameter that has the same name as a global and is referenced in the script, as the parameter is used
+    /// in place of the global.
+    pub(super) fn check_shadowed_globals(&mut self, script: &Script, globals: &[Global]) {
+        for p in &script.parameters {
+            let global = match globals.iter().find(|g| g.name == p.name) {
+                Some(n) => n,
+                None => continue
+            };
+            let reference = match find_parameter_reference(&script.node, &p.name) {
+                Some(n) => n,
+                None => continue
+            };
+
+            let token = &p.original_token;
+            self.push_warning(CompileError::from_message(self.files[token.file].as_str(), token.line, token.column, CompileErrorType::Warning, message!("parameter_shadows_global", p.name, script.name))
+                .with_end_position(token.end.0, token.end.1)
+                .with_code(CompileErrorCode::ShadowedGlobal)
+                .with_related_location(self.files[global.original_token.file].as_str(), global.original_token.line, global.original_token.column, message!("global_defined_here", global.name))
+                .with_related_location(self.files[reference.file].as_str(), reference.line, reference.column, message!("parameter_referenced_here", p.name)));
+        }
+    }
+
+    /// Warn about each parameter of a static script that is never referenced in the script, which usually means the script is called
+    /// differently than it is defined.
+    ///
+    /// Stubs are skipped, as they are placeholders meant to be replaced.
+    pub(super) fn check_unused_parameters(&mut self, script: &Script) {
+        if script.script_type != ScriptType::Static {
+            return
+        }
+
+        for p in &script.parameters {
+            if find_parameter_reference(&script.node, &p.name).is_none() {
+                compile_warn!(self, p.original_token, CompileErrorCode::UnusedParameter, message!("unused_parameter", p.name, script.name));
+            }
+        }
+    }
+
+    /// Check that every path through the script's trailing expression produces a value if the script returns one.
+    pub(super) fn check_return_paths(&mut self, script: &Script) {
+        if script.script_type.always_returns_void() || script.return_type == ValueType::Void {
+            return
+        }
+
+        if let Some(node) = find_valueless_path(&script.node) {
+            compile_warn!(self, node, CompileErrorCode::MissingReturnValue, message!("missing_return_value", script.name, script.return_type));
+        }
+    }
+
+    /// Warn if there are more continuous or dormant scripts than are recommended for the target, listing them.
+    pub(super) fn check_script_counts(&mut self, scripts: &[Script]) {
+        let (continuous, dormant) = (self.target_limits().recommended_maximum_continuous_scripts(), self.target_limits().recommended_maximum_dormant_scripts());
+        self.check_script_count(scripts, ScriptType::Continuous, continuous);
+        self.check_script_count(scripts, ScriptType::Dormant, dormant);
+    }
+
+    fn check_script_count(&mut self, scripts: &[Script], script_type: ScriptType, maximum: usize) {
+        let matching: Vec<&Script> = scripts.iter().filter(|s| s.script_type == script_type).collect();
+        if matching.len() <= maximum {
+            return
+        }
+
+        let names: Vec<String> = matching.iter().map(|s| format!("'{}'", s.name)).collect();
+        compile_warn!(self, matching[maximum].original_token, CompileErrorCode::ScriptCount, message!("script_count", matching.len(), script_type.as_str(), maximum, self.target_limits().get_name(), names.join(", ")));
+    }
+
+    /// Warn about each call in the node to an engine function that does nothing on a dedicated server.
+    pub(super) fn check_client_only_calls(&mut self, node: &Node) {
+        if let (NodeType::FunctionCall(true), Some(name)) = (node.node_type, node.string_data.as_deref()) {
+            if self.engine_definitions.functions().any(|f| f.client_only && f.name == name) {
+                compile_warn!(self, node, CompileErrorCode::ClientOnly, message!("client_only_call", name));
+            }
+        }
+        for p in node.parameters.iter().flatten() {
+            self.check_client_only_calls(p);
+        }
+    }
+
+    /// Check the typed node tree for constructs that are valid, but could be written more simply.
+    ///
+    /// This runs before the nodes are emitted, so any warnings point to the original tokens.
+    pub(super) fn lint_node(&mut self, node: &Node) {
+        let parameters = match node.parameters.as_ref() {
+            Some(n) => n,
+            None => return
+        };
+
+        match engine_function_name(node) {
+            // (= x true) is just x, and (= x false) is just (not x)
+            Some(f @ "=") | Some(f @ "!=") if parameters.len() == 2 => {
+                let comparison = match (boolean_literal(&parameters[0]), boolean_literal(&parameters[1])) {
+                    (_, Some(b)) => Some((b, &parameters[0])),
+                    (Some(b), _) => Some((b, &parameters[1])),
+                    _ => None
+                };
+
+                if let Some((literal, other)) = comparison {
+                    let other_description = describe_node(other);
+                    let suggestion = if literal == (f == "=") { other_description } else { format!("(not {other_description})") };
+                    compile_warn!(self, node, CompileErrorCode::Style, message!("redundant_boolean_comparison", literal, f, suggestion));
+                }
+            },
+
+            // (if (not c) a b) is just (if c b a)
+            Some("if") if parameters.len() == 3 && engine_function_name(&parameters[0]) == Some("not") => {
+                compile_warn!(self, node, CompileErrorCode::Style, message!("negated_if_condition"));
+            },
+
+            // Sleeping for a nonsensical amount of time is probably a mistake, except -1 which sleeps until woken
+            Some("sleep") => match parameters.first().and_then(|p| p.data) {
+                Some(NodeData::Short(ticks)) if ticks == 0 || !(-1..=MAXIMUM_SENSIBLE_SLEEP_TICKS).contains(&ticks) => {
+                    compile_warn!(self, parameters[0], CompileErrorCode::SuspiciousSleep, message!("suspicious_sleep", ticks, format!("{:.1}", ticks as f64 / 30.0)));
+                },
+                _ => ()
+            },
+
+            // A period that isn't positive, a timeout that isn't positive, or a period longer than the timeout is probably a mistake
+            Some("sleep_until") => {
+                let period = parameters.get(1).and_then(|p| integer_literal(p).map(|n| (p, n)));
+                let timeout = parameters.get(2).and_then(|p| integer_literal(p).map(|n| (p, n)));
+                if let Some((p, ticks)) = period.filter(|&(_, n)| n <= 0) {
+                    compile_warn!(self, p, CompileErrorCode::SuspiciousSleep, message!("suspicious_sleep_until_period", ticks));
+                }
+                if let Some((p, ticks)) = timeout.filter(|&(_, n)| n <= 0) {
+                    compile_warn!(self, p, CompileErrorCode::SuspiciousSleep, message!("suspicious_sleep_until_timeout", ticks));
+                }
+                if let (Some((_, period)), Some((p, timeout))) = (period, timeout) {
+                    if timeout > 0 && period > timeout {
+                        compile_warn!(self, p, CompileErrorCode::SuspiciousSleep, message!("sleep_until_period_exceeds_timeout", period, timeout));
+                    }
+                }
+            },
+
+            // (not (not x)) is just x
+            Some("not") if parameters.len() == 1 && engine_function_name(&parameters[0]) == Some("not") => {
+                compile_warn!(self, node, CompileErrorCode::Style, message!("double_negation"));
+            },
+
+            _ => ()
+        }
+
+        for p in parameters {
+            self.lint_node(p);
+        }
+    }
+}
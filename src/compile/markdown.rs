@@ -0,0 +1,52 @@
+use super::*;
+use super::decompile::script_header;
+
+/// Escape the text so it can be placed in a Markdown table cell as inline code.
+fn markdown_code(text: &str) -> String {
+    let text = text.replace('|', "\\|");
+    if text.contains('`') {
+        format!("`` {text} ``")
+    }
+    else {
+        format!("`{text}`")
+    }
+}
+
+impl CompiledScriptData {
+    /// Generate Markdown documentation of the scripts and globals, such as for a reference page of a map's scripts.
+    ///
+    /// Each script gets its own section with its definition header (including the `(name (type name)...)` syntax for parameters),
+    /// where it is defined, and a table of its parameters. Globals are listed in one table. Everything is in index order.
+    pub fn to_markdown(&self) -> String {
+        let location = |file: usize, line: usize| format!("{}:{line}", self.files[file].to_str().unwrap());
+        let mut markdown = String::new();
+
+        if !self.scripts.is_empty() {
+            markdown += "# Scripts\n";
+        }
+        for s in &self.scripts {
+            markdown += &format!("\n## {}\n\n", markdown_code(s.get_name()));
+            markdown += &format!("{}\n\n", markdown_code(&format!("{})", script_header(s))));
+            markdown += &format!("Defined in {}.\n", markdown_code(&location(s.file, s.line)));
+
+            if !s.parameters.is_empty() {
+                markdown += "\n| Parameter | Type |\n| --- | --- |\n";
+                for p in &s.parameters {
+                    markdown += &format!("| {} | {} |\n", markdown_code(p.get_name()), markdown_code(p.value_type.as_str()));
+                }
+            }
+        }
+
+        if !self.globals.is_empty() {
+            if !markdown.is_empty() {
+                markdown.push('\n');
+            }
+            markdown += "# Globals\n\n| Global | Type | Defined in |\n| --- | --- | --- |\n";
+            for g in &self.globals {
+                markdown += &format!("| {} | {} | {} |\n", markdown_code(g.get_name()), markdown_code(g.value_type.as_str()), markdown_code(&location(g.file, g.line)));
+            }
+        }
+
+        markdown
+    }
+}
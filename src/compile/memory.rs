@@ -0,0 +1,53 @@
+use super::*;
+use std::mem::size_of;
+
+/// Add the memory used by the tokens and their children.
+fn add_token_memory(tokens: &[Token], capacity: usize, usage: &mut MemoryUsage) {
+    usage.tokens += capacity * size_of::<Token>();
+    for t in tokens {
+        usage.strings += t.string.capacity();
+        if let Some(children) = t.children.as_ref() {
+            add_token_memory(children, children.capacity(), usage);
+        }
+    }
+}
+
+/// Add the memory used by the node and its parameters, not including the node itself.
+fn add_node_memory(node: &Node, usage: &mut MemoryUsage) {
+    usage.strings += node.string_data.as_ref().map(|s| s.capacity()).unwrap_or(0);
+    usage.strings += node.original_string_data.as_ref().map(|s| s.capacity()).unwrap_or(0);
+    if let Some(parameters) = node.parameters.as_ref() {
+        usage.nodes += parameters.capacity() * size_of::<Node>();
+        for p in parameters {
+            add_node_memory(p, usage);
+        }
+    }
+}
+
+/// Get the memory used by loaded tokens.
+pub(crate) fn token_memory(tokens: &Vec<Token>) -> MemoryUsage {
+    let mut usage = MemoryUsage::default();
+    add_token_memory(tokens, tokens.capacity(), &mut usage);
+    usage
+}
+
+/// Get the memory used by the tokens and node trees of parsed scripts and globals.
+pub(super) fn definition_memory(scripts: &[Script], globals: &[Global]) -> MemoryUsage {
+    let mut usage = MemoryUsage::default();
+    for (name, token, node) in scripts.iter().map(|s| (&s.name, &s.original_token, &s.node)).chain(globals.iter().map(|g| (&g.name, &g.original_token, &g.node))) {
+        usage.strings += name.capacity();
+        add_token_memory(std::slice::from_ref(token), 1, &mut usage);
+        usage.nodes += size_of::<Node>();
+        add_node_memory(node, &mut usage);
+    }
+    usage
+}
+
+/// Get the memory used by compiled nodes.
+pub(super) fn compiled_node_memory(nodes: &Vec<CompiledNode>) -> MemoryUsage {
+    MemoryUsage {
+        tokens: 0,
+        nodes: nodes.capacity() * size_of::<CompiledNode>(),
+        strings: nodes.iter().map(super::budget::string_data_length).sum()
+    }
+}
@@ -1,5 +1,6 @@
 use super::*;
-use super::definitions::{ALL_GLOBALS, ALL_FUNCTIONS, EngineFunction, EngineGlobal};
+use super::token::{build_token_tree, quote_name, TokenizedScript, TreeOptions, MAXIMUM_BLOCK_DEPTH};
+use super::definitions::EngineDefinitions;
 
 use std::collections::BTreeMap;
 
@@ -8,54 +9,271 @@ use std::ffi::{CString, CStr};
 mod types;
 pub use self::types::*;
 
-fn all_functions_and_globals_for_target(target: CompileTarget) -> (Vec<&'static EngineFunction>, Vec<&'static EngineGlobal>) {
-    let mut functions = Vec::new();
-    let mut globals = Vec::new();
+/// File name used for diagnostics when compiling a standalone expression.
+const EXPRESSION_FILE_NAME: &str = "<expression>";
 
-    for f in &ALL_FUNCTIONS {
-        if f.supports_target(target) {
-            functions.push(f);
-        }
-    }
+/// Words that are parsed as literals and should never be used as a name.
+const RESERVED_NAMES: [&str; 5] = ["true", "false", "on", "off", "none"];
 
-    for g in &ALL_GLOBALS {
-        if g.supports_target(target) {
-            globals.push(g);
-        }
+/// Get whether or not the string is a number literal, such as `1`, `-2.5`, or `1e3`.
+///
+/// Words that Rust parses as numbers, such as `inf` and `nan`, are not number literals in script data, so a number needs a digit.
+pub(crate) fn is_number_literal(string: &str) -> bool {
+    string.parse::<f64>().is_ok() && string.bytes().any(|c| c.is_ascii_digit())
+}
+
+/// Get whether or not the name would be parsed as a literal where a value is expected, such as `1`, `-2.5`, or `true`.
+fn is_literal_name(name: &str) -> bool {
+    is_number_literal(name) || RESERVED_NAMES.contains(&name)
+}
+
+/// Get the index of the engine global in the engine's table for the target, or `None` if it is not an engine global or its index is not
+/// known (the definitions store unknown indices as 0xFFFF).
+///
+/// No index is known for any of the built-in engine globals, so this is always `None` unless definitions with indices were added.
+pub(crate) fn engine_global_index(name: &str, target: CompileTarget, definitions: &EngineDefinitions) -> Option<u16> {
+    definitions.find_global(name)?.availability.index_for_target(target).filter(|i| *i != u16::MAX)
+}
+
+/// Get all engine functions and globals for the target, keyed by name.
+pub(crate) fn callable_engine_functions_and_globals(target: CompileTarget, definitions: &EngineDefinitions) -> (BTreeMap<&str, &dyn CallableFunction>, BTreeMap<&str, &dyn CallableGlobal>) {
+    let mut callable_functions = BTreeMap::<&str, &dyn CallableFunction>::new();
+    let mut callable_globals = BTreeMap::<&str, &dyn CallableGlobal>::new();
+
+    for f in definitions.functions().filter(|f| f.supports_target(target)) {
+        callable_functions.insert(&f.name, f);
+    }
+    for f in definitions.user_functions().iter().filter(|f| f.supports_target(target)) {
+        callable_functions.insert(f.get_name(), f);
+    }
+    for g in definitions.globals().filter(|g| g.supports_target(target)) {
+        callable_globals.insert(&g.name, g);
     }
 
-    return (functions, globals)
+    (callable_functions, callable_globals)
 }
 
 macro_rules! return_compile_error {
     ($compiler: expr, $token: expr, $message: expr) => {
-        return Err(CompileError::from_message($compiler.files[$token.file].as_str(), $token.line, $token.column, CompileErrorType::Error, $message.as_str()))
+        return Err(CompileError::from_message($compiler.files[$token.file].as_str(), $token.line, $token.column, CompileErrorType::Error, $message)
+            .with_end_position($token.end.0, $token.end.1))
     };
 }
 
 macro_rules! compile_warn {
-    ($compiler: expr, $token: expr, $message: expr) => {
-        $compiler.warnings.push(CompileError::from_message($compiler.files[$token.file].as_str(), $token.line, $token.column, CompileErrorType::Warning, $message.as_str()))
+    ($compiler: expr, $token: expr, $code: expr, $message: expr) => {
+        $compiler.push_warning(CompileError::from_message($compiler.files[$token.file].as_str(), $token.line, $token.column, CompileErrorType::Warning, $message)
+            .with_end_position($token.end.0, $token.end.1)
+            .with_code($code))
     };
 }
 
+mod backend;
+pub use self::backend::{EmitBackend, TypedNode, ScriptDefinition, GlobalDefinition};
+use self::backend::ScenarioEmitter;
+mod budget;
+mod cost;
+mod decompile;
+mod dialect;
+mod dot;
+mod markdown;
+pub(crate) use self::dialect::{Declaration, Dialect, HaloCEDialect};
+mod diff;
+mod flow;
+use self::flow::find_unconditional_initializations;
+pub use self::diff::{SymbolChange, NodeChange, SymbolDiff};
+use self::diff::parameter_nodes;
+mod lint;
+mod memory;
+pub(crate) use self::memory::token_memory;
+use self::memory::{compiled_node_memory, definition_memory};
+use self::lint::{boolean_literal, engine_function_name, find_global_usage};
+mod obfuscate;
+mod propagate;
+mod query;
+pub use self::propagate::PropagatedGlobal;
+use self::propagate::convert_literal;
+mod report;
+mod simulate;
+pub(crate) use self::simulate::SimulatedValue;
+pub use self::report::{CompileReport, TagReference, ScriptCall};
+use self::report::{find_script_calls, json_string};
+mod syntax;
+pub use self::syntax::{SyntaxData, SyntaxScript, SyntaxGlobal};
+
+/// Get the engine function that casts a value type to the expected type, such as `unit`, if the target has one.
+fn cast_function(from: ValueType, to: ValueType, target: CompileTarget, definitions: &EngineDefinitions) -> Option<String> {
+    let name = to.as_str().replace(' ', "_");
+    definitions.functions().find(|f| {
+        f.name == name && f.return_type == to && f.parameters.len() == 1 && from.can_convert_to(f.parameters[0].value_type) && f.supports_target(target)
+    }).map(|f| f.name.to_string())
+}
+
+/// Get the message for a value that cannot convert to the expected type, suggesting a cast if the target has one.
+fn cannot_convert_message(key: &'static str, cast_key: &'static str, name: &str, from: ValueType, to: ValueType, target: CompileTarget, definitions: &EngineDefinitions) -> Message {
+    match cast_function(from, to, target, definitions) {
+        Some(f) => message!(cast_key, name, from, to, f),
+        None => message!(key, name, from, to)
+    }
+}
 
 /// Get the index of the parameter from a slice of parameters.
 fn parameter_index(name: &str, parameters: &[ScriptParameter]) -> Option<usize> {
-    for i in 0..parameters.len() {
-        if parameters[i].name == name {
-            return Some(i)
+    parameters.iter().position(|p| p.name == name)
+}
+
+/// Get the number of single character insertions, deletions, and substitutions needed to turn one string into another.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + if ca == *cb { 0 } else { 1 };
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// Get the name that is closest to the given name, if any is close enough to likely be a typo of it.
+fn closest_name<'a, I: IntoIterator<Item = &'a str>>(name: &str, candidates: I) -> Option<&'a str> {
+    let maximum_distance = (name.len() / 3).max(1);
+    candidates.into_iter()
+        .map(|c| (edit_distance(name, c), c))
+        .filter(|&(distance, _)| distance <= maximum_distance)
+        .min()
+        .map(|(_, c)| c)
+}
+
+/// Number of ticks in a second.
+const TICKS_PER_SECOND: f64 = 30.0;
+
+/// Get the number of ticks in the time unit used with [`Compiler::set_time_units`], if it is one.
+fn ticks_per_time_unit(name: &str) -> Option<f64> {
+    match name {
+        "seconds" => Some(TICKS_PER_SECOND),
+        "minutes" => Some(TICKS_PER_SECOND * 60.0),
+        _ => None
+    }
+}
+
+/// Number of nodes a function call takes up without its parameters (the call and the function name).
+const FUNCTION_CALL_NODE_COUNT: usize = 2;
+
+/// Optimize 'begin' nodes with only one call, returning the number of nodes removed.
+fn optimize_begin(node_to_optimize: &mut Node) -> usize {
+    let mut removed = 0;
+    while matches!(node_to_optimize.node_type, NodeType::FunctionCall(true)) && node_to_optimize.string_data.as_ref().unwrap() == "begin" {
+        let parameters = node_to_optimize.parameters.as_mut().unwrap();
+        if parameters.len() == 1 {
+            *node_to_optimize = parameters.pop().unwrap();
+            removed += FUNCTION_CALL_NODE_COUNT;
+        }
+        else {
+            break;
+        }
+    }
+
+    // Optimize its parameters
+    if let NodeType::FunctionCall(_) = node_to_optimize.node_type {
+        for i in node_to_optimize.parameters.as_mut().unwrap() {
+            removed += optimize_begin(i);
+        }
+    }
+
+    removed
+}
+
+/// Resolve the script, global, and engine function indices referenced by the node and its parameters.
+fn find_global_script_indices_for_node(node: &mut Node, function_parameters: &[ScriptParameter], scripts: &BTreeMap::<String, i16>, globals: &BTreeMap::<String, i32>, target: CompileTarget, definitions: &EngineDefinitions) -> Result<(), CompileError> {
+    match node.node_type {
+        NodeType::Primitive(PrimitiveType::Static) => {
+            // Unresolved external scripts are left unset
+            if node.value_type == ValueType::Script {
+                node.data = scripts.get(node.string_data.as_ref().unwrap()).map(|n| NodeData::Short(*n));
+            }
+        },
+        NodeType::Primitive(PrimitiveType::Local) => {
+            node.data = Some(NodeData::Long(parameter_index(node.string_data.as_ref().unwrap(), function_parameters).unwrap() as i32));
+        },
+        NodeType::Primitive(PrimitiveType::Global) => {
+            let string_data = node.string_data.as_ref().unwrap();
+
+            // Script globals get the index of the global (unresolved external globals are left unset)
+            if let Some(n) = globals.get(string_data) {
+                node.data = Some(NodeData::Long(*n))
+            }
+
+            // Engine globals get the index of the global in the engine's table instead, unless the index union was already set for 'set'
+            else if node.index.is_none() {
+                node.index = engine_global_index(string_data, target, definitions);
+            }
+        },
+        NodeType::FunctionCall(is_engine_function) => {
+            let name = node.string_data.as_ref().unwrap();
+
+            // If it's an engine function, the node gets the index of the function
+            if is_engine_function {
+                node.index = definitions.function_index(name, target);
+                debug_assert!(node.index.is_some())
+            }
+            // If it's not an engine function, the node gets the index of the script then
+            else {
+                node.index = scripts.get(name).map(|n| *n as u16);
+            }
+
+            for p in node.parameters.as_mut().unwrap() {
+                find_global_script_indices_for_node(p, function_parameters, scripts, globals, target, definitions)?;
+            }
         }
     }
-    None
+
+    Ok(())
 }
 
+/// Offset all node indices stored in the nodes, scripts, and globals by the node index base.
+fn offset_node_indices(scripts: &mut [CompiledScript], globals: &mut [CompiledGlobal], nodes: &mut [CompiledNode], node_base: usize) {
+    if node_base == 0 {
+        return
+    }
+
+    for s in scripts {
+        s.first_node += node_base;
+    }
+    for g in globals {
+        g.first_node += node_base;
+    }
+    for n in nodes {
+        if let Some(NodeData::NodeOffset(offset)) = n.data.as_mut() {
+            *offset += node_base;
+        }
+        if let Some(next_node) = n.next_node.as_mut() {
+            *next_node += node_base;
+        }
+    }
+}
 
 impl Compiler {
-    /// Lowercase the token as needed.
-    fn lowercase_token(&mut self, token: &Token) -> String {
-        // Ideally, if this results in a different token, this should be a warning! However, the original HSCs would then have over 3000 warnings. Oh well.
-        token.string.to_ascii_lowercase()
+    /// Lowercase the token as needed, warning if it changed and case checking is enabled.
+    pub(crate) fn lowercase_token(&mut self, token: &Token) -> String {
+        let lowercase = token.string.to_ascii_lowercase();
+
+        // This isn't always a warning, as the original HSCs would then have over 3000 warnings. Oh well.
+        if self.check_case && lowercase != token.string {
+            // Tokens can be lowercased more than once, such as literals, which are lowercased again when parsed
+            let file = self.files[token.file].as_str();
+            let warned = self.warnings.iter().any(|w| w.get_code() == CompileErrorCode::TokenCase && w.get_file() == file && w.get_position() == (token.line, token.column));
+            if !warned {
+                self.push_warning(CompileError::from_message(file, token.line, token.column, CompileErrorType::Warning, message!("token_lowercased", token.string, lowercase))
+                    .with_end_position(token.end.0, token.end.1)
+                    .with_code(CompileErrorCode::TokenCase)
+                    .with_fix(quote_name(&lowercase)));
+            }
+        }
+
+        lowercase
     }
 
     fn create_node_from_tokens(&mut self,
@@ -65,10 +283,21 @@ impl Compiler {
                                available_functions: &BTreeMap<&str, &dyn CallableFunction>,
                                available_globals: &BTreeMap<&str, &dyn CallableGlobal>) -> Result<Node, CompileError> {
         let node = match token.children.as_ref() {
-            Some(ref children) => {
+            // Empty blocks are only kept in lenient mode
+            Some(children) if children.is_empty() => {
+                compile_warn!(self, token, CompileErrorCode::EmptyBlock, message!("empty_block_compiled_as_begin"));
+                self.create_node_from_function("begin".to_owned(), token, expected_type, &[], available_parameters, available_functions, available_globals)?
+            },
+            Some(children) => {
                 let function_name = self.lowercase_token(&children[0]);
+                let original_name = Some(children[0].string.clone()).filter(|n| *n != function_name);
 
-                self.create_node_from_function(function_name, token, expected_type, &children[1..], available_parameters, available_functions, available_globals)?
+                let mut node = self.create_node_from_function(function_name, token, expected_type, &children[1..], available_parameters, available_functions, available_globals)?;
+                // Some functions are compiled as other functions, such as cond, which becomes if
+                if node.string_data.as_ref().map(|n| n.to_ascii_uppercase()) == original_name.as_ref().map(|n| n.to_ascii_uppercase()) {
+                    node.original_string_data = original_name;
+                }
+                node
             },
             None => {
                 // Figure out if it's a global
@@ -85,7 +314,10 @@ impl Compiler {
                     }
                     else {
                         if !value_type.can_convert_to(expected_type) {
-                            return_compile_error!(self, token, format!("global '{literal_lowercase}' is '{}' which cannot convert to '{}'", value_type.as_str(), expected_type.as_str()))
+                            return_compile_error!(self, token, cannot_convert_message("global_cannot_convert", "global_cannot_convert_without_cast", &literal_lowercase, value_type, expected_type, self.target, &self.engine_definitions))
+                        }
+                        if self.is_forbidden_conversion(value_type, expected_type) {
+                            return_compile_error!(self, token, message!("global_loses_precision", literal_lowercase, value_type, expected_type))
                         }
                         Ok(expected_type)
                     }
@@ -111,12 +343,16 @@ impl Compiler {
                     }
                 };
 
-                // Use the global name as the string data
-                literal = self.lowercase_token(token);
+                // Use the global name as the string data (literals are lowercased again when they are parsed, unless uppercase is allowed)
+                literal = match primitive_type {
+                    PrimitiveType::Static => literal_lowercase,
+                    _ => self.lowercase_token(token)
+                };
 
                 Node {
                     value_type: final_type,
                     node_type: NodeType::Primitive(primitive_type),
+                    original_string_data: Some(token.string.clone()).filter(|n| *n != literal),
                     string_data: Some(literal),
                     data: None,
                     parameters: None,
@@ -124,7 +360,8 @@ impl Compiler {
 
                     file: token.file,
                     line: token.line,
-                    column: token.column
+                    column: token.column,
+                    end: token.end
                 }
             }
         };
@@ -132,6 +369,7 @@ impl Compiler {
         Ok(node)
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn create_node_from_function(&mut self,
                                  function_name: String,
                                  function_call_token: &Token,
@@ -141,18 +379,52 @@ impl Compiler {
                                  available_functions: &BTreeMap<&str, &dyn CallableFunction>,
                                  available_globals: &BTreeMap<&str, &dyn CallableGlobal>) -> Result<Node, CompileError> {
 
+        // Time units are turned into a literal number of ticks, such as (seconds 2.5) into 75, unless a script has the same name
+        if let Some(ticks_per_unit) = ticks_per_time_unit(&function_name).filter(|_| self.time_units && !available_functions.contains_key(function_name.as_str())) {
+            let amount = match tokens {
+                [amount] if amount.children.is_none() => amount.string.parse::<f64>().ok().filter(|a| a.is_finite()),
+                _ => None
+            };
+            let ticks = match amount {
+                Some(n) => (n * ticks_per_unit).round(),
+                None => return_compile_error!(self, function_call_token, message!("time_unit_requires_number", function_name))
+            };
+
+            // This is parsed like any other literal by the function it is passed to
+            return Ok(Node {
+                value_type: expected_type,
+                node_type: NodeType::Primitive(PrimitiveType::Static),
+                string_data: Some(ticks.to_string()),
+                original_string_data: None,
+                data: None,
+                parameters: None,
+                index: None,
+
+                file: function_call_token.file,
+                line: function_call_token.line,
+                column: function_call_token.column,
+                end: function_call_token.end
+            })
+        }
+
         // Special handling for the cond function, turning (cond (condition1 expression1...) (condition2 expression2...)) into (if condition1 (begin expression1...) (if condition2 (begin expression2...) ...)
         if function_name == "cond" {
             // Make sure we have somewhere first
             if tokens.is_empty() {
-                return_compile_error!(self, function_call_token, format!("cond requires at least one set of expressions"))
+                return_compile_error!(self, function_call_token, message!("cond_requires_expressions"))
+            }
+            if let Some(maximum_depth) = self.maximum_block_depth.filter(|m| tokens.len() > *m) {
+                return_compile_error!(self, function_call_token, message!("cond_too_many_conditions", maximum_depth))
+            }
+            if tokens.len() > MAXIMUM_BLOCK_DEPTH {
+                compile_warn!(self, function_call_token, CompileErrorCode::DeepNesting, message!("cond_many_conditions", MAXIMUM_BLOCK_DEPTH));
             }
 
             // Make our if statements
             let mut if_tree = Vec::<Token>::new();
             for token in tokens {
                 let fail = || {
-                    return_compile_error!(self, token, format!("cond requires each parameter to be (<condition> <expression(s)>)"))
+                    return_compile_error!(self, token, message!("cond_malformed_parameter"))
                 };
 
                 let children = match token.children.as_ref() {
@@ -165,14 +437,15 @@ impl Compiler {
                 let expressions = &children[1..];
 
                 // Make the begin block (begin <expression(s)>)
-                let mut expressions_vec = Vec::<Token>::new();
-                expressions_vec.reserve(expressions.len() + 1); // +1 for begin
+                let mut expressions_vec = Vec::<Token>::with_capacity(expressions.len() + 1); // +1 for begin
                 expressions_vec.push(Token {
                     line: expressions[0].line,
                     column: expressions[0].column,
                     file: expressions[0].file,
                     string: "begin".to_owned(),
-                    children: None
+                    children: None,
+                    synthetic: false,
+                    end: (expressions[0].line, expressions[0].column)
                 });
                 expressions_vec.extend_from_slice(expressions);
                 let begin_block = Token {
@@ -180,18 +453,21 @@ impl Compiler {
                     column: expressions[0].column,
                     file: expressions[0].file,
                     string: String::new(),
-                    children: Some(expressions_vec)
+                    children: Some(expressions_vec),
+                    synthetic: false,
+                    end: expressions[expressions.len() - 1].end
                 };
 
                 // Make the if statement (if (condition) (begin whatever the heck))
-                let mut if_expressions = Vec::<Token>::new();
-                if_expressions.reserve(3 + 1); // +1 in case there's an else condition
+                let mut if_expressions = Vec::<Token>::with_capacity(3 + 1); // +1 in case there's an else condition
                 if_expressions.push(Token {
                     line: token.line,
                     column: token.column,
                     file: token.file,
                     string: "if".to_owned(),
-                    children: None
+                    children: None,
+                    synthetic: false,
+                    end: (token.line, token.column)
                 });
                 if_expressions.push(condition.to_owned());
                 if_expressions.push(begin_block);
@@ -200,7 +476,9 @@ impl Compiler {
                     column: token.column,
                     file: token.file,
                     string: String::new(),
-                    children: Some(if_expressions)
+                    children: Some(if_expressions),
+                    synthetic: false,
+                    end: token.end
                 };
 
                 if_tree.push(if_block);
@@ -218,18 +496,45 @@ impl Compiler {
             return self.create_node_from_tokens(&if_tree.pop().unwrap(), expected_type, available_parameters, available_functions, available_globals);
         }
 
+        // An empty begin block does nothing
+        if function_name == "begin" && tokens.is_empty() {
+            if expected_type != ValueType::Passthrough && !ValueType::Void.can_convert_to(expected_type) {
+                return_compile_error!(self, function_call_token, message!("empty_begin_without_value", expected_type))
+            }
+
+            return Ok(Node {
+                value_type: ValueType::Void,
+                node_type: NodeType::FunctionCall(true),
+                string_data: Some(function_name),
+                original_string_data: None,
+                data: None,
+                parameters: Some(Vec::new()),
+                index: None,
+
+                file: function_call_token.file,
+                line: function_call_token.line,
+                column: function_call_token.column,
+                end: function_call_token.end
+            })
+        }
+
         // Get function information
         let function = match available_functions.get(function_name.as_str()) {
             Some(n) => n,
-            None => return_compile_error!(self, function_call_token, format!("function '{function_name}' is not defined"))
+            None => return_compile_error!(self, function_call_token, message!("function_not_defined", function_name))
         };
         let last_is_passthrough = function.is_passthrough_last();
 
+        // Some functions only make sense to be typed into the console
+        if function.is_console_only() && !self.console {
+            compile_warn!(self, function_call_token, CompileErrorCode::ConsoleFunction, message!("console_function", function_name));
+        }
+
         // Do we have enough parameters?
         let parameter_count = tokens.len();
         let minimum = function.get_minimum_parameter_count();
         if tokens.len() < minimum {
-            return_compile_error!(self, function_call_token, format!("function '{function_name}' takes at least {minimum} parameter(s), got {parameter_count} instead"))
+            return_compile_error!(self, function_call_token, message!("too_few_parameters", function_name, minimum, parameter_count))
         }
 
 
@@ -247,13 +552,30 @@ impl Compiler {
             // If this is the "set" function, the passthrough type should be the global type.
             if function_name == "set" {
                 let fn_token = &tokens[0];
-                if !matches!(fn_token.children, None) {
-                    return_compile_error!(self, function_call_token, "function 'set' cannot take a block as the variable name".to_owned())
+                if fn_token.children.is_some() {
+                    return_compile_error!(self, function_call_token, message!("set_variable_is_block"))
                 }
                 let string_data = self.lowercase_token(fn_token);
-                match available_globals.get(string_data.as_str()) {
-                    Some(n) => Some(n.get_value_type()),
-                    None => return_compile_error!(self, function_call_token, format!("parameter '{string_data}' is not a global variable name"))
+
+                // Parameters take precedence over globals, like when they are referenced anywhere else
+                if let Some(n) = parameter_index(string_data.as_str(), available_parameters) {
+                    Some(available_parameters[n].get_value_type())
+                }
+                else if let Some(n) = available_globals.get(string_data.as_str()) {
+                    Some(n.get_value_type())
+                }
+                else if available_functions.contains_key(string_data.as_str()) {
+                    return_compile_error!(self, fn_token, message!("set_target_is_function", string_data))
+                }
+                else if is_literal_name(&string_data) {
+                    return_compile_error!(self, fn_token, message!("set_target_is_literal", string_data))
+                }
+                else {
+                    let variables = available_parameters.iter().map(|p| p.name.as_str()).chain(available_globals.keys().copied());
+                    match closest_name(string_data.as_str(), variables) {
+                        Some(n) => return_compile_error!(self, fn_token, message!("not_a_global_did_you_mean", string_data, n)),
+                        None => return_compile_error!(self, fn_token, message!("not_a_global", string_data))
+                    }
                 }
             }
 
@@ -271,8 +593,7 @@ impl Compiler {
 
         // Go through each token and load them as parameters
         let mut parameters = Vec::<Node>::new();
-        for parameter_index in 0..parameter_count {
-            let token = &tokens[parameter_index];
+        for (parameter_index, token) in tokens.iter().enumerate() {
 
             // Get the type of this parameter. Or complain if this is impossible because we've hit the max number of parameters.
             let parameter_is_passthrough;
@@ -298,7 +619,7 @@ impl Compiler {
                 Some(n) => { parameter_is_passthrough = false; n },
 
                 // We exceeded the max number of parameters
-                None => return_compile_error!(self, token, format!("function '{function_name}' takes at most {} parameter(s) but extraneous parameter(s) were given", function.get_total_parameter_count()))
+                None => return_compile_error!(self, token, message!("too_many_parameters", function_name, function.get_total_parameter_count()))
             };
 
 
@@ -315,11 +636,11 @@ impl Compiler {
         }
 
 
-        // Set the index union to 0xFFFF for variables if set
+        // Set the index union to 0xFFFF for variables if set, like the original tools
         if function_name == "set" {
             let string_data = match &parameters[0].string_data {
                 Some(n) => n.to_ascii_lowercase(),
-                None => return_compile_error!(self, &tokens[0], format!("function 'set' requires a name of a variable"))
+                None => return_compile_error!(self, &tokens[0], message!("set_requires_variable"))
             };
 
             let parameter_type = match parameter_index(&string_data, available_parameters) {
@@ -328,7 +649,9 @@ impl Compiler {
             };
 
             debug_assert_eq!(parameters[0].node_type, NodeType::Primitive(parameter_type));
-            parameters[0].index = Some(0xFFFF);
+            if self.bug_compatibility.set_variable_index {
+                parameters[0].index = Some(0xFFFF);
+            }
         }
 
 
@@ -339,12 +662,12 @@ impl Compiler {
 
         // If we do number passthrough, make sure our passthrough type is numeric
         if function.is_number_passthrough() && !passthrough_type_is_numeric {
-            return_compile_error!(self, function_call_token, format!("passthrough parameters resolve to '{}', but function '{function_name}' takes only numeric parameters", final_passthrough_type.as_str()))
+            return_compile_error!(self, function_call_token, message!("passthrough_not_numeric", final_passthrough_type, function_name))
         }
 
         // Or if it's inequality, allow some types
         if function.is_inequality() && !(passthrough_type_is_numeric || final_passthrough_type == ValueType::GameDifficulty || final_passthrough_type == ValueType::Team) {
-            return_compile_error!(self, function_call_token, format!("passthrough parameters resolve to '{}', but function '{function_name}' is an inequality operator", final_passthrough_type.as_str()))
+            return_compile_error!(self, function_call_token, message!("passthrough_inequality", final_passthrough_type, function_name))
         }
 
 
@@ -354,7 +677,11 @@ impl Compiler {
 
             if matches!(parameter_node.node_type, NodeType::Primitive(PrimitiveType::Static)) {
                 let parameter_token = &tokens[parameter_index];
-                let string_to_parse = if function.is_uppercase_allowed_for_parameter(parameter_index) {
+                let string_to_parse = if parameter_token.children.is_some() {
+                    // Only time units are literals written as blocks, and they're already lowercase
+                    parameter_node.string_data.clone().unwrap()
+                }
+                else if function.is_uppercase_allowed_for_parameter(parameter_index) {
                     parameter_token.string.clone()
                 }
                 else {
@@ -377,10 +704,10 @@ impl Compiler {
 
                         match available_functions.get(string_to_parse_str) {
                             // If we have a function by this name, tell the user that such a function exists
-                            Some(_) => return_compile_error!(self, tokens[parameter_index], format!("cannot parse token '{string_to_parse_str}' as {value_type_name} and no global of this name defined; did you mean to call '({string_to_parse_str})' as a function?")),
+                            Some(_) => return_compile_error!(self, tokens[parameter_index], message!("cannot_parse_token_named_like_function", string_to_parse_str, value_type_name)),
 
                             // Otherwise we have no global or anything like that, so here
-                            None => return_compile_error!(self, tokens[parameter_index], format!("cannot parse token '{string_to_parse_str}' as {value_type_name} and no global of this name defined (expected {})", $allowed_values))
+                            None => return_compile_error!(self, tokens[parameter_index], message!("cannot_parse_token", string_to_parse_str, value_type_name, $allowed_values))
                         };
                     }};
                 }
@@ -399,7 +726,13 @@ impl Compiler {
                         clear_string_data = true;
                         match string_to_parse_str.parse::<i16>() {
                             Ok(n) => Some(NodeData::Short(n)),
-                            Err(_) => complain!("integer between [-32768,32767]")
+                            Err(_) => match string_to_parse_str.parse::<i64>() {
+                                Ok(n) => {
+                                    let suggestion = if n >= i32::MIN as i64 && n <= i32::MAX as i64 { "long" } else { "real" };
+                                    return_compile_error!(self, tokens[parameter_index], message!("short_out_of_range", n, suggestion))
+                                },
+                                Err(_) => complain!("integer between [-32768,32767]")
+                            }
                         }
                     },
 
@@ -407,52 +740,45 @@ impl Compiler {
                         clear_string_data = true;
                         match string_to_parse_str.parse::<i32>() {
                             Ok(n) => Some(NodeData::Long(n)),
-                            Err(_) => complain!("integer between [-2147483648,2147483647]")
+                            Err(_) => match string_to_parse_str.parse::<i128>() {
+                                Ok(n) => return_compile_error!(self, tokens[parameter_index], message!("long_out_of_range", n)),
+                                Err(_) => complain!("integer between [-2147483648,2147483647]")
+                            }
                         }
                     },
 
                     ValueType::Real => {
                         clear_string_data = true;
-                        match string_to_parse_str.parse::<f32>() {
-                            Ok(n) => Some(NodeData::Real(n)),
-                            Err(_) => complain!("numeric value")
-                        }
-                    },
-
-                    ValueType::GameDifficulty => {
-                        clear_string_data = false;
-                        match string_to_parse_str {
-                            "easy" => Some(NodeData::Short(0)),
-                            "normal" => Some(NodeData::Short(1)),
-                            "hard" => Some(NodeData::Short(2)),
-                            "impossible" => Some(NodeData::Short(3)),
-                            _ => complain!("easy/normal/hard/impossible")
+                        match string_to_parse_str.parse::<f32>().ok().filter(|_| is_number_literal(string_to_parse_str)) {
+                            Some(n) => {
+                                // Reals are single precision, so warn if the literal was written with more precision than we can store
+                                let written = string_to_parse_str.parse::<f64>().unwrap_or(n as f64);
+                                if n.is_infinite() && written.is_finite() {
+                                    compile_warn!(self, tokens[parameter_index], CompileErrorCode::RealPrecision, message!("real_out_of_range", string_to_parse_str, n));
+                                }
+                                else if n as f64 != written {
+                                    compile_warn!(self, tokens[parameter_index], CompileErrorCode::RealPrecision, message!("real_rounded", string_to_parse_str, n as f64));
+                                }
+                                Some(NodeData::Real(n))
+                            },
+                            None => complain!("numeric value")
                         }
                     },
 
-                    ValueType::Team => {
+                    ValueType::GameDifficulty | ValueType::Team => {
                         clear_string_data = false;
-                        match string_to_parse_str {
-                            "default" => Some(NodeData::Short(0)),
-                            "player" => Some(NodeData::Short(1)),
-                            "human" => Some(NodeData::Short(2)),
-                            "covenant" => Some(NodeData::Short(3)),
-                            "flood" => Some(NodeData::Short(4)),
-                            "sentinel" => Some(NodeData::Short(5)),
-                            "unused6" => Some(NodeData::Short(6)),
-                            "unused7" => Some(NodeData::Short(7)),
-                            "unused8" => Some(NodeData::Short(8)),
-                            "unused9" => Some(NodeData::Short(9)),
-                            _ => complain!("default/player/human/covenant/flood/sentinel/unused6/unused7/unused8/unused9")
+                        let values = parameter_node.value_type.enum_values();
+                        match values.iter().position(|v| *v == string_to_parse_str) {
+                            Some(n) => Some(NodeData::Short(n as i16)),
+                            None => complain!(values.join("/"))
                         }
                     },
 
                     ValueType::Script => {
                         clear_string_data = false;
                         match available_functions.get(string_to_parse_str) {
-                            Some(n) => match n.is_engine_function() {
-                                true => return_compile_error!(self, tokens[parameter_index], format!("no script '{string_to_parse_str}' defined (a function is defined by this name, but it cannot be used here)")),
-                                false => ()
+                            Some(n) => if n.is_engine_function() {
+                                return_compile_error!(self, tokens[parameter_index], message!("function_used_as_script", string_to_parse_str))
                             },
                             None => complain!("script name")
                         };
@@ -465,10 +791,30 @@ impl Compiler {
 
                     _ => {
                         clear_string_data = false;
+
+                        // Check against the names that exist, if we know them
+                        let value_type = parameter_node.value_type;
+                        let unknown = self.known_names.iter().find(|(t, _)| *t == value_type).and_then(|(_, names)| {
+                            let name = string_to_parse_str.to_ascii_lowercase();
+                            if names.contains(&name) {
+                                return None
+                            }
+                            Some(match closest_name(&name, names.iter().map(String::as_str)) {
+                                Some(n) => message!("unknown_name_did_you_mean", string_to_parse_str, value_type, n),
+                                None => message!("unknown_name", string_to_parse_str, value_type)
+                            })
+                        });
+                        if let Some(message) = unknown {
+                            return_compile_error!(self, tokens[parameter_index], message)
+                        }
                         None
                     }
                 };
 
+                parameter_node.original_string_data = match clear_string_data {
+                    false => parameter_node.original_string_data.take().filter(|n| *n != string_to_parse),
+                    true => None
+                };
                 parameter_node.string_data = if clear_string_data {
                     None
                 }
@@ -479,8 +825,11 @@ impl Compiler {
         }
 
         // Can we convert the function type?
-        if expected_type != ValueType::Passthrough && !final_type.can_convert_to(expected_type) {
-            return_compile_error!(self, function_call_token, format!("function '{function_name}' returns '{}' which cannot convert to '{}'", final_type.as_str(), expected_type.as_str()))
+        if expected_type != ValueType::Passthrough && function_return_type != ValueType::Passthrough && !function_return_type.can_convert_to(expected_type) {
+            return_compile_error!(self, function_call_token, cannot_convert_message("function_cannot_convert", "function_cannot_convert_without_cast", &function_name, function_return_type, expected_type, self.target, &self.engine_definitions))
+        }
+        if self.is_forbidden_conversion(function_return_type, expected_type) {
+            return_compile_error!(self, function_call_token, message!("function_loses_precision", function_name, function_return_type, expected_type))
         }
 
 
@@ -489,193 +838,167 @@ impl Compiler {
             value_type: final_type,
             node_type: NodeType::FunctionCall(function.is_engine_function()),
             string_data: Some(function_name),
+            original_string_data: None,
             data: None,
             parameters: Some(parameters),
             index: None,
 
             file: function_call_token.file,
             line: function_call_token.line,
-            column: function_call_token.column
+            column: function_call_token.column,
+            end: function_call_token.end
         })
     }
 
-    pub fn digest_tokens(&mut self) -> Result<CompiledScriptData, CompileError> {
-        let (mut scripts, mut globals) = {
-            let tokens : Vec<Token> = self.tokens.drain(..).collect();
-            let max_script_parameters = self.target.maximum_script_parameters();
+    /// Check that the name of a script, global, or parameter being defined can't be mistaken for a literal.
+    ///
+    /// This is an error unless lenient, in which case it is a warning, as the original tools accepted these names.
+    pub(crate) fn check_definition_name(&mut self, token: &Token, name: &str, message_key: &'static str) -> Result<(), CompileError> {
+        if !is_literal_name(name) {
+            return Ok(())
+        }
+        if self.strictness != Strictness::Lenient {
+            return_compile_error!(self, token, message!(message_key, name))
+        }
+        compile_warn!(self, token, CompileErrorCode::LiteralName, message!(message_key, name));
+        Ok(())
+    }
 
-            let mut scripts = Vec::<Script>::new();
-            let mut globals = Vec::<Global>::new();
+    /// Replace the error with a dedicated one if the script failed to compile because its final expression does not convert to its
+    /// return type.
+    fn explain_return_type_mismatch(&mut self,
+                                    script: &Script,
+                                    error: CompileError,
+                                    available_functions: &BTreeMap<&str, &dyn CallableFunction>,
+                                    available_globals: &BTreeMap<&str, &dyn CallableGlobal>) -> CompileError {
+        let children = script.original_token.children.as_ref().unwrap();
+        let final_expression = match children.last() {
+            Some(n) if script.return_type != ValueType::Void && children.len() > script.body_offset => n,
+            _ => return error
+        };
 
-            for token in tokens {
-                let children = token.children.as_ref().unwrap();
-
-                // Get the object type
-                let block_type = &children[0];
-                match self.lowercase_token(block_type).as_str() {
-                    "global" => {
-                        // Make sure we have enough tokens here
-                        match children.len() {
-                            n if n < 4 => {
-                                return_compile_error!(self, token, format!("incomplete global definition, expected (global <type> <name> <expression>)"));
-                            },
-                            n if n > 4 => {
-                                return_compile_error!(self, children[4], format!("extraneous token in global definition (note: globals do not have implicit begin blocks)"));
-                            },
-                            4 => (),
-                            _ => unreachable!()
-                        }
+        // Find the type of the final expression on its own, ignoring any warnings from doing so
+        let warning_count = self.warnings.len();
+        let final_type = self.create_node_from_tokens(final_expression, ValueType::Passthrough, &script.parameters, available_functions, available_globals).map(|n| n.value_type);
+        self.warnings.truncate(warning_count);
+
+        match final_type {
+            Ok(n) if n != ValueType::Passthrough && !n.can_convert_to(script.return_type) => {
+                let file = self.files[final_expression.file].as_str();
+                let return_type_token = &children[2];
+                CompileError::from_message(file, final_expression.line, final_expression.column, CompileErrorType::Error, message!("wrong_final_expression_type", script.name, script.return_type, n))
+                    .with_related_location(self.files[return_type_token.file].as_str(), return_type_token.line, return_type_token.column, message!("return_type_declared_here"))
+            },
+            _ => error
+        }
+    }
 
-                        // Add the global
-                        globals.push(Global {
-                            name: {
-                                let global_name_token = &children[2];
-                                if !matches!(global_name_token.children, None) {
-                                    return_compile_error!(self, global_name_token, format!("expected global name, got a block instead"))
-                                }
-                                self.lowercase_token(&global_name_token)
-                            },
-                            value_type: {
-                                let value_type_token = &children[1];
-                                let value_type_string = self.lowercase_token(&value_type_token);
-                                match ValueType::from_str_underscore(&value_type_string) {
-                                    Some(ValueType::Passthrough) => return_compile_error!(self, value_type_token, format!("cannot define '{value_type_string}' globals")),
-                                    Some(n) => n,
-                                    None => return_compile_error!(self, value_type_token, format!("expected global value type, got '{value_type_string}' instead"))
-                                }
-                            },
-                            original_token: token,
-                            node: Node::default() // we're going to parse this later
-                        });
-                    },
-                    "script" => {
-                        // Get the script type
-                        let script_type_token = match children.get(1) {
-                            Some(n) => n,
-                            None => return_compile_error!(self, token, format!("incomplete script definition, expected script type after 'script'"))
-                        };
-                        let script_type_string = self.lowercase_token(script_type_token);
-                        let script_type = match ScriptType::from_str(&script_type_string) {
-                            Some(n) => n,
-                            None => return_compile_error!(self, script_type_token, format!("expected script type, got '{script_type_string}' instead"))
-                        };
-                        let type_expected = !script_type.always_returns_void();
+    /// Get whether or not the conversion is allowed by the engine but not in pedantic mode.
+    fn is_forbidden_conversion(&self, from: ValueType, to: ValueType) -> bool {
+        self.strictness == Strictness::Pedantic && ValueType::conversion_chain(from, to) == Some(ConversionRule::NarrowingNumericConversion)
+    }
 
-                        // Do we have enough tokens?
-                        let minimum_number_of_tokens = script_type.expression_offset() + 1;
-                        if children.len() < minimum_number_of_tokens {
-                            return_compile_error!(self, token, format!("incomplete script definition, expected (script {script_type_string}{} <name> <expression(s)>)", if type_expected { "" } else { " <return type>" }))
-                        }
+    /// Error if the name of a script or global is too long.
+    fn check_name_length(&self, symbol_type: SymbolType, name: &str, token: &Token) -> Result<(), CompileError> {
+        let maximum = self.target_limits().maximum_name_length();
+        if name.len() > maximum {
+            let key = match symbol_type {
+                SymbolType::Script => "script_name_too_long",
+                SymbolType::Global => "global_name_too_long"
+            };
+            return_compile_error!(self, token, message!(key, name, maximum));
+        }
+        Ok(())
+    }
 
-                        // Parameters!
-                        let mut parameters = Vec::<ScriptParameter>::new();
-
-                        // Add the script
-                        scripts.push(Script {
-                            name: {
-                                let name_token = &children[minimum_number_of_tokens - 2];
-
-                                // Get the name. We may need to also get the script parameters.
-                                let name;
-                                match &name_token.children {
-                                    // If there are children, then that means script parameters were passed.
-                                    Some(c) => {
-                                        // Check if the target supports script parameters
-                                        if max_script_parameters == 0 {
-                                            return_compile_error!(self, name_token, format!("function parameters are not supported in {}", self.target));
-                                        }
-
-                                        // Can we even use them?
-                                        if script_type != ScriptType::Static && script_type != ScriptType::Stub {
-                                            return_compile_error!(self, name_token, format!("script parameters can only be used in static or stub functions"))
-                                        }
-
-                                        // Get the name
-                                        let name_token = &c[0];
-                                        if !matches!(name_token.children, None) {
-                                            return_compile_error!(self, name_token, format!("expected script name, got a block instead (note: function parameters are not supported prior to Halo 3)"))
-                                        }
-                                        name = self.lowercase_token(&name_token);
-
-                                        // Get the parameters
-                                        let parameter_tokens = &c[1..];
-                                        let parameter_count = parameter_tokens.len() - 1;
-                                        if parameter_count > max_script_parameters {
-                                            return_compile_error!(self, name_token, format!("only {max_script_parameters} script parameter(s) are supported in {}", self.target));
-                                        }
-
-                                        // Reserve it
-                                        parameters.reserve_exact(parameter_count);
-
-                                        for p in parameter_tokens {
-                                            let children = match &p.children {
-                                                Some(n) => n,
-                                                None => return_compile_error!(self, p, format!("expected script parameter"))
-                                            };
-
-                                            if children.len() != 2 || !matches!(children[0].children, None) || !matches!(children[1].children, None) {
-                                                return_compile_error!(self, p, format!("script parameters should be in (<type> <name>) format"))
-                                            }
-
-                                            let parameter_type = match ValueType::from_str_underscore(&children[0].string) {
-                                                Some(n) => n,
-                                                None => return_compile_error!(self, p, format!("expected parameter type, got {}", children[0].string))
-                                            };
-
-                                            let parameter_name = self.lowercase_token(&children[1]);
-                                            parameters.push(ScriptParameter { name: parameter_name, value_type: parameter_type, original_token: children[1].clone() });
-                                        }
-                                    },
-                                    None => name = self.lowercase_token(&name_token)
-                                };
-
-                                match name.as_str() {
-                                    "begin" | "if" | "cond" => return_compile_error!(self, name_token, format!("function '{name}' cannot be overridden by a script")),
-                                    _ => ()
-                                }
+    /// Record the error and keep going if error recovery or tolerant mode is enabled, or return it otherwise.
+    fn recover_from_error(&mut self, error: CompileError) -> Result<(), CompileError> {
+        if !self.error_recovery && !self.tolerant {
+            return Err(error)
+        }
+        self.errors.push(error);
+        self.check_diagnostic_limits()
+    }
 
-                                name
-                            },
-                            return_type: if type_expected {
-                                let return_type_token = &children[2];
-                                let return_type_token_string = self.lowercase_token(return_type_token);
-
-                                match ValueType::from_str_underscore(&return_type_token_string) {
-                                    Some(ValueType::Passthrough) => return_compile_error!(self, return_type_token, format!("cannot define '{return_type_token_string}' scripts")),
-                                    Some(n) => n,
-                                    None => return_compile_error!(self, return_type_token, format!("expected script return value type, got '{return_type_token_string}' instead"))
-                                }
-                            }
-                            else {
-                                ValueType::Void
-                            },
-                            script_type: script_type,
-                            original_token: token,
-                            parameters: parameters,
+    /// Record the warning unless the warning limit was already exceeded.
+    fn push_warning(&mut self, warning: CompileError) {
+        // One warning past the limit is kept so the limit can be reported at it
+        if self.maximum_warnings.map(|m| self.warnings.len() <= m).unwrap_or(true) {
+            self.warnings.push(warning);
+        }
+    }
 
-                            node: Node::default() // we're going to parse this later
-                        });
-                    },
-                    n => return_compile_error!(self, block_type, format!("expected 'global' or 'script', got '{n}' instead"))
+    /// Take all warnings, sorted by file (in the order the files were read), line, and column.
+    ///
+    /// Warnings are found in whatever order each check walks the scripts and globals, so they are sorted to keep build logs comparable.
+    /// The sort is stable, so warnings at the same position stay in the order they were found.
+    pub(crate) fn take_warnings(&mut self) -> Vec<CompileError> {
+        let mut warnings: Vec<CompileError> = self.warnings.drain(..).collect();
+        let files = &self.files;
+        warnings.sort_by_key(|w| (files.iter().position(|f| f == w.get_file()), w.get_position()));
+        warnings
+    }
+
+    /// Error if more warnings or recovered errors were produced than allowed, dropping any past the limit.
+    ///
+    /// The error is placed at the first diagnostic that was dropped.
+    pub(crate) fn check_diagnostic_limits(&mut self) -> Result<(), CompileError> {
+        let limits = [(&mut self.warnings, self.maximum_warnings, "too_many_warnings"), (&mut self.errors, self.maximum_errors, "too_many_errors")];
+        for (diagnostics, maximum, key) in limits {
+            let maximum = match maximum {
+                Some(n) if diagnostics.len() > n => n,
+                _ => continue
+            };
+            let first_dropped = &diagnostics[maximum];
+            let (line, column) = first_dropped.get_position();
+            let error = CompileError::from_message(first_dropped.get_file(), line, column, CompileErrorType::Error, message!(key, maximum))
+                .with_code(CompileErrorCode::DiagnosticLimit);
+            diagnostics.truncate(maximum);
+            return Err(error)
+        }
+        Ok(())
+    }
+
+    /// Parse and check all loaded tokens without resolving any indices, clearing the tokens.
+    ///
+    /// Returns the scripts, the globals, and the number of nodes removed by optimization. With error recovery or in tolerant mode,
+    /// scripts and globals that fail to parse are skipped, and their errors are recorded instead.
+    pub(super) fn analyze_tokens(&mut self) -> Result<(Vec<Script>, Vec<Global>, usize), CompileError> {
+        let start = std::time::Instant::now();
+        let error_count = self.errors.len();
+        let (mut scripts, mut globals) = {
+            let tokens : Vec<Token> = self.tokens.drain(..).collect();
+            let dialect = self.target.dialect();
+
+            let mut scripts = Vec::<Script>::new();
+            let mut globals = Vec::<Global>::new();
+
+            for token in tokens {
+                match dialect.parse_declaration(self, token) {
+                    Ok(Declaration::Script(s)) => scripts.push(s),
+                    Ok(Declaration::Global(g)) => globals.push(g),
+                    Err(e) => self.recover_from_error(e)?
                 }
             }
 
             (scripts, globals)
         };
+        self.report_phase(CompilePhase::Parse, start, scripts.len() + globals.len(), |_| definition_memory(&scripts, &globals));
 
         // Get all the things we can use
+        let start = std::time::Instant::now();
         let target = self.target;
+        let external_scripts = self.external_scripts.clone();
+        let external_globals = self.external_globals.clone();
+        let engine_definitions = self.engine_definitions.clone();
         let (callable_functions, callable_globals) = {
-            let mut callable_functions = BTreeMap::<&str, &dyn CallableFunction>::new();
-            let mut callable_globals = BTreeMap::<&str, &dyn CallableGlobal>::new();
-
-            let (targeted_functions, targeted_globals) = all_functions_and_globals_for_target(target);
+            let (mut callable_functions, mut callable_globals) = callable_engine_functions_and_globals(target, &engine_definitions);
 
             // Add everything
-            for f in targeted_functions {
-                callable_functions.insert(f.get_name(), f);
+            for s in &external_scripts {
+                callable_functions.insert(s.get_name(), s);
             }
-            for g in targeted_globals {
+            for g in &external_globals {
                 callable_globals.insert(g.get_name(), g);
             }
             for s in &scripts {
@@ -689,61 +1012,54 @@ impl Compiler {
             (callable_functions, callable_globals)
         };
 
-        let mut global_nodes = std::collections::VecDeque::<Node>::new();
-        let mut script_nodes = std::collections::VecDeque::<Node>::new();
+        let mut global_nodes = std::collections::VecDeque::<Option<Node>>::new();
+        let mut script_nodes = std::collections::VecDeque::<Option<Node>>::new();
 
         // Parse all the globals
         for g in &globals {
-            if g.name.len() > 31 {
-                return_compile_error!(self, g.original_token, format!("global name '{}' exceeds 31 characters in length", g.name));
-            }
-            global_nodes.push_back(self.create_node_from_function("begin".to_owned(), &g.original_token, g.value_type, &g.original_token.children.as_ref().unwrap()[3..], &[], &callable_functions, &callable_globals)?);
+            let node = self.check_name_length(SymbolType::Global, &g.name, &g.original_token)
+                .and_then(|_| self.create_node_from_function("begin".to_owned(), &g.original_token, g.value_type, &g.original_token.children.as_ref().unwrap()[g.body_offset..], &[], &callable_functions, &callable_globals));
+            global_nodes.push_back(match node {
+                Ok(n) => Some(n),
+                Err(e) => { self.recover_from_error(e)?; None }
+            });
+            self.check_diagnostic_limits()?;
         }
 
         // Now parse all the scripts
         for s in &scripts {
-            if s.name.len() > 31 {
-                return_compile_error!(self, s.original_token, format!("script name '{}' exceeds 31 characters in length", s.name));
-            }
-            script_nodes.push_back(self.create_node_from_function("begin".to_owned(), &s.original_token, s.return_type, &s.original_token.children.as_ref().unwrap()[s.script_type.expression_offset()..], &s.parameters, &callable_functions, &callable_globals)?);
+            let node = self.check_name_length(SymbolType::Script, &s.name, &s.original_token)
+                .and_then(|_| self.create_node_from_function("begin".to_owned(), &s.original_token, s.return_type, &s.original_token.children.as_ref().unwrap()[s.body_offset..], &s.parameters, &callable_functions, &callable_globals))
+                .map_err(|e| self.explain_return_type_mismatch(s, e, &callable_functions, &callable_globals));
+            script_nodes.push_back(match node {
+                Ok(n) => Some(n),
+                Err(e) => { self.recover_from_error(e)?; None }
+            });
+            self.check_diagnostic_limits()?;
         }
 
-        // Move all the globals and scripts
-        for g in &mut globals {
-            g.node = global_nodes.pop_front().unwrap();
-        }
-        for s in &mut scripts {
-            s.node = script_nodes.pop_front().unwrap();
-        }
+        // Move all the globals and scripts, dropping any that failed to parse
+        globals.retain_mut(|g| match global_nodes.pop_front().unwrap() {
+            Some(n) => { g.node = n; true },
+            None => false
+        });
+        scripts.retain_mut(|s| match script_nodes.pop_front().unwrap() {
+            Some(n) => { s.node = n; true },
+            None => false
+        });
 
         // Optimize 'begin' nodes with only one call
-        fn optimize_begin(node_to_optimize: &mut Node) {
-            while matches!(node_to_optimize.node_type, NodeType::FunctionCall(true)) && node_to_optimize.string_data.as_ref().unwrap() == "begin" {
-                let parameters = node_to_optimize.parameters.as_mut().unwrap();
-                if parameters.len() == 1 {
-                    *node_to_optimize = parameters.pop().unwrap();
-                }
-                else {
-                    break;
-                }
+        let mut optimized_node_count = 0;
+        if self.optimize_begin {
+            for g in &mut globals {
+                optimized_node_count += optimize_begin(&mut g.node);
             }
 
-            // Optimize its parameters
-            if let NodeType::FunctionCall(_) = node_to_optimize.node_type {
-                for i in node_to_optimize.parameters.as_mut().unwrap() {
-                    optimize_begin(i);
-                }
+            for s in &mut scripts {
+                optimized_node_count += optimize_begin(&mut s.node);
             }
         }
 
-        for g in &mut globals {
-            optimize_begin(&mut g.node)
-        }
-
-        for s in &mut scripts {
-            optimize_begin(&mut s.node)
-        }
-
         // Remove stubbed scripts
         'remove_stubs_loop: loop {
             let script_count = scripts.len();
@@ -756,12 +1072,12 @@ impl Compiler {
 
                         // Is the script a static script?
                         if scripts[j].script_type != ScriptType::Static {
-                            return_compile_error!(self, scripts[i].original_token, format!("cannot replace stub script '{}' with non-static script", scripts[i].name))
+                            return_compile_error!(self, scripts[i].original_token, message!("stub_replaced_with_non_static", scripts[i].name))
                         }
 
                         // Does the type match?
                         if scripts[j].return_type != scripts[i].return_type {
-                            return_compile_error!(self, scripts[i].original_token, format!("cannot replace stub script '{}' that returns '{}' with static script which returns '{}'", scripts[i].return_type.as_str(), scripts[i].name, scripts[j].return_type.as_str()))
+                            return_compile_error!(self, scripts[i].original_token, message!("stub_return_type_mismatch", scripts[i].name, scripts[i].return_type, scripts[j].return_type))
                         }
 
                         // Okay, we can remove it
@@ -774,6 +1090,34 @@ impl Compiler {
             }
             break;
         }
+        self.report_phase(CompilePhase::TypeCheck, start, scripts.len() + globals.len(), |_| definition_memory(&scripts, &globals));
+
+        // Look for anything that can be simplified
+        let start = std::time::Instant::now();
+        for g in &globals {
+            self.lint_node(&g.node);
+        }
+        for s in &scripts {
+            self.lint_node(&s.node);
+            self.check_return_paths(s);
+            self.check_shadowed_globals(s, &globals);
+            self.check_unused_parameters(s);
+        }
+        if self.check_script_counts {
+            self.check_script_counts(&scripts);
+        }
+        self.check_tick_costs(&scripts);
+        if self.check_global_usage {
+            self.find_unused_global_accesses(&scripts, &globals);
+        }
+        if self.check_unset_globals {
+            self.check_unset_globals(&scripts);
+        }
+        if self.dedicated_server {
+            for node in globals.iter().map(|g| &g.node).chain(scripts.iter().map(|s| &s.node)) {
+                self.check_client_only_calls(node);
+            }
+        }
 
         // Ensure there are no duplicate scripts or globals
         let final_script_count = scripts.len();
@@ -783,111 +1127,127 @@ impl Compiler {
             let script_name = &scripts[i].name;
             for j in i+1..final_script_count {
                 if script_name == &scripts[j].name {
-                    return_compile_error!(self, scripts[i].original_token, format!("multiple scripts '{script_name}' defined"))
+                    return_compile_error!(self, scripts[i].original_token, message!("duplicate_script", script_name))
                 }
             }
+            if self.external_scripts.iter().any(|e| &e.name == script_name) {
+                return_compile_error!(self, scripts[i].original_token, message!("external_script_redefined", script_name))
+            }
         }
 
         for i in 0..final_global_count {
             let global_name = &globals[i].name;
             for j in i+1..final_global_count {
                 if global_name == &globals[j].name {
-                    return_compile_error!(self, globals[i].original_token, format!("multiple globals '{global_name}' defined"))
+                    return_compile_error!(self, globals[i].original_token, message!("duplicate_global", global_name))
                 }
             }
+            if self.external_globals.iter().any(|e| &e.name == global_name) {
+                return_compile_error!(self, globals[i].original_token, message!("external_global_redefined", global_name))
+            }
+        }
+
+        // Do we exceed the maximum number of scripts? Appended scripts start at the script index base.
+        let maximum_script_count = (i16::MAX as usize).saturating_sub(self.script_index_base);
+        if final_script_count > maximum_script_count {
+            return_compile_error!(self, scripts[maximum_script_count].original_token, message!("too_many_scripts", i16::MAX, self.script_index_base + final_script_count));
+        }
+
+        // Detect uninitialized globals, unless a startup script initializes them and that is allowed
+        fn find_uninitialized_globals(node: &Node, globals: &[Global], initialized_at_startup: &[&str], compiler: &mut Compiler) {
+            match node.node_type {
+                NodeType::Primitive(PrimitiveType::Global) => {
+                    let global_name = node.string_data.as_ref().unwrap().as_str();
+                    if initialized_at_startup.contains(&global_name) {
+                        return
+                    }
+                    for g in globals {
+                        if g.name == global_name {
+                            compile_warn!(compiler, node, CompileErrorCode::UninitializedGlobal, message!("uninitialized_global", global_name));
+                            break;
+                        }
+                    }
+                },
+                NodeType::FunctionCall(_) => for c in node.parameters.as_ref().unwrap() { find_uninitialized_globals(c, globals, initialized_at_startup, compiler); },
+                _ => ()
+            }
+        }
+        let mut initialized_at_startup = Vec::new();
+        if self.startup_initialization {
+            let mut initializations = Vec::new();
+            for s in scripts.iter().filter(|s| s.script_type == ScriptType::Startup) {
+                find_unconditional_initializations(&s.node, &mut Vec::new(), &mut false, &mut initializations);
+            }
+            initialized_at_startup.extend(initializations.into_iter().map(|i| i.name));
+        }
+        for i in 0..globals.len() {
+            find_uninitialized_globals(&globals[i].node, &globals[i..], &initialized_at_startup, self);
+        }
+        self.check_diagnostic_limits()?;
+        self.report_phase(CompilePhase::Lint, start, scripts.len() + globals.len(), |_| definition_memory(&scripts, &globals));
+
+        // Anything that failed to parse was skipped, so the script data is incomplete (tolerant mode checks this once it is resolved)
+        if !self.tolerant {
+            if let Some(e) = self.errors.get(error_count) {
+                return Err(e.clone())
+            }
+        }
+
+        Ok((scripts, globals, optimized_node_count))
+    }
+
+    /// Resolve the indices of every script, global, and engine function referenced by the scripts and globals, renaming them first if
+    /// obfuscation is enabled, and return what was renamed.
+    fn resolve_definitions(&mut self, scripts: &mut [Script], globals: &mut [Global]) -> Result<Vec<RenamedSymbol>, CompileError> {
+        let target = self.target;
+
+        // Anything that failed to parse was skipped, so the script data is incomplete
+        if self.tolerant {
+            if let Some(e) = self.errors.first() {
+                return Err(e.clone())
+            }
         }
 
-        // Do we exceed the maximum number of scripts?
-        if final_script_count > i16::MAX as usize {
-            return_compile_error!(self, scripts[i16::MAX as usize + 1].original_token, format!("maximum script limit of {} exceeded ({} / {})", i16::MAX, final_script_count, i16::MAX));
+        // Rename scripts and globals if requested
+        let renamed_symbols = if self.obfuscate {
+            self.obfuscate_names(scripts, globals)
         }
+        else {
+            Vec::new()
+        };
 
         // Find the script and global indices
         let scripts_by_index = {
             let mut sbi = BTreeMap::<String, i16>::new();
-            for i in 0..final_script_count as usize {
-                sbi.insert(scripts[i].name.clone(), i as i16);
+            for s in &self.external_scripts {
+                if let Some(index) = s.index {
+                    sbi.insert(s.name.clone(), index as i16);
+                }
+            }
+            for (i, s) in scripts.iter().enumerate() {
+                sbi.insert(s.name.clone(), (self.script_index_base + i) as i16);
             }
             sbi
         };
         let globals_by_index = {
             let mut gbi = BTreeMap::<String, i32>::new();
-            for i in 0..final_global_count as usize {
-                gbi.insert(globals[i].name.clone(), i as i32);
+            for g in &self.external_globals {
+                if let Some(index) = g.index {
+                    gbi.insert(g.name.clone(), index as i32);
+                }
+            }
+            for (i, g) in globals.iter().enumerate() {
+                gbi.insert(g.name.clone(), (self.global_index_base + i) as i32);
             }
             gbi
         };
 
-        fn find_global_script_indices_for_node(node: &mut Node, function_parameters: &[ScriptParameter], scripts: &BTreeMap::<String, i16>, globals: &BTreeMap::<String, i32>, target: CompileTarget) -> Result<(), CompileError> {
-            match node.node_type {
-                NodeType::Primitive(PrimitiveType::Static) => {
-                    if node.value_type == ValueType::Script {
-                        node.data = Some(NodeData::Short(*scripts.get(node.string_data.as_ref().unwrap()).unwrap()));
-                    }
-                },
-                NodeType::Primitive(PrimitiveType::Local) => {
-                    node.data = Some(NodeData::Long(parameter_index(node.string_data.as_ref().unwrap(), function_parameters).unwrap() as i32));
-                },
-                NodeType::Primitive(PrimitiveType::Global) => {
-                    let string_data = node.string_data.as_ref().unwrap();
-
-                    // Otherwise try getting the global index
-                    match globals.get(string_data) {
-                        Some(n) => node.data = Some(NodeData::Long(*n)),
-                        None => ()
-                    }
-                },
-                NodeType::FunctionCall(is_engine_function) => {
-                    let name = node.string_data.as_ref().unwrap();
-
-                    // If it's an engine function, the node gets the index of the function
-                    if is_engine_function {
-                        for i in ALL_FUNCTIONS {
-                            if i.name == name {
-                                node.index = i.availability.index_for_target(target);
-                                break;
-                            }
-                        }
-
-                        debug_assert!(node.index != None)
-                    }
-                    // If it's not an engine function, the node gets the index of the script then
-                    else {
-                        let index = *scripts.get(name).unwrap();
-                        node.index = Some(index as u16);
-                    }
-
-                    for p in node.parameters.as_mut().unwrap() {
-                        find_global_script_indices_for_node(p, function_parameters, scripts, globals, target)?;
-                    }
-                }
-            }
-
-            Ok(())
-        }
-        for s in &mut scripts {
-            find_global_script_indices_for_node(&mut s.node, &s.parameters, &scripts_by_index, &globals_by_index, target)?;
+        for s in scripts.iter_mut() {
+            find_global_script_indices_for_node(&mut s.node, &s.parameters, &scripts_by_index, &globals_by_index, target, &self.engine_definitions)?;
         }
 
-        // Detect uninitialized globals (and also find script indices)
-        fn find_uninitialized_globals(node: &Node, globals: &[Global], compiler: &mut Compiler) {
-            match node.node_type {
-                NodeType::Primitive(PrimitiveType::Global) => {
-                    let global_name = node.string_data.as_ref().unwrap().as_str();
-                    for g in globals {
-                        if g.name == global_name {
-                            compile_warn!(compiler, node, format!("use of uninitialized global '{}'", global_name));
-                            break;
-                        }
-                    }
-                },
-                NodeType::FunctionCall(_) => for c in node.parameters.as_ref().unwrap() { find_uninitialized_globals(&c, globals, compiler); },
-                _ => ()
-            }
-        }
-        for i in 0..globals.len() {
-            find_global_script_indices_for_node(&mut globals[i].node, &[], &scripts_by_index, &globals_by_index, target)?;
-            find_uninitialized_globals(&globals[i].node, &globals[i..], self);
+        for g in globals.iter_mut() {
+            find_global_script_indices_for_node(&mut g.node, &[], &scripts_by_index, &globals_by_index, target, &self.engine_definitions)?;
         }
 
         // We should NOT have any passthrough stuff remaining
@@ -897,149 +1257,178 @@ impl Compiler {
                 assert!(node.value_type != ValueType::Passthrough);
                 assert!(node.value_type != ValueType::Unparsed);
 
-                match node.parameters.as_ref() {
-                    Some(n) => for i in n {
+                if let Some(n) = node.parameters.as_ref() {
+                    for i in n {
                         no_passthrough(i);
-                    },
-                    None => ()
+                    }
                 }
             }
-            for s in &scripts {
+            for s in scripts.iter() {
                 no_passthrough(&s.node);
             }
-            for g in &globals {
+            for g in globals.iter() {
                 no_passthrough(&g.node);
             }
         }
 
-        // All right, let's make our thing
-        let mut compiled_scripts = Vec::new();
-        let mut compiled_globals = Vec::new();
-        let mut nodes = Vec::new();
-
-        fn make_compiled_node_from_node(compiler: &Compiler, node: Node, node_array: &mut Vec<CompiledNode>, script_parameters: &[ScriptParameter]) -> usize {
-            // What type of node is it?
-            match node.node_type {
-                NodeType::Primitive(primitive_type) => {
-                    // Globals need to have string data set
-                    debug_assert!((primitive_type != PrimitiveType::Global && primitive_type != PrimitiveType::Local) || !matches!(node.string_data, None));
-
-                    let result = node_array.len();
-                    node_array.push(CompiledNode {
-                        node_type: node.node_type,
-                        value_type: node.value_type,
-                        data: node.data,
-                        string_data: match node.string_data { Some(n) => Some(CString::new(n.as_str()).unwrap()), None => None },
-                        next_node: None,
-                        index: node.index,
-
-                        file: node.file,
-                        column: node.column,
-                        line: node.line
-                    });
-                    result
-                },
-                NodeType::FunctionCall(_) => {
-                    let parameters = node.parameters.unwrap();
-
-                    // First let's get this function call done and over with
-                    let function_call_node = node_array.len();
-                    let function_name_node = function_call_node + 1;
-                    node_array.push(CompiledNode {
-                        node_type: node.node_type,
-                        value_type: node.value_type,
-                        data: Some(NodeData::NodeOffset(function_name_node)),
-                        string_data: None,
-                        next_node: None,
-                        index: node.index,
-
-                        file: node.file,
-                        column: node.column,
-                        line: node.line
-                    });
-
-                    // Next get the function name out of the way
-                    node_array.push(CompiledNode {
-                        node_type: NodeType::Primitive(PrimitiveType::Static),
-                        value_type: ValueType::FunctionName,
-                        data: Some(NodeData::Long(0)),
-                        string_data: match node.string_data { Some(n) => Some(CString::new(n.as_str()).unwrap()), None => None },
-                        next_node: None,
-                        index: node.index,
-
-                        file: node.file,
-                        column: node.column,
-                        line: node.line
-                    });
-
-                    // Let's get our parameters here now
-                    let mut previous_node = function_name_node;
-                    for p in parameters {
-                        let next_node = make_compiled_node_from_node(compiler, p, node_array, script_parameters);
-                        node_array[previous_node].next_node = Some(next_node);
-                        previous_node = next_node;
-                    }
+        Ok(renamed_symbols)
+    }
 
-                    // Done
-                    function_call_node
-                }
-            }
+    pub(super) fn digest_tokens_with_backend<B: EmitBackend>(&mut self, mut backend: B) -> Result<(B::Output, Vec<CompileError>), CompileError> {
+        let (mut scripts, mut globals, _) = self.analyze_tokens()?;
+        let start = std::time::Instant::now();
+        self.substitute_simulated_values(&mut scripts, &mut globals);
+        if self.propagate_constants {
+            self.propagate_constant_globals(&mut scripts, &mut globals);
         }
+        if !self.simulated_values.is_empty() {
+            self.fold_simulated_constants(&mut scripts, &mut globals);
+        }
+        self.resolve_definitions(&mut scripts, &mut globals)?;
+        self.emit_definitions(&scripts, &globals, &mut backend)?;
+        let output = backend.finish();
+        self.report_phase(CompilePhase::Emit, start, scripts.len() + globals.len(), |_| definition_memory(&scripts, &globals));
+
+        let warnings = self.take_warnings();
+        self.files.clear();
+        self.folding_ranges.clear();
+        Ok((output, warnings))
+    }
 
-        for s in scripts {
-            let mut parameters = Vec::new();
-            parameters.reserve_exact(s.parameters.len());
-            for p in &s.parameters {
-                parameters.push(CompiledScriptParameter {
-                    name: CString::new(p.name.as_str()).unwrap(),
-                    value_type: p.value_type,
-                    file: p.original_token.file,
-                    column: p.original_token.column,
-                    line: p.original_token.line,
-                });
-            }
-
-            compiled_scripts.push(
-                CompiledScript {
-                    name: CString::new(s.name.as_str()).unwrap(),
-                    value_type: s.return_type,
-                    script_type: s.script_type,
-                    first_node: make_compiled_node_from_node(self, s.node, &mut nodes, &s.parameters),
-                    parameters: parameters,
-
-                    file: s.original_token.file,
-                    column: s.original_token.column,
-                    line: s.original_token.line
-                }
-            )
+    pub fn digest_tokens(&mut self) -> Result<CompiledScriptData, CompileError> {
+        let (mut scripts, mut globals, optimized_node_count) = self.analyze_tokens()?;
+        let start = std::time::Instant::now();
+        self.substitute_simulated_values(&mut scripts, &mut globals);
+        let propagated_globals = if self.propagate_constants {
+            self.propagate_constant_globals(&mut scripts, &mut globals)
         }
-        for g in globals {
-            compiled_globals.push(
-                CompiledGlobal {
-                    name: CString::new(g.name.as_str()).unwrap(),
-                    value_type: g.value_type,
-                    first_node: make_compiled_node_from_node(self, g.node, &mut nodes, &[]),
-
-                    file: g.original_token.file,
-                    column: g.original_token.column,
-                    line: g.original_token.line
-                }
-            )
+        else {
+            Vec::new()
+        };
+        if !self.simulated_values.is_empty() {
+            self.fold_simulated_constants(&mut scripts, &mut globals);
         }
+        let renamed_symbols = self.resolve_definitions(&mut scripts, &mut globals)?;
+
+        // All right, let's make our thing
+        let mut emitter = ScenarioEmitter::default();
+        self.emit_definitions(&scripts, &globals, &mut emitter)?;
+        let (mut compiled_scripts, mut compiled_globals, mut nodes) = emitter.finish();
+
+        self.check_budgets(&compiled_scripts, &compiled_globals, &nodes);
+        offset_node_indices(&mut compiled_scripts, &mut compiled_globals, &mut nodes, self.node_index_base);
+        let relocations = self.find_relocations(&nodes);
+        self.report_phase(CompilePhase::Emit, start, nodes.len(), |_| compiled_node_memory(&nodes));
 
         // Make the files
+        let warnings = self.take_warnings();
         let mut files = Vec::<CString>::new();
         for i in self.files.drain(..) {
             files.push(CString::new(i.as_str()).unwrap());
         }
+        self.folding_ranges.clear();
 
         // Done!
         Ok(CompiledScriptData {
             scripts: compiled_scripts,
             globals: compiled_globals,
-            files: files,
-            warnings: self.warnings.drain(..).collect(),
-            nodes: nodes
+            files,
+            warnings,
+            nodes,
+            renamed_symbols,
+            propagated_globals,
+            relocations,
+            external_globals: self.external_globals.iter().map(|g| g.name.clone()).collect(),
+            optimized_node_count,
+            script_base: self.script_index_base,
+            global_base: self.global_index_base,
+            node_base: self.node_index_base,
+            target: self.target,
+            custom_target: self.custom_target.clone(),
+            encoding: self.encoding,
+            engine_definitions: self.engine_definitions.clone()
         })
     }
+
+    /// Find all nodes that reference external scripts and globals which were declared without an index.
+    fn find_relocations(&self, nodes: &[CompiledNode]) -> Vec<Relocation> {
+        let is_unresolved_script = |name: &str| self.external_scripts.iter().any(|s| s.index.is_none() && s.name == name);
+        let is_unresolved_global = |name: &str| self.external_globals.iter().any(|g| g.index.is_none() && g.name == name);
+
+        nodes.iter().enumerate().filter_map(|(i, n)| {
+            // Script calls get their name from their function name node
+            let name = match (n.node_type, n.data) {
+                (NodeType::FunctionCall(false), Some(NodeData::NodeOffset(function_name_node))) => nodes[function_name_node - self.node_index_base].get_string_data()?,
+                _ => n.get_string_data()?
+            };
+            let symbol_type = match (n.node_type, n.value_type) {
+                (NodeType::FunctionCall(false), _) if is_unresolved_script(name) => SymbolType::Script,
+                (NodeType::Primitive(PrimitiveType::Global), _) if is_unresolved_global(name) => SymbolType::Global,
+                (NodeType::Primitive(PrimitiveType::Static), ValueType::Script | ValueType::FunctionName) if is_unresolved_script(name) => SymbolType::Script,
+                _ => return None
+            };
+            Some(Relocation { symbol_type, name: CString::new(name).unwrap(), node: self.node_index_base + i })
+        }).collect()
+    }
+
+    pub(super) fn digest_expression(&mut self, expression: &str, expected_type: ValueType) -> Result<CompiledExpression, CompileError> {
+        // Compile with a clean slate so anything else loaded into the compiler is left alone
+        let files = std::mem::replace(&mut self.files, vec![EXPRESSION_FILE_NAME.to_owned()]);
+        let warnings = std::mem::take(&mut self.warnings);
+
+        let result = self.digest_expression_tokens(expression, expected_type);
+
+        let expression_warnings = self.take_warnings();
+        self.warnings = warnings;
+        self.files = files;
+
+        let (nodes, value_type) = result?;
+        Ok(CompiledExpression {
+            nodes,
+            value_type,
+            warnings: expression_warnings
+        })
+    }
+
+    fn digest_expression_tokens(&mut self, expression: &str, expected_type: ValueType) -> Result<(Vec<CompiledNode>, ValueType), CompileError> {
+        let TokenizedScript { mut tokens, end, .. } = self.tokenize(EXPRESSION_FILE_NAME, 0, expression.as_bytes(), CompileEncoding::UTF8)?;
+
+        // The console lets you leave out the outer parenthesis
+        if self.console && tokens.first().map(|t| t.string != "(") == Some(true) {
+            let parenthesis = |string: &str, column: usize| Token { line: 1, column, file: 0, string: string.to_owned(), children: None, synthetic: false, end: (1, column + 1) };
+            let end = expression.len() + 1;
+            tokens.insert(0, parenthesis("(", 1));
+            tokens.push(parenthesis(")", end));
+        }
+
+        // A lone token (such as a literal or a global) does not need to be in a block
+        let token = match tokens.len() {
+            0 => return Err(CompileError::from_message(EXPRESSION_FILE_NAME, 1, 1, CompileErrorType::Error, message!("expected_expression"))),
+            1 if tokens[0].string != "(" && tokens[0].string != ")" => tokens.into_iter().next().unwrap(),
+            _ => {
+                let mut tree = build_token_tree(EXPRESSION_FILE_NAME, tokens, end, TreeOptions { allow_empty_blocks: self.strictness == Strictness::Lenient, close_unterminated: false, maximum_depth: self.maximum_block_depth })?;
+                if tree.len() > 1 {
+                    return_compile_error!(self, tree[1], message!("expected_one_expression"))
+                }
+                tree.pop().unwrap()
+            }
+        };
+
+        let target = self.target;
+        let engine_definitions = self.engine_definitions.clone();
+        let (callable_functions, callable_globals) = callable_engine_functions_and_globals(target, &engine_definitions);
+
+        // Wrap it in a begin block so literals are parsed, then take it back out
+        let mut node = self.create_node_from_function("begin".to_owned(), &token, expected_type, std::slice::from_ref(&token), &[], &callable_functions, &callable_globals)?;
+        optimize_begin(&mut node);
+        self.lint_node(&node);
+        find_global_script_indices_for_node(&mut node, &[], &BTreeMap::new(), &BTreeMap::new(), target, &self.engine_definitions)?;
+
+        let value_type = node.value_type;
+        let mut emitter = ScenarioEmitter::default();
+        emitter.emit_node(TypedNode::new(&node, &self.files));
+        let (_, _, nodes) = emitter.finish();
+
+        Ok((nodes, value_type))
+    }
 }
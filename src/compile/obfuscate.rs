@@ -0,0 +1,93 @@
+use super::*;
+
+/// Get the generated name for the given index (a, b, ..., z, aa, ab, ...).
+fn generated_name(mut index: usize) -> String {
+    let mut name = Vec::new();
+    loop {
+        name.push(b'a' + (index % 26) as u8);
+        if index < 26 {
+            break;
+        }
+        index = index / 26 - 1;
+    }
+    name.reverse();
+    String::from_utf8(name).unwrap()
+}
+
+/// Rename all references to the renamed scripts and globals in the node and its parameters.
+fn rename_node(node: &mut Node, scripts: &BTreeMap<String, String>, globals: &BTreeMap<String, String>) {
+    let renamed = match node.node_type {
+        NodeType::FunctionCall(false) => Some(scripts),
+        NodeType::Primitive(PrimitiveType::Static) if node.value_type == ValueType::Script => Some(scripts),
+        NodeType::Primitive(PrimitiveType::Global) => Some(globals),
+        _ => None
+    };
+
+    if let Some(new_name) = renamed.and_then(|r| r.get(node.string_data.as_ref()?)) {
+        node.string_data = Some(new_name.clone());
+        node.original_string_data = None;
+    }
+
+    if let Some(parameters) = node.parameters.as_mut() {
+        for p in parameters {
+            rename_node(p, scripts, globals);
+        }
+    }
+}
+
+impl Compiler {
+    /// Rename scripts and globals to short generated names, returning what was renamed.
+    ///
+    /// Dormant scripts and preserved names keep their names, as they may be referenced by name from outside of the scripts.
+    pub(super) fn obfuscate_names(&self, scripts: &mut [Script], globals: &mut [Global]) -> Vec<RenamedSymbol> {
+        let is_preserved = |name: &str| self.preserved_names.iter().any(|n| n == name);
+        let is_renamed_script = |s: &Script| s.script_type != ScriptType::Dormant && !is_preserved(&s.name);
+
+        // Generated names must not collide with anything that keeps its name
+        let (engine_functions, engine_globals) = callable_engine_functions_and_globals(self.target, &self.engine_definitions);
+        let mut taken: Vec<&str> = RESERVED_NAMES.to_vec();
+        taken.extend(engine_functions.keys());
+        taken.extend(engine_globals.keys());
+        taken.extend(scripts.iter().filter(|s| !is_renamed_script(s)).map(|s| s.name.as_str()));
+        taken.extend(globals.iter().filter(|g| is_preserved(&g.name)).map(|g| g.name.as_str()));
+        taken.extend(self.external_scripts.iter().map(|s| s.name.as_str()));
+        taken.extend(self.external_globals.iter().map(|g| g.name.as_str()));
+
+        let mut next_index = 0;
+        let mut next_name = || loop {
+            let name = generated_name(next_index);
+            next_index += 1;
+            if !taken.contains(&name.as_str()) {
+                return name
+            }
+        };
+
+        let mut renamed_symbols = Vec::new();
+        let mut renamed_scripts = BTreeMap::<String, String>::new();
+        let mut renamed_globals = BTreeMap::<String, String>::new();
+
+        for s in scripts.iter().filter(|s| is_renamed_script(s)) {
+            renamed_scripts.insert(s.name.clone(), next_name());
+        }
+        for g in globals.iter().filter(|g| !is_preserved(&g.name)) {
+            renamed_globals.insert(g.name.clone(), next_name());
+        }
+
+        for s in scripts.iter_mut() {
+            if let Some(new_name) = renamed_scripts.get(&s.name) {
+                renamed_symbols.push(RenamedSymbol::new(SymbolType::Script, &s.name, new_name));
+                s.name = new_name.clone();
+            }
+            rename_node(&mut s.node, &renamed_scripts, &renamed_globals);
+        }
+        for g in globals.iter_mut() {
+            if let Some(new_name) = renamed_globals.get(&g.name) {
+                renamed_symbols.push(RenamedSymbol::new(SymbolType::Global, &g.name, new_name));
+                g.name = new_name.clone();
+            }
+            rename_node(&mut g.node, &renamed_scripts, &renamed_globals);
+        }
+
+        renamed_symbols
+    }
+}
@@ -0,0 +1,127 @@
+use super::*;
+
+/// Global whose references were replaced with its value by constant propagation.
+#[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct PropagatedGlobal {
+    name: String,
+    value_type: ValueType,
+    reference_count: usize,
+    removed: bool
+}
+
+impl PropagatedGlobal {
+    /// Get the name of the global.
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get the value type of the global.
+    pub fn get_value_type(&self) -> ValueType {
+        self.value_type
+    }
+
+    /// Get the number of references that were replaced with the value of the global.
+    pub fn get_reference_count(&self) -> usize {
+        self.reference_count
+    }
+
+    /// Get whether or not the global was removed, which is the case if every reference to it was replaced.
+    pub fn is_removed(&self) -> bool {
+        self.removed
+    }
+}
+
+/// Get the literal the node evaluates to, if it is one (or a `begin` block with only one literal).
+fn literal_value(node: &Node) -> Option<&Node> {
+    match node.node_type {
+        NodeType::Primitive(PrimitiveType::Static) => Some(node),
+        NodeType::FunctionCall(true) if node.string_data.as_deref() == Some("begin") => match node.parameters.as_deref()? {
+            [only] => literal_value(only),
+            _ => None
+        },
+        _ => None
+    }
+}
+
+/// Convert the literal to the value type, as the engine would when reading the global, or `None` if it can't be done at compile time.
+pub(super) fn convert_literal(literal: &Node, value_type: ValueType) -> Option<Node> {
+    let data = if value_type == literal.value_type {
+        literal.data
+    }
+    else {
+        match (literal.data?, value_type) {
+            (NodeData::Short(n), ValueType::Long) => Some(NodeData::Long(n as i32)),
+            (NodeData::Short(n), ValueType::Real) => Some(NodeData::Real(n as f32)),
+            (NodeData::Long(n), ValueType::Real) => Some(NodeData::Real(n as f32)),
+            _ => return None
+        }
+    };
+
+    Some(Node { value_type, data, ..literal.clone() })
+}
+
+/// Replace each reference to the global in the node with the literal, returning the number of references replaced and the number
+/// that could not be.
+fn substitute_global(node: &mut Node, name: &str, literal: &Node) -> (usize, usize) {
+    if node.node_type == NodeType::Primitive(PrimitiveType::Global) && node.string_data.as_deref() == Some(name) {
+        return match convert_literal(literal, node.value_type) {
+            Some(n) => {
+                *node = Node { file: node.file, line: node.line, column: node.column, end: node.end, ..n };
+                (1, 0)
+            },
+            None => (0, 1)
+        }
+    }
+
+    let mut counts = (0, 0);
+    for p in node.parameters.iter_mut().flatten() {
+        let (replaced, remaining) = substitute_global(p, name, literal);
+        counts.0 += replaced;
+        counts.1 += remaining;
+    }
+    counts
+}
+
+impl Compiler {
+    /// Replace references to globals that are initialized to a literal and never set with the literal, returning what was replaced.
+    ///
+    /// A global is removed if every reference to it was replaced. Preserved names are left alone.
+    pub(super) fn propagate_constant_globals(&self, scripts: &mut [Script], globals: &mut Vec<Global>) -> Vec<PropagatedGlobal> {
+        let written: Vec<String> = {
+            let mut usage = BTreeMap::new();
+            for node in scripts.iter().map(|s| &s.node).chain(globals.iter().map(|g| &g.node)) {
+                find_global_usage(node, &mut usage);
+            }
+            usage.into_iter().filter(|(_, u)| u.first_write.is_some()).map(|(name, _)| name.to_owned()).collect()
+        };
+
+        // Globals can only be initialized with earlier globals, so going in order also propagates globals initialized to other constants
+        let mut propagated_globals = Vec::new();
+        for i in 0..globals.len() {
+            let name = globals[i].name.clone();
+            if written.contains(&name) || self.preserved_names.contains(&name) {
+                continue
+            }
+            let literal = match literal_value(&globals[i].node) {
+                Some(n) => n.clone(),
+                None => continue
+            };
+
+            let (mut reference_count, mut remaining) = (0, 0);
+            let nodes = scripts.iter_mut().map(|s| &mut s.node).chain(globals.iter_mut().map(|g| &mut g.node));
+            for node in nodes {
+                let (replaced, not_replaced) = substitute_global(node, &name, &literal);
+                reference_count += replaced;
+                remaining += not_replaced;
+            }
+
+            if reference_count > 0 {
+                propagated_globals.push(PropagatedGlobal { name, value_type: globals[i].value_type, reference_count, removed: remaining == 0 });
+            }
+        }
+
+        globals.retain(|g| !propagated_globals.iter().any(|p| p.removed && p.name == g.name));
+        propagated_globals
+    }
+}
@@ -0,0 +1,39 @@
+use super::*;
+
+impl CompiledScriptData {
+    /// Get the name of the function called by a function call node.
+    fn called_function(&self, node: &CompiledNode) -> Option<&str> {
+        match (node.get_type(), node.get_data()) {
+            (NodeType::FunctionCall(_), Some(NodeData::NodeOffset(n))) => self.get_node(n)?.get_string_data(),
+            _ => None
+        }
+    }
+
+    /// Get the index of each node that calls the engine function or script with the given name, such as `ai_place`.
+    ///
+    /// Use [`CompiledScriptData::get_source_map_entry`] to find where each call is and which script or global it is in. Scripts that are
+    /// only referenced, such as with `wake`, are not called, so they are not included.
+    pub fn who_calls(&self, function: &str) -> Vec<usize> {
+        let function = function.to_ascii_lowercase();
+        self.nodes.iter().enumerate()
+            .filter(|(_, n)| self.called_function(n) == Some(function.as_str()))
+            .map(|(i, _)| self.node_base + i)
+            .collect()
+    }
+
+    /// Get the index of each `set` node that writes the global with the given name, such as `mission_state`.
+    ///
+    /// Use [`CompiledScriptData::get_source_map_entry`] to find where each write is and which script or global it is in. This includes
+    /// engine globals.
+    pub fn who_writes(&self, global: &str) -> Vec<usize> {
+        let global = global.to_ascii_lowercase();
+        self.nodes.iter().enumerate()
+            .filter(|(_, n)| n.get_type() == NodeType::FunctionCall(true) && self.called_function(n) == Some("set"))
+            .filter(|(_, n)| {
+                let variable = parameter_nodes(self, n).first().and_then(|v| self.get_node(*v));
+                variable.map(|v| v.get_type() == NodeType::Primitive(PrimitiveType::Global) && v.get_string_data() == Some(global.as_str())).unwrap_or(false)
+            })
+            .map(|(i, _)| self.node_base + i)
+            .collect()
+    }
+}
@@ -0,0 +1,248 @@
+use super::*;
+
+/// Value types whose values are paths to tags.
+const TAG_REFERENCE_TYPES: [ValueType; 8] = [
+    ValueType::Sound, ValueType::Effect, ValueType::Damage, ValueType::LoopingSound,
+    ValueType::AnimationGraph, ValueType::ActorVariant, ValueType::DamageEffect, ValueType::ObjectDefinition
+];
+
+/// Tag referenced by the compiled script data, as returned by [`CompileReport::get_tag_references`].
+#[derive(Clone, PartialEq, Debug)]
+pub struct TagReference {
+    /// Value type of the reference, which determines what kind of tag it is.
+    pub value_type: ValueType,
+
+    /// Path of the tag, as written in the script data.
+    pub path: String,
+
+    /// Index of the first node that references the tag.
+    pub node: usize
+}
+
+/// Script called or referenced (such as with `wake`) by a script or global, as returned by [`CompileReport::get_call_graph`].
+#[derive(Clone, PartialEq, Debug)]
+pub struct ScriptCall {
+    /// Whether the caller is a script or a global.
+    pub caller_type: SymbolType,
+
+    /// Index of the caller, corresponding to [`CompiledScriptData::get_scripts`] or [`CompiledScriptData::get_globals`].
+    pub caller_index: usize,
+
+    /// Name of the script being called.
+    pub script: String,
+
+    /// Index of the script being called, or `None` if it is an external script that is not resolved yet.
+    pub script_index: Option<usize>
+}
+
+/// Result of [`Compiler::compile_with_report`], bundling the compiled script data with everything a build pipeline may want to know
+/// about it.
+pub struct CompileReport {
+    script_data: Option<CompiledScriptData>,
+    files: Vec<String>,
+    diagnostics: Vec<CompileError>,
+    tag_references: Vec<TagReference>,
+    call_graph: Vec<ScriptCall>,
+    source_map: Vec<(usize, SourceMapEntry)>
+}
+
+impl CompileReport {
+    pub(crate) fn new(result: Result<CompiledScriptData, CompileError>, files: Vec<String>, mut diagnostics: Vec<CompileError>) -> CompileReport {
+        let file_index = |d: &CompileError| files.iter().position(|f| f == d.get_file());
+        let script_data = match result {
+            Ok(n) => n,
+            Err(e) => {
+                // With error recovery or in tolerant mode, the error returned was already recorded
+                if !diagnostics.iter().any(|d| d.get_position() == e.get_position() && d.get_message() == e.get_message()) {
+                    diagnostics.push(e);
+                }
+                diagnostics.sort_by_key(|d| (file_index(d), d.get_position()));
+                return CompileReport { script_data: None, files, diagnostics, tag_references: Vec::new(), call_graph: Vec::new(), source_map: Vec::new() }
+            }
+        };
+
+        diagnostics.extend(script_data.get_warnings().iter().cloned());
+        diagnostics.sort_by_key(|d| (file_index(d), d.get_position()));
+
+        let node_base = script_data.get_node_base();
+        let nodes = script_data.get_nodes();
+
+        let mut tag_references = Vec::<TagReference>::new();
+        let mut source_map = Vec::new();
+
+        for (i, n) in nodes.iter().enumerate() {
+            let node = node_base + i;
+            let entry = match script_data.get_source_map_entry(node) {
+                Some(n) => n,
+                None => continue
+            };
+            source_map.push((node, entry));
+
+            let value_type = n.get_value_type();
+            if n.get_type() != NodeType::Primitive(PrimitiveType::Static) || !TAG_REFERENCE_TYPES.contains(&value_type) {
+                continue
+            }
+            let path = match n.get_string_data() {
+                Some(n) => n,
+                None => continue
+            };
+            if !tag_references.iter().any(|t| t.value_type == value_type && t.path == path) {
+                tag_references.push(TagReference { value_type, path: path.to_owned(), node });
+            }
+        }
+
+        tag_references.sort_by(|a, b| (a.value_type.as_u16(), &a.path).cmp(&(b.value_type.as_u16(), &b.path)));
+        let call_graph = find_script_calls(&script_data);
+
+        CompileReport { script_data: Some(script_data), files, diagnostics, tag_references, call_graph, source_map }
+    }
+
+    /// Get whether or not the script data compiled.
+    pub fn is_success(&self) -> bool {
+        self.script_data.is_some()
+    }
+
+    /// Get the compiled script data, or `None` if compiling failed.
+    pub fn get_script_data(&self) -> Option<&CompiledScriptData> {
+        self.script_data.as_ref()
+    }
+
+    /// Take the compiled script data out of the report, or `None` if compiling failed.
+    pub fn into_script_data(self) -> Option<CompiledScriptData> {
+        self.script_data
+    }
+
+    /// Get the names of all files that were read, in the order they were read.
+    pub fn get_files(&self) -> &[String] {
+        &self.files
+    }
+
+    /// Get all errors and warnings, sorted by file and position.
+    pub fn get_diagnostics(&self) -> &[CompileError] {
+        &self.diagnostics
+    }
+
+    /// Get the statistics of the compiled script data, or `None` if compiling failed.
+    pub fn get_statistics(&self) -> Option<ScriptDataStatistics> {
+        self.script_data.as_ref().map(|s| s.get_statistics())
+    }
+
+    /// Get each tag referenced by the compiled script data once, sorted by value type and path.
+    pub fn get_tag_references(&self) -> &[TagReference] {
+        &self.tag_references
+    }
+
+    /// Get each script called or referenced by each script and global once, sorted by caller.
+    pub fn get_call_graph(&self) -> &[ScriptCall] {
+        &self.call_graph
+    }
+
+    /// Get the index of each node along with where it came from, as [`CompiledScriptData::get_source_map_entry`] would return.
+    pub fn get_source_map(&self) -> &[(usize, SourceMapEntry)] {
+        &self.source_map
+    }
+
+    /// Serialize the report to JSON.
+    ///
+    /// The object has the keys `success`, `files`, `diagnostics`, `statistics` (or `null` if compiling failed), `tag_references`,
+    /// `call_graph`, and `source_map`. File indices correspond to `files`. Each diagnostic has a `fix` replacing the source from its
+    /// position up to `end_line` and `end_column` with `text`, or `null` if it has none. The nodes themselves are not included.
+    pub fn to_json(&self) -> String {
+        let list = |items: Vec<String>| format!("[{}]", items.join(","));
+
+        let files = list(self.files.iter().map(|f| json_string(f)).collect());
+        let diagnostics = list(self.diagnostics.iter().map(|d| {
+            let (line, column) = d.get_position();
+            let related = list(d.get_related_locations().iter().map(|r| {
+                let (line, column) = r.get_position();
+                format!("{{\"file\":{},\"line\":{line},\"column\":{column},\"message\":{}}}", json_string(r.get_file()), json_string(r.get_message()))
+            }).collect());
+            let fix = match d.get_fix() {
+                Some(text) => {
+                    let (end_line, end_column) = d.get_end_position();
+                    format!("{{\"end_line\":{end_line},\"end_column\":{end_column},\"text\":{}}}", json_string(text))
+                },
+                None => "null".to_owned()
+            };
+            format!("{{\"type\":\"{}\",\"code\":{},\"file\":{},\"line\":{line},\"column\":{column},\"message\":{},\"related\":{related},\"fix\":{fix}}}",
+                    d.get_error_type(), d.get_code() as u16, json_string(d.get_file()), json_string(d.get_message()))
+        }).collect());
+        let statistics = match self.get_statistics() {
+            Some(s) => format!("{{\"script_count\":{},\"global_count\":{},\"node_count\":{},\"string_data_length\":{},\"maximum_node_count\":{},\"maximum_string_data_length\":{},\"optimized_node_count\":{}}}",
+                               s.script_count, s.global_count, s.node_count, s.string_data_length, s.maximum_node_count, s.maximum_string_data_length, s.optimized_node_count),
+            None => "null".to_owned()
+        };
+        let tag_references = list(self.tag_references.iter().map(|t| {
+            format!("{{\"type\":{},\"path\":{},\"node\":{}}}", json_string(t.value_type.as_str()), json_string(&t.path), t.node)
+        }).collect());
+        let call_graph = list(self.call_graph.iter().map(|c| {
+            let script_index = c.script_index.map(|i| i.to_string()).unwrap_or_else(|| "null".to_owned());
+            format!("{{\"caller_type\":\"{}\",\"caller\":{},\"script\":{},\"script_index\":{script_index}}}", c.caller_type.as_str(), c.caller_index, json_string(&c.script))
+        }).collect());
+        let source_map = list(self.source_map.iter().map(|(node, s)| {
+            format!("{{\"node\":{node},\"file\":{},\"line\":{},\"column\":{},\"owner_type\":\"{}\",\"owner\":{}}}", s.file, s.line, s.column, s.owner_type.as_str(), s.owner_index)
+        }).collect());
+
+        format!("{{\"success\":{},\"files\":{files},\"diagnostics\":{diagnostics},\"statistics\":{statistics},\"tag_references\":{tag_references},\"call_graph\":{call_graph},\"source_map\":{source_map}}}",
+                self.is_success())
+    }
+}
+
+/// Find each script called or referenced by each script and global once, sorted by caller.
+pub(super) fn find_script_calls(script_data: &CompiledScriptData) -> Vec<ScriptCall> {
+    let node_base = script_data.get_node_base();
+    let mut call_graph = Vec::<ScriptCall>::new();
+
+    for (i, n) in script_data.get_nodes().iter().enumerate() {
+        let entry = match script_data.get_source_map_entry(node_base + i) {
+            Some(n) => n,
+            None => continue
+        };
+
+        match (n.get_type(), n.get_value_type()) {
+            // Script calls get their name from their function name node
+            (NodeType::FunctionCall(false), _) => {
+                let script = match n.get_data() {
+                    Some(NodeData::NodeOffset(function_name_node)) => script_data.get_node(function_name_node).and_then(|f| f.get_string_data()),
+                    _ => None
+                };
+                if let Some(script) = script {
+                    call_graph.push(ScriptCall { caller_type: entry.owner_type, caller_index: entry.owner_index, script: script.to_owned(), script_index: n.get_index().map(usize::from) });
+                }
+            },
+
+            (NodeType::Primitive(PrimitiveType::Static), ValueType::Script) => if let Some(script) = n.get_string_data() {
+                let script_index = match n.get_data() {
+                    Some(NodeData::Short(index)) => Some(index as u16 as usize),
+                    _ => None
+                };
+                call_graph.push(ScriptCall { caller_type: entry.owner_type, caller_index: entry.owner_index, script: script.to_owned(), script_index });
+            },
+
+            _ => ()
+        }
+    }
+
+    call_graph.sort_by(|a, b| (a.caller_type as u8, a.caller_index, &a.script).cmp(&(b.caller_type as u8, b.caller_index, &b.script)));
+    call_graph.dedup();
+    call_graph
+}
+
+/// Quote the string as a JSON string.
+pub(super) fn json_string(string: &str) -> String {
+    let mut quoted = String::with_capacity(string.len() + 2);
+    quoted.push('"');
+    for c in string.chars() {
+        match c {
+            '"' => quoted += "\\\"",
+            '\\' => quoted += "\\\\",
+            '\n' => quoted += "\\n",
+            '\r' => quoted += "\\r",
+            '\t' => quoted += "\\t",
+            c if c.is_control() => quoted += &format!("\\u{:04x}", c as u32),
+            c => quoted.push(c)
+        }
+    }
+    quoted.push('"');
+    quoted
+}
@@ -0,0 +1,196 @@
+use super::*;
+
+/// Engine global or engine function assumed to always have a value, as set with [`Compiler::set_simulated_value`].
+#[derive(Clone)]
+pub(crate) struct SimulatedValue {
+    pub name: String,
+    pub literal: Node
+}
+
+/// Parse the value of a simulated engine global or function, or `None` if it can't be parsed.
+///
+/// This accepts the same literals as scripts do for the value type.
+fn parse_simulated_value(value_type: ValueType, value: &str) -> Option<NodeData> {
+    let value = value.to_ascii_lowercase();
+    match value_type {
+        ValueType::Boolean => match value.as_str() {
+            "0" | "false" | "off" => Some(NodeData::Boolean(false)),
+            "1" | "true" | "on" => Some(NodeData::Boolean(true)),
+            _ => None
+        },
+        ValueType::Short => value.parse::<i16>().ok().map(NodeData::Short),
+        ValueType::Long => value.parse::<i32>().ok().map(NodeData::Long),
+        ValueType::Real => value.parse::<f32>().ok().filter(|r| r.is_finite()).map(NodeData::Real),
+        _ => value_type.enum_values().iter().position(|v| *v == value).map(|n| NodeData::Short(n as i16))
+    }
+}
+
+/// Get the value of the literal as a number for comparing it, if it has one.
+fn literal_number(node: &Node) -> Option<f64> {
+    if node.node_type != NodeType::Primitive(PrimitiveType::Static) {
+        return None
+    }
+    match node.data? {
+        NodeData::Boolean(b) => Some(b as i32 as f64),
+        NodeData::Short(n) => Some(n as f64),
+        NodeData::Long(n) => Some(n as f64),
+        NodeData::Real(n) => Some(n as f64),
+        NodeData::NodeOffset(_) => None
+    }
+}
+
+/// Replace each reference to a simulated engine global and each call to a simulated engine function in the node with its value.
+///
+/// The variable passed to `set` is left alone, as it is written rather than read.
+fn substitute_simulated_values(node: &mut Node, values: &[&SimulatedValue]) {
+    let simulated = match node.node_type {
+        NodeType::Primitive(PrimitiveType::Global) => true,
+        NodeType::FunctionCall(true) => node.parameters.as_ref().map(|p| p.is_empty()).unwrap_or(false),
+        _ => false
+    };
+    if simulated {
+        let literal = values.iter().find(|v| node.string_data.as_deref() == Some(v.name.as_str())).and_then(|v| convert_literal(&v.literal, node.value_type));
+        if let Some(n) = literal {
+            *node = Node { file: node.file, line: node.line, column: node.column, end: node.end, ..n };
+            return
+        }
+    }
+
+    let first_read = if engine_function_name(node) == Some("set") { 1 } else { 0 };
+    for p in node.parameters.iter_mut().flatten().skip(first_read) {
+        substitute_simulated_values(p, values);
+    }
+}
+
+/// Evaluate `and` or `or`, or `None` if a parameter that is not a literal may be evaluated.
+fn fold_logic(parameters: &[Node], short_circuit: bool) -> Option<bool> {
+    for p in parameters {
+        if boolean_literal(p)? == short_circuit {
+            return Some(short_circuit)
+        }
+    }
+    Some(!short_circuit)
+}
+
+/// Fold comparisons, logic, and `if` branches in the node that have a fixed result, removing branches that are never taken.
+///
+/// An `if` is only replaced if what replaces it has the same value type, so the value is used the same way.
+fn fold_constants(node: &mut Node) {
+    for p in node.parameters.iter_mut().flatten() {
+        fold_constants(p);
+    }
+
+    // Branches removed from a begin block leave nothing behind, unless they were its value
+    if engine_function_name(node) == Some("begin") {
+        let parameters = node.parameters.as_mut().unwrap();
+        let last = parameters.len().saturating_sub(1);
+        let mut index = 0;
+        parameters.retain(|p| {
+            index += 1;
+            index > last || engine_function_name(p) != Some("begin") || p.parameters.as_ref().map(|p| !p.is_empty()).unwrap_or(true)
+        });
+    }
+
+    let parameters = match node.parameters.as_ref() {
+        Some(n) => n,
+        None => return
+    };
+    let boolean = match (engine_function_name(node), parameters.as_slice()) {
+        (Some("not"), [value]) => boolean_literal(value).map(|b| !b),
+        (Some("and"), _) => fold_logic(parameters, false),
+        (Some("or"), _) => fold_logic(parameters, true),
+        (Some(f), [a, b]) => match (literal_number(a), literal_number(b)) {
+            (Some(a), Some(b)) => match f {
+                "=" => Some(a == b),
+                "!=" => Some(a != b),
+                "<" => Some(a < b),
+                ">" => Some(a > b),
+                "<=" => Some(a <= b),
+                ">=" => Some(a >= b),
+                _ => None
+            },
+            _ => None
+        },
+        _ => None
+    };
+
+    let replacement = match boolean {
+        Some(b) => Node {
+            value_type: ValueType::Boolean,
+            node_type: NodeType::Primitive(PrimitiveType::Static),
+            string_data: None,
+            original_string_data: None,
+            data: Some(NodeData::Boolean(b)),
+            parameters: None,
+            index: None,
+            ..node.clone()
+        },
+        None if engine_function_name(node) == Some("if") => match boolean_literal(&parameters[0]) {
+            Some(true) => parameters[1].clone(),
+            Some(false) if parameters.len() > 2 => parameters[2].clone(),
+
+            // Nothing is left of an if without an else branch whose condition is always false
+            Some(false) if node.value_type == ValueType::Void => Node { parameters: Some(Vec::new()), string_data: Some("begin".to_owned()), original_string_data: None, ..node.clone() },
+            _ => return
+        },
+        None => return
+    };
+
+    if replacement.value_type == node.value_type {
+        *node = replacement;
+    }
+}
+
+impl Compiler {
+    /// Parse the value that the engine global or engine function is assumed to have.
+    pub(crate) fn parse_simulated_value(&self, name: &str, value: &str) -> Result<SimulatedValue, String> {
+        let name = name.to_ascii_lowercase();
+        let (functions, globals) = callable_engine_functions_and_globals(self.target, &self.engine_definitions);
+        let value_type = match (globals.get(name.as_str()), functions.get(name.as_str())) {
+            (Some(g), _) => g.get_value_type(),
+            (None, Some(f)) if f.get_total_parameter_count() == 0 => f.get_return_type(),
+            (None, Some(_)) => return Err(format!("cannot simulate '{name}', as only engine functions without parameters can be simulated")),
+            (None, None) => return Err(format!("'{name}' is not an engine global or function in {}", self.target))
+        };
+
+        if !matches!(value_type, ValueType::Boolean | ValueType::Short | ValueType::Long | ValueType::Real) && value_type.enum_values().is_empty() {
+            return Err(format!("cannot simulate '{name}', as {value_type} values cannot be simulated"))
+        }
+        let data = match parse_simulated_value(value_type, value) {
+            Some(n) => n,
+            None => return Err(format!("cannot parse '{value}' as a {value_type} for '{name}'"))
+        };
+
+        Ok(SimulatedValue {
+            name,
+            literal: Node {
+                value_type,
+                node_type: NodeType::Primitive(PrimitiveType::Static),
+                string_data: Some(value.to_ascii_lowercase()).filter(|_| !value_type.enum_values().is_empty()),
+                data: Some(data),
+                ..Node::default()
+            }
+        })
+    }
+
+    /// Replace simulated engine globals and functions with their values.
+    ///
+    /// Script globals with the same name as a simulated engine global are left alone. This runs before constant propagation, and
+    /// nothing is folded until `fold_simulated_constants` runs afterwards.
+    pub(super) fn substitute_simulated_values(&self, scripts: &mut [Script], globals: &mut [Global]) {
+        let values: Vec<&SimulatedValue> = self.simulated_values.iter().filter(|v| !globals.iter().any(|g| g.name == v.name)).collect();
+        for node in scripts.iter_mut().map(|s| &mut s.node).chain(globals.iter_mut().map(|g| &mut g.node)) {
+            substitute_simulated_values(node, &values);
+        }
+    }
+
+    /// Fold comparisons, logic, and `if` branches that have a fixed result once simulated values are substituted.
+    pub(super) fn fold_simulated_constants(&self, scripts: &mut [Script], globals: &mut [Global]) {
+        for node in scripts.iter_mut().map(|s| &mut s.node).chain(globals.iter_mut().map(|g| &mut g.node)) {
+            fold_constants(node);
+            if self.optimize_begin {
+                optimize_begin(node);
+            }
+        }
+    }
+}
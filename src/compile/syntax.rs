@@ -0,0 +1,372 @@
+use super::*;
+
+/// Name of the script node table, as stored in its header.
+const NODE_TABLE_NAME: &[u8] = b"script node";
+
+/// Size of the script node table header, in bytes.
+const NODE_TABLE_HEADER_SIZE: usize = 0x38;
+
+/// Size of each script node, in bytes.
+const NODE_SIZE: usize = 0x14;
+
+/// Salt of the first node.
+///
+/// This has not been compared against a scenario tag made by the original tools, so their salts may differ. The engine only needs
+/// the salt of each node ID to match the salt of the node it refers to.
+const FIRST_SALT: u16 = 0xE741;
+
+const NODE_FLAG_PRIMITIVE: u16 = 0x1;
+const NODE_FLAG_SCRIPT_CALL: u16 = 0x2;
+const NODE_FLAG_GLOBAL: u16 = 0x4;
+const NODE_FLAG_LOCAL: u16 = 0x10;
+
+/// Script syntax data and string data of a scenario tag, as returned by [`CompiledScriptData::to_syntax_data`] and read by
+/// [`CompiledScriptData::from_syntax_data`].
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct SyntaxData {
+    /// Script node table, including its header and every unused node up to the maximum for the target.
+    pub syntax_data: Vec<u8>,
+
+    /// Null terminated string data referenced by the nodes.
+    pub string_data: Vec<u8>
+}
+
+/// File name given to script data read from syntax data, which has no source.
+const SYNTAX_DATA_FILE_NAME: &str = "<syntax data>";
+
+/// Script of a scenario tag, as passed to [`CompiledScriptData::from_syntax_data`].
+#[derive(Clone, PartialEq)]
+pub struct SyntaxScript {
+    /// Name of the script.
+    pub name: String,
+
+    /// Type of the script.
+    pub script_type: ScriptType,
+
+    /// Return type of the script.
+    pub return_type: ValueType,
+
+    /// ID of the root node of the script, as stored in the tag. Only the index in the lower 16 bits is used.
+    pub root_node: u32,
+
+    /// Name and value type of each parameter.
+    pub parameters: Vec<(String, ValueType)>
+}
+
+/// Global of a scenario tag, as passed to [`CompiledScriptData::from_syntax_data`].
+#[derive(Clone, PartialEq, Debug)]
+pub struct SyntaxGlobal {
+    /// Name of the global.
+    pub name: String,
+
+    /// Value type of the global.
+    pub value_type: ValueType,
+
+    /// ID of the node that initializes the global, as stored in the tag. Only the index in the lower 16 bits is used.
+    pub initialization_node: u32
+}
+
+/// Get the salt of the node at the index. Salts always have the high bit set, so an ID of a valid node is never 0xFFFFFFFF.
+fn node_salt(index: usize) -> u16 {
+    0x8000 | FIRST_SALT.wrapping_add(index as u16)
+}
+
+/// Get the ID of the node at the index, which is its salt in the upper 16 bits and its index in the lower 16 bits.
+fn node_id(index: usize) -> u32 {
+    ((node_salt(index) as u32) << 16) | index as u32
+}
+
+impl CompiledScriptData {
+    /// Serialize the nodes into the script syntax data and string data of a scenario tag for the target, remapping engine function and
+    /// engine global indices first if the target differs from the one compiled for.
+    ///
+    /// Tag data is big endian on every target. Each node is given a salt, and each node with string data gets its own string in the
+    /// encoding compiled with, so strings are not deduplicated. Nodes without string data have an offset of 0.
+    ///
+    /// The layout has only been checked by reading it back with [`CompiledScriptData::from_syntax_data`], not against a scenario tag
+    /// made by the original tools, so it should be verified before relying on it.
+    ///
+    /// # Errors
+    ///
+    /// Errors if anything used is not supported by the target, if external scripts or globals have not been resolved, if the node index
+    /// base is not 0 (as the string data of the other nodes isn't known), or if the nodes or string data exceed the target's limits.
+    pub fn to_syntax_data<T: Target>(&self, target: T) -> Result<SyntaxData, CompileError> {
+        let error_at = |node: &CompiledNode, message: Message| {
+            CompileError::from_message(self.files[node.file].to_str().unwrap(), node.line, node.column, CompileErrorType::Error, message)
+        };
+
+        if let Some(r) = self.relocations.first() {
+            let message = match r.symbol_type {
+                SymbolType::Script => message!("unresolved_script", r.get_name()),
+                SymbolType::Global => message!("unresolved_global", r.get_name())
+            };
+            return Err(error_at(self.get_node(r.node).unwrap(), message).with_code(CompileErrorCode::UnresolvedSymbol))
+        }
+        if let Some(e) = self.check_against_target(&target).into_iter().next() {
+            return Err(e)
+        }
+        if self.node_base != 0 && !self.nodes.is_empty() {
+            return Err(error_at(&self.nodes[0], message!("syntax_data_node_base", self.node_base)))
+        }
+
+        let maximum_nodes = target.maximum_nodes();
+        if let Some(n) = self.nodes.get(maximum_nodes) {
+            return Err(error_at(n, message!("too_many_nodes", maximum_nodes, self.nodes.len())))
+        }
+
+        let mut syntax_data = Vec::with_capacity(NODE_TABLE_HEADER_SIZE + NODE_SIZE * maximum_nodes);
+        syntax_data.extend_from_slice(NODE_TABLE_NAME);
+        syntax_data.resize(0x20, 0);
+        syntax_data.extend_from_slice(&(maximum_nodes as u16).to_be_bytes());
+        syntax_data.extend_from_slice(&(NODE_SIZE as u16).to_be_bytes());
+        syntax_data.extend_from_slice(&[1, 0, 0, 0]);
+        syntax_data.extend_from_slice(b"d@t@");
+        syntax_data.extend_from_slice(&[0, 0]);
+        syntax_data.extend_from_slice(&(self.nodes.len() as u16).to_be_bytes());
+        syntax_data.extend_from_slice(&(self.nodes.len() as u16).to_be_bytes());
+        syntax_data.extend_from_slice(&node_salt(self.nodes.len()).to_be_bytes());
+        syntax_data.extend_from_slice(&[0, 0, 0, 0]);
+        debug_assert_eq!(NODE_TABLE_HEADER_SIZE, syntax_data.len());
+
+        let indices = self.indices_for_target(target.get_base_target());
+        let maximum_string_data_length = target.maximum_string_data_length();
+        let mut string_data = Vec::new();
+        let mut first_overflowing_node = None;
+        for (i, n) in self.nodes.iter().enumerate() {
+            let string_offset = match n.get_string_data() {
+                Some(s) => {
+                    let offset = string_data.len();
+                    string_data.extend_from_slice(self.encoding.encode_to_cstring(s).as_bytes_with_nul());
+                    if string_data.len() > maximum_string_data_length && first_overflowing_node.is_none() {
+                        first_overflowing_node = Some(n);
+                    }
+                    offset as u32
+                },
+                None => 0
+            };
+
+            let flags = match n.node_type {
+                NodeType::Primitive(PrimitiveType::Static) => NODE_FLAG_PRIMITIVE,
+                NodeType::Primitive(PrimitiveType::Global) => NODE_FLAG_PRIMITIVE | NODE_FLAG_GLOBAL,
+                NodeType::Primitive(PrimitiveType::Local) => NODE_FLAG_PRIMITIVE | NODE_FLAG_LOCAL,
+                NodeType::FunctionCall(true) => 0,
+                NodeType::FunctionCall(false) => NODE_FLAG_SCRIPT_CALL
+            };
+
+            // The data is a 32-bit union, so smaller values are at the start of it
+            let data = match n.data {
+                Some(NodeData::Boolean(b)) => [b as u8, 0, 0, 0],
+                Some(NodeData::Short(v)) => { let b = v.to_be_bytes(); [b[0], b[1], 0, 0] },
+                Some(NodeData::Long(v)) => v.to_be_bytes(),
+                Some(NodeData::Real(v)) => v.to_be_bytes(),
+                Some(NodeData::NodeOffset(o)) => node_id(o).to_be_bytes(),
+                None => u32::MAX.to_be_bytes()
+            };
+
+            syntax_data.extend_from_slice(&node_salt(i).to_be_bytes());
+            syntax_data.extend_from_slice(&indices[i].unwrap_or(n.value_type.as_u16()).to_be_bytes());
+            syntax_data.extend_from_slice(&n.value_type.as_u16().to_be_bytes());
+            syntax_data.extend_from_slice(&flags.to_be_bytes());
+            syntax_data.extend_from_slice(&n.next_node.map(node_id).unwrap_or(u32::MAX).to_be_bytes());
+            syntax_data.extend_from_slice(&string_offset.to_be_bytes());
+            syntax_data.extend_from_slice(&data);
+        }
+
+        if let Some(n) = first_overflowing_node {
+            return Err(error_at(n, message!("string_data_too_long", maximum_string_data_length, string_data.len())))
+        }
+
+        syntax_data.resize(NODE_TABLE_HEADER_SIZE + NODE_SIZE * maximum_nodes, 0);
+        Ok(SyntaxData { syntax_data, string_data })
+    }
+}
+
+fn syntax_data_error(message: Message) -> CompileError {
+    CompileError::from_message(SYNTAX_DATA_FILE_NAME, 1, 1, CompileErrorType::Error, message)
+}
+
+/// Whatever refers to a node in syntax data, for errors about invalid references.
+enum Referrer<'a> {
+    Node(usize),
+    Script(&'a str),
+    Global(&'a str)
+}
+
+impl<'a> Referrer<'a> {
+    fn invalid_reference(&self, index: impl ToString, node_count: usize) -> CompileError {
+        syntax_data_error(match self {
+            Referrer::Node(i) => message!("syntax_data_invalid_node_reference", i, index, node_count),
+            Referrer::Script(name) => message!("syntax_data_invalid_script_node", name, index, node_count),
+            Referrer::Global(name) => message!("syntax_data_invalid_global_node", name, index, node_count)
+        })
+    }
+}
+
+/// Get the node index from a node ID, or `None` if the ID is null.
+///
+/// # Errors
+///
+/// Errors if the node does not exist.
+fn node_index_from_id(id: u32, node_count: usize, referrer: &Referrer) -> Result<Option<usize>, CompileError> {
+    if id == u32::MAX {
+        return Ok(None)
+    }
+    let index = (id & 0xFFFF) as usize;
+    if index >= node_count {
+        return Err(referrer.invalid_reference(index, node_count))
+    }
+    Ok(Some(index))
+}
+
+impl CompiledScriptData {
+    /// Read the script syntax data and string data of a scenario tag back into script data, such as for inspecting the scripts of a
+    /// map or decompiling them with [`CompiledScriptData::to_source`].
+    ///
+    /// This is the inverse of [`CompiledScriptData::to_syntax_data`]. Scripts and globals are stored separately from the nodes in a
+    /// scenario tag, so they need to be given in the order they appear in the tag. Nothing read has a source position, so everything
+    /// is in a file named `<syntax data>` at line 0, column 0.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the node table header is invalid, if the data is truncated, if a node has an invalid value type, string offset, or
+    /// reference to another node, or if a script, parameter, or global name has a null character.
+    pub fn from_syntax_data(target: CompileTarget, encoding: CompileEncoding, syntax_data: &SyntaxData, scripts: &[SyntaxScript], globals: &[SyntaxGlobal]) -> Result<CompiledScriptData, CompileError> {
+        let table = syntax_data.syntax_data.as_slice();
+        let read_u16 = |offset: usize| u16::from_be_bytes([table[offset], table[offset + 1]]);
+        let read_u32 = |offset: usize| u32::from_be_bytes([table[offset], table[offset + 1], table[offset + 2], table[offset + 3]]);
+
+        if table.len() < NODE_TABLE_HEADER_SIZE || &table[0x28..0x2C] != b"d@t@" || read_u16(0x22) as usize != NODE_SIZE {
+            return Err(syntax_data_error(message!("syntax_data_invalid_header")))
+        }
+        let node_count = read_u16(0x2E) as usize;
+        let expected_length = NODE_TABLE_HEADER_SIZE + NODE_SIZE * node_count;
+        if table.len() < expected_length {
+            return Err(syntax_data_error(message!("syntax_data_truncated", expected_length, table.len())))
+        }
+
+        let mut nodes = Vec::with_capacity(node_count);
+        for i in 0..node_count {
+            let offset = NODE_TABLE_HEADER_SIZE + NODE_SIZE * i;
+            let index_union = read_u16(offset + 0x02);
+            let value_type = match ValueType::from_u16(read_u16(offset + 0x04)) {
+                Some(n) => n,
+                None => return Err(syntax_data_error(message!("syntax_data_invalid_value_type", i, read_u16(offset + 0x04))))
+            };
+            let flags = read_u16(offset + 0x06);
+            let next_node = node_index_from_id(read_u32(offset + 0x08), node_count, &Referrer::Node(i))?;
+            let raw_data = read_u32(offset + 0x10);
+
+            let node_type = if flags & NODE_FLAG_SCRIPT_CALL != 0 {
+                NodeType::FunctionCall(false)
+            }
+            else if flags & NODE_FLAG_PRIMITIVE == 0 {
+                NodeType::FunctionCall(true)
+            }
+            else if flags & NODE_FLAG_GLOBAL != 0 {
+                NodeType::Primitive(PrimitiveType::Global)
+            }
+            else if flags & NODE_FLAG_LOCAL != 0 {
+                NodeType::Primitive(PrimitiveType::Local)
+            }
+            else {
+                NodeType::Primitive(PrimitiveType::Static)
+            };
+
+            // Only the data that the compiler stores for each kind of node is read, as the rest is unused
+            let optional_data = |data: NodeData| if raw_data == u32::MAX { None } else { Some(data) };
+            let short = (raw_data >> 16) as u16 as i16;
+            let (data, has_string_data, index) = match (node_type, value_type) {
+                (NodeType::FunctionCall(_), _) => match node_index_from_id(raw_data, node_count, &Referrer::Node(i))? {
+                    Some(n) => (Some(NodeData::NodeOffset(n)), false, Some(index_union)),
+                    None => return Err(Referrer::Node(i).invalid_reference(raw_data, node_count))
+                },
+                (NodeType::Primitive(PrimitiveType::Global), _) => match optional_data(NodeData::Long(raw_data as i32)) {
+                    // Script globals have their index in the data, so the index union is only set for 'set'
+                    Some(data) => (Some(data), true, Some(index_union).filter(|&i| i == 0xFFFF)),
+                    // Engine globals without a known index have their value type in the index union, like other nodes without an index
+                    None => (None, true, Some(index_union).filter(|&i| i != value_type.as_u16()))
+                },
+                (NodeType::Primitive(PrimitiveType::Local), _) => (Some(NodeData::Long(raw_data as i32)), true, None),
+                (_, ValueType::FunctionName) => (Some(NodeData::Long(raw_data as i32)), true, Some(index_union)),
+                (_, ValueType::Boolean) => (Some(NodeData::Boolean(raw_data >> 24 != 0)), false, None),
+                (_, ValueType::Short) => (Some(NodeData::Short(short)), false, None),
+                (_, ValueType::Long) => (Some(NodeData::Long(raw_data as i32)), false, None),
+                (_, ValueType::Real) => (Some(NodeData::Real(f32::from_bits(raw_data))), false, None),
+                (_, ValueType::GameDifficulty) | (_, ValueType::Team) => (Some(NodeData::Short(short)), true, None),
+                (_, ValueType::Script) => (optional_data(NodeData::Short(short)), true, None),
+                (_, _) => (None, true, None)
+            };
+
+            let string_data = if has_string_data {
+                let string_offset = read_u32(offset + 0x0C) as usize;
+                let string = match syntax_data.string_data.get(string_offset..).and_then(|s| CStr::from_bytes_until_nul(s).ok()) {
+                    Some(n) => n,
+                    None => return Err(syntax_data_error(message!("syntax_data_invalid_string_offset", i, string_offset)))
+                };
+                match encoding.decode_from_cstring(string) {
+                    Ok(n) => Some(CString::new(n).unwrap()),
+                    Err(e) => return Err(syntax_data_error(message!("syntax_data_invalid_string", i, e)))
+                }
+            }
+            else {
+                None
+            };
+
+            nodes.push(CompiledNode { node_type, value_type, data, string_data, original_string_data: None, next_node, index, file: 0, line: 0, column: 0 });
+        }
+
+        let first_node = |id: u32, referrer: Referrer| match node_index_from_id(id, node_count, &referrer)? {
+            Some(n) => Ok(n),
+            None => Err(referrer.invalid_reference(id, node_count))
+        };
+        let name = |name: &str, message: Message| CString::new(name).map_err(|_| syntax_data_error(message));
+
+        let mut compiled_scripts = Vec::with_capacity(scripts.len());
+        for s in scripts {
+            let escaped_name = s.name.escape_debug().to_string();
+            let parameters = s.parameters.iter().enumerate().map(|(i, (parameter_name, value_type))| Ok(CompiledScriptParameter {
+                name: name(parameter_name, message!("syntax_data_invalid_parameter_name", i, escaped_name))?,
+                value_type: *value_type,
+                file: 0, line: 0, column: 0
+            })).collect::<Result<Vec<_>, CompileError>>()?;
+            compiled_scripts.push(CompiledScript {
+                name: name(&s.name, message!("syntax_data_invalid_script_name", escaped_name))?,
+                value_type: s.return_type,
+                script_type: s.script_type,
+                first_node: first_node(s.root_node, Referrer::Script(&s.name))?,
+                parameters,
+                file: 0, line: 0, column: 0
+            });
+        }
+
+        let mut compiled_globals = Vec::with_capacity(globals.len());
+        for g in globals {
+            compiled_globals.push(CompiledGlobal {
+                name: name(&g.name, message!("syntax_data_invalid_global_name", g.name.escape_debug()))?,
+                value_type: g.value_type,
+                first_node: first_node(g.initialization_node, Referrer::Global(&g.name))?,
+                file: 0, line: 0, column: 0
+            });
+        }
+
+        Ok(CompiledScriptData {
+            scripts: compiled_scripts,
+            globals: compiled_globals,
+            files: vec![CString::new(SYNTAX_DATA_FILE_NAME).unwrap()],
+            warnings: Vec::new(),
+            nodes,
+            renamed_symbols: Vec::new(),
+            propagated_globals: Vec::new(),
+            relocations: Vec::new(),
+            external_globals: Vec::new(),
+            optimized_node_count: 0,
+            script_base: 0,
+            global_base: 0,
+            node_base: 0,
+            target,
+            custom_target: None,
+            encoding,
+            engine_definitions: EngineDefinitions::default()
+        })
+    }
+}
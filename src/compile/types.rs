@@ -1,15 +1,57 @@
 use super::*;
 
 /// Result of a successful compilation.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct CompiledScriptData {
     pub(super) scripts: Vec<CompiledScript>,
     pub(super) globals: Vec<CompiledGlobal>,
     pub(super) files: Vec<CString>,
     pub(super) warnings: Vec<CompileError>,
-    pub(super) nodes: Vec<CompiledNode>
+    pub(super) nodes: Vec<CompiledNode>,
+    pub(super) renamed_symbols: Vec<RenamedSymbol>,
+    pub(super) propagated_globals: Vec<PropagatedGlobal>,
+    pub(super) relocations: Vec<Relocation>,
+
+    /// Names of the globals declared with [`Compiler::declare_external_global`], which are script globals defined elsewhere rather
+    /// than engine globals.
+    pub(super) external_globals: Vec<String>,
+    pub(super) optimized_node_count: usize,
+    pub(super) script_base: usize,
+    pub(super) global_base: usize,
+    pub(super) node_base: usize,
+    pub(super) target: CompileTarget,
+    pub(super) encoding: CompileEncoding,
+
+    /// Target compiled for if it is not a built-in target, in which case `target` is its base target.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(super) custom_target: Option<Arc<dyn Target>>,
+
+    /// Engine functions and globals known when compiling, including loaded ones, for checking against and retargeting to other targets.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(super) engine_definitions: EngineDefinitions
 }
 
 impl CompiledScriptData {
+    /// Get the target the script data was compiled for, or the target it is based on if it was compiled with
+    /// [`Compiler::with_target`].
+    pub fn get_target(&self) -> CompileTarget {
+        self.target
+    }
+
+    /// Get the limits of the target the script data was compiled for.
+    pub(super) fn target_limits(&self) -> &dyn Target {
+        match self.custom_target.as_deref() {
+            Some(n) => n,
+            None => &self.target
+        }
+    }
+
+    /// Get the encoding the script data was compiled with.
+    pub fn get_encoding(&self) -> CompileEncoding {
+        self.encoding
+    }
+
     /// Get all scripts that were compiled.
     pub fn get_scripts(&self) -> &[CompiledScript] {
         &self.scripts
@@ -26,17 +68,677 @@ impl CompiledScriptData {
     }
 
     /// Get all warnings from compiling.
+    ///
+    /// Warnings are sorted by file (in the order the files were read), line, and column. Warnings at the same position are in the order
+    /// they were found.
     pub fn get_warnings(&self) -> &[CompileError] {
         &self.warnings
     }
 
     /// Get all compiled nodes
+    ///
+    /// The first node is at the node index base, so that needs to be subtracted from node indices to index this. Use
+    /// [`CompiledScriptData::get_node`] to get a node by its index instead.
     pub fn get_nodes(&self) -> &[CompiledNode] {
         &self.nodes
     }
+
+    /// Get the node at the given index, or `None` if the node does not exist.
+    pub fn get_node(&self, index: usize) -> Option<&CompiledNode> {
+        self.nodes.get(index.checked_sub(self.node_base)?)
+    }
+
+    /// Get all references to external scripts and globals that have not been resolved yet.
+    ///
+    /// These are empty unless an external script or global was declared without an index. Resolve them with
+    /// [`CompiledScriptData::resolve_external`] or [`CompiledScriptData::link`] before using the script data.
+    pub fn get_relocations(&self) -> &[Relocation] {
+        &self.relocations
+    }
+
+    /// Resolve all references to the external script or global with the given name to the given index, returning the number of
+    /// nodes that were changed.
+    pub fn resolve_external(&mut self, symbol_type: SymbolType, name: &str, index: usize) -> usize {
+        let (resolved, unresolved): (Vec<Relocation>, Vec<Relocation>) = std::mem::take(&mut self.relocations).into_iter()
+            .partition(|r| r.symbol_type == symbol_type && r.get_name() == name);
+        self.relocations = unresolved;
+
+        for r in &resolved {
+            let node_index = r.node - self.node_base;
+            let node = &mut self.nodes[node_index];
+            match (node.node_type, node.value_type) {
+                (NodeType::Primitive(PrimitiveType::Global), _) => node.data = Some(NodeData::Long(index as i32)),
+                (NodeType::Primitive(PrimitiveType::Static), ValueType::Script) => node.data = Some(NodeData::Short(index as i16)),
+                _ => node.index = Some(index as u16)
+            }
+        }
+
+        resolved.len()
+    }
+
+    /// Resolve all references to external scripts and globals with the given resolver, which returns the index of a script or global
+    /// by its name, or `None` if it does not exist.
+    ///
+    /// The resolver is called once per symbol. To link against other script data, use [`CompiledScriptData::find_symbol_index`].
+    ///
+    /// # Errors
+    ///
+    /// Errors with one error per symbol that could not be resolved, listing every reference to it. Everything else is still resolved.
+    pub fn link<F: FnMut(SymbolType, &str) -> Option<usize>>(&mut self, mut resolver: F) -> Result<(), Vec<CompileError>> {
+        let mut symbols: Vec<(SymbolType, String)> = Vec::new();
+        for r in &self.relocations {
+            if !symbols.iter().any(|(t, n)| *t == r.symbol_type && n == r.get_name()) {
+                symbols.push((r.symbol_type, r.get_name().to_owned()));
+            }
+        }
+
+        let mut errors = Vec::new();
+        for (symbol_type, name) in symbols {
+            if let Some(index) = resolver(symbol_type, &name) {
+                self.resolve_external(symbol_type, &name, index);
+                continue
+            }
+
+            // A script call and its function name node are at the same position, so only list each position once
+            let mut locations: Vec<(usize, usize, usize)> = self.relocations.iter()
+                .filter(|r| r.symbol_type == symbol_type && r.get_name() == name)
+                .map(|r| self.get_node(r.node).unwrap())
+                .map(|n| (n.file, n.line, n.column))
+                .collect();
+            locations.dedup();
+
+            let (file, line, column) = locations[0];
+            let message = match symbol_type {
+                SymbolType::Script => message!("unresolved_script", name),
+                SymbolType::Global => message!("unresolved_global", name)
+            };
+            let mut error = CompileError::from_message(self.files[file].to_str().unwrap(), line, column, CompileErrorType::Error, message)
+                .with_code(CompileErrorCode::UnresolvedSymbol);
+            for &(file, line, column) in &locations[1..] {
+                error = error.with_related_location(self.files[file].to_str().unwrap(), line, column, message!("also_referenced_here", name));
+            }
+            errors.push(error);
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        }
+        else {
+            Err(errors)
+        }
+    }
+
+    /// Get the index of the script or global with the given name, offset by the index bases it was compiled with, or `None` if it
+    /// does not exist.
+    pub fn find_symbol_index(&self, symbol_type: SymbolType, name: &str) -> Option<usize> {
+        match symbol_type {
+            SymbolType::Script => self.scripts.iter().position(|s| s.get_name() == name).map(|i| self.script_base + i),
+            SymbolType::Global => self.globals.iter().position(|g| g.get_name() == name).map(|i| self.global_base + i)
+        }
+    }
+
+    /// Get the index of the first node, as set with [`Compiler::set_index_bases`].
+    ///
+    /// All node indices stored in the script data, such as [`CompiledNode::get_next_node_index`], are offset by this.
+    pub fn get_node_base(&self) -> usize {
+        self.node_base
+    }
+
+    /// Get all scripts and globals that were renamed by obfuscation.
+    ///
+    /// This is empty unless obfuscation was enabled with [`Compiler::set_obfuscate`].
+    pub fn get_renamed_symbols(&self) -> &[RenamedSymbol] {
+        &self.renamed_symbols
+    }
+
+    /// Find the renamed script or global with the given emitted name, such as one seen in a crash log.
+    pub fn find_renamed_symbol(&self, name: &str) -> Option<&RenamedSymbol> {
+        self.renamed_symbols.iter().find(|r| r.get_name() == name)
+    }
+
+    /// Get all globals whose references were replaced with their values.
+    ///
+    /// This is empty unless constant propagation was enabled with [`Compiler::set_propagate_constants`].
+    pub fn get_propagated_globals(&self) -> &[PropagatedGlobal] {
+        &self.propagated_globals
+    }
+
+    /// Get all renamed symbols as a rename map, with one `<type>\t<original name>\t<name>\t<hash>` line per symbol.
+    ///
+    /// The type is either `script` or `global`, and the hash is formatted as 8 lowercase hexadecimal digits.
+    pub fn get_rename_map(&self) -> String {
+        let mut map = String::new();
+        for r in &self.renamed_symbols {
+            map += &format!("{}\t{}\t{}\t{:08x}\n", r.symbol_type.as_str(), r.get_original_name(), r.get_name(), r.hash);
+        }
+        map
+    }
+
+    /// Get the indices of the nodes of the script in the order the engine begins evaluating them, or `None` if the script does not exist.
+    ///
+    /// A function call comes before its parameters, which are walked through their next node chain. Function name nodes are not
+    /// evaluated and are skipped. Every branch of a conditional (such as `if`) is included, as which one is taken is only known at runtime.
+    pub fn get_evaluation_order(&self, script_index: usize) -> Option<Vec<usize>> {
+        let script = self.scripts.get(script_index)?;
+        let mut order = Vec::new();
+        self.walk_evaluation_order(script.first_node, &mut order);
+        Some(order)
+    }
+
+    /// Get the source positions (line, column) in the given file that correspond to the start of a node the engine evaluates.
+    ///
+    /// The file index corresponds to [`CompiledScriptData::get_files`]. The positions are sorted and unique, so debuggers can snap a
+    /// requested breakpoint to the nearest valid position.
+    pub fn valid_breakpoint_positions(&self, file: usize) -> Vec<(usize, usize)> {
+        let mut positions: Vec<(usize, usize)> = self.nodes.iter()
+            .filter(|n| n.file == file && n.value_type != ValueType::FunctionName)
+            .map(|n| (n.line, n.column))
+            .collect();
+        positions.sort_unstable();
+        positions.dedup();
+        positions
+    }
+
+    /// Check the compiled script data against another target, returning an error for each function, global, or construct used that the
+    /// target does not support.
+    ///
+    /// This does not require recompiling, so it can be used to see what needs to change before porting scripts to another engine.
+    pub fn check_against_target<T: Target>(&self, target: T) -> Vec<CompileError> {
+        let (target_name, base) = (target.get_name(), target.get_base_target());
+        let mut errors = Vec::new();
+        let mut report = |file: usize, line: usize, column: usize, message: Message| {
+            errors.push(CompileError::from_message(self.files[file].to_str().unwrap(), line, column, CompileErrorType::Error, message).with_code(CompileErrorCode::UnsupportedByTarget));
+        };
+
+        let maximum_script_parameters = target.maximum_script_parameters();
+        for s in &self.scripts {
+            if s.parameters.len() > maximum_script_parameters {
+                let message = match maximum_script_parameters {
+                    0 => message!("target_script_parameters_unsupported", s.get_name(), target_name),
+                    n => message!("target_too_many_script_parameters", s.get_name(), s.parameters.len(), n, target_name)
+                };
+                report(s.file, s.line, s.column, message);
+            }
+        }
+
+        for n in &self.nodes {
+            match (n.node_type, n.data) {
+                (NodeType::FunctionCall(true), Some(NodeData::NodeOffset(function_name_node))) => {
+                    let function = self.get_node(function_name_node).unwrap().get_string_data().unwrap();
+                    if self.engine_definitions.function_index(function, base).is_none() {
+                        report(n.file, n.line, n.column, message!("target_function_unavailable", function, target_name));
+                    }
+                },
+                (NodeType::Primitive(PrimitiveType::Global), _) => {
+                    let global = n.get_string_data().unwrap();
+                    if !self.is_script_global(global) && !self.engine_definitions.find_global(global).map(|g| g.supports_target(base)).unwrap_or(false) {
+                        report(n.file, n.line, n.column, message!("target_global_unavailable", global, target_name));
+                    }
+                },
+                _ => ()
+            }
+        }
+
+        errors
+    }
+
+    /// Remap the engine function and engine global indices to another target in place.
+    ///
+    /// Function names are stored in the nodes, so the original source is not needed.
+    ///
+    /// # Errors
+    ///
+    /// Errors if anything used is not supported by the target, in which case nothing is changed. Use
+    /// [`CompiledScriptData::check_against_target`] to get every error.
+    pub fn retarget(&mut self, target: CompileTarget) -> Result<(), CompileError> {
+        if let Some(e) = self.check_against_target(target).into_iter().next() {
+            return Err(e)
+        }
+
+        let indices = self.indices_for_target(target);
+        for (n, index) in self.nodes.iter_mut().zip(indices) {
+            n.index = index;
+        }
+
+        self.target = target;
+        self.custom_target = None;
+        Ok(())
+    }
+
+    /// Remap to the target as with [`CompiledScriptData::retarget`], using the limits of the custom target if there is one.
+    pub(crate) fn retarget_with_limits(&mut self, target: CompileTarget, custom_target: Option<Arc<dyn Target>>) -> Result<(), CompileError> {
+        self.retarget(target)?;
+        self.custom_target = custom_target;
+        Ok(())
+    }
+
+    /// Get whether or not the global is a script global, either compiled here or declared as external, rather than an engine global.
+    fn is_script_global(&self, name: &str) -> bool {
+        self.globals.iter().any(|g| g.get_name() == name) || self.external_globals.iter().any(|g| g == name)
+    }
+
+    /// Get the index of each node with engine function and engine global indices remapped to the target.
+    ///
+    /// The target is expected to support everything used.
+    pub(super) fn indices_for_target(&self, target: CompileTarget) -> Vec<Option<u16>> {
+        let mut indices: Vec<Option<u16>> = self.nodes.iter().map(|n| n.index).collect();
+        for i in 0..self.nodes.len() {
+            if let (NodeType::FunctionCall(true), Some(NodeData::NodeOffset(function_name_node))) = (self.nodes[i].node_type, self.nodes[i].data) {
+                let function_name_node = function_name_node - self.node_base;
+                let name = self.nodes[function_name_node].get_string_data().unwrap();
+                let index = self.engine_definitions.function_index(name, target);
+                debug_assert!(index.is_some());
+
+                indices[i] = index;
+                indices[function_name_node] = index;
+            }
+            else if self.nodes[i].node_type == NodeType::Primitive(PrimitiveType::Global) && self.nodes[i].index != Some(0xFFFF) {
+                let name = self.nodes[i].get_string_data().unwrap();
+                if !self.is_script_global(name) {
+                    indices[i] = engine_global_index(name, target, &self.engine_definitions);
+                }
+            }
+        }
+        indices
+    }
+
+    /// Get statistics on the size of the compiled script data.
+    pub fn get_statistics(&self) -> ScriptDataStatistics {
+        ScriptDataStatistics {
+            script_count: self.scripts.len(),
+            global_count: self.globals.len(),
+            node_count: self.nodes.len(),
+            string_data_length: self.nodes.iter().map(super::budget::string_data_length).sum(),
+            maximum_node_count: self.target_limits().maximum_nodes(),
+            maximum_string_data_length: self.target_limits().maximum_string_data_length(),
+            optimized_node_count: self.optimized_node_count,
+            memory: compiled_node_memory(&self.nodes)
+        }
+    }
+
+    /// Get a 64-bit hash of everything in the script data that affects how it runs.
+    ///
+    /// This only covers the target, encoding, scripts, globals, and nodes. Source positions, file names, warnings, and renamed symbols are
+    /// not included, so moving code around or changing comments does not change the hash. The hash is stable between compiler versions
+    /// and platforms, so it can be stored to detect when a rebuild is not needed.
+    pub fn content_hash(&self) -> u64 {
+        let mut hasher = ContentHasher::new();
+        hasher.write(&[self.target as u8, self.encoding as u8]);
+
+        hasher.write_usize(self.scripts.len());
+        for s in &self.scripts {
+            hasher.write_cstr(&s.name);
+            hasher.write(&[s.script_type as u8]);
+            hasher.write(&s.value_type.as_u16().to_le_bytes());
+            hasher.write_usize(s.first_node);
+            hasher.write_usize(s.parameters.len());
+            for p in &s.parameters {
+                hasher.write_cstr(&p.name);
+                hasher.write(&p.value_type.as_u16().to_le_bytes());
+            }
+        }
+
+        hasher.write_usize(self.globals.len());
+        for g in &self.globals {
+            hasher.write_cstr(&g.name);
+            hasher.write(&g.value_type.as_u16().to_le_bytes());
+            hasher.write_usize(g.first_node);
+        }
+
+        hasher.write_usize(self.nodes.len());
+        for n in &self.nodes {
+            hasher.write(&match n.node_type {
+                NodeType::Primitive(PrimitiveType::Static) => [0],
+                NodeType::Primitive(PrimitiveType::Local) => [1],
+                NodeType::Primitive(PrimitiveType::Global) => [2],
+                NodeType::FunctionCall(true) => [3],
+                NodeType::FunctionCall(false) => [4]
+            });
+            hasher.write(&n.value_type.as_u16().to_le_bytes());
+            match n.data {
+                None => hasher.write(&[0]),
+                Some(NodeData::Boolean(b)) => hasher.write(&[1, b as u8]),
+                Some(NodeData::Short(v)) => { hasher.write(&[2]); hasher.write(&v.to_le_bytes()) },
+                Some(NodeData::Long(v)) => { hasher.write(&[3]); hasher.write(&v.to_le_bytes()) },
+                Some(NodeData::Real(v)) => { hasher.write(&[4]); hasher.write(&v.to_bits().to_le_bytes()) },
+                Some(NodeData::NodeOffset(v)) => { hasher.write(&[5]); hasher.write_usize(v) }
+            }
+            match n.string_data.as_ref() {
+                Some(string_data) => { hasher.write(&[1]); hasher.write_cstr(string_data) },
+                None => hasher.write(&[0])
+            }
+            hasher.write(&n.next_node.map(|n| n as u64).unwrap_or(u64::MAX).to_le_bytes());
+            hasher.write(&n.index.map(|i| i as u32).unwrap_or(u32::MAX).to_le_bytes());
+        }
+
+        hasher.finish()
+    }
+
+    /// Get where the node at the given index came from, or `None` if the node does not exist.
+    ///
+    /// This can be used to map a node index reported by the engine (such as in an error or crash log) back to the source.
+    pub fn get_source_map_entry(&self, node_index: usize) -> Option<SourceMapEntry> {
+        let node = self.get_node(node_index)?;
+
+        // Each script and global's nodes begin at its first node and end where the next one's begin
+        let (owner_type, owner_index) = self.scripts.iter().map(|s| s.first_node).enumerate().map(|(i, n)| (SymbolType::Script, i, n))
+            .chain(self.globals.iter().map(|g| g.first_node).enumerate().map(|(i, n)| (SymbolType::Global, i, n)))
+            .filter(|&(_, _, first_node)| first_node <= node_index)
+            .max_by_key(|&(_, _, first_node)| first_node)
+            .map(|(owner_type, owner_index, _)| (owner_type, owner_index))?;
+
+        Some(SourceMapEntry {
+            file: node.file,
+            line: node.line,
+            column: node.column,
+            owner_type,
+            owner_index
+        })
+    }
+
+    /// Serialize the scripts, globals, and nodes to JSON.
+    ///
+    /// The object has the keys `target`, `encoding`, `script_base`, `global_base`, `node_base`, `files`, `scripts`, `globals`, and
+    /// `nodes`. Nodes are in index order starting at `node_base`, and every node index (`first_node`, `next`, and `child`) includes the
+    /// node base, so node `i` is at `nodes[i - node_base]`. The `child` of a function call is its function name node, whose `next` chain
+    /// is the parameters. File indices correspond to `files`. This does not depend on the `serde` feature, and keys are only ever added.
+    pub fn to_json(&self) -> String {
+        let list = |items: Vec<String>| format!("[{}]", items.join(","));
+        let position = |file: usize, line: usize, column: usize| format!("\"file\":{file},\"line\":{line},\"column\":{column}");
+        let optional = |index: Option<usize>| index.map(|i| i.to_string()).unwrap_or_else(|| "null".to_owned());
+
+        let target = match self.target {
+            CompileTarget::HaloCEA => "HaloCEA",
+            CompileTarget::HaloCEXboxNTSC => "HaloCEXboxNTSC",
+            CompileTarget::HaloCEGBX => "HaloCEGBX",
+            CompileTarget::HaloCEGBXDemo => "HaloCEGBXDemo",
+            CompileTarget::HaloCustomEdition => "HaloCustomEdition",
+            CompileTarget::HaloCEGBXDemoMac => "HaloCEGBXDemoMac",
+            CompileTarget::HaloCEXboxPAL => "HaloCEXboxPAL",
+            CompileTarget::HaloCEXboxJP => "HaloCEXboxJP"
+        };
+        let files = list(self.files.iter().map(|f| json_string(&f.to_string_lossy())).collect());
+        let scripts = list(self.scripts.iter().map(|s| {
+            let parameters = list(s.parameters.iter().map(|p| {
+                format!("{{\"name\":{},\"value_type\":{},{}}}", json_string(p.get_name()), json_string(p.value_type.as_str()), position(p.file, p.line, p.column))
+            }).collect());
+            format!("{{\"name\":{},\"type\":\"{}\",\"return_type\":{},\"parameters\":{parameters},\"first_node\":{},{}}}",
+                    json_string(s.get_name()), s.script_type.as_str(), json_string(s.value_type.as_str()), s.first_node, position(s.file, s.line, s.column))
+        }).collect());
+        let globals = list(self.globals.iter().map(|g| {
+            format!("{{\"name\":{},\"value_type\":{},\"first_node\":{},{}}}",
+                    json_string(g.get_name()), json_string(g.value_type.as_str()), g.first_node, position(g.file, g.line, g.column))
+        }).collect());
+        let nodes = list(self.nodes.iter().map(|n| {
+            let node_type = match n.node_type {
+                NodeType::Primitive(PrimitiveType::Static) => "static",
+                NodeType::Primitive(PrimitiveType::Local) => "local",
+                NodeType::Primitive(PrimitiveType::Global) => "global",
+                NodeType::FunctionCall(true) => "engine_function_call",
+                NodeType::FunctionCall(false) => "script_call"
+            };
+            let (value, child) = match n.data {
+                Some(NodeData::Boolean(b)) => (b.to_string(), None),
+                Some(NodeData::Short(s)) => (s.to_string(), None),
+                Some(NodeData::Long(l)) => (l.to_string(), None),
+                Some(NodeData::Real(r)) if r.is_finite() => (r.to_string(), None),
+                Some(NodeData::NodeOffset(o)) => ("null".to_owned(), Some(o)),
+                Some(NodeData::Real(_)) | None => ("null".to_owned(), None)
+            };
+            let string = |s: Option<&str>| s.map(json_string).unwrap_or_else(|| "null".to_owned());
+            format!("{{\"type\":\"{node_type}\",\"value_type\":{},\"string_data\":{},\"original_string_data\":{},\"value\":{value},\"index\":{},\"child\":{},\"next\":{},{}}}",
+                    json_string(n.value_type.as_str()), string(n.get_string_data()), string(n.get_original_string_data()), optional(n.index.map(usize::from)),
+                    optional(child), optional(n.next_node), position(n.file, n.line, n.column))
+        }).collect());
+
+        format!("{{\"target\":\"{target}\",\"encoding\":\"{}\",\"script_base\":{},\"global_base\":{},\"node_base\":{},\"files\":{files},\"scripts\":{scripts},\"globals\":{globals},\"nodes\":{nodes}}}",
+                self.encoding.name(), self.script_base, self.global_base, self.node_base)
+    }
+
+    fn walk_evaluation_order(&self, node_index: usize, order: &mut Vec<usize>) {
+        order.push(node_index);
+
+        let node = self.get_node(node_index).unwrap();
+        if let (NodeType::FunctionCall(_), Some(NodeData::NodeOffset(function_name_node))) = (node.node_type, node.data) {
+            let mut parameter = self.get_node(function_name_node).unwrap().next_node;
+            while let Some(p) = parameter {
+                self.walk_evaluation_order(p, order);
+                parameter = self.get_node(p).unwrap().next_node;
+            }
+        }
+    }
+}
+
+/// 64-bit FNV-1a hasher used for [`CompiledScriptData::content_hash`].
+///
+/// This is used instead of [`std::hash::Hasher`] implementations, as those are not guaranteed to be stable between Rust versions.
+struct ContentHasher {
+    hash: u64
+}
+
+impl ContentHasher {
+    fn new() -> Self {
+        Self { hash: 0xCBF29CE484222325 }
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for b in bytes {
+            self.hash = (self.hash ^ *b as u64).wrapping_mul(0x00000100000001B3);
+        }
+    }
+
+    /// Write a size as 64 bits so the hash is the same on 32-bit and 64-bit platforms.
+    fn write_usize(&mut self, value: usize) {
+        self.write(&(value as u64).to_le_bytes());
+    }
+
+    /// Write a string along with its length, so adjacent strings can't be confused with each other.
+    fn write_cstr(&mut self, string: &CStr) {
+        let bytes = string.to_bytes();
+        self.write_usize(bytes.len());
+        self.write(bytes);
+    }
+
+    fn finish(&self) -> u64 {
+        self.hash
+    }
+}
+
+/// Result of a successful compilation of a standalone expression.
+pub struct CompiledExpression {
+    pub(super) nodes: Vec<CompiledNode>,
+    pub(super) value_type: ValueType,
+    pub(super) warnings: Vec<CompileError>
+}
+
+impl CompiledExpression {
+    /// Get all compiled nodes.
+    ///
+    /// The expression itself is always the first node, and any node offsets are relative to this slice.
+    pub fn get_nodes(&self) -> &[CompiledNode] {
+        &self.nodes
+    }
+
+    /// Get the value type the expression evaluates to.
+    pub fn get_value_type(&self) -> ValueType {
+        self.value_type
+    }
+
+    /// Get all warnings from compiling, sorted by line and column.
+    pub fn get_warnings(&self) -> &[CompileError] {
+        &self.warnings
+    }
+}
+
+/// Type of symbol that was renamed.
+#[derive(Copy, Clone, PartialEq, Debug)]
+#[repr(C)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum SymbolType {
+    /// Symbol is a script.
+    Script,
+
+    /// Symbol is a global.
+    Global
+}
+
+impl SymbolType {
+    /// Convert the symbol type to a string.
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            SymbolType::Script => "script",
+            SymbolType::Global => "global"
+        }
+    }
+}
+
+/// Statistics on the size of compiled script data, as returned by [`CompiledScriptData::get_statistics`].
+#[derive(Copy, Clone, PartialEq, Debug)]
+#[repr(C)]
+pub struct ScriptDataStatistics {
+    /// Number of scripts.
+    pub script_count: usize,
+
+    /// Number of globals.
+    pub global_count: usize,
+
+    /// Number of nodes.
+    pub node_count: usize,
+
+    /// Length of all string data in bytes, including null terminators.
+    pub string_data_length: usize,
+
+    /// Maximum number of nodes supported by the target.
+    pub maximum_node_count: usize,
+
+    /// Maximum length of string data in bytes supported by the target.
+    pub maximum_string_data_length: usize,
+
+    /// Number of nodes saved by replacing `begin` blocks with only one expression with that expression.
+    ///
+    /// This is always 0 if disabled with [`Compiler::set_optimize_begin`].
+    pub optimized_node_count: usize,
+
+    /// Memory used by the nodes and their string data. Tokens are not kept once compiled, so they are always 0.
+    pub memory: MemoryUsage
+}
+
+/// Source of a compiled node, as returned by [`CompiledScriptData::get_source_map_entry`].
+#[derive(Copy, Clone, PartialEq, Debug)]
+#[repr(C)]
+pub struct SourceMapEntry {
+    /// File index of the node, corresponding to [`CompiledScriptData::get_files`].
+    pub file: usize,
+
+    /// Line of the node, starting at 1.
+    pub line: usize,
+
+    /// Column of the node, starting at 1.
+    pub column: usize,
+
+    /// Whether the node belongs to a script or a global.
+    pub owner_type: SymbolType,
+
+    /// Index of the script or global the node belongs to, corresponding to [`CompiledScriptData::get_scripts`] or
+    /// [`CompiledScriptData::get_globals`].
+    pub owner_index: usize
+}
+
+/// Script or global that was renamed by obfuscation.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct RenamedSymbol {
+    pub(super) symbol_type: SymbolType,
+    pub(super) original_name: CString,
+    pub(super) name: CString,
+    pub(super) hash: u32
+}
+
+impl RenamedSymbol {
+    pub(super) fn new(symbol_type: SymbolType, original_name: &str, name: &str) -> RenamedSymbol {
+        RenamedSymbol {
+            symbol_type,
+            original_name: CString::new(original_name).unwrap(),
+            name: CString::new(name).unwrap(),
+            hash: symbol_hash(original_name)
+        }
+    }
+
+    /// Get the type of symbol.
+    pub fn get_type(&self) -> SymbolType {
+        self.symbol_type
+    }
+
+    /// Get the name of the symbol as written in the source.
+    pub fn get_original_name(&self) -> &str {
+        self.original_name.to_str().unwrap()
+    }
+
+    /// Get the name of the symbol as written in the source as a null terminated C string.
+    pub fn get_original_name_cstr(&self) -> &CStr {
+        &self.original_name
+    }
+
+    /// Get the name the symbol was renamed to.
+    pub fn get_name(&self) -> &str {
+        self.name.to_str().unwrap()
+    }
+
+    /// Get the name the symbol was renamed to as a null terminated C string.
+    pub fn get_name_cstr(&self) -> &CStr {
+        &self.name
+    }
+
+    /// Get the hash of the original name.
+    ///
+    /// This is the 32-bit FNV-1a hash of the original name, so it stays the same between compilations even if the generated name changes.
+    pub fn get_hash(&self) -> u32 {
+        self.hash
+    }
+}
+
+/// Reference to an external script or global that is resolved when linking.
+///
+/// The node is either a script call or its function name node, whose index is the script, or a script or global reference, whose
+/// data is the script or global.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Relocation {
+    pub(super) symbol_type: SymbolType,
+    pub(super) name: CString,
+    pub(super) node: usize
+}
+
+impl Relocation {
+    /// Get the type of symbol referenced.
+    pub fn get_type(&self) -> SymbolType {
+        self.symbol_type
+    }
+
+    /// Get the name of the symbol referenced.
+    pub fn get_name(&self) -> &str {
+        self.name.to_str().unwrap()
+    }
+
+    /// Get the name of the symbol referenced as a null terminated C string.
+    pub fn get_name_cstr(&self) -> &CStr {
+        &self.name
+    }
+
+    /// Get the index of the node that references the symbol.
+    pub fn get_node_index(&self) -> usize {
+        self.node
+    }
+}
+
+/// Get the 32-bit FNV-1a hash of a symbol name.
+pub fn symbol_hash(name: &str) -> u32 {
+    name.bytes().fold(0x811C9DC5, |hash, b| (hash ^ b as u32).wrapping_mul(0x01000193))
 }
 
 /// Script parameter
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct CompiledScriptParameter {
     pub(super) name: CString,
     pub(super) value_type: ValueType,
@@ -81,6 +783,8 @@ impl CompiledScriptParameter {
 }
 
 /// Compiled script result.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct CompiledScript {
     pub(super) name: CString,
     pub(super) value_type: ValueType,
@@ -144,6 +848,8 @@ impl CompiledScript {
 
 
 /// Compiled global result.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct CompiledGlobal {
     pub(super) name: CString,
     pub(super) value_type: ValueType,
@@ -195,11 +901,14 @@ impl CompiledGlobal {
 
 
 /// Compiled node result.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct CompiledNode {
     pub(super) node_type: NodeType,
     pub(super) value_type: ValueType,
     pub(super) data: Option<NodeData>,
     pub(super) string_data: Option<CString>,
+    pub(super) original_string_data: Option<String>,
     pub(super) next_node: Option<usize>,
     pub(super) index: Option<u16>,
 
@@ -240,12 +949,26 @@ impl CompiledNode {
         }
     }
 
+    /// Get the string data of the node as it was written in the script data, if it was lowercased when compiling.
+    ///
+    /// For example, a call written as `(Sleep 30)` has string data `sleep` on its function name node, and this is `Sleep`.
+    pub fn get_original_string_data(&self) -> Option<&str> {
+        self.original_string_data.as_deref()
+    }
+
     /// Get the next node index, if any.
     pub fn get_next_node_index(&self) -> Option<usize> {
         self.next_node
     }
 
     /// Get the index value, if any.
+    ///
+    /// This is the index of the engine function or script for function calls and function names, and the index of the global in the
+    /// engine's table for the target for references to engine globals whose index is known. References to script globals store their
+    /// index in the data instead. The variable passed to `set` may have an index of 0xFFFF instead, like the original tools.
+    ///
+    /// The built-in definitions don't know the index of any engine global, so references to engine globals only have an index if it
+    /// was given by definitions added with [`Compiler::add_definitions`](crate::Compiler::add_definitions).
     pub fn get_index(&self) -> Option<u16> {
         self.index
     }
@@ -280,6 +1003,9 @@ pub(crate) struct Node {
     /// String data
     pub string_data: Option<String>,
 
+    /// String data as written in the script data, if it was lowercased
+    pub original_string_data: Option<String>,
+
     /// Node data
     pub data: Option<NodeData>,
 
@@ -296,5 +1022,8 @@ pub(crate) struct Node {
     pub line: usize,
 
     /// Column the node is found on
-    pub column: usize
+    pub column: usize,
+
+    /// Line and column just past the end of the node
+    pub end: (usize, usize)
 }
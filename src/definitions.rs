@@ -1,18 +1,26 @@
 extern crate riat_definitions;
 use self::riat_definitions::generate_definitions;
-use super::{ValueType, CallableGlobal, CallableFunction, CompileTarget};
+use super::{ValueType, CallableGlobal, CallableFunction, CompileTarget, UserFunction};
+use super::json::{parse_json, JsonValue};
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::sync::Arc;
 
+#[derive(Clone, Default)]
 pub(crate) struct EngineAvailability {
     pub mcc_cea: Option<u16>,
     pub gbx_retail: Option<u16>,
     pub gbx_custom: Option<u16>,
     pub gbx_demo: Option<u16>,
-    pub xbox: Option<u16>
+    pub gbx_demo_mac: Option<u16>,
+    pub xbox: Option<u16>,
+    pub xbox_pal: Option<u16>,
+    pub xbox_jp: Option<u16>
 }
 
 impl EngineAvailability {
     pub(crate) fn supports_target(&self, target: CompileTarget) -> bool {
-        self.index_for_target(target) != None
+        self.index_for_target(target).is_some()
     }
     pub(crate) fn index_for_target(&self, target: CompileTarget) -> Option<u16> {
         match target {
@@ -20,31 +28,39 @@ impl EngineAvailability {
             CompileTarget::HaloCEXboxNTSC => self.xbox,
             CompileTarget::HaloCEGBX => self.gbx_retail,
             CompileTarget::HaloCEGBXDemo => self.gbx_demo,
-            CompileTarget::HaloCustomEdition => self.gbx_custom
+            CompileTarget::HaloCustomEdition => self.gbx_custom,
+            CompileTarget::HaloCEGBXDemoMac => self.gbx_demo_mac,
+            CompileTarget::HaloCEXboxPAL => self.xbox_pal,
+            CompileTarget::HaloCEXboxJP => self.xbox_jp
         }
     }
 }
 
+#[derive(Clone)]
 pub(crate) struct EngineFunctionParameter {
-    value_type: ValueType,
-    many: bool,
-    allow_uppercase: bool,
-    optional: bool
+    pub value_type: ValueType,
+    pub many: bool,
+    pub allow_uppercase: bool,
+    pub optional: bool
 }
 
+#[derive(Clone)]
 pub(crate) struct EngineFunction {
-    pub name: &'static str,
-    pub parameters: &'static [EngineFunctionParameter],
+    pub name: Cow<'static, str>,
+    pub parameters: Cow<'static, [EngineFunctionParameter]>,
     pub number_passthrough: bool,
     pub passthrough_last: bool,
     pub return_type: ValueType,
     pub inequality: bool,
+    pub console_only: bool,
+    pub expensive: bool,
+    pub client_only: bool,
     pub availability: EngineAvailability
 }
 
 impl CallableFunction for EngineFunction {
     fn get_name(&self) -> &str {
-        self.name
+        &self.name
     }
 
     fn get_return_type(&self) -> ValueType {
@@ -115,6 +131,10 @@ impl CallableFunction for EngineFunction {
         self.passthrough_last
     }
 
+    fn is_console_only(&self) -> bool {
+        self.console_only
+    }
+
     fn is_uppercase_allowed_for_parameter(&self, parameter_index: usize) -> bool {
         if parameter_index < self.parameters.len() {
             self.parameters[parameter_index].allow_uppercase
@@ -125,15 +145,16 @@ impl CallableFunction for EngineFunction {
     }
 }
 
+#[derive(Clone)]
 pub(crate) struct EngineGlobal {
-    pub name: &'static str,
+    pub name: Cow<'static, str>,
     pub value_type: ValueType,
     pub availability: EngineAvailability
 }
 
 impl CallableGlobal for EngineGlobal {
     fn get_name(&self) -> &str {
-        self.name
+        &self.name
     }
 
     fn get_value_type(&self) -> ValueType {
@@ -150,3 +171,251 @@ impl CallableGlobal for EngineGlobal {
 }
 
 generate_definitions!();
+
+/// Function registered with [`Compiler::register_function`](super::Compiler::register_function).
+#[derive(Clone)]
+pub(crate) struct RegisteredFunction(pub Arc<dyn UserFunction>);
+
+impl CallableFunction for RegisteredFunction {
+    fn get_name(&self) -> &str {
+        self.0.get_name()
+    }
+
+    fn get_return_type(&self) -> ValueType {
+        self.0.get_return_type()
+    }
+
+    fn get_total_parameter_count(&self) -> usize {
+        // Functions taking any number of parameters never return None, so stop somewhere
+        (0..u16::MAX as usize).take_while(|i| self.0.get_type_of_parameter(*i).is_some()).count()
+    }
+
+    fn get_minimum_parameter_count(&self) -> usize {
+        self.0.get_minimum_parameter_count()
+    }
+
+    fn get_type_of_parameter(&self, index: usize) -> Option<ValueType> {
+        self.0.get_type_of_parameter(index)
+    }
+
+    fn is_number_passthrough(&self) -> bool {
+        self.0.is_number_passthrough()
+    }
+
+    fn supports_target(&self, target: CompileTarget) -> bool {
+        self.0.get_index(target).is_some()
+    }
+
+    fn is_uppercase_allowed_for_parameter(&self, parameter_index: usize) -> bool {
+        self.0.is_uppercase_allowed_for_parameter(parameter_index)
+    }
+
+    fn is_engine_function(&self) -> bool {
+        true
+    }
+
+    fn is_console_only(&self) -> bool {
+        self.0.is_console_only()
+    }
+}
+
+/// Engine functions and globals, including ones loaded at runtime with [`Compiler::add_definitions`](super::Compiler::add_definitions)
+/// and functions registered with [`Compiler::register_function`](super::Compiler::register_function).
+///
+/// Loaded definitions replace built-in definitions with the same name, and registered functions replace both.
+#[derive(Clone, Default)]
+pub(crate) struct EngineDefinitions {
+    functions: Vec<EngineFunction>,
+    globals: Vec<EngineGlobal>,
+    user_functions: Vec<RegisteredFunction>
+}
+
+impl EngineDefinitions {
+    /// Get every engine function that is not replaced by a registered function, whether or not the target supports it.
+    pub fn functions(&self) -> impl Iterator<Item = &EngineFunction> {
+        let loaded = self.functions.iter().filter(move |f| self.find_user_function(&f.name).is_none());
+        loaded.chain(ALL_FUNCTIONS.iter().filter(move |f| self.functions.iter().all(|l| l.name != f.name) && self.find_user_function(&f.name).is_none()))
+    }
+
+    /// Get every engine global, whether or not the target supports it.
+    pub fn globals(&self) -> impl Iterator<Item = &EngineGlobal> {
+        self.globals.iter().chain(ALL_GLOBALS.iter().filter(move |g| self.globals.iter().all(|l| l.name != g.name)))
+    }
+
+    /// Get every registered function, whether or not the target supports it.
+    pub fn user_functions(&self) -> &[RegisteredFunction] {
+        &self.user_functions
+    }
+
+    pub fn find_function(&self, name: &str) -> Option<&EngineFunction> {
+        self.functions().find(|f| f.name == name)
+    }
+
+    pub fn find_global(&self, name: &str) -> Option<&EngineGlobal> {
+        self.globals().find(|g| g.name == name)
+    }
+
+    pub fn find_user_function(&self, name: &str) -> Option<&RegisteredFunction> {
+        self.user_functions.iter().find(|f| f.get_name() == name)
+    }
+
+    /// Get the index of the engine function or registered function in the engine's table for the target, or `None` if the target does
+    /// not have it.
+    pub fn function_index(&self, name: &str, target: CompileTarget) -> Option<u16> {
+        match self.find_user_function(name) {
+            Some(f) => f.0.get_index(target),
+            None => self.find_function(name)?.availability.index_for_target(target)
+        }
+    }
+
+    /// Register a function, replacing any engine function or registered function with the same name.
+    pub fn register_function(&mut self, function: Arc<dyn UserFunction>) {
+        self.user_functions.retain(|f| f.get_name() != function.get_name());
+        self.user_functions.push(RegisteredFunction(function));
+    }
+
+    /// Load the functions and globals of a JSON document using the same schema as the built-in definitions.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the document can't be parsed, in which case nothing is loaded.
+    pub fn load_json(&mut self, json: &str) -> Result<(), String> {
+        let document = parse_json(json)?;
+        if document.as_object().is_none() {
+            return Err("expected the definitions to be an object".to_owned())
+        }
+
+        // Engines with a base engine have the same availability as it, except where a function or global lists the engine itself
+        let mut bases: BTreeMap<&str, &str> = ALL_ENGINES.iter().filter_map(|(identifier, base)| Some((*identifier, (*base)?))).collect();
+        for e in definition_list(&document, "engines")? {
+            let identifier = e.get("identifier").and_then(JsonValue::as_str).ok_or_else(|| "expected each engine to have an identifier".to_owned())?;
+            check_engine_identifier(identifier)?;
+            match e.get("base").map(|b| b.as_str().ok_or_else(|| format!("engine '{identifier}': expected 'base' to be a string"))) {
+                Some(base) => {
+                    let base = base?;
+                    check_engine_identifier(base).map_err(|e| format!("engine '{identifier}': {e}"))?;
+                    bases.insert(identifier, base);
+                },
+                None => {
+                    bases.remove(identifier);
+                }
+            }
+        }
+
+        let mut functions = Vec::new();
+        for f in definition_list(&document, "functions")? {
+            let name = definition_name(f, "function")?;
+            let error = |message: String| format!("function '{name}': {message}");
+
+            let mut parameters = Vec::new();
+            for p in definition_list(f, "parameters").map_err(error)? {
+                parameters.push(EngineFunctionParameter {
+                    value_type: definition_type(p).map_err(error)?,
+                    many: definition_flag(p, "many").map_err(error)?,
+                    allow_uppercase: definition_flag(p, "allow_uppercase").map_err(error)?,
+                    optional: definition_flag(p, "optional").map_err(error)?
+                });
+            }
+
+            functions.push(EngineFunction {
+                return_type: definition_type(f).map_err(error)?,
+                parameters: Cow::Owned(parameters),
+                number_passthrough: definition_flag(f, "number_passthrough").map_err(error)?,
+                passthrough_last: definition_flag(f, "passthrough_last").map_err(error)?,
+                inequality: definition_flag(f, "inequality").map_err(error)?,
+                console_only: definition_flag(f, "console_only").map_err(error)?,
+                expensive: definition_flag(f, "expensive").map_err(error)?,
+                client_only: definition_flag(f, "client_only").map_err(error)?,
+                availability: definition_availability(f, &bases).map_err(error)?,
+                name: Cow::Owned(name)
+            });
+        }
+
+        let mut globals = Vec::new();
+        for g in definition_list(&document, "globals")? {
+            let name = definition_name(g, "global")?;
+            let error = |message: String| format!("global '{name}': {message}");
+            globals.push(EngineGlobal {
+                value_type: definition_type(g).map_err(error)?,
+                availability: definition_availability(g, &bases).map_err(error)?,
+                name: Cow::Owned(name)
+            });
+        }
+
+        // Later definitions replace earlier ones with the same name, including ones in the same document
+        for f in functions {
+            self.functions.retain(|l| l.name != f.name);
+            self.functions.push(f);
+        }
+        for g in globals {
+            self.globals.retain(|l| l.name != g.name);
+            self.globals.push(g);
+        }
+        Ok(())
+    }
+}
+
+/// Error if the engine is not one of the built-in engines, which are the only ones that can be targeted.
+fn check_engine_identifier(identifier: &str) -> Result<(), String> {
+    match ALL_ENGINES.iter().any(|e| e.0 == identifier) {
+        true => Ok(()),
+        false => Err(format!("unknown engine '{identifier}'"))
+    }
+}
+
+fn definition_name(definition: &JsonValue, kind: &str) -> Result<String, String> {
+    match definition.get("name").and_then(JsonValue::as_str) {
+        Some(n) if !n.is_empty() => Ok(n.to_ascii_lowercase()),
+        _ => Err(format!("expected each {kind} to have a name"))
+    }
+}
+
+fn definition_list<'a>(definition: &'a JsonValue, key: &str) -> Result<&'a [JsonValue], String> {
+    match definition.get(key) {
+        None => Ok(&[]),
+        Some(n) => n.as_array().ok_or_else(|| format!("expected '{key}' to be an array"))
+    }
+}
+
+fn definition_type(definition: &JsonValue) -> Result<ValueType, String> {
+    let value_type = definition.get("type").and_then(JsonValue::as_str).ok_or_else(|| "expected a type".to_owned())?;
+    ValueType::from_str_underscore(value_type).ok_or_else(|| format!("unknown type '{value_type}'"))
+}
+
+fn definition_flag(definition: &JsonValue, key: &str) -> Result<bool, String> {
+    match definition.get(key) {
+        None => Ok(false),
+        Some(n) => n.as_bool().ok_or_else(|| format!("expected '{key}' to be true or false"))
+    }
+}
+
+/// Get the availability of a function or global from its `engines` object, which maps each engine to its index, or to `null` if the
+/// index is not known.
+///
+/// Engines that are not built-in engines, such as later games, are errors.
+fn definition_availability(definition: &JsonValue, bases: &BTreeMap<&str, &str>) -> Result<EngineAvailability, String> {
+    let engines = definition.get("engines").and_then(JsonValue::as_object).ok_or_else(|| "expected 'engines' to be an object".to_owned())?;
+    for (engine, _) in engines {
+        check_engine_identifier(engine)?;
+    }
+    let index = |engine: &str| -> Result<Option<u16>, String> {
+        let value = engines.iter().find(|e| e.0 == engine).or_else(|| bases.get(engine).and_then(|base| engines.iter().find(|e| e.0 == *base)));
+        match value.map(|e| &e.1) {
+            None => Ok(None),
+            Some(JsonValue::Null) => Ok(Some(u16::MAX)),
+            Some(JsonValue::Number(n)) if n.fract() == 0.0 && (0.0..=u16::MAX as f64).contains(n) => Ok(Some(*n as u16)),
+            Some(_) => Err(format!("expected the index for '{engine}' to be null or a number from 0 to 65535"))
+        }
+    };
+
+    Ok(EngineAvailability {
+        mcc_cea: index("mcc-cea")?,
+        gbx_retail: index("gbx-retail")?,
+        gbx_custom: index("gbx-custom")?,
+        gbx_demo: index("gbx-demo")?,
+        gbx_demo_mac: index("gbx-demo-mac")?,
+        xbox: index("xbox")?,
+        xbox_pal: index("xbox-pal")?,
+        xbox_jp: index("xbox-jp")?
+    })
+}
@@ -0,0 +1,945 @@
+use super::*;
+use super::compile::{callable_engine_functions_and_globals, is_number_literal};
+use super::token::{build_token_tree_recovering, quote_name, TokenizedScript, TreeOptions};
+use std::collections::BTreeMap;
+
+/// Parameter of a [`FunctionSignature`].
+#[derive(Clone, PartialEq, Debug)]
+pub struct SignatureParameter {
+    /// Name of the parameter, if it is a parameter of a script (engine function parameters are unnamed).
+    pub name: Option<String>,
+
+    /// Value type of the parameter.
+    pub value_type: ValueType,
+
+    /// The parameter can be left out.
+    pub optional: bool,
+
+    /// The parameter can be passed any number of times.
+    pub many: bool
+}
+
+/// Signature of an engine function or script.
+#[derive(Clone, PartialEq, Debug)]
+pub struct FunctionSignature {
+    /// Name of the function or script.
+    pub name: String,
+
+    /// Return type of the function or script.
+    pub return_type: ValueType,
+
+    /// Parameters of the function or script.
+    pub parameters: Vec<SignatureParameter>
+}
+
+impl FunctionSignature {
+    /// Format the signature as it would be written in a call, such as `(print <string>)`, or `(add <short a> <short b>)` for a script.
+    pub fn to_call_string(&self) -> String {
+        let mut call = format!("({}", self.name);
+        for p in &self.parameters {
+            let (open, close) = if p.optional { ('[', ']') } else { ('<', '>') };
+            match p.name.as_ref() {
+                Some(name) => call += &format!(" {open}{} {name}{close}", p.value_type),
+                None => call += &format!(" {open}{}{close}", p.value_type)
+            }
+            if p.many {
+                call += "...";
+            }
+        }
+        call.push(')');
+        call
+    }
+
+    /// Format the signature as a snippet for inserting a call, such as `(ai_place ${1:ai})`, with a placeholder for each parameter.
+    ///
+    /// Optional parameters are left out, and parameters that take a team or game difficulty are a choice between their values. The
+    /// snippet uses the syntax of the Language Server Protocol.
+    pub fn to_snippet(&self) -> String {
+        let escape = |text: &str, special: &[char]| text.chars().fold(String::new(), |mut escaped, c| {
+            if c == '\\' || special.contains(&c) {
+                escaped.push('\\');
+            }
+            escaped.push(c);
+            escaped
+        });
+
+        let mut snippet = format!("({}", escape(&self.name, &['$', '}']));
+        for (i, p) in self.parameters.iter().filter(|p| !p.optional).enumerate() {
+            let values = p.value_type.enum_values();
+            if values.is_empty() {
+                let placeholder = p.name.clone().unwrap_or_else(|| p.value_type.as_str().replace(' ', "_"));
+                snippet += &format!(" ${{{}:{}}}", i + 1, escape(&placeholder, &['$', '}']));
+            }
+            else {
+                let choices: Vec<String> = values.iter().map(|v| escape(v, &[',', '|'])).collect();
+                snippet += &format!(" ${{{}|{}|}}", i + 1, choices.join(","));
+            }
+        }
+        snippet.push(')');
+        snippet
+    }
+}
+
+/// Result of [`Compiler::signature_help`].
+#[derive(Clone, PartialEq, Debug)]
+pub struct SignatureHelp {
+    /// Signature of the function or script being called.
+    pub signature: FunctionSignature,
+
+    /// Index of the parameter the cursor is on.
+    ///
+    /// If the last parameter can be passed many times, this is clamped to it.
+    pub active_parameter: usize
+}
+
+/// Kind of symbol suggested by [`Compiler::completions`].
+#[derive(Copy, Clone, PartialEq, Debug)]
+#[repr(C)]
+pub enum CompletionKind {
+    /// Engine function
+    Function,
+
+    /// Script defined in the script data
+    Script,
+
+    /// Engine global or global defined in the script data
+    Global,
+
+    /// Parameter of the script being edited
+    Parameter,
+
+    /// Literal value, such as a team name
+    Literal
+}
+
+/// Candidate returned by [`Compiler::completions`].
+#[derive(Clone, PartialEq, Debug)]
+pub struct Completion {
+    /// Text to insert, which is quoted if the name would otherwise be split into multiple tokens (e.g. `"my object"`).
+    pub label: String,
+
+    /// Kind of symbol being suggested.
+    pub kind: CompletionKind,
+
+    /// Value type of the symbol, or the return type if it is a function or script.
+    pub value_type: ValueType
+}
+
+/// Type of a [`SemanticToken`].
+#[derive(Copy, Clone, PartialEq, Debug)]
+#[repr(C)]
+pub enum SemanticTokenType {
+    /// `script`, `global`, or a script type
+    Keyword,
+
+    /// Value type of a script, global, or parameter
+    Type,
+
+    /// Engine function
+    Function,
+
+    /// Script defined in the script data
+    Script,
+
+    /// Engine global or global defined in the script data
+    Global,
+
+    /// Parameter of the script it is in
+    Parameter,
+
+    /// Number literal
+    Number,
+
+    /// Quoted string
+    String,
+
+    /// Any other literal value, such as a team name
+    Literal,
+
+    /// Single line or multi line comment
+    Comment
+}
+
+impl SemanticTokenType {
+    /// Get the name of the token type in lowercase, such as `keyword`.
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            SemanticTokenType::Keyword => "keyword",
+            SemanticTokenType::Type => "type",
+            SemanticTokenType::Function => "function",
+            SemanticTokenType::Script => "script",
+            SemanticTokenType::Global => "global",
+            SemanticTokenType::Parameter => "parameter",
+            SemanticTokenType::Number => "number",
+            SemanticTokenType::String => "string",
+            SemanticTokenType::Literal => "literal",
+            SemanticTokenType::Comment => "comment"
+        }
+    }
+}
+
+/// Span of script data classified by [`Compiler::semantic_tokens`].
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct SemanticToken {
+    /// Line of the start of the token, starting at 1.
+    pub line: usize,
+
+    /// Column of the start of the token, starting at 1.
+    pub column: usize,
+
+    /// Line and column just past the end of the token.
+    pub end: (usize, usize),
+
+    /// What the token is.
+    pub token_type: SemanticTokenType,
+
+    /// The token is the name of a script, global, or parameter where it is defined.
+    pub definition: bool
+}
+
+/// Where a script, global, or parameter is defined, as found by [`Compiler::definition`].
+#[derive(Clone, PartialEq, Debug)]
+pub struct DefinitionLocation {
+    /// Name of the file it is defined in, or `None` if it is defined in the script data being edited.
+    pub file: Option<String>,
+
+    /// Line of the start of the name where it is defined, starting at 1.
+    pub line: usize,
+
+    /// Column of the start of the name where it is defined, starting at 1.
+    pub column: usize,
+
+    /// Line and column just past the end of the name.
+    pub end: (usize, usize),
+
+    /// Kind of symbol being defined.
+    pub kind: CompletionKind
+}
+
+/// Result of [`Compiler::hover`].
+#[derive(Clone, PartialEq, Debug)]
+pub struct Hover {
+    /// Line of the start of the token being hovered over, starting at 1.
+    pub line: usize,
+
+    /// Column of the start of the token being hovered over, starting at 1.
+    pub column: usize,
+
+    /// Line and column just past the end of the token.
+    pub end: (usize, usize),
+
+    /// Name of the symbol in lowercase.
+    pub name: String,
+
+    /// Kind of symbol the token refers to.
+    pub kind: CompletionKind,
+
+    /// Value type of the symbol, or the return type if it is a function or script.
+    pub value_type: ValueType,
+
+    /// Signature of the engine function or script, if it is one.
+    pub signature: Option<FunctionSignature>
+}
+
+/// Script or global found in script data that may not compile.
+struct Definition {
+    name: String,
+    value_type: ValueType,
+    parameters: Vec<(String, ValueType)>,
+    is_script: bool,
+
+    /// Name token where it is defined
+    file: usize,
+    line: usize,
+    column: usize,
+    end: (usize, usize)
+}
+
+impl Definition {
+    /// Read the definition from a top level block, if it is complete enough to have a name and type.
+    fn from_block(token: &Token) -> Option<Definition> {
+        let children = token.children.as_ref()?;
+        let lowercase = |index: usize| children.get(index).map(|t| t.string.to_ascii_lowercase());
+
+        match lowercase(0)?.as_str() {
+            "global" => Some(Definition {
+                name: lowercase(2)?,
+                value_type: ValueType::from_str_underscore(&lowercase(1)?)?,
+                parameters: Vec::new(),
+                is_script: false,
+                file: children[2].file,
+                line: children[2].line,
+                column: children[2].column,
+                end: children[2].end
+            }),
+            "script" => {
+                let script_type = ScriptType::from_str(&lowercase(1)?)?;
+                let value_type = if script_type.always_returns_void() { ValueType::Void } else { ValueType::from_str_underscore(&lowercase(2)?)? };
+
+                // Script parameters are in a block with the name
+                let name_token = children.get(script_type.expression_offset() - 1)?;
+                let (name_token, parameters) = match name_token.children.as_ref() {
+                    Some(c) => (c.first()?, c[1..].iter().filter_map(|p| {
+                        let p = p.children.as_ref()?;
+                        Some((p.get(1)?.string.to_ascii_lowercase(), ValueType::from_str_underscore(&p.first()?.string.to_ascii_lowercase())?))
+                    }).collect()),
+                    None => (name_token, Vec::new())
+                };
+
+                Some(Definition {
+                    name: name_token.string.to_ascii_lowercase(),
+                    value_type,
+                    parameters,
+                    is_script: true,
+                    file: name_token.file,
+                    line: name_token.line,
+                    column: name_token.column,
+                    end: name_token.end
+                })
+            },
+            _ => None
+        }
+    }
+
+    /// Get the signature of the script.
+    fn to_signature(&self) -> FunctionSignature {
+        FunctionSignature {
+            name: self.name.clone(),
+            return_type: self.value_type,
+            parameters: self.parameters.iter().map(|(name, value_type)| SignatureParameter {
+                name: Some(name.clone()), value_type: *value_type, optional: false, many: false
+            }).collect()
+        }
+    }
+}
+
+/// Parameter of a script found in script data that may not compile.
+struct ParameterDefinition {
+    name: String,
+    value_type: ValueType,
+    line: usize,
+    column: usize,
+    end: (usize, usize)
+}
+
+/// Get the parameters of the script defined by a top level block where they are defined.
+fn parameter_definitions(block: &Token) -> Vec<ParameterDefinition> {
+    let children = match block.children.as_ref() {
+        Some(n) => n,
+        None => return Vec::new()
+    };
+    let script_type = match (children.first(), children.get(1)) {
+        (Some(k), Some(t)) if k.string.eq_ignore_ascii_case("script") => ScriptType::from_str(&t.string.to_ascii_lowercase()),
+        _ => None
+    };
+    let name_block = script_type.and_then(|t| children.get(t.expression_offset() - 1)).and_then(|t| t.children.as_ref());
+
+    name_block.map(|c| c.iter().skip(1).filter_map(|p| {
+        let p = p.children.as_ref()?;
+        let name = p.get(1)?;
+        Some(ParameterDefinition {
+            name: name.string.to_ascii_lowercase(),
+            value_type: ValueType::from_str_underscore(&p.first()?.string.to_ascii_lowercase())?,
+            line: name.line,
+            column: name.column,
+            end: name.end
+        })
+    }).collect()).unwrap_or_default()
+}
+
+/// What a token in script data refers to.
+enum Symbol {
+    Function(FunctionSignature),
+    Script(Definition),
+    Global(Definition),
+    EngineGlobal(ValueType),
+    Parameter(ParameterDefinition)
+}
+
+/// What can be written at a position.
+#[derive(Copy, Clone)]
+enum Slot {
+    /// Name of a function or script that returns something which converts to the value type
+    Call(ValueType),
+
+    /// Value that converts to the value type
+    Value(ValueType),
+
+    /// Name of a global being set
+    SetGlobal
+}
+
+/// Get whether or not a value of one type can be used where the other is expected.
+fn converts(from: ValueType, to: ValueType) -> bool {
+    from == ValueType::Passthrough || from.can_convert_to(to)
+}
+
+impl Compiler {
+    /// Get the signature of the engine function or script being called at the given position (line, column) of the script data, and
+    /// which parameter the cursor is on.
+    ///
+    /// Scripts are found in the script data as well as any script data that was already read, and take precedence over engine
+    /// functions of the same name. The script data may be incomplete (e.g. a call that is still being typed), as only what comes before
+    /// the position is read. Returns `None` if the position is not inside of a call to a script or an engine function available on the
+    /// target.
+    pub fn signature_help(&self, script: &[u8], position: (usize, usize)) -> Option<SignatureHelp> {
+        let script = &script[..offset_of_position(script, position)];
+        let tokens = self.tokenize_incomplete(script)?;
+
+        // Find the innermost block that hasn't been closed yet
+        let mut open_blocks = Vec::<usize>::new();
+        for (i, t) in tokens.iter().enumerate() {
+            match t.string.as_str() {
+                "(" => open_blocks.push(i),
+                ")" => { open_blocks.pop(); },
+                _ => ()
+            }
+        }
+        let block_start = *open_blocks.last()?;
+
+        // The function name must be an ordinary token
+        let name = tokens.get(block_start + 1).map(|t| t.string.to_ascii_lowercase()).filter(|n| n != "(" && n != ")")?;
+        let end = tokens.last().map(|t| t.end).unwrap_or((1, 1));
+        let options = TreeOptions { allow_empty_blocks: true, close_unterminated: true, maximum_depth: None };
+        let (tree, _) = build_token_tree_recovering("", tokens.clone(), end, options);
+        let definitions: Vec<Definition> = self.tokens.iter().chain(tree.iter()).filter_map(Definition::from_block).collect();
+        let signature = self.function_signature(&name, &definitions)?;
+
+        // Count the parameters in the block (parameters which are blocks themselves count once)
+        let mut parameter_count = 0usize;
+        let mut depth = 0;
+        for t in &tokens[block_start + 2..] {
+            match t.string.as_str() {
+                "(" => {
+                    if depth == 0 {
+                        parameter_count += 1;
+                    }
+                    depth += 1;
+                },
+                ")" => depth -= 1,
+                _ => if depth == 0 {
+                    parameter_count += 1;
+                }
+            }
+        }
+
+        // If nothing separates the cursor from the last parameter, it's still being typed
+        let still_typing = script.last().map(|c| !c.is_ascii_whitespace()).unwrap_or(false);
+        let mut active_parameter = if still_typing { parameter_count.saturating_sub(1) } else { parameter_count };
+
+        if signature.parameters.last().map(|p| p.many) == Some(true) {
+            active_parameter = active_parameter.min(signature.parameters.len() - 1);
+        }
+
+        Some(SignatureHelp { signature, active_parameter })
+    }
+
+    /// Get what can be written at the given position (line, column) of the script data, sorted by label.
+    ///
+    /// Candidates are filtered by the value type expected at the position, such as functions whose return type converts to the type
+    /// of the parameter being written, as well as by any partially written token before the position. Scripts and globals are found
+    /// in the script data as well as any script data that was already read. The script data may be incomplete, as only what comes
+    /// before the position is read.
+    pub fn completions(&self, script: &[u8], position: (usize, usize)) -> Vec<Completion> {
+        let script = &script[..offset_of_position(script, position)];
+
+        // Nothing can be completed inside of a string
+        let TokenizedScript { mut tokens, end, .. } = match self.tokenize("", 0, script, self.encoding) {
+            Ok(n) => n,
+            Err(_) => return Vec::new()
+        };
+
+        // If nothing separates the cursor from the last token, it's still being typed
+        let still_typing = script.last().map(|c| !c.is_ascii_whitespace()).unwrap_or(false);
+        let prefix = match tokens.last().map(|t| t.string.as_str()) {
+            Some(")") if still_typing => return Vec::new(),
+            Some("(") | None => String::new(),
+            Some(_) if still_typing => tokens.pop().unwrap().string.to_ascii_lowercase(),
+            Some(_) => String::new()
+        };
+
+        // The cursor is in the last top level block if it was never closed
+        let options = TreeOptions { allow_empty_blocks: true, close_unterminated: true, maximum_depth: None };
+        let (tree, _) = build_token_tree_recovering("", tokens, end, options);
+        let mut path = match tree.last() {
+            Some(n) if n.synthetic => vec![n],
+            _ => return Vec::new()
+        };
+        while let Some(n) = path.last().unwrap().children.as_ref().unwrap().last().filter(|n| n.synthetic) {
+            path.push(n);
+        }
+
+        let definitions: Vec<Definition> = self.tokens.iter().chain(tree.iter()).filter_map(Definition::from_block).collect();
+        let (functions, globals) = callable_engine_functions_and_globals(self.target, &self.engine_definitions);
+
+        let index = path.last().unwrap().children.as_ref().unwrap().len();
+        let slot = match slot_at(&path, index, &functions, &globals, &definitions) {
+            Some(n) => n,
+            None => return Vec::new()
+        };
+
+        let mut completions = Vec::new();
+        let mut add = |name: &str, kind: CompletionKind, value_type: ValueType| {
+            if name.starts_with(prefix.as_str()) {
+                completions.push(Completion { label: quote_name(name), kind, value_type });
+            }
+        };
+
+        match slot {
+            Slot::Call(expected) => {
+                for f in functions.values().filter(|f| converts(f.get_return_type(), expected)) {
+                    add(f.get_name(), CompletionKind::Function, f.get_return_type());
+                }
+                for d in definitions.iter().filter(|d| d.is_script && converts(d.value_type, expected)) {
+                    add(&d.name, CompletionKind::Script, d.value_type);
+                }
+            },
+            Slot::Value(expected) => {
+                for g in globals.values().filter(|g| converts(g.get_value_type(), expected)) {
+                    add(g.get_name(), CompletionKind::Global, g.get_value_type());
+                }
+                for d in definitions.iter().filter(|d| !d.is_script && converts(d.value_type, expected)) {
+                    add(&d.name, CompletionKind::Global, d.value_type);
+                }
+
+                // Parameters of the script being edited
+                if let Some(d) = Definition::from_block(path[0]).filter(|d| d.is_script) {
+                    for (name, value_type) in d.parameters.iter().filter(|p| converts(p.1, expected)) {
+                        add(name, CompletionKind::Parameter, *value_type);
+                    }
+                }
+
+                match expected {
+                    ValueType::Boolean => for v in ["true", "false"] {
+                        add(v, CompletionKind::Literal, expected);
+                    },
+                    ValueType::Script => for d in definitions.iter().filter(|d| d.is_script) {
+                        add(&d.name, CompletionKind::Script, expected);
+                    },
+                    _ => for v in expected.enum_values() {
+                        add(v, CompletionKind::Literal, expected);
+                    }
+                }
+            },
+            Slot::SetGlobal => {
+                for g in globals.values() {
+                    add(g.get_name(), CompletionKind::Global, g.get_value_type());
+                }
+                for d in definitions.iter().filter(|d| !d.is_script) {
+                    add(&d.name, CompletionKind::Global, d.value_type);
+                }
+            }
+        }
+
+        completions.sort_by(|a, b| a.label.cmp(&b.label));
+        completions.dedup_by(|a, b| a.label == b.label);
+        completions
+    }
+
+    /// Get a snippet for inserting a call to the engine function with the given name, or `None` if the target does not have it.
+    ///
+    /// See [`FunctionSignature::to_snippet`] for what the snippet contains.
+    pub fn function_snippet(&self, name: &str) -> Option<String> {
+        self.function_signature(&name.to_ascii_lowercase(), &[]).map(|s| s.to_snippet())
+    }
+
+    /// Classify the tokens and comments of the script data, sorted by position.
+    ///
+    /// Scripts and globals are found in the script data as well as any script data that was already read. Malformed top level blocks are
+    /// skipped, so only their comments are classified.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the script data cannot be tokenized, such as if a string or multi line comment is never terminated.
+    pub fn semantic_tokens(&self, filename: &str, script: &[u8]) -> Result<Vec<SemanticToken>, CompileError> {
+        let TokenizedScript { tokens, end, comments, .. } = self.tokenize(filename, 0, script, self.encoding)?;
+        let options = TreeOptions { allow_empty_blocks: true, close_unterminated: true, maximum_depth: None };
+        let (tree, _) = build_token_tree_recovering(filename, tokens, end, options);
+
+        let definitions: Vec<Definition> = self.tokens.iter().chain(tree.iter()).filter_map(Definition::from_block).collect();
+        let (functions, globals) = callable_engine_functions_and_globals(self.target, &self.engine_definitions);
+        let classifier = TokenClassifier { script, definitions: &definitions, functions: &functions, globals: &globals };
+
+        let mut semantic_tokens: Vec<SemanticToken> = comments.into_iter().map(|((line, column), end)| SemanticToken {
+            line, column, end, token_type: SemanticTokenType::Comment, definition: false
+        }).collect();
+        for block in &tree {
+            classifier.classify_top_level_block(block, &mut semantic_tokens);
+        }
+
+        semantic_tokens.sort_by_key(|t| (t.line, t.column));
+        Ok(semantic_tokens)
+    }
+
+    /// Get where the script, global, or parameter at the given position (line, column) of the script data is defined.
+    ///
+    /// Scripts and globals are found in the script data as well as any script data that was already read, in which case the file is
+    /// set. The script data may not compile, as malformed blocks are skipped. Returns `None` if there is no name at the position or if
+    /// it is an engine function or global, which are not defined in script data.
+    pub fn definition(&self, script: &[u8], position: (usize, usize)) -> Option<DefinitionLocation> {
+        let (_, symbol) = self.symbol_at(script, position)?;
+        let (file, line, column, end, kind) = match symbol {
+            Symbol::Script(d) => (d.file, d.line, d.column, d.end, CompletionKind::Script),
+            Symbol::Global(d) => (d.file, d.line, d.column, d.end, CompletionKind::Global),
+            Symbol::Parameter(p) => (self.files.len(), p.line, p.column, p.end, CompletionKind::Parameter),
+            Symbol::Function(_) | Symbol::EngineGlobal(_) => return None
+        };
+        Some(DefinitionLocation { file: self.files.get(file).cloned(), line, column, end, kind })
+    }
+
+    /// Get what the name at the given position (line, column) of the script data refers to.
+    ///
+    /// Engine functions include their signature from the definitions of the target, and scripts include the signature they are defined
+    /// with. Scripts and globals are found in the script data as well as any script data that was already read. Returns `None` if there
+    /// is no name at the position, such as if it is a literal or a keyword.
+    pub fn hover(&self, script: &[u8], position: (usize, usize)) -> Option<Hover> {
+        let (token, symbol) = self.symbol_at(script, position)?;
+        let (kind, value_type, signature) = match symbol {
+            Symbol::Function(f) => (CompletionKind::Function, f.return_type, Some(f)),
+            Symbol::Script(d) => (CompletionKind::Script, d.value_type, Some(d.to_signature())),
+            Symbol::Global(d) => (CompletionKind::Global, d.value_type, None),
+            Symbol::EngineGlobal(value_type) => (CompletionKind::Global, value_type, None),
+            Symbol::Parameter(p) => (CompletionKind::Parameter, p.value_type, None)
+        };
+        Some(Hover { line: token.line, column: token.column, end: token.end, name: token.string.to_ascii_lowercase(), kind, value_type, signature })
+    }
+
+    /// Find the name at the given position of the script data and what it refers to.
+    ///
+    /// Tokens of the script data have a file index one past the files that were already read.
+    fn symbol_at(&self, script: &[u8], position: (usize, usize)) -> Option<(Token, Symbol)> {
+        let TokenizedScript { tokens, end, .. } = self.tokenize("", self.files.len(), script, self.encoding).ok()?;
+        let options = TreeOptions { allow_empty_blocks: true, close_unterminated: true, maximum_depth: None };
+        let (tree, _) = build_token_tree_recovering("", tokens, end, options);
+
+        // Find the token at the position and the blocks it is in
+        let mut path = Vec::<&Token>::new();
+        let mut siblings = &tree[..];
+        let token = loop {
+            let t = siblings.iter().find(|t| (t.line, t.column) <= position && position <= t.end)?;
+            match t.children.as_ref() {
+                Some(c) => {
+                    path.push(t);
+                    siblings = c;
+                },
+                None => break t
+            }
+        };
+        let top_level = path.first()?.children.as_ref().unwrap();
+        let name = token.string.to_ascii_lowercase();
+        let is_token = |line: usize, column: usize| token.file == self.files.len() && (token.line, token.column) == (line, column);
+
+        let definitions: Vec<Definition> = self.tokens.iter().chain(tree.iter()).filter_map(Definition::from_block).collect();
+        let parameters = parameter_definitions(path[0]);
+        let (_, globals) = callable_engine_functions_and_globals(self.target, &self.engine_definitions);
+
+        // Names where they are defined
+        if let Some(d) = definitions.iter().position(|d| d.file == self.files.len() && is_token(d.line, d.column)) {
+            let d = definitions.into_iter().nth(d).unwrap();
+            return Some((token.clone(), if d.is_script { Symbol::Script(d) } else { Symbol::Global(d) }))
+        }
+        if let Some(p) = parameters.iter().position(|p| is_token(p.line, p.column)) {
+            return Some((token.clone(), Symbol::Parameter(parameters.into_iter().nth(p).unwrap())))
+        }
+
+        // Anything else must be in an expression rather than a keyword or type
+        let expression_offset = match top_level.first().map(|t| t.string.to_ascii_lowercase()).as_deref() {
+            Some("global") => 3,
+            Some("script") => ScriptType::from_str(&top_level.get(1)?.string.to_ascii_lowercase())?.expression_offset(),
+            _ => return None
+        };
+        let outermost = path.get(1).copied().unwrap_or(token);
+        if top_level.iter().position(|t| std::ptr::eq(t, outermost))? < expression_offset {
+            return None
+        }
+
+        // Scripts take precedence over engine functions of the same name
+        let block = path.last().unwrap().children.as_ref().unwrap();
+        let index = block.iter().position(|t| std::ptr::eq(t, token))?;
+        let symbol = if path.len() > 1 && index == 0 {
+            match definitions.into_iter().find(|d| d.is_script && d.name == name) {
+                Some(d) => Symbol::Script(d),
+                None => Symbol::Function(self.function_signature(&name, &[])?)
+            }
+        }
+        else if let Some(p) = parameters.into_iter().find(|p| p.name == name) {
+            Symbol::Parameter(p)
+        }
+        else if let Some(g) = globals.get(name.as_str()) {
+            Symbol::EngineGlobal(g.get_value_type())
+        }
+        else {
+            let d = definitions.into_iter().filter(|d| d.name == name).min_by_key(|d| d.is_script)?;
+            if d.is_script { Symbol::Script(d) } else { Symbol::Global(d) }
+        };
+
+        Some((token.clone(), symbol))
+    }
+
+    /// Get the signature of the script or engine function with the given name.
+    ///
+    /// Scripts take precedence over engine functions of the same name.
+    fn function_signature(&self, name: &str, definitions: &[Definition]) -> Option<FunctionSignature> {
+        if let Some(d) = definitions.iter().find(|d| d.is_script && d.name == name) {
+            return Some(d.to_signature())
+        }
+
+        if let Some(f) = self.engine_definitions.find_user_function(name).filter(|f| f.supports_target(self.target)) {
+            return Some(user_function_signature(&*f.0))
+        }
+
+        let function = self.engine_definitions.find_function(name).filter(|f| f.availability.supports_target(self.target))?;
+        Some(FunctionSignature {
+            name: name.to_owned(),
+            return_type: function.return_type,
+            parameters: function.parameters.iter().map(|p| SignatureParameter { name: None, value_type: p.value_type, optional: p.optional, many: p.many }).collect()
+        })
+    }
+
+    /// Tokenize script data which may end in the middle of a string.
+    fn tokenize_incomplete(&self, script: &[u8]) -> Option<Vec<Token>> {
+        match self.tokenize("", 0, script, self.encoding) {
+            Ok(TokenizedScript { tokens, .. }) => Some(tokens),
+
+            // The cursor may be inside of a string that is still being typed
+            Err(_) => {
+                let mut terminated = script.to_vec();
+                terminated.push(b'"');
+                self.tokenize("", 0, &terminated, self.encoding).ok().map(|t| t.tokens)
+            }
+        }
+    }
+}
+
+/// Classifies tokens for [`Compiler::semantic_tokens`].
+struct TokenClassifier<'a> {
+    script: &'a [u8],
+    definitions: &'a [Definition],
+    functions: &'a BTreeMap<&'a str, &'a dyn CallableFunction>,
+    globals: &'a BTreeMap<&'a str, &'a dyn CallableGlobal>
+}
+
+impl<'a> TokenClassifier<'a> {
+    fn classify_top_level_block(&self, block: &Token, semantic_tokens: &mut Vec<SemanticToken>) {
+        let children = match block.children.as_ref() {
+            Some(n) => n,
+            None => return
+        };
+        let mut add = |token: &Token, token_type: SemanticTokenType, definition: bool| if token.children.is_none() {
+            semantic_tokens.push(semantic_token(token, token_type, definition));
+        };
+
+        let expression_offset = match children.first().map(|t| t.string.to_ascii_lowercase()).as_deref() {
+            Some("global") => {
+                add(&children[0], SemanticTokenType::Keyword, false);
+                if let Some(t) = children.get(1) {
+                    add(t, SemanticTokenType::Type, false);
+                }
+                if let Some(t) = children.get(2) {
+                    add(t, SemanticTokenType::Global, true);
+                }
+                3
+            },
+            Some("script") => {
+                add(&children[0], SemanticTokenType::Keyword, false);
+                let script_type = match children.get(1) {
+                    Some(t) => {
+                        add(t, SemanticTokenType::Keyword, false);
+                        match ScriptType::from_str(&t.string.to_ascii_lowercase()) {
+                            Some(n) => n,
+                            None => return
+                        }
+                    },
+                    None => return
+                };
+                if !script_type.always_returns_void() {
+                    if let Some(t) = children.get(2) {
+                        add(t, SemanticTokenType::Type, false);
+                    }
+                }
+
+                // Script parameters are in a block with the name
+                match children.get(script_type.expression_offset() - 1).and_then(|t| t.children.as_ref()) {
+                    Some(c) => {
+                        if let Some(t) = c.first() {
+                            add(t, SemanticTokenType::Script, true);
+                        }
+                        for p in c[1..].iter().filter_map(|p| p.children.as_ref()) {
+                            if let Some(t) = p.first() {
+                                add(t, SemanticTokenType::Type, false);
+                            }
+                            if let Some(t) = p.get(1) {
+                                add(t, SemanticTokenType::Parameter, true);
+                            }
+                        }
+                    },
+                    None => if let Some(t) = children.get(script_type.expression_offset() - 1) {
+                        add(t, SemanticTokenType::Script, true);
+                    }
+                }
+                script_type.expression_offset()
+            },
+            _ => return
+        };
+
+        let parameters: Vec<String> = Definition::from_block(block).map(|d| d.parameters.into_iter().map(|p| p.0).collect()).unwrap_or_default();
+        for t in children.iter().skip(expression_offset) {
+            self.classify_expression(t, &parameters, semantic_tokens);
+        }
+    }
+
+    fn classify_expression(&self, token: &Token, parameters: &[String], semantic_tokens: &mut Vec<SemanticToken>) {
+        let children = match token.children.as_ref() {
+            Some(n) => n,
+            None => return semantic_tokens.push(semantic_token(token, self.classify_value(token, parameters), false))
+        };
+
+        let mut arguments = children.iter();
+        let function_name = match children.first() {
+            Some(t) if t.children.is_none() => {
+                arguments.next();
+                t.string.to_ascii_lowercase()
+            },
+            _ => String::new()
+        };
+
+        // Scripts take precedence over engine functions of the same name
+        if self.definitions.iter().any(|d| d.is_script && d.name == function_name) {
+            semantic_tokens.push(semantic_token(&children[0], SemanticTokenType::Script, false));
+        }
+        else if self.functions.contains_key(function_name.as_str()) {
+            semantic_tokens.push(semantic_token(&children[0], SemanticTokenType::Function, false));
+        }
+
+        if function_name == "set" {
+            if let Some(t) = arguments.next().filter(|t| t.children.is_none()) {
+                semantic_tokens.push(semantic_token(t, SemanticTokenType::Global, false));
+            }
+        }
+
+        for t in arguments {
+            self.classify_expression(t, parameters, semantic_tokens);
+        }
+    }
+
+    fn classify_value(&self, token: &Token, parameters: &[String]) -> SemanticTokenType {
+        if self.script.get(offset_of_position(self.script, (token.line, token.column))) == Some(&b'"') {
+            return SemanticTokenType::String
+        }
+
+        let name = token.string.to_ascii_lowercase();
+        if parameters.contains(&name) {
+            SemanticTokenType::Parameter
+        }
+        else if self.globals.contains_key(name.as_str()) || self.definitions.iter().any(|d| !d.is_script && d.name == name) {
+            SemanticTokenType::Global
+        }
+        else if self.definitions.iter().any(|d| d.is_script && d.name == name) {
+            SemanticTokenType::Script
+        }
+        else if is_number_literal(&name) {
+            SemanticTokenType::Number
+        }
+        else {
+            SemanticTokenType::Literal
+        }
+    }
+}
+
+fn semantic_token(token: &Token, token_type: SemanticTokenType, definition: bool) -> SemanticToken {
+    SemanticToken { line: token.line, column: token.column, end: token.end, token_type, definition }
+}
+
+/// Parameters of registered functions shown in signatures before the last one shown is assumed to be repeated.
+const MAXIMUM_USER_FUNCTION_SIGNATURE_PARAMETERS: usize = 16;
+
+/// Get the signature of a registered function.
+///
+/// Its parameters are only known by asking for each one, so parameters after the last one shown are assumed to have its type.
+fn user_function_signature(function: &dyn UserFunction) -> FunctionSignature {
+    let minimum = function.get_minimum_parameter_count();
+    let mut parameters: Vec<SignatureParameter> = (0..MAXIMUM_USER_FUNCTION_SIGNATURE_PARAMETERS)
+        .map_while(|i| function.get_type_of_parameter(i).map(|value_type| SignatureParameter { name: None, value_type, optional: i >= minimum, many: false }))
+        .collect();
+    if parameters.len() == MAXIMUM_USER_FUNCTION_SIGNATURE_PARAMETERS && function.get_type_of_parameter(parameters.len()).is_some() {
+        parameters.last_mut().unwrap().many = true;
+    }
+
+    FunctionSignature { name: function.get_name().to_owned(), return_type: function.get_return_type(), parameters }
+}
+
+/// Get what can be written at the given index of the innermost block of the path.
+///
+/// The path goes from a top level block down to the innermost block.
+fn slot_at(path: &[&Token], index: usize, functions: &BTreeMap<&str, &dyn CallableFunction>, globals: &BTreeMap<&str, &dyn CallableGlobal>, definitions: &[Definition]) -> Option<Slot> {
+    let (block, parents) = path.split_last()?;
+    let children = block.children.as_ref()?;
+    let lowercase = |index: usize| children.get(index).filter(|t| t.children.is_none()).map(|t| t.string.to_ascii_lowercase());
+
+    // Top level blocks define scripts and globals
+    let parent = match parents.last() {
+        Some(n) => n,
+        None => return match lowercase(0)?.as_str() {
+            "global" if index == 3 => ValueType::from_str_underscore(&lowercase(1)?).map(Slot::Value),
+            "script" if index >= ScriptType::from_str(&lowercase(1)?)?.expression_offset() => Some(Slot::Value(ValueType::Void)),
+            _ => None
+        }
+    };
+
+    // The block is a parameter of its parent, so it can call anything that can be passed there
+    let block_slot = || slot_at(parents, parent.children.as_ref().unwrap().len() - 1, functions, globals, definitions);
+    if index == 0 {
+        return match block_slot()? {
+            Slot::Value(n) => Some(Slot::Call(n)),
+            _ => None
+        }
+    }
+
+    let function_name = lowercase(0)?;
+    if function_name == "set" {
+        return match index {
+            1 => Some(Slot::SetGlobal),
+            2 => {
+                let global = lowercase(1)?;
+                definitions.iter().find(|d| !d.is_script && d.name == global).map(|d| d.value_type)
+                    .or_else(|| globals.get(global.as_str()).map(|g| g.get_value_type()))
+                    .map(Slot::Value)
+            },
+            _ => None
+        }
+    }
+
+    // Scripts take precedence over engine functions of the same name
+    if let Some(d) = definitions.iter().find(|d| d.is_script && d.name == function_name) {
+        return d.parameters.get(index - 1).map(|p| Slot::Value(p.1))
+    }
+
+    let function = functions.get(function_name.as_str())?;
+    match function.get_type_of_parameter(index - 1)? {
+        // Passthrough parameters take anything, unless the function returns one of them
+        ValueType::Passthrough if function.is_number_passthrough() => Some(Slot::Value(ValueType::Real)),
+        ValueType::Passthrough if function.get_return_type() == ValueType::Passthrough && !function.is_passthrough_last() => block_slot(),
+        ValueType::Passthrough => Some(Slot::Value(ValueType::Void)),
+        n => Some(Slot::Value(n))
+    }
+}
+
+/// Get the byte offset of a position (line, column) in the script data, or the length of the script data if it's past the end.
+pub(crate) fn offset_of_position(script: &[u8], position: (usize, usize)) -> usize {
+    let (mut line, mut column) = (1, 1);
+    for (i, c) in script.iter().enumerate() {
+        if (line, column) >= position {
+            return i
+        }
+
+        match c {
+            b'\n' if i > 0 && script[i - 1] == b'\r' => (),
+            b'\n' | b'\r' => {
+                line += 1;
+                column = 1;
+            },
+            _ => column += 1
+        }
+    }
+    script.len()
+}
@@ -1,14 +1,17 @@
 use std::fmt;
 use std::ffi::{CStr, CString};
+use super::message::{Message, MessageCatalog};
 
 /// Error type for CompileError.
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, PartialEq, Debug)]
+#[repr(u16)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum CompileErrorType {
     /// Warning, typically for potentially bad, but not technically invalid code
-    Warning,
+    Warning = 0,
 
     /// The code was invalid
-    Error
+    Error = 1
 }
 
 impl CompileErrorType {
@@ -26,30 +29,209 @@ impl fmt::Display for CompileErrorType {
     }
 }
 
+/// Code identifying what a [`CompileError`] is about, so diagnostics can be filtered without matching messages.
+///
+/// The numeric values are stable. New codes must only be appended.
+#[derive(Copy, Clone, PartialEq, Debug)]
+#[repr(u16)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum CompileErrorCode {
+    /// Any diagnostic without a more specific code, including all errors that prevent compilation
+    Generic = 0,
+
+    /// An empty block was compiled as `(begin)`
+    EmptyBlock = 1,
+
+    /// A function meant for the console was used in a script
+    ConsoleFunction = 2,
+
+    /// A real literal is out of range or cannot be represented exactly
+    RealPrecision = 3,
+
+    /// A global is used before it is initialized
+    UninitializedGlobal = 4,
+
+    /// A script may not return a value on every path
+    MissingReturnValue = 5,
+
+    /// More scripts of a type are defined than are recommended for the target
+    ScriptCount = 6,
+
+    /// An expression can be written more simply
+    Style = 7,
+
+    /// Script nodes or string data are close to the target's maximum
+    Budget = 8,
+
+    /// Something used is not supported by the target
+    UnsupportedByTarget = 9,
+
+    /// An external script or global could not be resolved when linking
+    UnresolvedSymbol = 10,
+
+    /// Something used cannot be emitted by the backend
+    UnsupportedByBackend = 11,
+
+    /// Too many warnings or errors were produced, so the rest were not reported
+    DiagnosticLimit = 12,
+
+    /// A script parameter has the same name as a global, so the global cannot be referenced in the script
+    ShadowedGlobal = 13,
+
+    /// A global is set but never read, or read but never set
+    GlobalUsage = 14,
+
+    /// A continuous script is estimated to be expensive to evaluate every tick
+    TickCost = 15,
+
+    /// A script sleeps for an amount of time, or with a `sleep_until` period or timeout, that is probably a mistake
+    SuspiciousSleep = 16,
+
+    /// A parameter of a static script is never referenced in the script
+    UnusedParameter = 17,
+
+    /// A global may be read on the first tick before the startup script that initializes it sets it
+    UnsetGlobal = 18,
+
+    /// The name of a script, global, or parameter looks like a literal
+    LiteralName = 19,
+
+    /// Bytes of a token could not be decoded and were replaced or left out
+    UndecodableToken = 20,
+
+    /// A token is not written in lowercase, though it is compiled as if it were
+    TokenCase = 21,
+
+    /// An engine function that does nothing on a dedicated server is called by script data run by one
+    ClientOnly = 22,
+
+    /// Blocks are nested deeply enough that compiling them may overflow the stack
+    DeepNesting = 23
+}
+
+/// Secondary location that gives context to a [`CompileError`].
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct RelatedLocation {
+    message: CString,
+    localizable_message: Box<Message>,
+    file: CString,
+    line: usize,
+    column: usize
+}
+
+impl RelatedLocation {
+    /// Get the message describing the location.
+    pub fn get_message(&self) -> &str {
+        self.message.to_str().unwrap()
+    }
+
+    /// Get the message describing the location, translated with the catalog.
+    pub fn get_localized_message(&self, catalog: &MessageCatalog) -> String {
+        catalog.format(self.localizable_message.key, &self.localizable_message.arguments)
+    }
+
+    /// Get the filename.
+    pub fn get_file(&self) -> &str {
+        self.file.to_str().unwrap()
+    }
+
+    /// Get the message describing the location.
+    pub fn get_message_cstr(&self) -> &CStr {
+        &self.message
+    }
+
+    /// Get the filename.
+    pub fn get_file_cstr(&self) -> &CStr {
+        &self.file
+    }
+
+    /// Return the line and column of the location.
+    pub fn get_position(&self) -> (usize, usize) {
+        (self.line, self.column)
+    }
+}
+
 /// Diagnostic message generated on warning or error.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct CompileError {
     message: CString,
+    localizable_message: Box<Message>,
     file: CString,
     error_type: CompileErrorType,
+    code: CompileErrorCode,
     line: usize,
-    column: usize
+    column: usize,
+    end: (usize, usize),
+    related_locations: Vec<RelatedLocation>,
+    fix: Option<Box<str>>
 }
 
 impl CompileError {
     /// Create a `CompileError` from the given parameters.
-    pub(crate) fn from_message(file: &str, line: usize, column: usize, error_type: CompileErrorType, message: &str) -> CompileError {
+    pub(crate) fn from_message(file: &str, line: usize, column: usize, error_type: CompileErrorType, message: Message) -> CompileError {
         CompileError {
             line, column, error_type,
-            file: CString::new(file).unwrap(), message: CString::new(message).unwrap()
+            code: CompileErrorCode::Generic,
+            end: (line, column),
+            file: CString::new(file).unwrap(), message: CString::new(message.to_default_string()).unwrap(),
+            localizable_message: Box::new(message),
+            related_locations: Vec::new(),
+            fix: None
         }
     }
 
+    /// Set the code of the error.
+    pub(crate) fn with_code(mut self, code: CompileErrorCode) -> CompileError {
+        self.code = code;
+        self
+    }
+
+    /// Set the line and column just past the end of what the error refers to.
+    pub(crate) fn with_end_position(mut self, line: usize, column: usize) -> CompileError {
+        self.end = (line, column);
+        self
+    }
+
+    /// Set the text that what the error refers to can be replaced with to fix it.
+    pub(crate) fn with_fix(mut self, replacement: String) -> CompileError {
+        self.fix = Some(replacement.into_boxed_str());
+        self
+    }
+
+    /// Add a related location to the error.
+    pub(crate) fn with_related_location(mut self, file: &str, line: usize, column: usize, message: Message) -> CompileError {
+        self.related_locations.push(RelatedLocation {
+            line, column,
+            file: CString::new(file).unwrap(), message: CString::new(message.to_default_string()).unwrap(),
+            localizable_message: Box::new(message)
+        });
+        self
+    }
+
     /// Get the message of the error.
     pub fn get_message(&self) -> &str {
         self.message.to_str().unwrap()
     }
 
+    /// Get the key of the message, which is one of the keys in [`DEFAULT_MESSAGES`](crate::DEFAULT_MESSAGES).
+    ///
+    /// Unlike the message, this does not change between versions unless the meaning of the message changes.
+    pub fn get_message_key(&self) -> &str {
+        self.localizable_message.key
+    }
+
+    /// Get the arguments the message was formatted with, in order.
+    pub fn get_message_arguments(&self) -> &[String] {
+        &self.localizable_message.arguments
+    }
+
+    /// Get the message of the error, translated with the catalog.
+    pub fn get_localized_message(&self, catalog: &MessageCatalog) -> String {
+        catalog.format(self.localizable_message.key, &self.localizable_message.arguments)
+    }
+
     /// Get the filename.
     pub fn get_file(&self) -> &str {
         self.file.to_str().unwrap()
@@ -70,14 +252,41 @@ impl CompileError {
         self.error_type
     }
 
+    /// Get the code of the error.
+    pub fn get_code(&self) -> CompileErrorCode {
+        self.code
+    }
+
     /// Return the line and column of the error token.
     pub fn get_position(&self) -> (usize, usize) {
         (self.line, self.column)
     }
+
+    /// Return the line and column just past the end of the error token.
+    ///
+    /// This is the same as [`CompileError::get_position`] if the end is not known.
+    pub fn get_end_position(&self) -> (usize, usize) {
+        self.end
+    }
+
+    /// Get the text that the source from [`CompileError::get_position`] to [`CompileError::get_end_position`] can be replaced with to
+    /// fix the error, if any.
+    pub fn get_fix(&self) -> Option<&str> {
+        self.fix.as_deref()
+    }
+
+    /// Get any other locations relevant to the error, such as where the end of the file was reached for an unterminated block.
+    pub fn get_related_locations(&self) -> &[RelatedLocation] {
+        &self.related_locations
+    }
 }
 
 impl fmt::Display for CompileError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}:{}:{}: {}: {}", self.file.to_str().unwrap(), self.line, self.column, self.error_type, self.message.to_str().unwrap())
+        write!(f, "{}:{}:{}: {}: {}", self.file.to_str().unwrap(), self.line, self.column, self.error_type, self.message.to_str().unwrap())?;
+        for r in &self.related_locations {
+            write!(f, "\n{}:{}:{}: note: {}", r.get_file(), r.line, r.column, r.get_message())?;
+        }
+        Ok(())
     }
 }
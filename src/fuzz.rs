@@ -0,0 +1,81 @@
+//! Entry points for fuzzing the compiler with arbitrary input.
+//!
+//! These are not part of the stable API.
+
+use super::*;
+use super::token::MAXIMUM_BLOCK_DEPTH;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+/// File name used for fuzzed input.
+const FUZZ_FILE_NAME: &str = "<fuzz>";
+
+/// Prefix of the message of an error converted from a panic.
+pub const INTERNAL_ERROR_PREFIX: &str = "internal compiler error";
+
+/// All targets, one of which is picked by the first byte of the input.
+const TARGETS: [CompileTarget; 8] = [
+    CompileTarget::HaloCEA,
+    CompileTarget::HaloCEXboxNTSC,
+    CompileTarget::HaloCEGBX,
+    CompileTarget::HaloCEGBXDemo,
+    CompileTarget::HaloCustomEdition,
+    CompileTarget::HaloCEGBXDemoMac,
+    CompileTarget::HaloCEXboxPAL,
+    CompileTarget::HaloCEXboxJP
+];
+
+/// Run the function, converting a panic into an error whose message starts with [`INTERNAL_ERROR_PREFIX`].
+fn catch_panic<T>(f: impl FnOnce() -> Result<T, CompileError>) -> Result<T, CompileError> {
+    catch_unwind(AssertUnwindSafe(f)).unwrap_or_else(|panic| {
+        let reason = panic.downcast_ref::<&str>().map(|s| s.to_string())
+            .or_else(|| panic.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "unknown panic".to_owned());
+        Err(CompileError::from_message(FUZZ_FILE_NAME, 1, 1, CompileErrorType::Error, message!("internal_error", reason)))
+    })
+}
+
+/// Make a compiler for the input, where the first byte picks the target and whether or not it is Windows-1252 encoded.
+///
+/// Unlike a compiler made by [`Compiler::new`], blocks nested deeper than [`MAXIMUM_BLOCK_DEPTH`] are errors.
+fn compiler_for_input(data: &[u8]) -> (Compiler, &[u8]) {
+    let (settings, input) = match data.split_first() {
+        Some((settings, input)) => (*settings as usize, input),
+        None => (0, data)
+    };
+    let encoding = if settings & 0x80 == 0 { CompileEncoding::Windows1252 } else { CompileEncoding::UTF8 };
+    let mut compiler = Compiler::new(TARGETS[(settings & 0x7F) % TARGETS.len()], encoding);
+
+    // Deeply nested input would otherwise overflow the stack rather than fail
+    compiler.maximum_block_depth = Some(MAXIMUM_BLOCK_DEPTH);
+    (compiler, input)
+}
+
+/// Get whether or not the error was converted from a panic, which is always a bug.
+pub fn is_internal_error(error: &CompileError) -> bool {
+    error.get_message().starts_with(INTERNAL_ERROR_PREFIX)
+}
+
+/// Compile arbitrary bytes as a script file.
+///
+/// The first byte picks the target and encoding, and the rest is the script.
+pub fn compile_script_bytes(data: &[u8]) -> Result<CompiledScriptData, CompileError> {
+    catch_panic(|| {
+        let (mut compiler, input) = compiler_for_input(data);
+        compiler.read_script_data(FUZZ_FILE_NAME, input)?;
+        compiler.compile_script_data()
+    })
+}
+
+/// Compile arbitrary bytes as a console command.
+///
+/// The first byte picks the target, and the rest is the command, which is an error if it is not valid UTF-8.
+pub fn compile_console_command_bytes(data: &[u8]) -> Result<CompiledExpression, CompileError> {
+    catch_panic(|| {
+        let (mut compiler, input) = compiler_for_input(data);
+        let command = match std::str::from_utf8(input) {
+            Ok(n) => n,
+            Err(_) => return Err(CompileError::from_message(FUZZ_FILE_NAME, 1, 1, CompileErrorType::Error, message!("command_not_utf8")))
+        };
+        compiler.compile_console_command(command)
+    })
+}
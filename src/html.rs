@@ -0,0 +1,72 @@
+use super::*;
+
+/// Escape the text so it can be placed in an HTML element or a quoted attribute.
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped += "&amp;",
+            '<' => escaped += "&lt;",
+            '>' => escaped += "&gt;",
+            '"' => escaped += "&quot;",
+            '\'' => escaped += "&#39;",
+            c => escaped.push(c)
+        }
+    }
+    escaped
+}
+
+impl Compiler {
+    /// Export the script data as syntax highlighted HTML, such as for documentation or code review pages.
+    ///
+    /// The output is a `<pre class="hsc">` element where each token from [`Compiler::semantic_tokens`] is wrapped in an element with the
+    /// class `hsc-` followed by [`SemanticTokenType::as_str`] (e.g. `hsc-function`), so colors are left to the page's stylesheet. Where
+    /// a script or global is defined, it gets the ID `script-<name>` or `global-<name>`, and references to it are links to that ID.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the script data cannot be tokenized or decoded.
+    pub fn export_html(&self, filename: &str, script: &[u8]) -> Result<String, CompileError> {
+        let script = match script.iter().position(|c| *c == 0) {
+            Some(n) => &script[..n],
+            None => script
+        };
+        let semantic_tokens = self.semantic_tokens(filename, script)?;
+
+        let decode = |start: usize, end: usize, position: (usize, usize)| self.encoding.decode_from_bytes(&script[start..end])
+            .map_err(|e| CompileError::from_message(filename, position.0, position.1, CompileErrorType::Error, message!("decode_script_data_failed", e)));
+
+        // Only scripts and globals defined here can be linked to
+        let mut defined = Vec::<(SemanticTokenType, String)>::new();
+        for t in semantic_tokens.iter().filter(|t| t.definition && t.token_type != SemanticTokenType::Parameter) {
+            let text = decode(offset_of_position(script, (t.line, t.column)), offset_of_position(script, t.end), (t.line, t.column))?;
+            defined.push((t.token_type, text.to_ascii_lowercase()));
+        }
+
+        let mut html = String::from("<pre class=\"hsc\">");
+        let mut offset = 0;
+        let mut position = (1, 1);
+        for t in &semantic_tokens {
+            let start = offset_of_position(script, (t.line, t.column));
+            let end = offset_of_position(script, t.end);
+            html += &escape_html(&decode(offset, start, position)?);
+
+            let text = decode(start, end, (t.line, t.column))?;
+            let class = t.token_type.as_str();
+            let name = text.to_ascii_lowercase();
+            let is_defined = defined.iter().any(|(token_type, n)| *token_type == t.token_type && *n == name);
+            html += &match t.token_type {
+                SemanticTokenType::Script | SemanticTokenType::Global if t.definition => format!("<span class=\"hsc-{class}\" id=\"{class}-{}\">{}</span>", escape_html(&name), escape_html(&text)),
+                SemanticTokenType::Script | SemanticTokenType::Global if is_defined => format!("<a class=\"hsc-{class}\" href=\"#{class}-{}\">{}</a>", escape_html(&name), escape_html(&text)),
+                _ => format!("<span class=\"hsc-{class}\">{}</span>", escape_html(&text))
+            };
+
+            offset = end;
+            position = t.end;
+        }
+        html += &escape_html(&decode(offset, script.len(), position)?);
+        html += "</pre>";
+
+        Ok(html)
+    }
+}
@@ -0,0 +1,236 @@
+/// Value of a JSON document, as read by [`parse_json`].
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum JsonValue {
+    Null,
+    Boolean(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<JsonValue>),
+
+    /// Members of an object in the order they were written.
+    Object(Vec<(String, JsonValue)>)
+}
+
+impl JsonValue {
+    /// Get the member of the object with the given key, if it is an object and the member exists.
+    pub fn get(&self, key: &str) -> Option<&JsonValue> {
+        match self {
+            JsonValue::Object(members) => members.iter().find(|m| m.0 == key).map(|m| &m.1),
+            _ => None
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            JsonValue::String(s) => Some(s),
+            _ => None
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            JsonValue::Boolean(b) => Some(*b),
+            _ => None
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[JsonValue]> {
+        match self {
+            JsonValue::Array(a) => Some(a),
+            _ => None
+        }
+    }
+
+    pub fn as_object(&self) -> Option<&[(String, JsonValue)]> {
+        match self {
+            JsonValue::Object(o) => Some(o),
+            _ => None
+        }
+    }
+}
+
+/// Parse a JSON document.
+///
+/// # Errors
+///
+/// Errors with the line and column of the problem if the document is not valid JSON.
+pub(crate) fn parse_json(json: &str) -> Result<JsonValue, String> {
+    let mut parser = JsonParser { json: json.as_bytes(), position: 0 };
+    let value = parser.parse_value(0)?;
+    parser.skip_whitespace();
+    if parser.position != json.len() {
+        return Err(parser.error("expected the end of the document"))
+    }
+    Ok(value)
+}
+
+/// Arrays and objects nested deeper than this are an error rather than a stack overflow.
+const MAXIMUM_JSON_DEPTH: usize = 128;
+
+struct JsonParser<'a> {
+    json: &'a [u8],
+    position: usize
+}
+
+impl<'a> JsonParser<'a> {
+    fn error(&self, message: &str) -> String {
+        let before = &self.json[..self.position.min(self.json.len())];
+        let line = before.iter().filter(|c| **c == b'\n').count() + 1;
+        let column = before.iter().rev().take_while(|c| **c != b'\n').count() + 1;
+        format!("{message} at line {line}, column {column}")
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.json.get(self.position).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(b' ' | b'\t' | b'\r' | b'\n') = self.peek() {
+            self.position += 1;
+        }
+    }
+
+    fn expect(&mut self, c: u8) -> Result<(), String> {
+        self.skip_whitespace();
+        if self.peek() != Some(c) {
+            return Err(self.error(&format!("expected '{}'", c as char)))
+        }
+        self.position += 1;
+        Ok(())
+    }
+
+    fn parse_value(&mut self, depth: usize) -> Result<JsonValue, String> {
+        if depth > MAXIMUM_JSON_DEPTH {
+            return Err(self.error("too deeply nested"))
+        }
+
+        self.skip_whitespace();
+        match self.peek() {
+            Some(b'{') => {
+                self.position += 1;
+                let mut members = Vec::new();
+                self.skip_whitespace();
+                if self.peek() == Some(b'}') {
+                    self.position += 1;
+                    return Ok(JsonValue::Object(members))
+                }
+                loop {
+                    self.skip_whitespace();
+                    if self.peek() != Some(b'"') {
+                        return Err(self.error("expected a key"))
+                    }
+                    let key = self.parse_string()?;
+                    self.expect(b':')?;
+                    members.push((key, self.parse_value(depth + 1)?));
+                    self.skip_whitespace();
+                    match self.peek() {
+                        Some(b',') => self.position += 1,
+                        Some(b'}') => { self.position += 1; return Ok(JsonValue::Object(members)) },
+                        _ => return Err(self.error("expected ',' or '}'"))
+                    }
+                }
+            },
+            Some(b'[') => {
+                self.position += 1;
+                let mut values = Vec::new();
+                self.skip_whitespace();
+                if self.peek() == Some(b']') {
+                    self.position += 1;
+                    return Ok(JsonValue::Array(values))
+                }
+                loop {
+                    values.push(self.parse_value(depth + 1)?);
+                    self.skip_whitespace();
+                    match self.peek() {
+                        Some(b',') => self.position += 1,
+                        Some(b']') => { self.position += 1; return Ok(JsonValue::Array(values)) },
+                        _ => return Err(self.error("expected ',' or ']'"))
+                    }
+                }
+            },
+            Some(b'"') => Ok(JsonValue::String(self.parse_string()?)),
+            Some(b'-' | b'0'..=b'9') => {
+                let start = self.position;
+                while let Some(b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9') = self.peek() {
+                    self.position += 1;
+                }
+                let number = std::str::from_utf8(&self.json[start..self.position]).unwrap();
+                match number.parse::<f64>() {
+                    Ok(n) if n.is_finite() => Ok(JsonValue::Number(n)),
+                    _ => { self.position = start; Err(self.error("invalid number")) }
+                }
+            },
+            _ => {
+                for (word, value) in [("null", JsonValue::Null), ("true", JsonValue::Boolean(true)), ("false", JsonValue::Boolean(false))] {
+                    if self.json[self.position..].starts_with(word.as_bytes()) {
+                        self.position += word.len();
+                        return Ok(value)
+                    }
+                }
+                Err(self.error("expected a value"))
+            }
+        }
+    }
+
+    /// Parse a string, starting at its opening quote.
+    fn parse_string(&mut self) -> Result<String, String> {
+        self.position += 1;
+        let mut string = Vec::new();
+        loop {
+            let c = match self.peek() {
+                Some(n) => n,
+                None => return Err(self.error("unterminated string"))
+            };
+            self.position += 1;
+            match c {
+                b'"' => break,
+                b'\\' => {
+                    let escaped = match self.peek() {
+                        Some(b'"') => '"',
+                        Some(b'\\') => '\\',
+                        Some(b'/') => '/',
+                        Some(b'b') => '\u{8}',
+                        Some(b'f') => '\u{C}',
+                        Some(b'n') => '\n',
+                        Some(b'r') => '\r',
+                        Some(b't') => '\t',
+                        Some(b'u') => {
+                            self.position += 1;
+                            let unit = self.parse_utf16_unit()?;
+                            let c = if (0xD800..0xDC00).contains(&unit) && self.json[self.position..].starts_with(b"\\u") {
+                                self.position += 2;
+                                let low = self.parse_utf16_unit()?;
+                                char::decode_utf16([unit, low]).next().and_then(|c| c.ok())
+                            }
+                            else {
+                                char::from_u32(unit as u32)
+                            };
+                            let c = c.ok_or_else(|| self.error("invalid escape"))?;
+                            string.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+                            continue
+                        },
+                        _ => return Err(self.error("invalid escape"))
+                    };
+                    self.position += 1;
+                    string.extend_from_slice(escaped.encode_utf8(&mut [0; 4]).as_bytes());
+                },
+                c if c < 0x20 => return Err(self.error("control character in string")),
+                c => string.push(c)
+            }
+        }
+
+        // The document is a str and only whole characters were added, so this is always valid
+        Ok(String::from_utf8(string).unwrap())
+    }
+
+    fn parse_utf16_unit(&mut self) -> Result<u16, String> {
+        let digits = self.json.get(self.position..self.position + 4).and_then(|d| std::str::from_utf8(d).ok());
+        match digits.and_then(|d| u16::from_str_radix(d, 16).ok()) {
+            Some(n) => {
+                self.position += 4;
+                Ok(n)
+            },
+            None => Err(self.error("invalid escape"))
+        }
+    }
+}
@@ -1,10 +1,23 @@
+#[cfg(feature = "windows-1252")]
+extern crate encoding;
+#[cfg(feature = "serde")]
+#[macro_use]
+extern crate serde;
+
+#[macro_use]
+mod message;
+pub use message::{MessageCatalog, DEFAULT_MESSAGES};
+use message::Message;
+
 #[cfg(test)]
 mod test; // test module for unit testing
 
 mod definitions;
+use definitions::EngineDefinitions;
+mod json;
 mod value_type;
 mod error;
-pub use error::{CompileErrorType, CompileError};
+pub use error::{CompileErrorType, CompileErrorCode, CompileError, RelatedLocation};
 
 mod compile;
 pub use compile::*;
@@ -14,8 +27,24 @@ pub use types::*;
 
 mod token;
 use token::Token;
+pub use token::ParsedScriptData;
+
+mod editor;
+pub use editor::*;
+
+mod actions;
+pub use actions::*;
+
+mod html;
+
+#[doc(hidden)]
+pub mod fuzz;
+
+pub mod lua;
 
-pub use value_type::ValueType;
+pub use value_type::{ConversionRule, ValueType};
+
+use std::sync::Arc;
 
 /// Compiler instance.
 pub struct Compiler {
@@ -23,28 +52,481 @@ pub struct Compiler {
     files: Vec<String>,
 
     target: CompileTarget,
-    warnings: Vec<CompileError>
+    custom_target: Option<Arc<dyn Target>>,
+    encoding: CompileEncoding,
+    warnings: Vec<CompileError>,
+
+    console: bool,
+
+    obfuscate: bool,
+    optimize_begin: bool,
+    propagate_constants: bool,
+    time_units: bool,
+    output_mode: OutputMode,
+    preserved_names: Vec<String>,
+
+    budget_thresholds: BudgetThresholds,
+    tick_cost_threshold: Option<usize>,
+    check_global_usage: bool,
+    check_script_counts: bool,
+    startup_initialization: bool,
+    check_unset_globals: bool,
+    check_case: bool,
+    dedicated_server: bool,
+    maximum_block_depth: Option<usize>,
+
+    script_index_base: usize,
+    global_index_base: usize,
+    node_index_base: usize,
+    external_scripts: Vec<ExternalScript>,
+    external_globals: Vec<ExternalGlobal>,
+    known_names: Vec<(ValueType, Vec<String>)>,
+    simulated_values: Vec<SimulatedValue>,
+    engine_definitions: EngineDefinitions,
+
+    null_ends_input: bool,
+    decode_error_policy: DecodeErrorPolicy,
+
+    error_recovery: bool,
+    tolerant: bool,
+    errors: Vec<CompileError>,
+
+    maximum_warnings: Option<usize>,
+    maximum_errors: Option<usize>,
+
+    strictness: Strictness,
+    bug_compatibility: BugCompatibility,
+
+    folding_ranges: Vec<Vec<FoldingRange>>,
+
+    trace_callback: Option<TraceCallback>
 }
 
 impl Compiler {
-    /// Instantiate a new compiler instance with the given compile target.
-    pub fn new(target: CompileTarget) -> Compiler {
+    /// Instantiate a new compiler instance with the given compile target and encoding.
+    pub fn new(target: CompileTarget, encoding: CompileEncoding) -> Compiler {
         Compiler {
             tokens: Vec::new(),
             files: Vec::new(),
 
-            target: target,
-            warnings: Vec::new()
+            target,
+            custom_target: None,
+            encoding,
+            warnings: Vec::new(),
+
+            console: false,
+
+            obfuscate: false,
+            optimize_begin: true,
+            propagate_constants: false,
+            time_units: false,
+            output_mode: OutputMode::Optimize,
+            preserved_names: Vec::new(),
+
+            budget_thresholds: BudgetThresholds::default(),
+            tick_cost_threshold: None,
+            check_global_usage: false,
+            check_script_counts: false,
+            startup_initialization: false,
+            check_unset_globals: false,
+            check_case: false,
+            dedicated_server: false,
+            maximum_block_depth: None,
+
+            script_index_base: 0,
+            global_index_base: 0,
+            node_index_base: 0,
+            external_scripts: Vec::new(),
+            external_globals: Vec::new(),
+            known_names: Vec::new(),
+            simulated_values: Vec::new(),
+            engine_definitions: EngineDefinitions::default(),
+
+            null_ends_input: false,
+            decode_error_policy: DecodeErrorPolicy::default(),
+
+            error_recovery: false,
+            tolerant: false,
+            errors: Vec::new(),
+
+            maximum_warnings: None,
+            maximum_errors: None,
+
+            strictness: Strictness::default(),
+            bug_compatibility: BugCompatibility::default(),
+
+            folding_ranges: Vec::new(),
+
+            trace_callback: None
         }
     }
 
+    /// Instantiate a new compiler instance for a target that is not built in, such as an engine patched by a mod to raise its limits.
+    ///
+    /// The limits of the target are used when compiling, while everything else that depends on the target, such as which engine
+    /// functions and globals are available, follows the target it is based on.
+    pub fn with_target(target: Box<dyn Target>, encoding: CompileEncoding) -> Compiler {
+        let mut compiler = Compiler::new(target.get_base_target(), encoding);
+        compiler.custom_target = Some(target.into());
+        compiler
+    }
+
+    /// Get the limits of the target being compiled for.
+    pub(crate) fn target_limits(&self) -> &dyn Target {
+        match self.custom_target.as_deref() {
+            Some(n) => n,
+            None => &self.target
+        }
+    }
+
+    /// Set whether or not a null terminator ends the script data, ignoring anything after it.
+    ///
+    /// This is useful for scripts extracted from tag data, which may be padded. By default, only the last byte may be a null terminator.
+    pub fn set_null_ends_input(&mut self, null_ends_input: bool) {
+        self.null_ends_input = null_ends_input;
+    }
+
+    /// Set what is done with bytes in script data that cannot be decoded with the compiler's encoding.
+    ///
+    /// By default, each sequence of bytes that cannot be decoded is replaced with U+FFFD and a warning is emitted. Use
+    /// [`DecodeErrorPolicy::Error`] to never compile corrupted bytes into strings or names. Every byte can be decoded with Windows-1252,
+    /// so this only matters for UTF-8.
+    pub fn set_decode_error_policy(&mut self, decode_error_policy: DecodeErrorPolicy) {
+        self.decode_error_policy = decode_error_policy;
+    }
+
+    /// Set whether or not to keep going after an error, so that every error can be reported in one pass.
+    ///
+    /// When reading script data, a malformed block (such as one with unbalanced parenthesis) is skipped, and reading resumes at the next
+    /// left parenthesis at the start of a line. The well-formed blocks are still loaded. When compiling, a script or global that fails to
+    /// compile is skipped so the rest can be checked, though compiling still fails with the first of these errors. All errors can be
+    /// retrieved with [`Compiler::get_errors`], or use [`Compiler::compile_script_data_all_errors`] to get them from compiling directly.
+    pub fn set_error_recovery(&mut self, error_recovery: bool) {
+        self.error_recovery = error_recovery;
+    }
+
+    /// Set whether or not incomplete script data, such as a document that is still being edited, is analyzed as far as possible.
+    ///
+    /// This implies error recovery. Additionally, any block that is still open at the end of the script data is closed there when
+    /// reading (though it is still an error), and compiling fails if there were any errors when reading, too. Every error can be
+    /// retrieved with [`Compiler::get_errors`].
+    pub fn set_tolerant(&mut self, tolerant: bool) {
+        self.tolerant = tolerant;
+    }
+
+    /// Get all errors that were recovered from.
+    ///
+    /// This is always empty unless error recovery was enabled with [`Compiler::set_error_recovery`] or [`Compiler::set_tolerant`].
+    pub fn get_errors(&self) -> &[CompileError] {
+        &self.errors
+    }
+
+    /// Set how many warnings can be produced before compiling stops with an error, or `None` for no limit. By default, there is no
+    /// limit.
+    ///
+    /// This protects editors from pathological script data that produces an overwhelming number of diagnostics. Only the warnings up
+    /// to the limit are kept.
+    pub fn set_maximum_warnings(&mut self, maximum: Option<usize>) {
+        self.maximum_warnings = maximum;
+    }
+
+    /// Set how many errors can be recovered from before reading or compiling stops with an error, or `None` for no limit. By default,
+    /// there is no limit.
+    ///
+    /// This only applies if error recovery or tolerant mode is enabled. Only the errors up to the limit are kept in
+    /// [`Compiler::get_errors`].
+    pub fn set_maximum_errors(&mut self, maximum: Option<usize>) {
+        self.maximum_errors = maximum;
+    }
+
+    /// Set how strictly questionable constructs are handled. By default, this is [`Strictness::Strict`].
+    ///
+    /// This must be set before reading script data, as some constructs are handled when reading.
+    pub fn set_strictness(&mut self, strictness: Strictness) {
+        self.strictness = strictness;
+    }
+
+    /// Set which quirks of the engine and the original tools are reproduced. By default, every quirk is reproduced.
+    pub fn set_bug_compatibility(&mut self, bug_compatibility: BugCompatibility) {
+        self.bug_compatibility = bug_compatibility;
+    }
+
     /// Read the tokens from a u8 slice containing string data.
     ///
+    /// A quoted token (e.g. `"my object"`) is the same as an unquoted one, except that it can contain whitespace, parentheses, and
+    /// semicolons. Names can therefore be quoted anywhere they are expected, whether they are name literals such as object names, or the
+    /// names of scripts, globals, and parameters, both where they are defined and where they are referenced.
+    ///
     /// # Errors
     ///
-    /// Errors if the script contains data that could not be decoded, if non-parenthesis tokens exist outside of a block, or if any parenthesis are unmatched.
+    /// Errors if the compiler's encoding is disabled (see [`CompileEncoding::is_enabled`]), if the script contains data that could not be
+    /// decoded and the decode error policy is [`DecodeErrorPolicy::Error`], if non-parenthesis tokens exist outside of a block, or if any parenthesis are unmatched.
+    ///
+    /// If error recovery or tolerant mode is enabled, this returns the first error after reading the rest of the script data.
     pub fn read_script_data(&mut self, filename: &str, script: &[u8]) -> Result<(), CompileError> {
-        self.tokenize_script_data(filename, script)
+        let start = std::time::Instant::now();
+        self.tokenize_script_data(filename, script)?;
+        self.report_phase(CompilePhase::Tokenize, start, script.len(), |c| token_memory(&c.tokens));
+        Ok(())
+    }
+
+    /// Tokenize script data like [`Compiler::read_script_data`], but return it instead of loading it, so it can be read any number of
+    /// times with [`Compiler::read_parsed_script_data`] without being tokenized again.
+    ///
+    /// This is useful for editors, which check every open file again whenever one of them changes. The script data is tokenized with
+    /// this compiler's settings, such as its encoding and whether or not it is tolerant, so it should only be read by compilers with the
+    /// same settings. Nothing is loaded into this compiler.
+    pub fn parse_script_data(&mut self, filename: &str, script: &[u8]) -> ParsedScriptData {
+        self.parse_script_data_separately(filename, script)
+    }
+
+    /// Load script data tokenized with [`Compiler::parse_script_data`], as if it were read with [`Compiler::read_script_data`].
+    ///
+    /// # Errors
+    ///
+    /// Errors with the same error that reading the script data did, if any, or if too many diagnostics were found.
+    pub fn read_parsed_script_data(&mut self, parsed: &ParsedScriptData) -> Result<(), CompileError> {
+        self.read_parsed_script_data_tokens(parsed)
+    }
+
+    /// Set whether or not scripts and globals are renamed to short generated names when compiling script data.
+    ///
+    /// Dormant scripts and names passed to [`Compiler::preserve_name`] are not renamed. The renamed symbols can be retrieved with
+    /// [`CompiledScriptData::get_renamed_symbols`].
+    pub fn set_obfuscate(&mut self, obfuscate: bool) {
+        self.obfuscate = obfuscate;
+    }
+
+    /// Set whether or not `begin` blocks with only one expression are replaced with that expression when compiling script data.
+    ///
+    /// This saves nodes, but the node indices will no longer match what the original tools emit. By default, this is enabled. The
+    /// number of nodes saved can be retrieved with [`CompiledScriptData::get_statistics`].
+    pub fn set_optimize_begin(&mut self, optimize_begin: bool) {
+        self.optimize_begin = optimize_begin;
+    }
+
+    /// Set whether or not references to globals that are initialized to a literal and never set are replaced with that literal when
+    /// compiling script data.
+    ///
+    /// This saves global lookups at runtime, and globals that are no longer referenced are removed. Globals set by other script data,
+    /// such as a scenario being appended to, cannot be seen, so this is disabled by default. Preserved names are not propagated. What
+    /// was propagated can be retrieved with [`CompiledScriptData::get_propagated_globals`].
+    pub fn set_propagate_constants(&mut self, propagate_constants: bool) {
+        self.propagate_constants = propagate_constants;
+    }
+
+    /// Set whether or not `(seconds <number>)` and `(minutes <number>)` can be used in place of a number of ticks, such as with
+    /// `(sleep (seconds 2.5))`.
+    ///
+    /// These are converted to a literal number of ticks (30 per second) when compiling, so they cost nothing at runtime. A script with
+    /// the same name takes precedence. By default, this is disabled, as the original tools do not support it.
+    pub fn set_time_units(&mut self, time_units: bool) {
+        self.time_units = time_units;
+    }
+
+    /// Set how compiled script data is laid out. By default, this is [`OutputMode::Optimize`].
+    ///
+    /// This also sets [`Compiler::set_optimize_begin`] to match the mode, and [`OutputMode::SourceOrder`] enables
+    /// [`BugCompatibility::set_variable_index`], so anything set before this is overridden.
+    pub fn set_output_mode(&mut self, output_mode: OutputMode) {
+        self.output_mode = output_mode;
+        self.optimize_begin = output_mode == OutputMode::Optimize;
+        if output_mode == OutputMode::SourceOrder {
+            self.bug_compatibility.set_variable_index = true;
+        }
+    }
+
+    /// Prevent a script or global from being renamed by obfuscation, such as if it is referenced by name from a tag.
+    pub fn preserve_name(&mut self, name: &str) {
+        self.preserved_names.push(name.to_ascii_lowercase());
+    }
+
+    /// Set the soft limits at which compiling script data warns about node and string data usage.
+    ///
+    /// The warnings list the scripts and globals that use the most. By default, no budget warnings are emitted.
+    pub fn set_budget_thresholds(&mut self, thresholds: BudgetThresholds) {
+        self.budget_thresholds = thresholds;
+    }
+
+    /// Set the estimated cost at which compiling script data warns about a continuous script, as continuous scripts are evaluated every
+    /// tick. By default, no cost warnings are emitted.
+    ///
+    /// The cost is estimated by counting every node that may be evaluated, including the nodes of static scripts that are called, with
+    /// calls to engine functions that are known to be expensive (such as line of sight checks) counting for more.
+    pub fn set_tick_cost_threshold(&mut self, threshold: Option<usize>) {
+        self.tick_cost_threshold = threshold;
+    }
+
+    /// Set whether or not compiling script data warns about globals that are set with `set` but never read, and globals that are read
+    /// but never set after they are initialized.
+    ///
+    /// Globals used only by other script data, such as a scenario being appended to, cannot be seen, so this is disabled by default.
+    pub fn set_check_global_usage(&mut self, check_global_usage: bool) {
+        self.check_global_usage = check_global_usage;
+    }
+
+    /// Set whether or not compiling script data warns about having more continuous or dormant scripts than are recommended for the
+    /// target.
+    ///
+    /// The recommended counts are rough guidelines rather than limits enforced by the engine, so this is disabled by default.
+    pub fn set_check_script_counts(&mut self, check_script_counts: bool) {
+        self.check_script_counts = check_script_counts;
+    }
+
+    /// Set whether or not globals that a startup script sets before reading them are treated as initialized by that script, so using
+    /// them to initialize an earlier global is not warned about.
+    ///
+    /// Only `set` calls that are always evaluated count, such as those directly in the script's body or in a `begin` block, and not
+    /// those in an `if` or another script. This is disabled by default, as the earlier global is still initialized with the global's
+    /// value from before the startup script runs.
+    pub fn set_startup_initialization(&mut self, startup_initialization: bool) {
+        self.startup_initialization = startup_initialization;
+    }
+
+    /// Set whether or not compiling script data warns about globals that a startup script initializes, but that another startup or
+    /// continuous script may read before they are set, such as if it is defined earlier or the startup script sleeps first.
+    ///
+    /// A global counts as initialized by a startup script if the script always sets it before reading it. The warning notes each call to
+    /// a static script on the way to the read. This is disabled by default.
+    pub fn set_check_unset_globals(&mut self, check_unset_globals: bool) {
+        self.check_unset_globals = check_unset_globals;
+    }
+
+    /// Set whether or not compiling script data warns about tokens that are not written in lowercase, such as `Sleep`, as they are
+    /// compiled as if they were.
+    ///
+    /// Each warning has a fix replacing the token with its lowercase form. Strings passed where uppercase is allowed, such as to `print`,
+    /// are left alone. The original tools lowercased tokens silently, so this is disabled by default.
+    pub fn set_check_case(&mut self, check_case: bool) {
+        self.check_case = check_case;
+    }
+
+    /// Set whether or not the script data is run by a dedicated server, warning about calls to engine functions that do nothing there,
+    /// such as ones that only change what the local player sees or hears.
+    ///
+    /// Which functions these are is read from the `client_only` flag of the definitions. This is disabled by default.
+    pub fn set_dedicated_server(&mut self, dedicated_server: bool) {
+        self.dedicated_server = dedicated_server;
+    }
+
+    /// Set the indices that compiled scripts, globals, and nodes start at, for appending to a scenario that already has script data.
+    ///
+    /// Script and global references use the offset indices, and so do node indices stored in the compiled script data, such as
+    /// [`CompiledScript::get_first_node_index`]. By default, all indices start at 0.
+    pub fn set_index_bases(&mut self, script_base: usize, global_base: usize, node_base: usize) {
+        self.script_index_base = script_base;
+        self.global_index_base = global_base;
+        self.node_index_base = node_base;
+    }
+
+    /// Declare a script that is defined elsewhere, so that compiled scripts and globals can call it.
+    ///
+    /// If the index is known, such as for a script already in the scenario, references use it. Otherwise, references are left
+    /// unresolved and listed in [`CompiledScriptData::get_relocations`]. The script is not compiled, and defining a script with the
+    /// same name is an error.
+    pub fn declare_external_script(&mut self, name: &str, index: Option<usize>, return_type: ValueType, parameters: &[ValueType]) {
+        self.external_scripts.push(ExternalScript { name: name.to_ascii_lowercase(), index, return_type, parameters: parameters.to_owned() });
+    }
+
+    /// Declare a global that is defined elsewhere, so that compiled scripts and globals can use it.
+    ///
+    /// If the index is known, such as for a global already in the scenario, references use it. Otherwise, references are left
+    /// unresolved and listed in [`CompiledScriptData::get_relocations`]. The global is not compiled, and defining a global with the
+    /// same name is an error.
+    pub fn declare_external_global(&mut self, name: &str, index: Option<usize>, value_type: ValueType) {
+        self.external_globals.push(ExternalGlobal { name: name.to_ascii_lowercase(), index, value_type });
+    }
+
+    /// Set every valid name for literals of the value type, such as the hud message names or cutscene titles in the scenario, or
+    /// `None` to stop checking them.
+    ///
+    /// Literals of the value type that are not in the list are an error, suggesting the closest name if one is similar. Names are not
+    /// case sensitive.
+    pub fn set_known_names(&mut self, value_type: ValueType, names: Option<&[&str]>) {
+        self.known_names.retain(|(t, _)| *t != value_type);
+        if let Some(names) = names {
+            self.known_names.push((value_type, names.iter().map(|n| n.to_ascii_lowercase()).collect()));
+        }
+    }
+
+    /// Assume that an engine global or an engine function without parameters, such as `game_difficulty_get`, always has the value, or
+    /// stop assuming it if `None`.
+    ///
+    /// When compiling, references to it are replaced with the value, and comparisons, `and`, `or`, `not`, and `if` that then always
+    /// have the same result are folded, removing branches that are never taken. With constant propagation, constant globals are folded
+    /// too. This is meant for analysis, such as finding what runs on a given difficulty, as the script data no longer does what the
+    /// scripts do. The value is written like a literal in a script, and only booleans, numbers, difficulties, and teams can be simulated.
+    /// Strings can't be, though no built-in engine global or function without parameters has a string value, so scripts can't read
+    /// something like the map name to begin with.
+    ///
+    /// # Errors
+    ///
+    /// Errors if it is not an engine global or function without parameters for the target, if its type can't be simulated, or if the
+    /// value can't be parsed.
+    pub fn set_simulated_value(&mut self, name: &str, value: Option<&str>) -> Result<(), String> {
+        let name = name.to_ascii_lowercase();
+        let simulated = match value {
+            Some(n) => Some(self.parse_simulated_value(&name, n)?),
+            None => None
+        };
+        self.simulated_values.retain(|v| v.name != name);
+        self.simulated_values.extend(simulated);
+        Ok(())
+    }
+
+    /// Load additional engine functions and globals, such as ones added by a server mod, from JSON using the same schema as the
+    /// built-in definitions.
+    ///
+    /// The document has a `functions` array and a `globals` array, each entry having a `name`, a `type`, and an `engines` object mapping
+    /// each target it is available on (such as `gbx-custom`) to its index in the engine's table, or `null` if the index is not known.
+    /// Functions can also have `parameters`, each with a `type` and optionally `many`, `allow_uppercase`, and `optional`, as well as the
+    /// other flags used by the built-in definitions. Engines listed in an `engines` array with a `base` share the base's indices, and
+    /// built-in engines that have a base, such as `xbox-pal`, share it unless listed without one. Every engine must be a built-in
+    /// engine.
+    ///
+    /// Loaded definitions replace built-in or previously loaded definitions with the same name.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the document can't be parsed or does not follow the schema, in which case nothing is loaded.
+    pub fn add_definitions(&mut self, json: &str) -> Result<(), String> {
+        self.engine_definitions.load_json(json)
+    }
+
+    /// Register a function supplied by the application, such as one added to the engine by a mod, so scripts can call it.
+    ///
+    /// Calls to it are compiled as calls to an engine function with the index it gives for the target, and targets it gives no index
+    /// for do not have it. It replaces any engine function or previously registered function with the same name.
+    pub fn register_function(&mut self, function: Box<dyn UserFunction>) {
+        self.engine_definitions.register_function(function.into());
+    }
+
+    /// Set a function to call with the time taken by each phase of compilation, or `None` to stop reporting them.
+    ///
+    /// This is useful for finding out what makes a large compile slow or uses a lot of memory. Phases that fail are not reported.
+    pub fn set_trace_callback(&mut self, callback: Option<TraceCallback>) {
+        self.trace_callback = callback;
+    }
+
+    fn report_phase<F: FnOnce(&Compiler) -> MemoryUsage>(&mut self, phase: CompilePhase, start: std::time::Instant, count: usize, memory: F) {
+        if self.trace_callback.is_none() {
+            return
+        }
+
+        // Measure the memory after the time, as walking the tokens or nodes takes time too
+        let duration = start.elapsed();
+        let memory = memory(self);
+        if let Some(callback) = self.trace_callback.as_mut() {
+            callback(&PhaseTiming { phase, duration, count, memory });
+        }
+    }
+
+    /// Get the folding ranges of a file that was read, sorted by their first line.
+    ///
+    /// The file index is the order the file was read in, starting at 0. This is empty if the file does not exist or if the script data
+    /// was already compiled.
+    pub fn folding_ranges(&self, file: usize) -> &[FoldingRange] {
+        self.folding_ranges.get(file).map(|f| f.as_slice()).unwrap_or(&[])
     }
 
     /// Parse all loaded tokens and then clear the tokens if successful.
@@ -55,4 +537,152 @@ impl Compiler {
     pub fn compile_script_data(&mut self) -> Result<CompiledScriptData, CompileError> {
         self.digest_tokens()
     }
+
+    /// Parse all loaded tokens like [`Compiler::compile_script_data`] with error recovery enabled, returning every error rather than
+    /// just the first.
+    ///
+    /// # Errors
+    ///
+    /// Errors with every error that was recovered from while compiling, followed by the error that stopped compiling if it was not one
+    /// of them.
+    pub fn compile_script_data_all_errors(&mut self) -> Result<CompiledScriptData, Vec<CompileError>> {
+        let error_recovery = std::mem::replace(&mut self.error_recovery, true);
+        let error_count = self.errors.len();
+        let result = self.digest_tokens();
+        self.error_recovery = error_recovery;
+
+        result.map_err(|e| {
+            let mut errors = self.errors.split_off(error_count);
+            if !errors.iter().any(|d| d.get_position() == e.get_position() && d.get_message() == e.get_message()) {
+                errors.push(e);
+            }
+            errors
+        })
+    }
+
+    /// Parse all loaded tokens like [`Compiler::compile_script_data`], bundling the script data with its diagnostics, statistics,
+    /// tag references, call graph, and source map.
+    ///
+    /// This is meant for build pipelines, which can serialize the whole report with [`CompileReport::to_json`]. The report is returned
+    /// even if compiling fails, in which case it only has diagnostics. Enable error recovery with [`Compiler::set_error_recovery`] to
+    /// get every error rather than just the first.
+    pub fn compile_with_report(&mut self) -> CompileReport {
+        let files = self.files.clone();
+        let result = self.digest_tokens();
+        let diagnostics = self.errors.drain(..).chain(self.warnings.drain(..)).collect();
+        CompileReport::new(result, files, diagnostics)
+    }
+
+    /// Parse all loaded tokens and pass the typed node tree of each script and global to the backend, and then clear the tokens if
+    /// successful.
+    ///
+    /// This returns the backend's output along with any warnings, sorted like [`CompiledScriptData::get_warnings`]. Nodes are not
+    /// optimized differently for the backend, so the typed node trees are the same as what [`Compiler::compile_script_data`] flattens
+    /// into nodes.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the script data is invalid or if the backend fails.
+    pub fn compile_with_backend<B: EmitBackend>(&mut self, backend: B) -> Result<(B::Output, Vec<CompileError>), CompileError> {
+        self.digest_tokens_with_backend(backend)
+    }
+
+    /// Check all loaded tokens in tolerant mode without compiling them, and then clear the tokens.
+    ///
+    /// This is faster than [`Compiler::compile_script_data`], as no nodes are emitted, which is useful for editors that check script
+    /// data as it is edited. Every file that was read is returned, even if it has no diagnostics. Errors that were recovered from when
+    /// reading are included, so tolerant mode should also be enabled before reading with [`Compiler::set_tolerant`].
+    pub fn check_all(&mut self) -> Vec<FileDiagnostics> {
+        let tolerant = std::mem::replace(&mut self.tolerant, true);
+        if let Err(e) = self.analyze_tokens() {
+            self.errors.push(e);
+        }
+        self.tolerant = tolerant;
+
+        let mut diagnostics: Vec<CompileError> = self.errors.drain(..).chain(self.warnings.drain(..)).collect();
+        diagnostics.sort_by_key(|d| d.get_position());
+        self.folding_ranges.clear();
+
+        self.files.drain(..).map(|file| {
+            let (file_diagnostics, rest) = diagnostics.drain(..).partition(|d| d.get_file() == file);
+            diagnostics = rest;
+            FileDiagnostics { file, diagnostics: file_diagnostics }
+        }).collect()
+    }
+
+    /// Parse all loaded tokens once and then clear the tokens if successful, returning the script data for each target.
+    ///
+    /// The results are in the same order as the targets, each with the function indices of its target. This is useful for scripts
+    /// that ship on multiple engines at once.
+    ///
+    /// The script data is only analyzed for the first target. It is then remapped to each other target with
+    /// [`CompiledScriptData::retarget`], so any warnings are the ones for the first target. If there are no targets or anything used
+    /// is not supported by any of them, the loaded tokens, files, and warnings are left as they were.
+    ///
+    /// If the compiler was made with [`Compiler::with_target`], the custom target's limits are only used for its base target.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the script data is invalid for the first target or uses anything not supported by another target.
+    pub fn compile_for_targets(&mut self, targets: &[CompileTarget]) -> Result<Vec<CompiledScriptData>, CompileError> {
+        let first_target = match targets.first() {
+            Some(&n) => n,
+            None => return Ok(Vec::new())
+        };
+
+        let original_target = self.target;
+        let custom_target = self.custom_target.take();
+        let tokens = self.tokens.clone();
+        let files = self.files.clone();
+        let warnings = self.warnings.clone();
+
+        self.target = first_target;
+        self.custom_target = custom_target.clone().filter(|t| t.get_base_target() == first_target);
+        let result = self.digest_tokens();
+        self.target = original_target;
+        self.custom_target = custom_target.clone();
+
+        let result = result.and_then(|first| {
+            let mut results = Vec::with_capacity(targets.len());
+            for &target in &targets[1..] {
+                let mut data = first.clone();
+                data.retarget_with_limits(target, custom_target.clone().filter(|t| t.get_base_target() == target))?;
+                results.push(data);
+            }
+            results.insert(0, first);
+            Ok(results)
+        });
+
+        if result.is_err() {
+            self.tokens = tokens;
+            self.files = files;
+            self.warnings = warnings;
+        }
+        result
+    }
+
+    /// Compile a single expression, such as `(ai_place "foo")`, that is expected to convert to `expected_type`.
+    ///
+    /// The expression does not need to be in a script or global, and only engine functions and globals can be referenced. Any loaded tokens are left untouched.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the expression is invalid or is not exactly one expression.
+    pub fn compile_expression(&mut self, expression: &str, expected_type: ValueType) -> Result<CompiledExpression, CompileError> {
+        self.digest_expression(expression, expected_type)
+    }
+
+    /// Compile a single console command, such as `(ai_place "foo")` or `ai_place "foo"`, for injecting into a running game.
+    ///
+    /// Like the in-game console, the outer parenthesis are optional, the command is evaluated in a void context, and console-only functions can be used without warning.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the command is invalid or is not exactly one command.
+    pub fn compile_console_command(&mut self, command: &str) -> Result<CompiledExpression, CompileError> {
+        self.console = true;
+        let result = self.digest_expression(command, ValueType::Void);
+        self.console = false;
+        result
+    }
 }
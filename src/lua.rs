@@ -0,0 +1,153 @@
+//! Experimental transpiler from HSC to Lua, for prototyping script logic where HSC cannot be run (such as SAPP or Chimera Lua).
+//!
+//! The generated chunk takes four tables as its arguments: `hsc`, `G`, `S`, and `T`. Engine functions are called through `hsc`,
+//! which the host must provide shims for (e.g. `hsc["unit_get_health"]`). Globals are stored in `G`, which can have a metatable to
+//! look up engine globals. Scripts are stored in `S` as functions, and their types are stored in `T`, so the host can run startup and
+//! continuous scripts. Scripts should be run in coroutines, as shims such as `sleep` are expected to yield.
+
+use super::*;
+
+/// Engine functions which act on the script engine itself, so they have no meaning outside of it.
+const UNSUPPORTED_FUNCTIONS: [&str; 4] = ["script_recompile", "script_doc", "debug_scripting", "debug_script_thread"];
+
+/// Engine functions which are translated to Lua operators.
+const OPERATORS: [(&str, &str); 10] = [
+    ("+", "+"), ("-", "-"), ("*", "*"), ("/", "/"),
+    ("=", "=="), ("!=", "~="), ("<", "<"), (">", ">"), ("<=", "<="), (">=", ">=")
+];
+
+/// Backend which transpiles scripts and globals to a Lua chunk, for use with [`Compiler::compile_with_backend`].
+///
+/// The output is the Lua source, or an error for each construct that cannot be translated.
+#[derive(Default)]
+pub struct LuaBackend {
+    code: String,
+    errors: Vec<CompileError>
+}
+
+impl LuaBackend {
+    /// Instantiate a new Lua backend.
+    pub fn new() -> LuaBackend {
+        LuaBackend::default()
+    }
+
+    fn translate(&mut self, node: TypedNode) -> String {
+        match node.get_type() {
+            NodeType::Primitive(PrimitiveType::Local) => match node.get_data() {
+                Some(NodeData::Long(n)) => format!("a{}", n + 1),
+                _ => "nil".to_owned()
+            },
+            NodeType::Primitive(PrimitiveType::Global) => format!("G[{}]", lua_string(node.get_string_data().unwrap())),
+            NodeType::Primitive(PrimitiveType::Static) => match (node.get_data(), node.get_string_data()) {
+                (_, Some(s)) => lua_string(s),
+                (Some(NodeData::Boolean(b)), None) => b.to_string(),
+                (Some(NodeData::Short(n)), None) => n.to_string(),
+                (Some(NodeData::Long(n)), None) => n.to_string(),
+                (Some(NodeData::Real(n)), None) => n.to_string(),
+                _ => "nil".to_owned()
+            },
+            NodeType::FunctionCall(false) => {
+                let parameters = self.translate_parameters(node);
+                format!("S[{}]({})", lua_string(node.get_string_data().unwrap()), parameters.join(", "))
+            },
+            NodeType::FunctionCall(true) => self.translate_engine_function(node)
+        }
+    }
+
+    fn translate_parameters(&mut self, node: TypedNode) -> Vec<String> {
+        node.get_parameters().map(|p| self.translate(p)).collect()
+    }
+
+    fn translate_engine_function(&mut self, node: TypedNode) -> String {
+        let name = node.get_string_data().unwrap();
+
+        if UNSUPPORTED_FUNCTIONS.contains(&name) {
+            self.errors.push(CompileError::from_message(node.get_file_name(), node.get_line(), node.get_column(), CompileErrorType::Error, message!("lua_unsupported_function", name))
+                .with_code(CompileErrorCode::UnsupportedByBackend));
+            return "nil".to_owned()
+        }
+
+        if let Some((_, operator)) = OPERATORS.iter().find(|(f, _)| *f == name) {
+            return format!("({})", self.translate_parameters(node).join(&format!(" {operator} ")))
+        }
+
+        // Anything that doesn't evaluate all of its parameters in order needs to be written out
+        let mut parameters = self.translate_parameters(node);
+        match name {
+            "begin" => match parameters.len() {
+                0 => "nil".to_owned(),
+                1 => parameters.pop().unwrap(),
+                _ => {
+                    let last = parameters.pop().unwrap();
+                    let statements: String = parameters.iter().map(|p| format!("local _ = {p} ")).collect();
+                    format!("(function() {statements}return {last} end)()")
+                }
+            },
+            "if" => match parameters.as_slice() {
+                [condition, then] => format!("(function() if {condition} then return {then} end end)()"),
+                [condition, then, otherwise] => format!("(function() if {condition} then return {then} else return {otherwise} end end)()"),
+                _ => unreachable!()
+            },
+            "and" | "or" => format!("({})", parameters.join(&format!(" {name} "))),
+            "not" => format!("(not {})", parameters[0]),
+            "set" => {
+                let global = &parameters[0];
+                format!("(function() {global} = {} return {global} end)()", parameters[1])
+            },
+            "begin_random" | "sleep_until" => {
+                // These evaluate their first parameter (or all of them for begin_random) later or repeatedly
+                let lazy_count = if name == "begin_random" { parameters.len() } else { 1 };
+                for p in &mut parameters[..lazy_count] {
+                    *p = format!("function() return {p} end");
+                }
+                format!("hsc[{}]({})", lua_string(name), parameters.join(", "))
+            },
+            _ => format!("hsc[{}]({})", lua_string(name), parameters.join(", "))
+        }
+    }
+}
+
+impl EmitBackend for LuaBackend {
+    type Output = Result<String, Vec<CompileError>>;
+
+    fn emit_script(&mut self, script: ScriptDefinition) -> Result<(), CompileError> {
+        let name = lua_string(script.get_name());
+        let parameters: Vec<String> = (1..=script.get_parameters().count()).map(|i| format!("a{i}")).collect();
+        let body = self.translate(script.get_node());
+        self.code += &format!("T[{name}] = {}\nS[{name}] = function({}) return {body} end\n", lua_string(script.get_type().as_str()), parameters.join(", "));
+        Ok(())
+    }
+
+    fn emit_global(&mut self, global: GlobalDefinition) -> Result<(), CompileError> {
+        let value = self.translate(global.get_node());
+        self.code += &format!("G[{}] = {value}\n", lua_string(global.get_name()));
+        Ok(())
+    }
+
+    fn finish(self) -> Self::Output {
+        if self.errors.is_empty() {
+            Ok(format!("-- Generated from HSC by Rat In a Tube\nlocal hsc, G, S, T = ...\n{}", self.code))
+        }
+        else {
+            Err(self.errors)
+        }
+    }
+}
+
+/// Quote the string as a Lua string literal.
+fn lua_string(string: &str) -> String {
+    let mut quoted = String::with_capacity(string.len() + 2);
+    quoted.push('"');
+    for c in string.chars() {
+        match c {
+            '"' => quoted += "\\\"",
+            '\\' => quoted += "\\\\",
+            '\n' => quoted += "\\n",
+            '\r' => quoted += "\\r",
+            c if c.is_ascii_control() => quoted += &format!("\\{:03}", c as u8),
+            c => quoted.push(c)
+        }
+    }
+    quoted.push('"');
+    quoted
+}
@@ -0,0 +1,294 @@
+use std::collections::BTreeMap;
+
+/// Make a [`Message`] from a key in [`DEFAULT_MESSAGES`] and its arguments, which can be anything that implements `Display`.
+macro_rules! message {
+    ($key: expr) => {
+        Message::new($key, Vec::new())
+    };
+    ($key: expr, $($argument: expr),+) => {
+        Message::new($key, vec![$($argument.to_string()),+])
+    };
+}
+
+/// English template of every diagnostic message, keyed by message key.
+///
+/// Arguments are written as `{0}`, `{1}`, and so on. Translated catalogs use the same keys and arguments, though arguments may be in
+/// any order and may be left out.
+pub const DEFAULT_MESSAGES: &[(&str, &str)] = &[
+    // Reading script data
+    ("decode_token_failed", "failed to decode token - {0}"),
+    ("decode_token_replaced", "{0} byte sequence(s) in token could not be decoded as {1} and were replaced with U+FFFD"),
+    ("token_lowercased", "'{0}' is compiled as '{1}'"),
+    ("decode_token_skipped", "{0} byte sequence(s) in token could not be decoded as {1} and were left out"),
+    ("encoding_disabled", "{0} support is disabled (note: enable the '{1}' feature of rat-in-a-tube to use it)"),
+    ("unexpected_null_terminator", "unexpected null terminator at byte offset {0} (note: a null terminator can only be the last byte)"),
+    ("unterminated_token", "unterminated token"),
+    ("unterminated_multiline_comment", "unterminated multiline comment (note: multiline comments end with '*;')"),
+    ("end_of_file_reached_here", "end of file reached here"),
+    ("unterminated_block", "unterminated block"),
+    ("next_block_starts_here", "next block starts here"),
+    ("expected_left_parenthesis", "expected left parenthesis, got {0} instead"),
+    ("empty_block", "empty block (note: use '(begin)' for a block that does nothing, or remove the '()')"),
+    ("block_nested_too_deeply", "block is nested too deeply (note: blocks can be nested at most {0} deep)"),
+    ("block_nested_deeply", "block is nested more than {0} deep (note: blocks are compiled recursively, so very deep nesting can overflow the stack)"),
+
+    // Definitions
+    ("incomplete_global", "incomplete global definition, expected (global <type> <name> <expression>)"),
+    ("extraneous_global_token", "extraneous token in global definition (note: globals do not have implicit begin blocks)"),
+    ("global_name_is_block", "expected global name, got a block instead"),
+    ("invalid_global_type", "cannot define '{0}' globals"),
+    ("global_name_is_literal", "global name '{0}' looks like a literal, so it would be parsed as one instead of referring to the global"),
+    ("script_name_is_literal", "script name '{0}' looks like a literal, so it would be parsed as one instead of referring to the script"),
+    ("parameter_name_is_literal", "parameter name '{0}' looks like a literal, so it would be parsed as one instead of referring to the parameter"),
+    ("expected_global_type", "expected global value type, got '{0}' instead"),
+    ("incomplete_script_type", "incomplete script definition, expected script type after 'script'"),
+    ("expected_script_type", "expected script type, got '{0}' instead"),
+    ("incomplete_script", "incomplete script definition, expected (script {0} <name> <expression(s)>)"),
+    ("incomplete_script_with_return_type", "incomplete script definition, expected (script {0} <return type> <name> <expression(s)>)"),
+    ("void_script_return_type", "{0} scripts always return void and cannot have a return type (remove '{1}'; expected (script {0} <name> <expression(s)>))"),
+    ("script_parameters_unsupported", "function parameters are not supported in {0}"),
+    ("script_parameters_static_only", "script parameters can only be used in static or stub functions"),
+    ("script_name_empty_block", "expected script name, got an empty block instead"),
+    ("script_name_is_block", "expected script name, got a block instead (note: function parameters are not supported prior to Halo 3)"),
+    ("too_many_script_parameters", "only {0} script parameter(s) are supported in {1}"),
+    ("expected_script_parameter", "expected script parameter"),
+    ("malformed_script_parameter", "script parameters should be in (<type> <name>) format"),
+    ("expected_parameter_type", "expected parameter type, got {0}"),
+    ("function_cannot_be_overridden", "function '{0}' cannot be overridden by a script"),
+    ("invalid_script_type", "cannot define '{0}' scripts"),
+    ("expected_return_type", "expected script return value type, got '{0}' instead"),
+    ("expected_global_or_script", "expected 'global' or 'script', got '{0}' instead"),
+    ("wrong_final_expression_type", "script '{0}' declared to return '{1}' but its final expression is '{2}'"),
+    ("return_type_declared_here", "return type declared here"),
+    ("script_name_too_long", "script name '{0}' exceeds {1} characters in length"),
+    ("global_name_too_long", "global name '{0}' exceeds {1} characters in length"),
+    ("stub_replaced_with_non_static", "cannot replace stub script '{0}' with non-static script"),
+    ("stub_return_type_mismatch", "cannot replace stub script '{0}' that returns '{1}' with static script which returns '{2}'"),
+    ("duplicate_script", "multiple scripts '{0}' defined"),
+    ("external_script_redefined", "script '{0}' is already defined in the scenario (note: it was declared as an external script)"),
+    ("duplicate_global", "multiple globals '{0}' defined"),
+    ("external_global_redefined", "global '{0}' is already defined in the scenario (note: it was declared as an external global)"),
+    ("too_many_scripts", "maximum script limit of {0} exceeded ({1} / {0})"),
+    ("too_many_nodes", "maximum script node limit of {0} exceeded ({1} / {0})"),
+    ("string_data_too_long", "maximum script string data length of {0} bytes exceeded ({1} / {0})"),
+    ("syntax_data_node_base", "script data with a node index base of {0} can't be written as syntax data on its own, as the other nodes are needed"),
+    ("syntax_data_invalid_header", "script node table header is invalid"),
+    ("syntax_data_truncated", "script node table is truncated (expected at least {0} bytes, got {1})"),
+    ("syntax_data_invalid_value_type", "script node {0} has an invalid value type ({1})"),
+    ("syntax_data_invalid_node_reference", "script node {0} refers to node {1}, but there are only {2} nodes"),
+    ("syntax_data_invalid_script_node", "script '{0}' refers to node {1}, but there are only {2} nodes"),
+    ("syntax_data_invalid_global_node", "global '{0}' refers to node {1}, but there are only {2} nodes"),
+    ("syntax_data_invalid_string_offset", "script node {0} has a string offset of {1}, which is past the end of the string data"),
+    ("syntax_data_invalid_string", "script node {0} has string data that could not be decoded: {1}"),
+    ("syntax_data_invalid_script_name", "script '{0}' has a name containing a null character"),
+    ("syntax_data_invalid_parameter_name", "parameter {0} of script '{1}' has a name containing a null character"),
+    ("syntax_data_invalid_global_name", "global '{0}' has a name containing a null character"),
+
+    // Expressions
+    ("empty_block_compiled_as_begin", "empty block compiled as '(begin)', which does nothing"),
+    ("global_cannot_convert", "global '{0}' is '{1}' which cannot convert to '{2}'"),
+    ("global_cannot_convert_without_cast", "global '{0}' is '{1}' which cannot convert to '{2}' (note: use '({3} <{1}>)' to cast it)"),
+    ("global_loses_precision", "global '{0}' is '{1}' which may lose precision when converted to '{2}' (note: this is only an error in pedantic mode)"),
+    ("cond_requires_expressions", "cond requires at least one set of expressions"),
+    ("cond_too_many_conditions", "cond has too many conditions (note: each condition is nested in the previous one, so cond can have at most {0} conditions)"),
+    ("cond_many_conditions", "cond has more than {0} conditions (note: each condition is nested in the previous one, so very long conds can overflow the stack)"),
+    ("cond_malformed_parameter", "cond requires each parameter to be (<condition> <expression(s)>)"),
+    ("empty_begin_without_value", "empty 'begin' block does not return a value, but '{0}' is expected"),
+    ("function_not_defined", "function '{0}' is not defined"),
+    ("console_function", "function '{0}' is meant to be used from the console and may not work in scripts"),
+    ("too_few_parameters", "function '{0}' takes at least {1} parameter(s), got {2} instead"),
+    ("too_many_parameters", "function '{0}' takes at most {1} parameter(s) but extraneous parameter(s) were given"),
+    ("set_variable_is_block", "function 'set' cannot take a block as the variable name"),
+    ("set_requires_variable", "function 'set' requires a name of a variable"),
+    ("not_a_global", "parameter '{0}' is not a global variable name"),
+    ("not_a_global_did_you_mean", "parameter '{0}' is not a global variable name; did you mean '{1}'?"),
+    ("set_target_is_function", "function 'set' cannot assign to '{0}', which is a function and not a variable"),
+    ("set_target_is_literal", "function 'set' cannot assign to '{0}', which is a literal value and not a variable"),
+    ("passthrough_not_numeric", "passthrough parameters resolve to '{0}', but function '{1}' takes only numeric parameters"),
+    ("passthrough_inequality", "passthrough parameters resolve to '{0}', but function '{1}' is an inequality operator"),
+    ("cannot_parse_token_named_like_function", "cannot parse token '{0}' as {1} and no global of this name defined; did you mean to call '({0})' as a function?"),
+    ("cannot_parse_token", "cannot parse token '{0}' as {1} and no global of this name defined (expected {2})"),
+    ("short_out_of_range", "integer {0} is out of range for short (expected integer between [-32768,32767]); use a {1} instead of short"),
+    ("long_out_of_range", "integer {0} is out of range for long (expected integer between [-2147483648,2147483647]); use a real instead of long (note: reals cannot represent every integer in this range exactly)"),
+    ("real_out_of_range", "real {0} is out of range for a 32-bit real and will become {1}"),
+    ("real_rounded", "real {0} cannot be represented exactly as a 32-bit real and will be rounded to {1}"),
+    ("unknown_name", "'{0}' is not a known {1}"),
+    ("unknown_name_did_you_mean", "'{0}' is not a known {1}; did you mean '{2}'?"),
+    ("function_used_as_script", "no script '{0}' defined (a function is defined by this name, but it cannot be used here)"),
+    ("function_cannot_convert", "function '{0}' returns '{1}' which cannot convert to '{2}'"),
+    ("function_cannot_convert_without_cast", "function '{0}' returns '{1}' which cannot convert to '{2}' (note: use '({3} <{1}>)' to cast it)"),
+    ("function_loses_precision", "function '{0}' returns '{1}' which may lose precision when converted to '{2}' (note: this is only an error in pedantic mode)"),
+    ("time_unit_requires_number", "'{0}' requires one number, such as '({0} 2.5)'"),
+    ("uninitialized_global", "use of uninitialized global '{0}'"),
+    ("expected_expression", "expected an expression"),
+    ("expected_one_expression", "expected only one expression"),
+    ("too_many_warnings", "too many warnings; stopped after {0} (note: the rest of the script data was not checked)"),
+    ("too_many_errors", "too many errors; stopped after {0} (note: the rest of the script data was not checked)"),
+
+    // Lints and budgets
+    ("missing_return_value", "script '{0}' returns '{1}', but this 'if' has no else branch, so no value is returned when its condition is false"),
+    ("parameter_shadows_global", "parameter '{0}' of script '{1}' has the same name as a global, so '{0}' refers to the parameter in this script (note: parameters take precedence over globals)"),
+    ("unused_parameter", "parameter '{0}' of script '{1}' is never referenced, so the script may not be called the way it is defined"),
+    ("global_defined_here", "global '{0}' is defined here"),
+    ("parameter_referenced_here", "'{0}' is referenced here"),
+    ("global_never_read", "global '{0}' is set but never read, so it may be unneeded"),
+    ("global_set_here", "'{0}' is set here"),
+    ("possibly_unset_global", "global '{0}' may be read by '{1}' before startup script '{2}' sets it"),
+    ("script_called_here", "'{0}' is called here"),
+    ("global_never_set", "global '{0}' is never set after it is initialized, so it could be replaced with its value"),
+    ("tick_cost", "continuous script '{0}' has an estimated cost of {1} per tick, which exceeds the threshold of {2}"),
+    ("expensive_call", "'{0}' is expensive to call every tick"),
+    ("client_only_call", "'{0}' does nothing when scripts are run by a dedicated server"),
+    ("suspicious_sleep", "sleeping for {0} tick(s) ({1} seconds) is probably a mistake (note: '(sleep -1)' sleeps until the script is woken)"),
+    ("suspicious_sleep_until_period", "checking the condition of 'sleep_until' every {0} tick(s) is probably a mistake, as the period should be positive"),
+    ("suspicious_sleep_until_timeout", "a 'sleep_until' timeout of {0} tick(s) is probably a mistake, as the condition may never be checked"),
+    ("sleep_until_period_exceeds_timeout", "'sleep_until' checks its condition every {0} tick(s), which is longer than its timeout of {1} tick(s)"),
+    ("script_count", "{0} {1} scripts are defined, but no more than {2} are recommended for {3}: {4}"),
+    ("redundant_boolean_comparison", "comparison to boolean literal '{0}' with '{1}' is redundant; use '{2}' instead"),
+    ("negated_if_condition", "condition of 'if' is negated; consider removing 'not' and swapping the branches"),
+    ("double_negation", "double negation; use the inner expression directly"),
+    ("node_budget", "script nodes usage is at {0}% of the maximum ({1} / {2}); the largest users are {3}"),
+    ("string_data_budget", "script string data usage is at {0}% of the maximum ({1} / {2}); the largest users are {3}"),
+
+    // Compiled script data
+    ("unresolved_script", "script '{0}' could not be resolved"),
+    ("unresolved_global", "global '{0}' could not be resolved"),
+    ("also_referenced_here", "'{0}' is also referenced here"),
+    ("target_script_parameters_unsupported", "script '{0}' uses function parameters, which are not supported in {1}"),
+    ("target_too_many_script_parameters", "script '{0}' has {1} parameters, but only {2} script parameter(s) are supported in {3}"),
+    ("target_function_unavailable", "function '{0}' is not available in {1}"),
+    ("target_global_unavailable", "global '{0}' is not available in {1}"),
+
+    // Everything else
+    ("lua_unsupported_function", "function '{0}' cannot be translated to Lua (note: it acts on the script engine itself)"),
+    ("decode_script_data_failed", "failed to decode script data - {0}"),
+    ("internal_error", "internal compiler error: {0}"),
+    ("command_not_utf8", "command is not valid UTF-8")
+];
+
+/// Key and arguments of a diagnostic message.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub(crate) struct Message {
+    pub key: &'static str,
+    pub arguments: Vec<String>
+}
+
+impl Message {
+    pub fn new(key: &'static str, arguments: Vec<String>) -> Message {
+        debug_assert!(DEFAULT_MESSAGES.iter().any(|m| m.0 == key), "no default message for key '{}'", key);
+        Message { key, arguments }
+    }
+
+    /// Format the message with its English template.
+    pub fn to_default_string(&self) -> String {
+        match DEFAULT_MESSAGES.iter().find(|m| m.0 == self.key) {
+            Some((_, template)) => format_template(template, &self.arguments),
+            None => self.key.to_owned()
+        }
+    }
+}
+
+// The key is one of the keys of the default messages, so it is looked up when deserializing
+#[cfg(feature = "serde")]
+impl<'de> ::serde::Deserialize<'de> for Message {
+    fn deserialize<D: ::serde::Deserializer<'de>>(deserializer: D) -> Result<Message, D::Error> {
+        #[derive(Deserialize)]
+        struct OwnedMessage {
+            key: String,
+            arguments: Vec<String>
+        }
+
+        let message = OwnedMessage::deserialize(deserializer)?;
+        match DEFAULT_MESSAGES.iter().find(|m| m.0 == message.key) {
+            Some((key, _)) => Ok(Message { key, arguments: message.arguments }),
+            None => Err(::serde::de::Error::custom(format!("unknown message key '{}'", message.key)))
+        }
+    }
+}
+
+/// Translated templates of diagnostic messages, used with [`CompileError::get_localized_message`](crate::CompileError::get_localized_message).
+///
+/// Any message without a translated template falls back to its English template from [`DEFAULT_MESSAGES`].
+#[derive(Clone, Default, Debug)]
+pub struct MessageCatalog {
+    templates: BTreeMap<String, String>
+}
+
+impl MessageCatalog {
+    /// Instantiate an empty catalog, where every message is in English.
+    pub fn new() -> MessageCatalog {
+        MessageCatalog::default()
+    }
+
+    /// Parse a catalog with one `<key> = <template>` line per message.
+    ///
+    /// Blank lines and lines starting with `#` are ignored. Whitespace around the key and the template is trimmed.
+    ///
+    /// # Errors
+    ///
+    /// Errors if a line is not a key and template, or if the key is not in [`DEFAULT_MESSAGES`].
+    pub fn parse(catalog: &str) -> Result<MessageCatalog, String> {
+        let mut result = MessageCatalog::new();
+        for (i, line) in catalog.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue
+            }
+
+            let (key, template) = match line.split_once('=') {
+                Some(n) => n,
+                None => return Err(format!("line {} is not in '<key> = <template>' format", i + 1))
+            };
+            let key = key.trim();
+            if !DEFAULT_MESSAGES.iter().any(|m| m.0 == key) {
+                return Err(format!("line {} has unknown message key '{key}'", i + 1))
+            }
+            result.set_template(key, template.trim());
+        }
+        Ok(result)
+    }
+
+    /// Set the template of a message, replacing any that was already set.
+    pub fn set_template(&mut self, key: &str, template: &str) {
+        self.templates.insert(key.to_owned(), template.to_owned());
+    }
+
+    /// Get the template of a message, or its English template if none was set, or `None` if the key does not exist.
+    pub fn get_template(&self, key: &str) -> Option<&str> {
+        self.templates.get(key).map(|t| t.as_str()).or_else(|| DEFAULT_MESSAGES.iter().find(|m| m.0 == key).map(|m| m.1))
+    }
+
+    /// Format a message with the given arguments, or return the key if it does not exist.
+    pub fn format(&self, key: &str, arguments: &[String]) -> String {
+        match self.get_template(key) {
+            Some(n) => format_template(n, arguments),
+            None => key.to_owned()
+        }
+    }
+}
+
+/// Replace each `{n}` in the template with the nth argument. Anything else, including `{n}` past the last argument, is kept as is.
+fn format_template(template: &str, arguments: &[String]) -> String {
+    let mut formatted = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        formatted += &rest[..start];
+        rest = &rest[start..];
+
+        let argument = rest[1..].find('}')
+            .and_then(|end| Some((end, arguments.get(rest[1..end + 1].parse::<usize>().ok()?)?)));
+        match argument {
+            Some((end, argument)) => {
+                formatted += argument;
+                rest = &rest[end + 2..];
+            },
+            None => {
+                formatted.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+    formatted += rest;
+    formatted
+}
@@ -1,8 +1,12 @@
 use super::*;
 
-const HELLO_WORLD_HSC : &'static [u8] = include_bytes!("script/hello_world.hsc");
+#[cfg(feature = "serde")]
+extern crate serde_json;
+
+const HELLO_WORLD_HSC : &[u8] = include_bytes!("script/hello_world.hsc");
 
 #[test]
+#[cfg(feature = "windows-1252")]
 fn test_tokenizer_hello_world() {
     let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
     compiler.read_script_data("hello_world.hsc", HELLO_WORLD_HSC).unwrap();
@@ -13,6 +17,7 @@ fn test_tokenizer_hello_world() {
 }
 
 #[test]
+#[cfg(feature = "windows-1252")]
 fn test_compiler_hello_world() {
     let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
     compiler.read_script_data("hello_world.hsc", HELLO_WORLD_HSC).unwrap();
@@ -22,19 +27,21 @@ fn test_compiler_hello_world() {
 }
 
 #[test]
+#[cfg(feature = "windows-1252")]
 fn test_compatibility() {
     let test_compatibility_gbx_only_hsc = include_bytes!("script/test_compatibility_gbx_only.hsc");
 
     let mut compiler_cea = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
     compiler_cea.read_script_data("test_compatibility_gbx_only_hsc.hsc", test_compatibility_gbx_only_hsc).unwrap();
-    assert!(matches!(compiler_cea.compile_script_data(), Err(_))); // this needs to fail
+    assert!(compiler_cea.compile_script_data().is_err()); // this needs to fail
 
     let mut compiler_gbx = Compiler::new(CompileTarget::HaloCustomEdition, CompileEncoding::Windows1252);
     compiler_gbx.read_script_data("test_compatibility_gbx_only_hsc.hsc", test_compatibility_gbx_only_hsc).unwrap();
-    assert!(matches!(compiler_gbx.compile_script_data(), Ok(_))); // this needs to pass
+    assert!(compiler_gbx.compile_script_data().is_ok()); // this needs to pass
 }
 
 #[test]
+#[cfg(feature = "windows-1252")]
 fn test_compiler_script_parameters() {
     let test_script_parameters_hsc = include_bytes!("script/test_script_parameters.hsc");
 
@@ -98,6 +105,7 @@ fn test_compiler_script_parameters() {
 }
 
 #[test]
+#[cfg(feature = "windows-1252")]
 fn test_number_passthrough() {
     let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
     compiler.read_script_data("test_number_passthrough.hsc", include_bytes!("script/number_passthrough.hsc")).unwrap();
@@ -197,3 +205,2421 @@ fn test_number_passthrough() {
     // That's everything
     assert_eq!(None, eleven_is_greater_than_zero_2nd_parameter.get_next_node_index());
 }
+
+#[test]
+#[cfg(feature = "windows-1252")]
+fn test_numeric_literal_range() {
+    let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
+    compiler.read_script_data("test_numeric_literal_range.hsc", b"(global short too_big 40000)").unwrap();
+    let error = compiler.compile_script_data().err().unwrap();
+    assert!(error.get_message().contains("integer 40000 is out of range for short"));
+    assert!(error.get_message().contains("use a long instead of short"));
+    assert_eq!((1, 23), error.get_position());
+
+    let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
+    compiler.read_script_data("test_numeric_literal_range.hsc", b"(global long too_big 4000000000)").unwrap();
+    assert!(compiler.compile_script_data().err().unwrap().get_message().contains("use a real instead of long"));
+
+    // Reals that are exactly representable are fine, but any other real produces a warning with the value that is stored
+    let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
+    compiler.read_script_data("test_numeric_literal_range.hsc", b"(global real fine 0.5)(global real exact 0.100000001490116119384765625)(global real precise 0.1)").unwrap();
+    let result = compiler.compile_script_data().unwrap();
+    let warnings = result.get_warnings();
+    assert_eq!(1, warnings.len());
+    assert_eq!("real 0.1 cannot be represented exactly as a 32-bit real and will be rounded to 0.10000000149011612", warnings[0].get_message());
+}
+
+#[test]
+#[cfg(feature = "windows-1252")]
+fn test_error_codes_and_end_positions() {
+    let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
+    compiler.read_script_data("test_error_codes.hsc", b"(global real precise 3.14159265358979)").unwrap();
+    let result = compiler.compile_script_data().unwrap();
+    let warning = &result.get_warnings()[0];
+    assert_eq!(CompileErrorCode::RealPrecision, warning.get_code());
+    assert_eq!(((1, 22), (1, 38)), (warning.get_position(), warning.get_end_position()));
+
+    // Quoted tokens include the quotes
+    let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
+    compiler.read_script_data("test_error_codes.hsc", b"(global short x \"abc\")").unwrap();
+    let error = compiler.compile_script_data().err().unwrap();
+    assert_eq!(CompileErrorCode::Generic, error.get_code());
+    assert_eq!(((1, 17), (1, 22)), (error.get_position(), error.get_end_position()));
+}
+
+#[test]
+fn test_maximum_block_depth() {
+    // Deeply nested blocks need more stack than the test thread has by default in debug builds
+    std::thread::Builder::new().stack_size(64 * 1024 * 1024).spawn(|| {
+        let nested = |depth: usize| format!("(global boolean x {}true{})", "(not ".repeat(depth), ")".repeat(depth));
+        let deep_nesting_warnings = |script: &str| {
+            let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::UTF8);
+            compiler.read_script_data("test_block_depth.hsc", script.as_bytes()).unwrap();
+            compiler.compile_script_data().unwrap().get_warnings().iter().filter(|w| w.get_code() == CompileErrorCode::DeepNesting).map(|w| w.get_message().to_owned()).collect::<Vec<String>>()
+        };
+
+        // The global's block counts towards the depth
+        assert!(deep_nesting_warnings(&nested(63)).is_empty());
+        let warnings = deep_nesting_warnings(&nested(64));
+        assert_eq!(1, warnings.len());
+        assert!(warnings[0].starts_with("block is nested more than 64 deep"));
+
+        // Each condition of a cond is nested in the previous one
+        let conditions = format!("(global short x (cond {}))", "(true 1)".repeat(65));
+        let warnings = deep_nesting_warnings(&conditions);
+        assert_eq!(1, warnings.len());
+        assert!(warnings[0].starts_with("cond has more than 64 conditions"));
+
+        // Only the fuzzing entry points make these errors (0x80 picks UTF-8 so this works without the windows-1252 feature)
+        let fuzz_input = |script: &str| [&[0x80], script.as_bytes()].concat();
+        let error = fuzz::compile_script_bytes(&fuzz_input(&nested(64))).err().unwrap();
+        assert!(error.get_message().starts_with("block is nested too deeply"));
+        let error = fuzz::compile_script_bytes(&fuzz_input(&conditions)).err().unwrap();
+        assert!(error.get_message().starts_with("cond has too many conditions"));
+    }).unwrap().join().unwrap();
+}
+
+#[test]
+#[cfg(feature = "windows-1252")]
+fn test_fuzz_entry_points() {
+    assert!(fuzz::compile_script_bytes(b"\x04(script startup hi (print \"hi\"))").is_ok());
+    assert!(fuzz::compile_console_command_bytes(b"\x00print \"hi\"").is_ok());
+
+    // Malformed input is an error, but never an internal one
+    for input in [&b"\x00)("[..], b"\xFF(script", b"\x00(global short x (cond))", b"\x00\xFF\xFE"] {
+        let error = fuzz::compile_script_bytes(input).err().unwrap();
+        assert!(!fuzz::is_internal_error(&error), "{}", error);
+        let error = fuzz::compile_console_command_bytes(input).err().unwrap();
+        assert!(!fuzz::is_internal_error(&error), "{}", error);
+    }
+}
+
+#[test]
+#[cfg(feature = "windows-1252")]
+fn test_boolean_simplification_warnings() {
+    let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
+    compiler.read_script_data("test_boolean_simplification.hsc", include_bytes!("script/boolean_simplification.hsc")).unwrap();
+    let result = compiler.compile_script_data().unwrap();
+    let warnings = result.get_warnings();
+    assert_eq!(4, warnings.len());
+    assert!(warnings[0].get_message().contains("use 'flag' instead"));
+    assert!(warnings[1].get_message().contains("use '(not flag)' instead"));
+    assert!(warnings[2].get_message().contains("swapping the branches"));
+    assert!(warnings[3].get_message().contains("double negation"));
+}
+
+#[test]
+#[cfg(feature = "windows-1252")]
+fn test_return_paths() {
+    let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
+    compiler.read_script_data("return_paths.hsc", include_bytes!("script/return_paths.hsc")).unwrap();
+    let result = compiler.compile_script_data().unwrap();
+    let warnings: Vec<&CompileError> = result.get_warnings().iter().filter(|w| w.get_message().contains("no else branch")).collect();
+    assert_eq!(2, warnings.len());
+    assert_eq!((9, 30), warnings[0].get_position());
+    assert_eq!((10, 60), warnings[1].get_position());
+}
+
+#[test]
+#[cfg(feature = "windows-1252")]
+fn test_function_return_type_conversion() {
+    let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
+    compiler.read_script_data("test_function_return_type_conversion.hsc", b"(global short count (players))").unwrap();
+    let error = compiler.compile_script_data().err().unwrap();
+    assert!(error.get_message().starts_with("function 'players' returns 'object list' which cannot convert to 'short'"));
+
+    // Passthrough functions take the type they are converted to
+    let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
+    compiler.read_script_data("test_function_return_type_conversion.hsc", b"(global short count (+ 1 2))").unwrap();
+    compiler.compile_script_data().unwrap();
+}
+
+#[test]
+#[cfg(feature = "windows-1252")]
+fn test_compile_expression() {
+    let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
+
+    // Function calls
+    let expression = compiler.compile_expression("(+ 1 (unit_get_health (unit (list_get (players) 0))))", ValueType::Real).unwrap();
+    let nodes = expression.get_nodes();
+    assert_eq!(ValueType::Real, expression.get_value_type());
+    assert_eq!(NodeType::FunctionCall(true), nodes[0].get_type());
+    let function_name_node = match nodes[0].get_data() {
+        Some(NodeData::NodeOffset(offset)) => &nodes[offset],
+        _ => panic!()
+    };
+    assert_eq!("+", function_name_node.get_string_data().unwrap());
+    let one_node = &nodes[function_name_node.get_next_node_index().unwrap()];
+    assert_eq!(Some(NodeData::Real(1.0)), one_node.get_data());
+
+    // Lone literals
+    let expression = compiler.compile_expression("covenant", ValueType::Team).unwrap();
+    assert_eq!(1, expression.get_nodes().len());
+    assert_eq!(Some(NodeData::Short(3)), expression.get_nodes()[0].get_data());
+
+    // Only one expression is allowed, and it has to convert to the expected type
+    assert!(compiler.compile_expression("(print \"a\") (print \"b\")", ValueType::Void).is_err());
+    let error = compiler.compile_expression("(players)", ValueType::Short).err().unwrap();
+    assert_eq!("<expression>", error.get_file());
+
+    // Loaded scripts are left alone
+    compiler.read_script_data("hello_world.hsc", HELLO_WORLD_HSC).unwrap();
+    compiler.compile_expression("(print \"test\")", ValueType::Void).unwrap();
+    assert_eq!(1, compiler.compile_script_data().unwrap().get_scripts().len());
+}
+
+#[test]
+fn test_compile_console_command() {
+    let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
+
+    // Parenthesis are optional in the console
+    let with_parenthesis = compiler.compile_console_command("(print \"hi\")").unwrap();
+    let without_parenthesis = compiler.compile_console_command("print \"hi\"").unwrap();
+    assert_eq!(with_parenthesis.get_nodes().len(), without_parenthesis.get_nodes().len());
+    assert_eq!(ValueType::Void, without_parenthesis.get_value_type());
+    assert_eq!(6, compiler.compile_console_command("sleep (+ 1 2)").unwrap().get_nodes().len());
+    assert!(compiler.compile_console_command("print \"a\") (print \"b\"").is_err());
+
+    // Console-only functions are fine in the console, but not in scripts
+    assert!(compiler.compile_console_command("cls").unwrap().get_warnings().is_empty());
+    assert_eq!(1, compiler.compile_expression("(cls)", ValueType::Void).unwrap().get_warnings().len());
+}
+
+#[test]
+#[cfg(feature = "windows-1252")]
+fn test_evaluation_order() {
+    let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
+    compiler.read_script_data("test_script_parameters.hsc", include_bytes!("script/test_script_parameters.hsc")).unwrap();
+    let script_data = compiler.compile_script_data().unwrap();
+    let nodes = script_data.get_nodes();
+
+    // (/ (+ a b) two) is evaluated as /, +, a, b, two
+    let order = script_data.get_evaluation_order(0).unwrap();
+    let names: Vec<&str> = order.iter().map(|n| {
+        let node = &nodes[*n];
+        match node.get_data() {
+            Some(NodeData::NodeOffset(name)) if node.get_type().is_function_call() => nodes[name].get_string_data().unwrap(),
+            _ => node.get_string_data().unwrap()
+        }
+    }).collect();
+    assert_eq!(vec!["/", "+", "a", "b", "two"], names);
+    assert!(script_data.get_evaluation_order(1).is_none());
+}
+
+#[test]
+#[cfg(feature = "windows-1252")]
+fn test_valid_breakpoint_positions() {
+    let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
+    compiler.read_script_data("test_script_parameters.hsc", include_bytes!("script/test_script_parameters.hsc")).unwrap();
+    let script_data = compiler.compile_script_data().unwrap();
+
+    // (global real two 2) and (/ (+ a b) two)
+    assert_eq!(vec![(7, 18), (10, 5), (10, 8), (10, 11), (10, 13), (10, 16)], script_data.valid_breakpoint_positions(0));
+    assert!(script_data.valid_breakpoint_positions(1).is_empty());
+}
+
+#[test]
+#[cfg(feature = "windows-1252")]
+fn test_statistics_and_source_map() {
+    let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
+    compiler.read_script_data("test_script_parameters.hsc", include_bytes!("script/test_script_parameters.hsc")).unwrap();
+    let script_data = compiler.compile_script_data().unwrap();
+
+    let statistics = script_data.get_statistics();
+    assert_eq!((1, 1, script_data.get_nodes().len()), (statistics.script_count, statistics.global_count, statistics.node_count));
+    assert_eq!(CompileTarget::HaloCEA.maximum_nodes(), statistics.maximum_node_count);
+
+    // The script's nodes come first, followed by the global's
+    let script_entry = script_data.get_source_map_entry(script_data.get_scripts()[0].get_first_node_index()).unwrap();
+    assert_eq!((0, 10, 5, SymbolType::Script, 0), (script_entry.file, script_entry.line, script_entry.column, script_entry.owner_type, script_entry.owner_index));
+
+    let global_entry = script_data.get_source_map_entry(script_data.get_nodes().len() - 1).unwrap();
+    assert_eq!((7, 18, SymbolType::Global, 0), (global_entry.line, global_entry.column, global_entry.owner_type, global_entry.owner_index));
+
+    assert!(script_data.get_source_map_entry(script_data.get_nodes().len()).is_none());
+}
+
+#[test]
+#[cfg(feature = "windows-1252")]
+fn test_optimize_begin() {
+    let compile = |optimize_begin: bool| {
+        let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
+        compiler.set_optimize_begin(optimize_begin);
+        compiler.read_script_data("test_optimize_begin.hsc", b"(global short x (begin 1))(script static short get_x (begin (begin x)))").unwrap();
+        compiler.compile_script_data().unwrap()
+    };
+
+    let optimized = compile(true);
+    let unoptimized = compile(false);
+    let saved = optimized.get_statistics().optimized_node_count;
+    assert!(saved > 0);
+    assert_eq!(unoptimized.get_nodes().len(), optimized.get_nodes().len() + saved);
+    assert_eq!(0, unoptimized.get_statistics().optimized_node_count);
+}
+
+#[test]
+#[cfg(feature = "windows-1252")]
+fn test_source_order_output() {
+    let mut compiler = Compiler::new(CompileTarget::HaloCustomEdition, CompileEncoding::Windows1252);
+    compiler.set_output_mode(OutputMode::SourceOrder);
+    compiler.read_script_data("source_order.hsc", include_bytes!("script/source_order.hsc")).unwrap();
+    let script_data = compiler.compile_script_data().unwrap();
+
+    // Scripts and globals are emitted in source order, and the script's begin is kept. The golden output was made by this compiler,
+    // not the original tools, so this only catches changes to the layout.
+    let output: Vec<String> = script_data.get_nodes().iter().enumerate().map(|(i, n)| {
+        format!("{} {} {:?} {:?} {:?} {:?}", i, n.get_value_type().as_str(), n.get_data(), n.get_index(), n.get_string_data(), n.get_next_node_index())
+    }).collect();
+    assert_eq!(include_str!("script/source_order.golden").lines().collect::<Vec<&str>>(), output);
+
+    assert_eq!(0, script_data.get_globals()[0].get_first_node_index());
+    assert_eq!(0, script_data.get_statistics().optimized_node_count);
+}
+
+#[test]
+#[cfg(feature = "windows-1252")]
+fn test_index_bases_and_external_symbols() {
+    let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
+    compiler.set_index_bases(3, 5, 100);
+    compiler.declare_external_script("existing_script", Some(1), ValueType::Short, &[ValueType::Short]);
+    compiler.declare_external_global("existing_global", Some(2), ValueType::Short);
+    compiler.read_script_data("test_index_bases.hsc", b"(global short new_global (existing_script existing_global))(script static short new_script new_global)(script startup main (new_script))").unwrap();
+    let script_data = compiler.compile_script_data().unwrap();
+
+    // External scripts and globals are referenced by their declared indices, and new ones start at the bases
+    let string_data = |s: &str| script_data.get_nodes().iter().find(|n| n.get_string_data() == Some(s)).unwrap();
+    assert_eq!(Some(1), string_data("existing_script").get_index());
+    assert_eq!(Some(NodeData::Long(2)), string_data("existing_global").get_data());
+    assert_eq!(Some(NodeData::Long(5)), string_data("new_global").get_data());
+    assert_eq!(Some(3), string_data("new_script").get_index());
+
+    // Node indices are offset by the node base
+    assert_eq!(100, script_data.get_node_base());
+    let first_node = script_data.get_scripts()[0].get_first_node_index();
+    assert!(first_node >= 100);
+    assert!(script_data.get_node(first_node).is_some());
+    assert!(script_data.get_node(99).is_none());
+    assert_eq!(vec![script_data.get_scripts()[1].get_first_node_index()], script_data.get_evaluation_order(1).unwrap());
+
+    // External symbols cannot be redefined
+    let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
+    compiler.declare_external_global("existing_global", Some(2), ValueType::Short);
+    compiler.read_script_data("test_index_bases.hsc", b"(global short existing_global 1)").unwrap();
+    assert!(compiler.compile_script_data().is_err());
+}
+
+#[test]
+#[cfg(feature = "windows-1252")]
+fn test_external_relocations() {
+    let mut library_compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
+    library_compiler.read_script_data("library.hsc", b"(global short library_global 4)(script static short library_script (+ library_global 1))").unwrap();
+    let library = library_compiler.compile_script_data().unwrap();
+
+    let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
+    compiler.set_index_bases(1, 1, library.get_nodes().len());
+    compiler.declare_external_script("library_script", None, ValueType::Short, &[]);
+    compiler.declare_external_global("library_global", None, ValueType::Short);
+    compiler.read_script_data("main.hsc", b"(script static short main (+ (library_script) library_global))").unwrap();
+    let mut script_data = compiler.compile_script_data().unwrap();
+
+    // The script call, its function name, and the global reference are unresolved
+    let relocations: Vec<(SymbolType, &str)> = script_data.get_relocations().iter().map(|r| (r.get_type(), r.get_name())).collect();
+    assert_eq!(vec![(SymbolType::Script, "library_script"), (SymbolType::Script, "library_script"), (SymbolType::Global, "library_global")], relocations);
+    let global_node = script_data.get_relocations()[2].get_node_index();
+    assert_eq!(None, script_data.get_node(global_node).unwrap().get_data());
+
+    assert!(script_data.link(|symbol_type, name| library.find_symbol_index(symbol_type, name)).is_ok());
+    assert!(script_data.get_relocations().is_empty());
+    assert_eq!(Some(NodeData::Long(0)), script_data.get_node(global_node).unwrap().get_data());
+    assert!(script_data.get_nodes().iter().filter(|n| n.get_type() == NodeType::FunctionCall(false)).all(|n| n.get_index() == Some(0)));
+}
+
+#[test]
+fn test_retarget_external_globals() {
+    // External globals are script globals, so they don't need to exist in the engine for either target
+    for index in [Some(2), None] {
+        let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::UTF8);
+        compiler.declare_external_global("ext", index, ValueType::Short);
+        compiler.read_script_data("external.hsc", b"(script static short main (+ ext 1))").unwrap();
+        let mut script_data = compiler.compile_script_data().unwrap();
+        assert!(script_data.check_against_target(CompileTarget::HaloCustomEdition).is_empty());
+        script_data.retarget(CompileTarget::HaloCustomEdition).unwrap();
+        assert!(script_data.get_target() == CompileTarget::HaloCustomEdition);
+        script_data.resolve_external(SymbolType::Global, "ext", 2);
+        script_data.to_syntax_data(CompileTarget::HaloCustomEdition).unwrap();
+    }
+}
+
+#[test]
+#[cfg(feature = "windows-1252")]
+fn test_link_unresolved_symbols() {
+    let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
+    compiler.declare_external_script("missing_script", None, ValueType::Short, &[]);
+    compiler.declare_external_global("found_global", None, ValueType::Short);
+    compiler.read_script_data("test_link.hsc", b"(script static short a (+ (missing_script) found_global))\n(script static short b (missing_script))").unwrap();
+    let mut script_data = compiler.compile_script_data().unwrap();
+
+    let mut resolved = Vec::new();
+    let errors = script_data.link(|symbol_type, name| {
+        resolved.push(name.to_owned());
+        if symbol_type == SymbolType::Global { Some(7) } else { None }
+    }).err().unwrap();
+
+    // Each symbol is resolved once, and unresolved ones list every reference
+    assert_eq!(vec!["missing_script", "found_global"], resolved);
+    assert_eq!(1, errors.len());
+    assert_eq!(CompileErrorCode::UnresolvedSymbol, errors[0].get_code());
+    assert_eq!("script 'missing_script' could not be resolved", errors[0].get_message());
+    assert_eq!((1, 27), errors[0].get_position());
+    assert_eq!(1, errors[0].get_related_locations().len());
+    assert!(script_data.get_relocations().iter().all(|r| r.get_name() == "missing_script"));
+}
+
+#[test]
+#[cfg(feature = "windows-1252")]
+fn test_emit_backend() {
+    #[derive(Default)]
+    struct PrintBackend {
+        lines: Vec<String>
+    }
+
+    fn print_node(node: TypedNode) -> String {
+        match node.get_type() {
+            NodeType::FunctionCall(_) => {
+                let mut parameters: Vec<String> = vec![node.get_string_data().unwrap().to_owned()];
+                parameters.extend(node.get_parameters().map(print_node));
+                format!("({})", parameters.join(" "))
+            },
+            _ => match (node.get_string_data(), node.get_data()) {
+                (Some(s), _) => s.to_owned(),
+                (None, Some(NodeData::Short(n))) => n.to_string(),
+                (None, Some(NodeData::Real(n))) => n.to_string(),
+                _ => "?".to_owned()
+            }
+        }
+    }
+
+    impl EmitBackend for PrintBackend {
+        type Output = Vec<String>;
+
+        fn emit_script(&mut self, script: ScriptDefinition) -> Result<(), CompileError> {
+            let parameters: Vec<String> = script.get_parameters().map(|(name, value_type)| format!("{} {}", value_type.as_str(), name)).collect();
+            self.lines.push(format!("{} {}({}) = {}", script.get_index(), script.get_name(), parameters.join(", "), print_node(script.get_node())));
+            Ok(())
+        }
+
+        fn emit_global(&mut self, global: GlobalDefinition) -> Result<(), CompileError> {
+            self.lines.push(format!("{} {} = {}", global.get_index(), global.get_name(), print_node(global.get_node())));
+            Ok(())
+        }
+
+        fn finish(self) -> Vec<String> {
+            self.lines
+        }
+    }
+
+    let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
+    compiler.read_script_data("test_emit_backend.hsc", b"(global short x 4)(script static real (add (short a)) (+ a x 1))").unwrap();
+    let (lines, warnings) = compiler.compile_with_backend(PrintBackend::default()).unwrap();
+    assert!(warnings.is_empty());
+    assert_eq!(vec!["0 add(short a) = (+ a x 1)", "0 x = 4"], lines);
+}
+
+#[test]
+#[cfg(feature = "windows-1252")]
+fn test_lua_backend() {
+    let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
+    compiler.read_script_data("test_lua.hsc", b"(global short count 0)\n(script static short (add (short a)) (+ a count 1))\n(script continuous tick (if (< count 3) (set count (add 1))) (sleep_until (= count 0) 30))").unwrap();
+    let (lua, _) = compiler.compile_with_backend(lua::LuaBackend::new()).unwrap();
+    let lua = lua.unwrap();
+
+    assert!(lua.contains("G[\"count\"] = 0\n"));
+    assert!(lua.contains("S[\"add\"] = function(a1) return (a1 + G[\"count\"] + 1) end\n"));
+    assert!(lua.contains("T[\"tick\"] = \"continuous\"\n"));
+    assert!(lua.contains("if (G[\"count\"] < 3) then return (function() G[\"count\"] = S[\"add\"](1) return G[\"count\"] end)() end"));
+    assert!(lua.contains("hsc[\"sleep_until\"](function() return (G[\"count\"] == 0) end, 30)"));
+
+    // Functions that act on the script engine itself can't be translated
+    let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
+    compiler.read_script_data("test_lua.hsc", b"(script startup main\n    (script_recompile)\n)").unwrap();
+    let errors = compiler.compile_with_backend(lua::LuaBackend::new()).unwrap().0.err().unwrap();
+    assert_eq!(1, errors.len());
+    assert_eq!(CompileErrorCode::UnsupportedByBackend, errors[0].get_code());
+    assert_eq!((2, 5), errors[0].get_position());
+}
+
+#[test]
+#[cfg(feature = "windows-1252")]
+fn test_content_hash() {
+    let hash = |script: &[u8]| {
+        let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
+        compiler.read_script_data("test_content_hash.hsc", script).unwrap();
+        compiler.compile_script_data().unwrap().content_hash()
+    };
+
+    // Positions and comments are not included
+    let original = hash(b"(global short x 1)(script static short get_x x)");
+    assert_eq!(original, hash(b"; comment\n(global short x 1)\n\n(script static short get_x\n    x\n)"));
+
+    assert_ne!(original, hash(b"(global short x 2)(script static short get_x x)"));
+    assert_ne!(original, hash(b"(global short y 1)(script static short get_x y)"));
+}
+
+#[test]
+#[cfg(feature = "windows-1252")]
+fn test_trace_callback() {
+    let timings = std::sync::Arc::new(std::sync::Mutex::new(Vec::<PhaseTiming>::new()));
+    let timings_callback = timings.clone();
+
+    let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
+    compiler.set_trace_callback(Some(Box::new(move |t| timings_callback.lock().unwrap().push(*t))));
+    compiler.read_script_data("test_script_parameters.hsc", include_bytes!("script/test_script_parameters.hsc")).unwrap();
+    let script_data = compiler.compile_script_data().unwrap();
+
+    let timings = timings.lock().unwrap();
+    let phases: Vec<CompilePhase> = timings.iter().map(|t| t.phase).collect();
+    assert_eq!(vec![CompilePhase::Tokenize, CompilePhase::Parse, CompilePhase::TypeCheck, CompilePhase::Lint, CompilePhase::Emit], phases);
+    assert_eq!(include_bytes!("script/test_script_parameters.hsc").len(), timings[0].count);
+    assert_eq!(2, timings[1].count);
+    assert_eq!(script_data.get_nodes().len(), timings[4].count);
+
+    // Tokens are held until they are parsed, and only the compiled nodes are left after emitting
+    assert!(timings[0].memory.tokens > 0 && timings[0].memory.strings > 0 && timings[0].memory.nodes == 0);
+    assert!(timings[2].memory.tokens > 0 && timings[2].memory.nodes > 0);
+    assert_eq!(0, timings[4].memory.tokens);
+    assert_eq!(script_data.get_statistics().memory, timings[4].memory);
+    assert!(script_data.get_statistics().memory.nodes >= std::mem::size_of_val(script_data.get_nodes()));
+}
+
+#[test]
+#[cfg(feature = "windows-1252")]
+fn test_obfuscation() {
+    let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
+    compiler.read_script_data("obfuscation.hsc", include_bytes!("script/obfuscation.hsc")).unwrap();
+    compiler.set_obfuscate(true);
+    compiler.preserve_name("TAG_REFERENCED");
+    let script_data = compiler.compile_script_data().unwrap();
+
+    // Dormant scripts and preserved names keep their names
+    let script_names: Vec<&str> = script_data.get_scripts().iter().map(|s| s.get_name()).collect();
+    let global_names: Vec<&str> = script_data.get_globals().iter().map(|g| g.get_name()).collect();
+    assert_eq!(vec!["a", "woken_by_engine", "b"], script_names);
+    assert_eq!(vec!["c", "tag_referenced"], global_names);
+
+    let renamed: Vec<(SymbolType, &str, &str)> = script_data.get_renamed_symbols().iter().map(|r| (r.get_type(), r.get_original_name(), r.get_name())).collect();
+    assert_eq!(vec![
+        (SymbolType::Script, "increment_counter", "a"),
+        (SymbolType::Script, "begin_mission", "b"),
+        (SymbolType::Global, "counter", "c")
+    ], renamed);
+
+    // References are renamed too
+    let strings: Vec<&str> = script_data.get_nodes().iter().filter_map(|n| n.get_string_data()).collect();
+    assert!(strings.contains(&"a") && strings.contains(&"c") && strings.contains(&"woken_by_engine"));
+    assert!(!strings.contains(&"increment_counter") && !strings.contains(&"counter"));
+}
+
+#[test]
+#[cfg(feature = "windows-1252")]
+fn test_rename_map() {
+    let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
+    compiler.read_script_data("obfuscation.hsc", include_bytes!("script/obfuscation.hsc")).unwrap();
+    compiler.set_obfuscate(true);
+    compiler.preserve_name("tag_referenced");
+    let script_data = compiler.compile_script_data().unwrap();
+
+    assert_eq!(0xE40C292C, symbol_hash("a"));
+    assert_eq!(symbol_hash("counter"), script_data.find_renamed_symbol("c").unwrap().get_hash());
+    assert!(script_data.find_renamed_symbol("counter").is_none());
+
+    let map = script_data.get_rename_map();
+    assert_eq!(3, map.lines().count());
+    assert_eq!(format!("global\tcounter\tc\t{:08x}", symbol_hash("counter")), map.lines().last().unwrap());
+}
+
+#[test]
+#[cfg(feature = "windows-1252")]
+fn test_budget_warnings() {
+    let compile = |thresholds: BudgetThresholds| {
+        let mut compiler = Compiler::new(CompileTarget::HaloCustomEdition, CompileEncoding::Windows1252);
+        compiler.read_script_data("obfuscation.hsc", include_bytes!("script/obfuscation.hsc")).unwrap();
+        compiler.set_budget_thresholds(thresholds);
+        compiler.compile_script_data().unwrap()
+    };
+
+    assert!(compile(BudgetThresholds::default()).get_warnings().is_empty());
+    assert!(compile(BudgetThresholds { nodes: Some(0.8), string_data: Some(0.8) }).get_warnings().is_empty());
+
+    // A threshold of 0 always warns, and the largest user is listed first
+    let script_data = compile(BudgetThresholds { nodes: Some(0.0), string_data: None });
+    let warnings = script_data.get_warnings();
+    assert_eq!(1, warnings.len());
+    assert!(warnings[0].get_message().starts_with(&format!("script nodes usage is at 0% of the maximum ({} / 19001); the largest users are 'increment_counter'", script_data.get_nodes().len())));
+    assert_eq!((10, 1), warnings[0].get_position());
+
+    // Nodes already in the scenario count towards the budget
+    let mut compiler = Compiler::new(CompileTarget::HaloCustomEdition, CompileEncoding::Windows1252);
+    compiler.read_script_data("obfuscation.hsc", include_bytes!("script/obfuscation.hsc")).unwrap();
+    compiler.set_budget_thresholds(BudgetThresholds { nodes: Some(0.8), string_data: None });
+    compiler.set_index_bases(0, 0, 16000);
+    let warnings = compiler.compile_script_data().unwrap().get_warnings().to_vec();
+    assert_eq!(1, warnings.len());
+    assert!(warnings[0].get_message().starts_with("script nodes usage is at 8"));
+}
+
+#[test]
+#[cfg(feature = "windows-1252")]
+fn test_check_against_target() {
+    let mut compiler = Compiler::new(CompileTarget::HaloCustomEdition, CompileEncoding::Windows1252);
+    compiler.read_script_data("test_compatibility_gbx_only.hsc", include_bytes!("script/test_compatibility_gbx_only.hsc")).unwrap();
+    let script_data = compiler.compile_script_data().unwrap();
+    assert!(script_data.check_against_target(CompileTarget::HaloCEGBX).is_empty());
+
+    let errors = script_data.check_against_target(CompileTarget::HaloCEA);
+    assert_eq!(1, errors.len());
+    assert_eq!("function 'connect' is not available in Halo: Combat Evolved Anniversary", errors[0].get_message());
+    assert_eq!((7, 32), errors[0].get_position());
+
+    // Script parameters are only supported on MCC
+    let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
+    compiler.read_script_data("test_script_parameters.hsc", include_bytes!("script/test_script_parameters.hsc")).unwrap();
+    let script_data = compiler.compile_script_data().unwrap();
+    assert!(script_data.check_against_target(CompileTarget::HaloCEA).is_empty());
+    assert_eq!(1, script_data.check_against_target(CompileTarget::HaloCustomEdition).len());
+}
+
+#[test]
+#[cfg(feature = "windows-1252")]
+fn test_compile_for_targets() {
+    let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
+    compiler.read_script_data("hello_world.hsc", HELLO_WORLD_HSC).unwrap();
+
+    let results = compiler.compile_for_targets(&[CompileTarget::HaloCEA, CompileTarget::HaloCustomEdition]).unwrap();
+    assert_eq!(2, results.len());
+
+    // print has a different index on each target
+    let print_index = |script_data: &CompiledScriptData| script_data.get_nodes()[script_data.get_scripts()[0].get_first_node_index()].get_index();
+    assert_ne!(print_index(&results[0]), print_index(&results[1]));
+    assert_eq!(1, results[1].get_files().len());
+
+    // Compiling once and retargeting gives the same indices as compiling for each target
+    let mut separate = Compiler::new(CompileTarget::HaloCustomEdition, CompileEncoding::UTF8);
+    separate.read_script_data("hello_world.hsc", HELLO_WORLD_HSC).unwrap();
+    let indices = |script_data: &CompiledScriptData| script_data.get_nodes().iter().map(|n| n.get_index()).collect::<Vec<_>>();
+    assert_eq!(indices(&separate.compile_script_data().unwrap()), indices(&results[1]));
+    assert!(results[1].get_target() == CompileTarget::HaloCustomEdition);
+
+    // The tokens were used up
+    assert!(compiler.compile_for_targets(&[CompileTarget::HaloCEA]).unwrap()[0].get_scripts().is_empty());
+
+    // Any target failing fails everything, and the loaded script data is kept, as it is without any targets
+    let mut compiler = Compiler::new(CompileTarget::HaloCustomEdition, CompileEncoding::Windows1252);
+    compiler.read_script_data("test_compatibility_gbx_only.hsc", include_bytes!("script/test_compatibility_gbx_only.hsc")).unwrap();
+    assert!(compiler.compile_for_targets(&[CompileTarget::HaloCustomEdition, CompileTarget::HaloCEA]).is_err());
+    assert!(compiler.compile_for_targets(&[]).unwrap().is_empty());
+    assert_eq!(1, compiler.compile_script_data().unwrap().get_scripts().len());
+}
+
+#[test]
+#[cfg(feature = "windows-1252")]
+fn test_retarget() {
+    let compile = |target: CompileTarget| {
+        let mut compiler = Compiler::new(target, CompileEncoding::Windows1252);
+        compiler.read_script_data("hello_world.hsc", HELLO_WORLD_HSC).unwrap();
+        compiler.compile_script_data().unwrap()
+    };
+    let indices = |script_data: &CompiledScriptData| script_data.get_nodes().iter().map(|n| n.get_index()).collect::<Vec<_>>();
+
+    let mut script_data = compile(CompileTarget::HaloCEA);
+    assert!(script_data.get_target() == CompileTarget::HaloCEA);
+    assert_eq!(CompileEncoding::Windows1252, script_data.get_encoding());
+    script_data.retarget(CompileTarget::HaloCEXboxNTSC).unwrap();
+    assert!(script_data.get_target() == CompileTarget::HaloCEXboxNTSC);
+    assert_eq!(indices(&compile(CompileTarget::HaloCEXboxNTSC)), indices(&script_data));
+
+    // Nothing changes if the target is unsupported
+    let mut compiler = Compiler::new(CompileTarget::HaloCustomEdition, CompileEncoding::Windows1252);
+    compiler.read_script_data("test_compatibility_gbx_only.hsc", include_bytes!("script/test_compatibility_gbx_only.hsc")).unwrap();
+    let mut script_data = compiler.compile_script_data().unwrap();
+    let original_indices = indices(&script_data);
+    assert!(script_data.retarget(CompileTarget::HaloCEA).is_err());
+    assert!(script_data.get_target() == CompileTarget::HaloCustomEdition);
+    assert_eq!(original_indices, indices(&script_data));
+}
+
+#[test]
+#[cfg(feature = "windows-1252")]
+fn test_engine_global_index() {
+    let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
+    compiler.read_script_data("engine_globals.hsc", b"(global boolean b cheat_deathless_player)\n(script startup a (set cheat_deathless_player b))").unwrap();
+    let mut script_data = compiler.compile_script_data().unwrap();
+    let global_indices = |script_data: &CompiledScriptData| script_data.get_nodes().iter()
+        .filter(|n| n.get_type() == NodeType::Primitive(PrimitiveType::Global))
+        .map(|n| (n.get_string_data().unwrap().to_owned(), n.get_index()))
+        .collect::<Vec<_>>();
+
+    // The definitions don't know the index of any engine global yet, so nothing is set except the index union of the variable being set
+    let expected = vec![("cheat_deathless_player".to_owned(), Some(0xFFFF)), ("b".to_owned(), None), ("cheat_deathless_player".to_owned(), None)];
+    assert_eq!(expected, global_indices(&script_data));
+
+    // Script globals are never engine globals
+    assert_eq!(None, compile::engine_global_index("b", CompileTarget::HaloCEA, &definitions::EngineDefinitions::default()));
+
+    // Retargeting keeps the index union of the variable being set
+    script_data.retarget(CompileTarget::HaloCustomEdition).unwrap();
+    assert_eq!(expected, global_indices(&script_data));
+
+    // Indices are only known if added definitions give them
+    let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::UTF8);
+    compiler.add_definitions(r#"{"globals": [{"name": "cheat_deathless_player", "type": "boolean", "engines": {"mcc-cea": 12, "gbx-custom": 34}}]}"#).unwrap();
+    compiler.read_script_data("engine_globals.hsc", b"(global boolean b cheat_deathless_player)\n(script startup a (set cheat_deathless_player b))").unwrap();
+    let mut script_data = compiler.compile_script_data().unwrap();
+    assert_eq!(vec![("cheat_deathless_player".to_owned(), Some(0xFFFF)), ("b".to_owned(), None), ("cheat_deathless_player".to_owned(), Some(12))], global_indices(&script_data));
+    script_data.retarget(CompileTarget::HaloCustomEdition).unwrap();
+    assert_eq!(vec![("cheat_deathless_player".to_owned(), Some(0xFFFF)), ("b".to_owned(), None), ("cheat_deathless_player".to_owned(), Some(34))], global_indices(&script_data));
+}
+
+#[test]
+#[cfg(feature = "windows-1252")]
+fn test_newline_conventions() {
+    let script = "; comment\n(script static void a\n    (print \"hi\") ;* multi\nline *;\n    (not_a_function))\n";
+
+    let diagnostic = |newline: &str| {
+        let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
+        compiler.read_script_data("newlines.hsc", script.replace('\n', newline).as_bytes()).unwrap();
+        compiler.compile_script_data().err().unwrap().to_string()
+    };
+
+    let expected = diagnostic("\n");
+    assert_eq!("newlines.hsc:5:5: error: function 'not_a_function' is not defined", expected);
+    assert_eq!(expected, diagnostic("\r\n"));
+    assert_eq!(expected, diagnostic("\r"));
+}
+
+#[test]
+#[cfg(feature = "windows-1252")]
+fn test_null_terminator() {
+    let script = b"(script static void a (print \"hi\"))\0\0garbage";
+
+    let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
+    let error = compiler.read_script_data("padded.hsc", script).err().unwrap();
+    assert_eq!("unexpected null terminator at byte offset 35 (note: a null terminator can only be the last byte)", error.get_message());
+    assert_eq!((1, 36), error.get_position());
+
+    // A single trailing null terminator is always fine
+    compiler.read_script_data("terminated.hsc", &script[..36]).unwrap();
+
+    let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
+    compiler.set_null_ends_input(true);
+    compiler.read_script_data("padded.hsc", script).unwrap();
+    assert_eq!(1, compiler.compile_script_data().unwrap().get_scripts().len());
+}
+
+#[test]
+#[cfg(feature = "windows-1252")]
+fn test_error_recovery() {
+    let unbalanced_hsc = include_bytes!("script/unbalanced.hsc");
+
+    // The parenthesis are balanced overall, so only the error recovery heuristics find this
+    let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
+    compiler.read_script_data("unbalanced.hsc", unbalanced_hsc).unwrap();
+    assert!(compiler.get_errors().is_empty());
+
+    // Both the unterminated block and the extra parenthesis are reported, and the rest is still loaded
+    let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
+    compiler.set_error_recovery(true);
+    assert_eq!((1, 1), compiler.read_script_data("unbalanced.hsc", unbalanced_hsc).err().unwrap().get_position());
+
+    let errors: Vec<(&str, (usize, usize))> = compiler.get_errors().iter().map(|e| (e.get_message(), e.get_position())).collect();
+    assert_eq!(vec![("unterminated block", (1, 1)), ("expected left parenthesis, got ) instead", (8, 18))], errors);
+
+    let script_data = compiler.compile_script_data().unwrap();
+    let names: Vec<&str> = script_data.get_scripts().iter().map(|s| s.get_name()).collect();
+    assert_eq!(vec!["fine", "extra_parenthesis", "also_fine"], names);
+}
+
+#[test]
+#[cfg(feature = "windows-1252")]
+fn test_unterminated_block_location() {
+    let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
+    let error = compiler.read_script_data("unterminated.hsc", b"(script static void a\n    (print \"hi\")\n").err().unwrap();
+    assert_eq!("unterminated.hsc:1:1: error: unterminated block\nunterminated.hsc:3:1: note: end of file reached here", error.to_string());
+
+    // When recovering, the block ends where the next one seems to start
+    let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
+    compiler.set_error_recovery(true);
+    let error = compiler.read_script_data("unbalanced.hsc", include_bytes!("script/unbalanced.hsc")).err().unwrap();
+    let related = error.get_related_locations();
+    assert_eq!(1, related.len());
+    assert_eq!(("next block starts here", (4, 1)), (related[0].get_message(), related[0].get_position()));
+
+    let error = compiler.compile_expression("(+ 1 (+ 2 3)", ValueType::Short).err().unwrap();
+    assert_eq!((1, 13), error.get_related_locations()[0].get_position());
+}
+
+#[test]
+#[cfg(feature = "windows-1252")]
+fn test_empty_blocks() {
+    let script = b"(script static void a (if (= 1 1) () (print \"hi\")))";
+
+    let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
+    let error = compiler.read_script_data("empty.hsc", script).err().unwrap();
+    assert_eq!("empty block (note: use '(begin)' for a block that does nothing, or remove the '()')", error.get_message());
+    assert_eq!((1, 35), error.get_position());
+
+    // An empty begin block is fine
+    assert_eq!(ValueType::Void, compiler.compile_expression("(begin)", ValueType::Void).unwrap().get_value_type());
+    assert!(compiler.compile_expression("(begin)", ValueType::Short).is_err());
+
+    // Lenient mode compiles () as (begin), but never at the top level
+    let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
+    compiler.set_strictness(Strictness::Lenient);
+    assert!(compiler.read_script_data("empty.hsc", b"()").is_err());
+    compiler.read_script_data("empty.hsc", script).unwrap();
+
+    let script_data = compiler.compile_script_data().unwrap();
+    let warnings = script_data.get_warnings();
+    assert_eq!(1, warnings.len());
+    assert_eq!((1, 35), warnings[0].get_position());
+    assert!(script_data.get_nodes().iter().any(|n| n.get_string_data() == Some("begin")));
+}
+
+#[test]
+#[cfg(feature = "windows-1252")]
+fn test_multiline_comments() {
+    let read = |script: &[u8]| {
+        let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
+        compiler.read_script_data("comments.hsc", script).map(|_| compiler.tokens.len()).map_err(|e| e.to_string())
+    };
+
+    // ;*; does not end the comment it starts, but ;**; is a complete comment
+    assert_eq!(Ok(1), read(b"(script static void a (print \"a\")) ;*; still a comment\n(script static void b (print \"b\")) *;"));
+    assert_eq!(Ok(2), read(b"(script static void a (print \"a\")) ;**;\n(script static void b (print \"b\"))"));
+
+    // Comments at the end of the file are fine if they are terminated
+    assert_eq!(Ok(1), read(b"(script static void a (print \"a\")) ; single line"));
+    assert_eq!(Ok(1), read(b"(script static void a (print \"a\"))\n;* multi\nline *;"));
+
+    let error = read(b"(script static void a (print \"a\"))\n  ;* multi\nline;").err().unwrap();
+    assert_eq!("comments.hsc:2:3: error: unterminated multiline comment (note: multiline comments end with '*;')\ncomments.hsc:3:6: note: end of file reached here", error);
+}
+
+#[test]
+#[cfg(feature = "windows-1252")]
+fn test_folding_ranges() {
+    let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
+    compiler.read_script_data("hello_world.hsc", HELLO_WORLD_HSC).unwrap();
+    compiler.read_script_data("folding.hsc", b"(script static void a\n    (begin\n        (print \"a\")\n    )\n) (global short b\n 1)").unwrap();
+
+    assert_eq!(&[
+        FoldingRange { kind: FoldingRangeKind::Comment, start_line: 1, end_line: 5 },
+        FoldingRange { kind: FoldingRangeKind::Definition, start_line: 7, end_line: 9 }
+    ], compiler.folding_ranges(0));
+
+    assert_eq!(&[
+        FoldingRange { kind: FoldingRangeKind::Definition, start_line: 1, end_line: 5 },
+        FoldingRange { kind: FoldingRangeKind::Block, start_line: 2, end_line: 4 },
+        FoldingRange { kind: FoldingRangeKind::Definition, start_line: 5, end_line: 6 }
+    ], compiler.folding_ranges(1));
+
+    assert!(compiler.folding_ranges(2).is_empty());
+    compiler.compile_script_data().unwrap();
+    assert!(compiler.folding_ranges(0).is_empty());
+}
+
+#[test]
+#[cfg(feature = "windows-1252")]
+fn test_signature_help() {
+    let compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
+    let script = b"(script static void a\n    (sleep (+ 1 2) \n    (if (begin) \"unterminated";
+
+    // Line 2 ends right after the first parameter, so the second one is active
+    let help = compiler.signature_help(script, (2, 20)).unwrap();
+    assert_eq!("sleep", help.signature.name);
+    assert_eq!(ValueType::Void, help.signature.return_type);
+    assert_eq!(1, help.active_parameter);
+    assert_eq!("(sleep <short> [script])", help.signature.to_call_string());
+
+    // Still typing the first parameter
+    assert_eq!(0, compiler.signature_help(script, (2, 18)).unwrap().active_parameter);
+
+    // Inside of a nested call
+    assert_eq!("+", compiler.signature_help(script, (2, 15)).unwrap().signature.name);
+
+    // Inside of an unterminated string
+    let help = compiler.signature_help(script, (3, 26)).unwrap();
+    assert_eq!("if", help.signature.name);
+    assert_eq!(1, help.active_parameter);
+
+    // The last parameter of begin can be passed many times
+    let help = compiler.signature_help(b"(begin 1 2 3 ", (1, 100)).unwrap();
+    assert_eq!("(begin <passthrough>...)", help.signature.to_call_string());
+    assert_eq!(0, help.active_parameter);
+
+    // Scripts with parameters, including ones defined in script data that was already read
+    let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
+    compiler.read_script_data("read.hsc", b"(script static real (scale (real value) (short times)) (* value times))").unwrap();
+    let help = compiler.signature_help(b"(script static void a (scale 1.0 ", (1, 100)).unwrap();
+    assert_eq!("(scale <real value> <short times>)", help.signature.to_call_string());
+    assert_eq!(ValueType::Real, help.signature.return_type);
+    assert_eq!(Some("times"), help.signature.parameters[1].name.as_deref());
+    assert_eq!(1, help.active_parameter);
+    let help = compiler.signature_help(b"(script static void (print (string text)) (begin))\n(script startup b (print ", (2, 100)).unwrap();
+    assert_eq!("(print <string text>)", help.signature.to_call_string());
+
+    // Not in a call to an engine function or script
+    assert!(compiler.signature_help(script, (1, 10)).is_none());
+    assert!(compiler.signature_help(b"(not_a_function ", (1, 100)).is_none());
+}
+
+#[test]
+#[cfg(feature = "windows-1252")]
+fn test_compile_all_errors() {
+    let script = b"(global short a \"not a number\")\n(global short b 1)\n(script static void c (not_a_function))\n(script static void d (sleep b))\n(script static void e (set b 1.5 2))";
+
+    // Every script and global that fails to compile is reported
+    let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
+    compiler.read_script_data("errors.hsc", script).unwrap();
+    let errors = compiler.compile_script_data_all_errors().err().unwrap();
+    assert_eq!(vec![1, 3, 5], errors.iter().map(|e| e.get_position().0).collect::<Vec<usize>>());
+    assert!(compiler.get_errors().is_empty());
+
+    // Error recovery alone fails with the first error, but records the rest
+    let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
+    compiler.set_error_recovery(true);
+    compiler.read_script_data("errors.hsc", script).unwrap();
+    assert_eq!((1, 17), compiler.compile_script_data().err().unwrap().get_position());
+    assert_eq!(3, compiler.get_errors().len());
+
+    // Errors that can't be recovered from are still returned
+    let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
+    compiler.read_script_data("errors.hsc", b"(script static void a (begin))\n(script static void a (begin))").unwrap();
+    let errors = compiler.compile_script_data_all_errors().err().unwrap();
+    assert_eq!(1, errors.len());
+    assert_eq!("duplicate_script", errors[0].get_message_key());
+
+    let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
+    compiler.read_script_data("errors.hsc", b"(global short a 1)").unwrap();
+    assert!(compiler.compile_script_data_all_errors().is_ok());
+}
+
+#[test]
+#[cfg(feature = "windows-1252")]
+fn test_decompile() {
+    let script = b"(global real \"spawn delay\" 2.5)\n(global boolean armed false)\n(global string door \"door;main\")\n(global short count (+ 1 2))\n\
+        (script static void (arm (short times) (real delay))\n    (set armed true)\n    (sleep (* times delay)))\n\
+        (script static short get_count count)\n\
+        (script startup main\n    (arm (get_count) \"spawn delay\")\n    (print door)\n    (if armed (sleep_forever)))";
+
+    let compile = |source: &[u8]| {
+        let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
+        compiler.read_script_data("decompile.hsc", source).unwrap();
+        compiler.compile_script_data().unwrap()
+    };
+
+    // Compiling the decompiled source results in the same script data
+    let compiled = compile(script);
+    let source = compiled.to_source();
+    assert!(compiled.diff(&compile(source.as_bytes())).is_empty(), "{}", source);
+
+    // Names are quoted when needed, and each expression of a script is on its own line
+    assert!(source.starts_with("(global real \"spawn delay\" 2.5)\n"), "{}", source);
+    assert!(source.contains("(script static void (arm (short times) (real delay))\n    (set armed true)\n    (sleep (* times delay)))\n"), "{}", source);
+    assert!(source.contains("(script static short get_count\n    count)\n"), "{}", source);
+    assert!(source.contains("(arm (get_count) \"spawn delay\")"), "{}", source);
+}
+
+#[test]
+#[cfg(feature = "windows-1252")]
+fn test_syntax_data() {
+    let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
+    compiler.read_script_data("syntax.hsc", b"(global short a 5)\n(script dormant b (sleep_until (> a 1)))").unwrap();
+    let script_data = compiler.compile_script_data().unwrap();
+    let nodes = script_data.get_nodes();
+
+    let syntax_data = script_data.to_syntax_data(CompileTarget::HaloCustomEdition).unwrap();
+    let node_table = &syntax_data.syntax_data;
+    assert_eq!(0x38 + 19001 * 0x14, node_table.len());
+    assert_eq!(b"script node\0", &node_table[0x00..0x0C]);
+    assert_eq!([0x4A, 0x39, 0x00, 0x14], node_table[0x20..0x24]);
+    assert_eq!(b"d@t@", &node_table[0x28..0x2C]);
+    assert_eq!((nodes.len() as u16).to_be_bytes(), node_table[0x30..0x32]);
+    assert_eq!(script_data.get_statistics().string_data_length, syntax_data.string_data.len());
+
+    let read_u16 = |offset: usize| u16::from_be_bytes([node_table[offset], node_table[offset + 1]]);
+    let read_u32 = |offset: usize| u32::from_be_bytes([node_table[offset], node_table[offset + 1], node_table[offset + 2], node_table[offset + 3]]);
+    let node_offset = |index: usize| 0x38 + index * 0x14;
+
+    // Function calls refer to their function name node by ID, and their index is remapped to the target
+    let (call_index, call) = nodes.iter().enumerate().find(|(_, n)| n.get_type() == NodeType::FunctionCall(true) && n.get_value_type() == ValueType::Void).unwrap();
+    let function_name_index = match call.get_data() {
+        Some(NodeData::NodeOffset(n)) => n,
+        _ => unreachable!()
+    };
+    let function_name_id = ((read_u16(node_offset(function_name_index)) as u32) << 16) | function_name_index as u32;
+    assert_eq!(function_name_id, read_u32(node_offset(call_index) + 0x10));
+    let first_parameter = nodes[function_name_index].get_next_node_index().unwrap();
+    assert_eq!(first_parameter as u32, read_u32(node_offset(function_name_index) + 0x08) & 0xFFFF);
+    assert_eq!((Some(21), 20), (nodes[call_index].get_index(), read_u16(node_offset(call_index) + 0x02)));
+    assert_eq!(0, read_u16(node_offset(call_index) + 0x06));
+    assert!(read_u16(node_offset(0)) & 0x8000 != 0);
+
+    // Each string is at its node's offset
+    let (string_index, string_node) = nodes.iter().enumerate().find(|(_, n)| n.get_string_data() == Some("a")).unwrap();
+    let string_offset = read_u32(node_offset(string_index) + 0x0C) as usize;
+    assert_eq!(b"a\0", &syntax_data.string_data[string_offset..string_offset + 2]);
+    assert_eq!(0x1 | 0x4, read_u16(node_offset(string_index) + 0x06));
+    assert_eq!(string_node.get_value_type().as_u16(), read_u16(node_offset(string_index) + 0x04));
+
+    // Unresolved externals can't be written
+    let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
+    compiler.declare_external_global("external", None, ValueType::Short);
+    compiler.read_script_data("syntax.hsc", b"(script static short b external)").unwrap();
+    let error = compiler.compile_script_data().unwrap().to_syntax_data(CompileTarget::HaloCEA).err().unwrap();
+    assert_eq!(CompileErrorCode::UnresolvedSymbol, error.get_code());
+}
+
+#[test]
+#[cfg(feature = "windows-1252")]
+fn test_syntax_data_round_trip() {
+    let script = b"(global real delay 2.5)\n(global boolean armed false)\n(global string door \"door main\")\n(global short count (+ 1 2))\n\
+        (script static void (arm (short times) (real wait))\n    (set armed true)\n    (sleep (* times wait)))\n\
+        (script static short get_count count)\n\
+        (script startup main\n    (arm (get_count) delay)\n    (print door)\n    (set cheat_deathless_player armed)\n    (if cheat_deathless_player (sleep_until (> (get_count) 1) 15)))";
+
+    let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
+    compiler.read_script_data("round_trip.hsc", script).unwrap();
+    let script_data = compiler.compile_script_data().unwrap();
+    let syntax_data = script_data.to_syntax_data(CompileTarget::HaloCEA).unwrap();
+
+    let scripts: Vec<SyntaxScript> = script_data.get_scripts().iter().map(|s| SyntaxScript {
+        name: s.get_name().to_owned(),
+        script_type: s.get_type(),
+        return_type: s.get_value_type(),
+        root_node: s.get_first_node_index() as u32,
+        parameters: s.get_parameters().iter().map(|p| (p.get_name().to_owned(), p.get_value_type())).collect()
+    }).collect();
+    let globals: Vec<SyntaxGlobal> = script_data.get_globals().iter().map(|g| SyntaxGlobal {
+        name: g.get_name().to_owned(),
+        value_type: g.get_value_type(),
+        initialization_node: g.get_first_node_index() as u32
+    }).collect();
+
+    // Everything but source positions is read back
+    let read = CompiledScriptData::from_syntax_data(CompileTarget::HaloCEA, CompileEncoding::Windows1252, &syntax_data, &scripts, &globals).unwrap();
+    assert_eq!(script_data.get_nodes().len(), read.get_nodes().len());
+    for (original, read) in script_data.get_nodes().iter().zip(read.get_nodes()) {
+        assert_eq!(original.get_type(), read.get_type());
+        assert_eq!(original.get_value_type(), read.get_value_type());
+        assert_eq!(original.get_data(), read.get_data());
+        assert_eq!(original.get_string_data(), read.get_string_data());
+        assert_eq!(original.get_next_node_index(), read.get_next_node_index());
+        assert_eq!(original.get_index(), read.get_index());
+    }
+    assert!(script_data.diff(&read).is_empty());
+    assert_eq!(script_data.to_source(), read.to_source());
+
+    // Invalid data is rejected
+    let mut truncated = syntax_data.clone();
+    truncated.syntax_data.truncate(0x38 + 0x14);
+    assert_eq!("syntax_data_truncated", CompiledScriptData::from_syntax_data(CompileTarget::HaloCEA, CompileEncoding::Windows1252, &truncated, &scripts, &globals).err().unwrap().get_message_key());
+
+    let mut bad_root = scripts.clone();
+    bad_root[0].root_node = 0xFFFF;
+    assert_eq!("syntax_data_invalid_script_node", CompiledScriptData::from_syntax_data(CompileTarget::HaloCEA, CompileEncoding::Windows1252, &syntax_data, &bad_root, &globals).err().unwrap().get_message_key());
+
+    // Names can't have null characters, as they are stored as C strings
+    let mut bad_name = scripts.clone();
+    bad_name[0].parameters[0].0 = "ti\0mes".to_owned();
+    let error = CompiledScriptData::from_syntax_data(CompileTarget::HaloCEA, CompileEncoding::Windows1252, &syntax_data, &bad_name, &globals).err().unwrap();
+    assert_eq!("syntax_data_invalid_parameter_name", error.get_message_key());
+    assert_eq!("parameter 0 of script 'arm' has a name containing a null character", error.get_message());
+
+    let mut bad_name = globals.clone();
+    bad_name[0].name = "delay\0".to_owned();
+    assert_eq!("syntax_data_invalid_global_name", CompiledScriptData::from_syntax_data(CompileTarget::HaloCEA, CompileEncoding::Windows1252, &syntax_data, &scripts, &bad_name).err().unwrap().get_message_key());
+}
+
+#[test]
+#[cfg(feature = "windows-1252")]
+fn test_warning_order() {
+    let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
+    compiler.read_script_data("second.hsc", b"(script static void (a (short unused)) (sleep 0))\n(global boolean d false)\n(global boolean b (= d true))").unwrap();
+    compiler.read_script_data("first.hsc", b"(script dormant c (if (not b) (sleep 1) (sleep 2)))").unwrap();
+    let script_data = compiler.compile_script_data().unwrap();
+
+    // Warnings are sorted by the order the files were read in, and then by position
+    let warnings: Vec<(&str, (usize, usize))> = script_data.get_warnings().iter().map(|w| (w.get_file(), w.get_position())).collect();
+    let mut sorted = warnings.clone();
+    sorted.sort_by_key(|&(file, position)| (file == "first.hsc", position));
+    assert_eq!(sorted, warnings);
+    assert_eq!(4, warnings.len());
+}
+
+#[test]
+fn test_encoding_feature() {
+    assert!(CompileEncoding::UTF8.is_enabled());
+    assert_eq!(cfg!(feature = "windows-1252"), CompileEncoding::Windows1252.is_enabled());
+
+    // UTF-8 can always be compiled
+    let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::UTF8);
+    compiler.read_script_data("hello_world.hsc", HELLO_WORLD_HSC).unwrap();
+    compiler.compile_script_data().unwrap();
+
+    // Windows-1252 is read, or rejected before anything is tokenized if it was not compiled in
+    let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
+    let result = compiler.read_script_data("encoding.hsc", b"(global string caf\xE9 \"caf\xE9\")");
+    if CompileEncoding::Windows1252.is_enabled() {
+        result.unwrap();
+        let script_data = compiler.digest_tokens().unwrap();
+        assert_eq!("caf\u{E9}", script_data.get_globals()[0].get_name());
+    }
+    else {
+        let error = result.unwrap_err();
+        assert_eq!((1, 1), error.get_position());
+        assert!(compiler.tokens.is_empty());
+        assert_eq!("?", CompileEncoding::Windows1252.encode_to_cstring("\u{E9}").to_str().unwrap());
+    }
+}
+
+#[test]
+fn test_decode_error_policy() {
+    let read = |encoding: CompileEncoding, policy: DecodeErrorPolicy, script: &[u8]| {
+        let mut compiler = Compiler::new(CompileTarget::HaloCEA, encoding);
+        compiler.set_decode_error_policy(policy);
+        compiler.read_script_data("decode.hsc", script).map(|_| compiler)
+    };
+    let value = |compiler: &Compiler| compiler.tokens[0].children.as_ref().unwrap()[3].string.clone();
+
+    // Invalid bytes can be errors, pointing to the token they are in
+    let script = b"(global string a \"h\xFFi\xC3\")";
+    let error = read(CompileEncoding::UTF8, DecodeErrorPolicy::Error, script).err().unwrap();
+    assert_eq!("decode_token_failed", error.get_message_key());
+    assert_eq!(((1, 18), (1, 24)), (error.get_position(), error.get_end_position()));
+
+    // Replacing them, as is done by default, or leaving them out warns for each token
+    let compiler = read(CompileEncoding::UTF8, DecodeErrorPolicy::default(), script).unwrap();
+    assert_eq!("h\u{FFFD}i\u{FFFD}", value(&compiler));
+    assert_eq!(1, compiler.warnings.len());
+    assert_eq!(CompileErrorCode::UndecodableToken, compiler.warnings[0].get_code());
+    assert_eq!(["2", "UTF-8"], compiler.warnings[0].get_message_arguments());
+    assert_eq!((1, 18), compiler.warnings[0].get_position());
+
+    let compiler = read(CompileEncoding::UTF8, DecodeErrorPolicy::Skip, script).unwrap();
+    assert_eq!("hi", value(&compiler));
+    assert_eq!("decode_token_skipped", compiler.warnings[0].get_message_key());
+
+    // The few bytes that Windows-1252 doesn't map to anything are control characters, so they are kept without a warning
+    if CompileEncoding::Windows1252.is_enabled() {
+        let script = b"(global string a \"caf\xE9\x81\x8D\x8F\x90\x9D\")";
+        let compiler = read(CompileEncoding::Windows1252, DecodeErrorPolicy::Error, script).unwrap();
+        assert_eq!("caf\u{E9}\u{81}\u{8D}\u{8F}\u{90}\u{9D}", value(&compiler));
+        assert!(compiler.warnings.is_empty());
+        assert_eq!(b"caf\xE9\x81\x8D\x8F\x90\x9D", CompileEncoding::Windows1252.encode_to_cstring(&value(&compiler)).as_bytes());
+    }
+}
+
+#[test]
+#[cfg(feature = "windows-1252")]
+fn test_token_case() {
+    let script = b"(global Short Counter 0)\n(script startup main\n    (Sleep 30)\n    (print \"Hello World\")\n    (set counter (+ COUNTER 1))\n    (Cond (TRUE (sleep 1))))";
+    let compile = |check_case: bool| {
+        let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
+        compiler.set_check_case(check_case);
+        compiler.read_script_data("case.hsc", script).unwrap();
+        compiler.compile_script_data().unwrap()
+    };
+
+    // The original case is kept alongside the lowercase string data
+    let script_data = compile(false);
+    assert!(script_data.get_warnings().iter().all(|w| w.get_code() != CompileErrorCode::TokenCase));
+    let original = |string_data: &str| script_data.get_nodes().iter().filter(|n| n.get_string_data() == Some(string_data)).map(|n| n.get_original_string_data()).collect::<Vec<_>>();
+    assert_eq!(vec![Some("Sleep"), None], original("sleep"));
+    assert_eq!(vec![None, Some("COUNTER")], original("counter"));
+    assert_eq!(vec![None], original("Hello World"));
+    assert_eq!(vec![None], original("if"));
+    assert!(script_data.get_nodes().iter().all(|n| n.get_original_string_data() != Some("TRUE")));
+
+    // The decompiler writes it back
+    let source = script_data.to_source();
+    assert!(source.contains("(Sleep 30)"));
+    assert!(source.contains("(+ COUNTER 1)"));
+    assert!(source.contains("(set counter"));
+
+    // Each lowercased token is warned about once, with a fix
+    let script_data = compile(true);
+    let warnings: Vec<&CompileError> = script_data.get_warnings().iter().filter(|w| w.get_code() == CompileErrorCode::TokenCase).collect();
+    let tokens: Vec<&str> = warnings.iter().map(|w| w.get_message_arguments()[0].as_str()).collect();
+    assert_eq!(vec!["Short", "Counter", "Sleep", "COUNTER", "Cond", "TRUE"], tokens);
+    assert_eq!(Some("counter"), warnings[1].get_fix());
+    assert_eq!(((1, 15), (1, 22)), (warnings[1].get_position(), warnings[1].get_end_position()));
+
+    let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
+    compiler.set_check_case(true);
+    compiler.read_script_data("case.hsc", script).unwrap();
+    assert!(compiler.compile_with_report().to_json().contains("\"fix\":{\"end_line\":1,\"end_column\":22,\"text\":\"counter\"}"));
+}
+
+#[test]
+#[cfg(feature = "windows-1252")]
+fn test_dedicated_server() {
+    let script = b"(script startup intro\n    (fade_in 0 0 0 30)\n    (sleep 30)\n    (if (= 1 1) (hud_show_health false)))";
+    let compile = |dedicated_server: bool, definitions: Option<&str>| {
+        let mut compiler = Compiler::new(CompileTarget::HaloCustomEdition, CompileEncoding::Windows1252);
+        compiler.set_dedicated_server(dedicated_server);
+        if let Some(json) = definitions {
+            compiler.add_definitions(json).unwrap();
+        }
+        compiler.read_script_data("server.hsc", script).unwrap();
+        let script_data = compiler.compile_script_data().unwrap();
+        script_data.get_warnings().iter().filter(|w| w.get_code() == CompileErrorCode::ClientOnly).map(|w| w.get_position()).collect::<Vec<(usize, usize)>>()
+    };
+
+    assert!(compile(false, None).is_empty());
+    assert_eq!(vec![(2, 5), (4, 17)], compile(true, None));
+
+    // Loaded definitions can say otherwise
+    assert_eq!(vec![(3, 5), (4, 17)], compile(true, Some(r#"{"functions": [
+        {"name": "fade_in", "type": "void", "parameters": [{"type": "real"}, {"type": "real"}, {"type": "real"}, {"type": "short"}], "engines": {"gbx-custom": 343}},
+        {"name": "sleep", "type": "void", "client_only": true, "parameters": [{"type": "short"}, {"type": "script", "optional": true}], "engines": {"gbx-custom": 2}}
+    ]}"#)));
+}
+
+#[test]
+fn test_script_data_json() {
+    let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::UTF8);
+    compiler.set_index_bases(0, 0, 10);
+    compiler.read_script_data("json.hsc", b"(global real Scale 0.5)\n(script static short (add (short a) (short b)) (+ a B))").unwrap();
+    let script_data = compiler.compile_script_data().unwrap();
+    let json: serde_json::Value = serde_json::from_str(&script_data.to_json()).unwrap();
+
+    assert_eq!(("HaloCEA", "UTF-8", 10), (json["target"].as_str().unwrap(), json["encoding"].as_str().unwrap(), json["node_base"].as_u64().unwrap()));
+    assert_eq!(serde_json::json!(["json.hsc"]), json["files"]);
+    assert_eq!(serde_json::json!({"name": "scale", "value_type": "real", "first_node": script_data.get_globals()[0].get_first_node_index(), "file": 0, "line": 1, "column": 1}),
+               json["globals"][0]);
+
+    let script = &json["scripts"][0];
+    assert_eq!(("add", "static", "short"), (script["name"].as_str().unwrap(), script["type"].as_str().unwrap(), script["return_type"].as_str().unwrap()));
+    assert_eq!(serde_json::json!({"name": "b", "value_type": "short", "file": 0, "line": 2, "column": 44}), script["parameters"][1]);
+
+    // Follow the node chain of the script's (+ a b), going through the function name node to the parameters
+    let nodes = json["nodes"].as_array().unwrap();
+    let node = |index: &serde_json::Value| &nodes[index.as_u64().unwrap() as usize - 10];
+    let call = node(&script["first_node"]);
+    assert_eq!(("engine_function_call", "short"), (call["type"].as_str().unwrap(), call["value_type"].as_str().unwrap()));
+    let function_name = node(&call["child"]);
+    assert_eq!("+", function_name["string_data"]);
+    let a = node(&function_name["next"]);
+    assert_eq!(("local", "a"), (a["type"].as_str().unwrap(), a["string_data"].as_str().unwrap()));
+    assert_eq!("b", node(&a["next"])["string_data"]);
+    assert!(node(&a["next"])["next"].is_null());
+
+    // Literals have their parsed values, and names keep how they were written
+    let scale = node(&json["globals"][0]["first_node"]);
+    assert_eq!(("static", 0.5), (scale["type"].as_str().unwrap(), scale["value"].as_f64().unwrap()));
+    assert!(scale["child"].is_null());
+    assert_eq!(Some("B"), nodes.iter().find_map(|n| n["original_string_data"].as_str()));
+}
+
+#[test]
+fn test_dot() {
+    let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::UTF8);
+    compiler.declare_external_script("library_script", None, ValueType::Short, &[]);
+    compiler.read_script_data("dot.hsc", b"(script static short twice (* (library_script) 2))
+(script continuous \"loop\" (if (> (twice) 3) (wake loop)))
+(global short value (twice))").unwrap();
+    let script_data = compiler.compile_script_data().unwrap();
+
+    let dot = script_data.call_graph_to_dot();
+    assert!(dot.starts_with("digraph calls {\n    script_0 [shape=box, label=\"twice\\nstatic short\"];\n"));
+    assert!(dot.contains("    global_0 [shape=ellipse, label=\"value\\nshort\"];\n"));
+    assert!(dot.contains("    external_0 [shape=box, style=dashed, label=\"library_script\"];\n    script_0 -> external_0;\n"));
+    assert!(dot.contains("    script_1 -> script_1;\n    script_1 -> script_0;\n    global_0 -> script_0;\n}\n"));
+
+    // Parameters are in order under their function call, labeled with the type they are converted to
+    let dot = script_data.script_tree_to_dot(0).unwrap();
+    let first_node = script_data.get_scripts()[0].get_first_node_index();
+    assert!(dot.starts_with("digraph \"twice\" {\n    ordering=out;\n"));
+    assert!(dot.contains(&format!("    node_{first_node} [shape=box, label=\"*\\nshort\"];\n    node_{first_node} -> node_")));
+    assert!(dot.contains("[shape=box, label=\"library_script\\nreal\"];\n"));
+    assert!(dot.contains("[shape=ellipse, label=\"2\\nreal\"];\n}\n"));
+    assert!(script_data.script_tree_to_dot(2).is_none());
+}
+
+#[test]
+fn test_markdown() {
+    let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::UTF8);
+    compiler.read_script_data("docs.hsc", b"(global short count 0)
+(script static void (add (short amount) (real scale))
+    (set count (+ count amount)))
+(script startup main (add 1 2))").unwrap();
+    let script_data = compiler.compile_script_data().unwrap();
+
+    let expected = "# Scripts
+
+## `add`
+
+`(script static void (add (short amount) (real scale)))`
+
+Defined in `docs.hsc:2`.
+
+| Parameter | Type |
+| --- | --- |
+| `amount` | `short` |
+| `scale` | `real` |
+
+## `main`
+
+`(script startup main)`
+
+Defined in `docs.hsc:4`.
+
+# Globals
+
+| Global | Type | Defined in |
+| --- | --- | --- |
+| `count` | `short` | `docs.hsc:1` |
+";
+    assert_eq!(expected, script_data.to_markdown());
+}
+
+#[test]
+fn test_simulated_values() {
+    let script = b"(global boolean cheats false)
+(script startup mission
+    (if (= (game_difficulty_get) impossible) (print \"legendary\") (print \"easier\"))
+    (if (and cheats (not (game_is_cooperative))) (print \"cheating alone\"))
+    (if (>= (game_difficulty_get) hard) (print \"heroic or harder\")))";
+
+    let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::UTF8);
+    compiler.set_simulated_value("GAME_DIFFICULTY_GET", Some("impossible")).unwrap();
+    compiler.set_simulated_value("game_is_cooperative", Some("false")).unwrap();
+    compiler.set_propagate_constants(true);
+    compiler.read_script_data("simulated.hsc", script).unwrap();
+    let source = compiler.compile_script_data().unwrap().to_source();
+    assert!(source.contains("(print \"legendary\")") && source.contains("(print \"heroic or harder\")"));
+    assert!(!source.contains("easier") && !source.contains("cheating alone") && !source.contains("game_difficulty_get") && !source.contains("(begin)"));
+
+    // Only what is simulated is folded
+    compiler.set_simulated_value("game_is_cooperative", None).unwrap();
+    compiler.set_propagate_constants(false);
+    compiler.read_script_data("simulated.hsc", script).unwrap();
+    let source = compiler.compile_script_data().unwrap().to_source();
+    assert!(!source.contains("easier") && source.contains("cheating alone") && source.contains("(game_is_cooperative)"));
+
+    // Values must be valid for the engine global or function
+    assert!(compiler.set_simulated_value("game_difficulty_get", Some("legendary")).is_err());
+    assert!(compiler.set_simulated_value("not_a_global", Some("1")).is_err());
+    assert!(compiler.set_simulated_value("sleep", Some("1")).is_err());
+    assert!(compiler.set_simulated_value("players", Some("1")).is_err());
+
+    // Strings can't be simulated
+    compiler.add_definitions(r#"{"globals": [{"name": "current_map_name", "type": "string", "engines": {"mcc-cea": null}}]}"#).unwrap();
+    assert_eq!("cannot simulate 'current_map_name', as string values cannot be simulated", compiler.set_simulated_value("current_map_name", Some("a10")).unwrap_err());
+}
+
+#[test]
+#[cfg(feature = "windows-1252")]
+fn test_add_definitions() {
+    let definitions = r#"{
+        "engines": [{ "identifier": "gbx-custom" }, { "identifier": "xbox" }, { "identifier": "xbox-pal", "base": "xbox" }],
+        "functions": [
+            {
+                "name": "mod_execute",
+                "type": "boolean",
+                "parameters": [{ "type": "string", "allow_uppercase": true }, { "type": "short", "optional": true }],
+                "engines": { "gbx-custom": 600, "xbox": 601 }
+            },
+            { "name": "print", "type": "void", "parameters": [{ "type": "string" }], "engines": { "gbx-custom": 700 } }
+        ],
+        "globals": [{ "name": "mod_enabled", "type": "boolean", "engines": { "gbx-custom": null } }]
+    }"#;
+
+    let compile = |target: CompileTarget| {
+        let mut compiler = Compiler::new(target, CompileEncoding::Windows1252);
+        compiler.add_definitions(definitions).unwrap();
+        compiler.read_script_data("mod.hsc", b"(script startup a (if mod_enabled (mod_execute \"Hello\")) (print \"hi\"))").unwrap();
+        compiler.compile_script_data()
+    };
+    let index_of = |script_data: &CompiledScriptData, name: &str| script_data.get_nodes().iter().find(|n| n.get_string_data() == Some(name)).unwrap().get_index();
+
+    // Loaded functions and globals can be used, and replace built-in ones with the same name
+    let mut script_data = compile(CompileTarget::HaloCustomEdition).unwrap();
+    assert_eq!(Some(600), index_of(&script_data, "mod_execute"));
+    assert_eq!(Some(700), index_of(&script_data, "print"));
+    assert_eq!(None, index_of(&script_data, "mod_enabled"));
+
+    // Targets they are not available on don't have them
+    assert!(compile(CompileTarget::HaloCEA).is_err());
+    let errors = script_data.check_against_target(CompileTarget::HaloCEXboxPAL);
+    assert_eq!(vec!["mod_enabled", "print"], errors.iter().map(|e| e.get_message().split('\'').nth(1).unwrap()).collect::<Vec<_>>());
+
+    // Engines with a base use the base's indices
+    let mut compiler = Compiler::new(CompileTarget::HaloCEXboxPAL, CompileEncoding::Windows1252);
+    compiler.add_definitions(definitions).unwrap();
+    let expression = compiler.compile_expression("(mod_execute \"a\" 1)", ValueType::Boolean).unwrap();
+    assert_eq!(Some(601), expression.get_nodes()[0].get_index());
+    assert!(script_data.retarget(CompileTarget::HaloCEXboxPAL).is_err());
+
+    // Built-in engines keep their bases without being listed
+    let mut compiler = Compiler::new(CompileTarget::HaloCEXboxJP, CompileEncoding::UTF8);
+    compiler.add_definitions(r#"{"functions": [{"name": "mod_execute", "type": "boolean", "engines": {"xbox": 601}}]}"#).unwrap();
+    assert_eq!(Some(601), compiler.compile_expression("(mod_execute)", ValueType::Boolean).unwrap().get_nodes()[0].get_index());
+
+    // Nothing is loaded from documents that don't follow the schema
+    let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
+    assert_eq!("expected ',' or '}' at line 1, column 17", compiler.add_definitions(r#"{"functions": []"#).unwrap_err());
+    let error = compiler.add_definitions(r#"{"globals": [{"name": "a", "type": "short", "engines": {}}], "functions": [{"name": "b", "type": "number", "engines": {}}]}"#);
+    assert_eq!("function 'b': unknown type 'number'", error.unwrap_err());
+    assert_eq!("global 'a': expected the index for 'mcc-cea' to be null or a number from 0 to 65535", compiler.add_definitions(r#"{"globals": [{"name": "a", "type": "short", "engines": {"mcc-cea": -1}}]}"#).unwrap_err());
+    assert_eq!("global 'a': unknown engine 'halo2'", compiler.add_definitions(r#"{"globals": [{"name": "a", "type": "short", "engines": {"halo2": 5}}]}"#).unwrap_err());
+    assert_eq!("unknown engine 'halo2'", compiler.add_definitions(r#"{"engines": [{"identifier": "halo2", "base": "xbox"}]}"#).unwrap_err());
+    assert_eq!("engine 'xbox-pal': unknown engine 'halo2'", compiler.add_definitions(r#"{"engines": [{"identifier": "xbox-pal", "base": "halo2"}]}"#).unwrap_err());
+    assert!(compiler.compile_expression("a", ValueType::Short).is_err());
+}
+
+#[test]
+#[cfg(feature = "windows-1252")]
+fn test_register_function() {
+    // Takes any number of name and count pairs
+    struct Pairs;
+    impl UserFunction for Pairs {
+        fn get_name(&self) -> &str {
+            "mod_pairs"
+        }
+        fn get_return_type(&self) -> ValueType {
+            ValueType::Short
+        }
+        fn get_index(&self, target: CompileTarget) -> Option<u16> {
+            (target == CompileTarget::HaloCustomEdition).then_some(500)
+        }
+        fn get_minimum_parameter_count(&self) -> usize {
+            2
+        }
+        fn get_type_of_parameter(&self, index: usize) -> Option<ValueType> {
+            Some(if index.is_multiple_of(2) { ValueType::String } else { ValueType::Short })
+        }
+        fn is_uppercase_allowed_for_parameter(&self, index: usize) -> bool {
+            index == 0
+        }
+    }
+
+    let compile = |target: CompileTarget, script: &str| {
+        let mut compiler = Compiler::new(target, CompileEncoding::Windows1252);
+        compiler.register_function(Box::new(Pairs));
+        compiler.read_script_data("pairs.hsc", script.as_bytes()).unwrap();
+        compiler.compile_script_data()
+    };
+
+    let script_data = compile(CompileTarget::HaloCustomEdition, "(script static short a (mod_pairs \"A\" 1 \"b\" 2))").unwrap();
+    let call = script_data.get_nodes().iter().find(|n| n.get_string_data() == Some("mod_pairs")).unwrap();
+    assert_eq!(Some(500), call.get_index());
+    assert_eq!(Some("A"), script_data.get_nodes().iter().find_map(|n| n.get_string_data().filter(|s| s.eq_ignore_ascii_case("a"))));
+    assert!(script_data.check_against_target(CompileTarget::HaloCEA).iter().any(|e| e.get_message().contains("mod_pairs")));
+
+    // Parameters are typed by the function's rule
+    let error = compile(CompileTarget::HaloCustomEdition, "(script static short a (mod_pairs \"a\" 1 \"b\" \"c\"))").err().unwrap();
+    assert_eq!((1, 45), error.get_position());
+    let error = compile(CompileTarget::HaloCustomEdition, "(script static short a (mod_pairs \"a\"))").err().unwrap();
+    assert_eq!("function 'mod_pairs' takes at least 2 parameter(s), got 1 instead", error.get_message());
+
+    // Targets without an index don't have it
+    let error = compile(CompileTarget::HaloCEA, "(script static short a (mod_pairs \"a\" 1))").err().unwrap();
+    assert_eq!("function 'mod_pairs' is not defined", error.get_message());
+
+    // Signatures show parameters up to a limit, repeating the last one
+    let mut compiler = Compiler::new(CompileTarget::HaloCustomEdition, CompileEncoding::Windows1252);
+    compiler.register_function(Box::new(Pairs));
+    let help = compiler.signature_help(b"(mod_pairs \"a\" ", (1, 16)).unwrap();
+    assert_eq!("(mod_pairs <string> <short> [string] [short] [string] [short] [string] [short] [string] [short] [string] [short] [string] [short] [string] [short]...)", help.signature.to_call_string());
+    assert_eq!(ValueType::Short, help.signature.return_type);
+}
+
+#[test]
+#[cfg(feature = "windows-1252")]
+fn test_queries() {
+    let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
+    compiler.read_script_data("queries.hsc", b"(global short mission_state 0)
+(script static void place (ai_place \"a\") (set mission_state 1))
+(script dormant d (ai_place \"b\") (place))
+(script startup s (set mission_state (+ mission_state 1)) (wake d) (set cheat_deathless_player true))").unwrap();
+    let script_data = compiler.compile_script_data().unwrap();
+    let owners = |nodes: Vec<usize>| nodes.into_iter().map(|n| {
+        let entry = script_data.get_source_map_entry(n).unwrap();
+        (script_data.get_scripts()[entry.owner_index].get_name().to_owned(), entry.line, entry.column)
+    }).collect::<Vec<_>>();
+
+    assert_eq!(vec![("place".to_owned(), 2, 27), ("d".to_owned(), 3, 19)], owners(script_data.who_calls("ai_place")));
+    assert_eq!(vec![("d".to_owned(), 3, 34)], owners(script_data.who_calls("PLACE")));
+    assert_eq!(vec![("place".to_owned(), 2, 42), ("s".to_owned(), 4, 19)], owners(script_data.who_writes("mission_state")));
+    assert_eq!(vec![("s".to_owned(), 4, 68)], owners(script_data.who_writes("cheat_deathless_player")));
+
+    // Referencing a script is not calling it, and reading a global is not writing it
+    assert!(script_data.who_calls("d").is_empty());
+    assert!(script_data.who_writes("place").is_empty());
+}
+
+#[test]
+#[cfg(feature = "windows-1252")]
+fn test_code_actions() {
+    let apply = |script: &str, edits: &[TextEdit]| {
+        let mut script = script.to_owned();
+        for e in edits.iter().rev() {
+            let start = offset_of_position(script.as_bytes(), e.start);
+            let end = offset_of_position(script.as_bytes(), e.end);
+            script.replace_range(start..end, &e.text);
+        }
+        script
+    };
+
+    let compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
+    let script = "; Counts things\n(script static void count\n    (set counter (+ counter 1))) ; Adds one\n\n(script startup main\n    (print \"Hello\")\n    (count))\n(global short counter 0)\n(script dormant later (SLEEP 30))";
+    let actions = compiler.code_actions(script.as_bytes(), (1, 1));
+    assert_eq!(vec![CodeActionKind::SortGlobalsFirst, CodeActionKind::GroupScriptsByType, CodeActionKind::NormalizeCase], actions.iter().map(|a| a.kind).collect::<Vec<CodeActionKind>>());
+
+    // Comments move with the blocks, but what is between the blocks stays where it is
+    assert_eq!("(global short counter 0)\n\n; Counts things\n(script static void count\n    (set counter (+ counter 1))) ; Adds one\n(script startup main\n    (print \"Hello\")\n    (count))\n(script dormant later (SLEEP 30))", apply(script, &actions[0].edits));
+    assert_eq!("(script startup main\n    (print \"Hello\")\n    (count))\n\n(script dormant later (SLEEP 30))\n(global short counter 0)\n; Counts things\n(script static void count\n    (set counter (+ counter 1))) ; Adds one", apply(script, &actions[1].edits));
+
+    // Strings printed as is are left alone
+    assert_eq!(vec![TextEdit { start: (9, 24), end: (9, 29), text: "sleep".to_owned() }], actions[2].edits);
+
+    // Organized script data has nothing to organize, and unterminated blocks are never reordered
+    let organized = "(global short counter 0)\n(script startup main (sleep 30))\n(script static void count (set counter 1))";
+    assert!(compiler.code_actions(organized.as_bytes(), (1, 1)).is_empty());
+    assert!(compiler.code_actions(b"(script static void b (sleep 1))\n(global short a 0)\n(script static void c", (1, 1)).iter().all(|a| a.kind != CodeActionKind::SortGlobalsFirst));
+
+    // The whole chain is converted from wherever it is
+    let script = "(script static short pick\n    (if (= counter 0) 1 (if (= counter 1) (begin (print \"one\") 2) 3)))";
+    let expected = "(script static short pick\n    (cond\n        ((= counter 0) 1)\n        ((= counter 1) (print \"one\") 2)\n        (true 3)))";
+    for position in [(2, 6), (2, 30), (2, 65)] {
+        let actions = compiler.code_actions(script.as_bytes(), position);
+        assert_eq!(vec![CodeActionKind::ConvertToCond], actions.iter().map(|a| a.kind).collect::<Vec<CodeActionKind>>());
+        assert_eq!(expected, apply(script, &actions[0].edits));
+    }
+
+    // A single if is not a chain
+    assert!(compiler.code_actions(b"(script static short pick (if (= counter 0) 1 2))", (1, 30)).is_empty());
+}
+
+#[test]
+fn test_dialect() {
+    // Declarations in another order, reusing how names and types are read
+    struct NameFirstDialect;
+    impl Dialect for NameFirstDialect {
+        fn parse_declaration(&self, compiler: &mut Compiler, token: Token) -> Result<Declaration, CompileError> {
+            let children = token.children.as_ref().unwrap();
+            let name = compiler.lowercase_token(&children[1]);
+            let value_type = ValueType::from_str_underscore(&compiler.lowercase_token(&children[2])).unwrap();
+            Ok(Declaration::Global(Global { name, value_type, body_offset: 3, original_token: token, node: Node::default() }))
+        }
+    }
+
+    let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::UTF8);
+    compiler.read_script_data("dialect.hsc", b"(defglobal Counter short 1)").unwrap();
+    let token = compiler.tokens.remove(0);
+    match NameFirstDialect.parse_declaration(&mut compiler, token).unwrap() {
+        Declaration::Global(g) => {
+            assert_eq!(("counter", ValueType::Short), (g.name.as_str(), g.value_type));
+            assert_eq!("1", g.original_token.children.as_ref().unwrap()[g.body_offset].string);
+        },
+        Declaration::Script(_) => panic!("expected a global")
+    }
+
+    // Bodies start after the name, which differs by script type
+    compiler.read_script_data("dialect.hsc", b"(script static short (add (short a) (short b)) (+ a b))\n(script startup go (print \"go\"))").unwrap();
+    let mut offsets = Vec::new();
+    for token in std::mem::take(&mut compiler.tokens) {
+        match CompileTarget::HaloCEA.dialect().parse_declaration(&mut compiler, token).unwrap() {
+            Declaration::Script(s) => offsets.push(s.body_offset),
+            Declaration::Global(_) => panic!("expected a script")
+        }
+    }
+    assert_eq!(vec![4, 3], offsets);
+
+    compiler.read_script_data("dialect.hsc", b"(defglobal counter short 1)").unwrap();
+    let token = compiler.tokens.remove(0);
+    assert_eq!("expected_global_or_script", HaloCEDialect.parse_declaration(&mut compiler, token).err().unwrap().get_message_key());
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_serde() {
+    let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
+    compiler.read_script_data("hello_world.hsc", HELLO_WORLD_HSC).unwrap();
+    compiler.read_script_data("warnings.hsc", b"(global short Counter (+ 1 2))\n(script static short get_count Counter)").unwrap();
+    compiler.set_check_case(true);
+    let script_data = compiler.compile_script_data().unwrap();
+    assert!(!script_data.get_warnings().is_empty());
+
+    // Everything is the same after reloading, including the warnings
+    let json = serde_json::to_string(&script_data).unwrap();
+    let reloaded: CompiledScriptData = serde_json::from_str(&json).unwrap();
+    assert_eq!(json, serde_json::to_string(&reloaded).unwrap());
+    assert!(script_data.diff(&reloaded).is_empty());
+    assert_eq!(script_data.to_source(), reloaded.to_source());
+
+    let warning = &reloaded.get_warnings()[0];
+    assert_eq!(script_data.get_warnings()[0].get_message_key(), warning.get_message_key());
+    assert_eq!(script_data.get_warnings()[0].get_message(), warning.get_message());
+    assert_eq!(Some("Counter"), reloaded.get_nodes().iter().find_map(|n| n.get_original_string_data()));
+
+    // Message keys must be known
+    let json = serde_json::to_string(&script_data.get_warnings()[0]).unwrap();
+    let key = format!("\"{}\"", warning.get_message_key());
+    assert!(serde_json::from_str::<CompileError>(&json.replace(&key, "\"not_a_message\"")).is_err());
+}
+
+#[test]
+#[cfg(feature = "windows-1252")]
+fn test_tolerant() {
+    let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
+    compiler.set_tolerant(true);
+
+    // The unterminated blocks are closed at the end of the file, but each one is still an error
+    let error = compiler.read_script_data("tolerant.hsc", b"(global short a 1)\n(script static void b\n    (set a (+ a 1)\n    (print 1)").unwrap_err();
+    assert_eq!((2, 1), error.get_position());
+    assert_eq!(2, compiler.get_errors().len());
+    assert_eq!((3, 5), compiler.get_errors()[1].get_position());
+    assert_eq!("end of file reached here", compiler.get_errors()[1].get_related_locations()[0].get_message());
+
+    // Every script and global is still checked, including the contents of the unterminated blocks
+    compiler.read_script_data("tolerant2.hsc", b"(global bad c 1)\n(script static void d (print 1))\n(script static void e (sleep \"x\"))").unwrap();
+    assert!(compiler.compile_script_data().is_err());
+    let errors: Vec<(&str, (usize, usize))> = compiler.get_errors().iter().map(|e| (e.get_file(), e.get_position())).collect();
+    assert_eq!(vec![("tolerant.hsc", (2, 1)), ("tolerant.hsc", (3, 5)), ("tolerant2.hsc", (1, 9)), ("tolerant.hsc", (4, 5)), ("tolerant2.hsc", (3, 30))], errors);
+}
+
+#[test]
+#[cfg(feature = "windows-1252")]
+fn test_definition_and_hover() {
+    let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
+    compiler.read_script_data("read.hsc", b"(global short counter 0)").unwrap();
+    let script = b"(script static short (add (short a) (short b))\n    (+ a b counter))\n(script continuous c\n    (set counter (add 1 developer_mode)) (sleep 30";
+
+    // Globals defined in script data that was already read
+    let location = compiler.definition(script, (2, 14)).unwrap();
+    assert_eq!(Some("read.hsc"), location.file.as_deref());
+    assert_eq!((1, 15, (1, 22)), (location.line, location.column, location.end));
+    assert_eq!(CompletionKind::Global, location.kind);
+    assert_eq!(CompletionKind::Global, compiler.definition(script, (4, 10)).unwrap().kind);
+
+    // Scripts and parameters defined in the script data being edited
+    let location = compiler.definition(script, (4, 19)).unwrap();
+    assert_eq!((None, 1, 23, CompletionKind::Script), (location.file, location.line, location.column, location.kind));
+    let location = compiler.definition(script, (2, 8)).unwrap();
+    assert_eq!((1, 34, CompletionKind::Parameter), (location.line, location.column, location.kind));
+
+    // Names where they are defined refer to themselves
+    assert_eq!((1, 23), compiler.definition(script, (1, 24)).map(|l| (l.line, l.column)).unwrap());
+
+    // Engine functions and globals are not defined in script data, but they can be hovered over
+    assert!(compiler.definition(script, (2, 6)).is_none());
+    assert!(compiler.definition(script, (4, 25)).is_none());
+    let hover = compiler.hover(script, (4, 45)).unwrap();
+    assert_eq!(("sleep", CompletionKind::Function), (hover.name.as_str(), hover.kind));
+    assert_eq!("(sleep <short> [script])", hover.signature.unwrap().to_call_string());
+    let hover = compiler.hover(script, (4, 25)).unwrap();
+    assert_eq!((CompletionKind::Global, ValueType::Short, (4, 25), (4, 39)), (hover.kind, hover.value_type, (hover.line, hover.column), hover.end));
+
+    let hover = compiler.hover(script, (4, 20)).unwrap();
+    assert_eq!(CompletionKind::Script, hover.kind);
+    assert_eq!(ValueType::Short, hover.value_type);
+    assert_eq!("(add <short a> <short b>)", hover.signature.unwrap().to_call_string());
+    assert_eq!((CompletionKind::Parameter, ValueType::Short), compiler.hover(script, (2, 10)).map(|h| (h.kind, h.value_type)).unwrap());
+
+    // Keywords, types, literals, and whitespace
+    assert!(compiler.hover(script, (1, 3)).is_none());
+    assert!(compiler.hover(script, (1, 17)).is_none());
+    assert!(compiler.hover(script, (4, 50)).is_none());
+    assert!(compiler.hover(script, (3, 10)).is_none());
+    assert!(compiler.hover(script, (4, 2)).is_none());
+}
+
+#[test]
+#[cfg(feature = "windows-1252")]
+fn test_completions() {
+    let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
+    compiler.read_script_data("globals.hsc", b"(global unit player_unit none)\n(global short counter 0)").unwrap();
+
+    let script = b"(script static boolean (is_team (team t))\n    (ai_allegiance hu\n    (set counter \n    (sleep 1 ";
+    let labels = |position: (usize, usize)| -> Vec<String> { compiler.completions(script, position).into_iter().map(|c| c.label).collect() };
+
+    // Teams, including the script parameter, with the partially written token
+    assert_eq!(vec!["human"], labels((2, 22)));
+    let completions = compiler.completions(script, (2, 20));
+    assert!(completions.iter().any(|c| c.label == "t" && c.kind == CompletionKind::Parameter));
+    assert!(completions.iter().any(|c| c.label == "covenant" && c.kind == CompletionKind::Literal));
+    assert!(completions.iter().all(|c| c.value_type == ValueType::Team));
+
+    // Only globals of the same type can be set to anything but a literal
+    let completions = compiler.completions(script, (3, 18));
+    assert!(completions.iter().any(|c| c.label == "counter"));
+    assert!(!completions.iter().any(|c| c.label == "player_unit"));
+
+    // Any global can be set
+    assert!(labels((3, 10)).contains(&"player_unit".to_owned()));
+
+    // Scripts can be slept
+    assert_eq!(vec!["is_team"], labels((4, 14)));
+
+    // Functions are filtered by their return type
+    let completions = compiler.completions(b"(global unit a (unit_get_h", (1, 100));
+    assert!(completions.is_empty());
+    let completions = compiler.completions(b"(global real a (unit_get_h", (1, 100));
+    assert!(completions.iter().any(|c| c.label == "unit_get_health" && c.kind == CompletionKind::Function));
+
+    // Nothing at the top level or inside of a string
+    assert!(compiler.completions(b"(global short a 1) ", (1, 100)).is_empty());
+    assert!(compiler.completions(b"(script static void a (print \"hu", (1, 100)).is_empty());
+}
+
+#[test]
+fn test_function_snippet() {
+    let compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
+    assert_eq!(Some("(ai_place ${1:ai})".to_owned()), compiler.function_snippet("AI_PLACE"));
+    assert_eq!(Some("(sound_impulse_start ${1:sound} ${2:object} ${3:real})".to_owned()), compiler.function_snippet("sound_impulse_start"));
+    assert_eq!(Some("(game_difficulty_get)".to_owned()), compiler.function_snippet("game_difficulty_get"));
+
+    // Teams are chosen from their values, and optional parameters are left out
+    assert_eq!(Some("(ai_allegiance ${1|default,player,human,covenant,flood,sentinel,unused6,unused7,unused8,unused9|} ${2|default,player,human,covenant,flood,sentinel,unused6,unused7,unused8,unused9|})".to_owned()), compiler.function_snippet("ai_allegiance"));
+    assert_eq!(Some("(sleep ${1:short})".to_owned()), compiler.function_snippet("sleep"));
+
+    // Only engine functions of the target
+    assert_eq!(None, compiler.function_snippet("not_a_function"));
+
+    // Scripts use the names of their parameters, and special characters are escaped
+    let signature = FunctionSignature {
+        name: "a$b".to_owned(),
+        return_type: ValueType::Void,
+        parameters: vec![
+            SignatureParameter { name: Some("x}".to_owned()), value_type: ValueType::Short, optional: false, many: false },
+            SignatureParameter { name: None, value_type: ValueType::ObjectList, optional: false, many: true }
+        ]
+    };
+    assert_eq!("(a\\$b ${1:x\\}} ${2:object_list})", signature.to_snippet());
+}
+
+#[test]
+#[cfg(feature = "windows-1252")]
+fn test_semantic_tokens_and_html() {
+    let compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
+    let script = b"; counts things\n(global short counter 0)\n(script static void (bump (short n))\n    (set counter (+ counter n))\n    (print \"a<b\"))";
+
+    let types: Vec<(usize, usize, SemanticTokenType, bool)> = compiler.semantic_tokens("html.hsc", script).unwrap().iter().map(|t| (t.line, t.column, t.token_type, t.definition)).collect();
+    assert_eq!(vec![
+        (1, 1, SemanticTokenType::Comment, false),
+        (2, 2, SemanticTokenType::Keyword, false),
+        (2, 9, SemanticTokenType::Type, false),
+        (2, 15, SemanticTokenType::Global, true),
+        (2, 23, SemanticTokenType::Number, false),
+        (3, 2, SemanticTokenType::Keyword, false),
+        (3, 9, SemanticTokenType::Keyword, false),
+        (3, 16, SemanticTokenType::Type, false),
+        (3, 22, SemanticTokenType::Script, true),
+        (3, 28, SemanticTokenType::Type, false),
+        (3, 34, SemanticTokenType::Parameter, true),
+        (4, 6, SemanticTokenType::Function, false),
+        (4, 10, SemanticTokenType::Global, false),
+        (4, 19, SemanticTokenType::Function, false),
+        (4, 21, SemanticTokenType::Global, false),
+        (4, 29, SemanticTokenType::Parameter, false),
+        (5, 6, SemanticTokenType::Function, false),
+        (5, 12, SemanticTokenType::String, false)
+    ], types);
+
+    let html = compiler.export_html("html.hsc", script).unwrap();
+    assert!(html.starts_with("<pre class=\"hsc\"><span class=\"hsc-comment\">; counts things</span>\n(<span class=\"hsc-keyword\">global</span>"));
+    assert!(html.contains("<span class=\"hsc-global\" id=\"global-counter\">counter</span>"));
+    assert!(html.contains("<span class=\"hsc-script\" id=\"script-bump\">bump</span>"));
+    assert!(html.contains("(<span class=\"hsc-function\">set</span> <a class=\"hsc-global\" href=\"#global-counter\">counter</a>"));
+    assert!(html.ends_with("<span class=\"hsc-string\">&quot;a&lt;b&quot;</span>))</pre>"));
+
+    // Unterminated strings cannot be tokenized
+    assert!(compiler.export_html("html.hsc", b"(print \"a").is_err());
+}
+
+#[test]
+#[cfg(feature = "windows-1252")]
+fn test_compile_with_report() {
+    let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
+    compiler.read_script_data("report.hsc", b"(script static void beep (sound_impulse_start \"sound\\beep\" none 1))\n(script startup main (beep) (beep) (wake noise))\n(script dormant noise (sound_impulse_start \"sound\\beep\" none 1))").unwrap();
+    let report = compiler.compile_with_report();
+    assert!(report.is_success());
+    assert_eq!(["report.hsc"], report.get_files());
+    assert_eq!(report.get_script_data().unwrap().get_nodes().len(), report.get_statistics().unwrap().node_count);
+    assert_eq!(report.get_statistics().unwrap().node_count, report.get_source_map().len());
+
+    // Each tag and call is listed once
+    assert_eq!(1, report.get_tag_references().len());
+    assert_eq!((ValueType::Sound, "sound\\beep"), (report.get_tag_references()[0].value_type, report.get_tag_references()[0].path.as_str()));
+    let calls: Vec<(usize, &str, Option<usize>)> = report.get_call_graph().iter().map(|c| (c.caller_index, c.script.as_str(), c.script_index)).collect();
+    assert_eq!(vec![(1, "beep", Some(0)), (1, "noise", Some(2))], calls);
+
+    let json = report.to_json();
+    assert!(json.starts_with("{\"success\":true,\"files\":[\"report.hsc\"],\"diagnostics\":[],\"statistics\":{\"script_count\":3,"));
+    assert!(json.contains("\"tag_references\":[{\"type\":\"sound\",\"path\":\"sound\\\\beep\","));
+
+    // Failed compiles still report their errors
+    compiler.read_script_data("report.hsc", b"(script static void a (sleep \"x\"))").unwrap();
+    let report = compiler.compile_with_report();
+    assert!(!report.is_success() && report.get_statistics().is_none());
+    assert_eq!(1, report.get_diagnostics().len());
+    assert!(report.to_json().contains("\"statistics\":null"));
+}
+
+#[test]
+#[cfg(feature = "windows-1252")]
+fn test_message_catalog() {
+    let mut keys: Vec<&str> = DEFAULT_MESSAGES.iter().map(|m| m.0).collect();
+    keys.sort_unstable();
+    keys.dedup();
+    assert_eq!(DEFAULT_MESSAGES.len(), keys.len());
+
+    let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
+    compiler.read_script_data("catalog.hsc", b"(script static void a (not_a_function 1))").unwrap();
+    let error = compiler.compile_script_data().err().unwrap();
+    assert_eq!("function_not_defined", error.get_message_key());
+    assert_eq!(["not_a_function"], error.get_message_arguments());
+
+    // Messages without a translation stay in English
+    let catalog = MessageCatalog::parse("# German\nfunction_not_defined = Funktion '{0}' ist nicht definiert\n\nduplicate_script = {0}: {1}").unwrap();
+    assert_eq!("Funktion 'not_a_function' ist nicht definiert", error.get_localized_message(&catalog));
+    assert_eq!(error.get_message(), error.get_localized_message(&MessageCatalog::new()));
+    assert_eq!("{0}: {1}", catalog.format("duplicate_script", &[]));
+
+    assert!(MessageCatalog::parse("not_a_key = x").is_err());
+    assert!(MessageCatalog::parse("function_not_defined").is_err());
+}
+
+#[test]
+#[cfg(feature = "windows-1252")]
+fn test_diagnostic_limits() {
+    let script: String = (0..5).map(|i| format!("(global real r{i} 0.1234567891)\n")).collect();
+
+    let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
+    compiler.read_script_data("warnings.hsc", script.as_bytes()).unwrap();
+    assert_eq!(5, compiler.compile_script_data().unwrap().get_warnings().len());
+
+    // The summary error is at the first warning that was dropped
+    let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
+    compiler.set_maximum_warnings(Some(3));
+    compiler.read_script_data("warnings.hsc", script.as_bytes()).unwrap();
+    let error = compiler.compile_script_data().err().unwrap();
+    assert_eq!(CompileErrorCode::DiagnosticLimit, error.get_code());
+    assert_eq!("too_many_warnings", error.get_message_key());
+    assert_eq!((4, 17), error.get_position());
+
+    let script: String = (0..5).map(|i| format!("(script static void s{i} (not_a_function))\n")).collect();
+    let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
+    compiler.set_tolerant(true);
+    compiler.set_maximum_errors(Some(2));
+    compiler.read_script_data("errors.hsc", script.as_bytes()).unwrap();
+    let error = compiler.compile_script_data().err().unwrap();
+    assert_eq!("too_many_errors", error.get_message_key());
+    assert_eq!((3, 24), error.get_position());
+    assert_eq!(2, compiler.get_errors().len());
+}
+
+#[test]
+#[cfg(feature = "windows-1252")]
+fn test_shadowed_globals() {
+    let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
+    compiler.read_script_data("shadow.hsc", b"(global short a 1)\n(script static short (b (short a))\n    (+ a 1))\n(script static short (c (short a)) 2)").unwrap();
+    let script_data = compiler.compile_script_data().unwrap();
+
+    // Only the script that references the parameter is warned about
+    let warnings: Vec<&CompileError> = script_data.get_warnings().iter().filter(|w| w.get_code() == CompileErrorCode::ShadowedGlobal).collect();
+    assert_eq!(1, warnings.len());
+    assert_eq!((2, 32), warnings[0].get_position());
+    let related: Vec<(&str, (usize, usize))> = warnings[0].get_related_locations().iter().map(|r| (r.get_message(), r.get_position())).collect();
+    assert_eq!(vec![("global 'a' is defined here", (1, 1)), ("'a' is referenced here", (3, 8))], related);
+}
+
+#[test]
+#[cfg(feature = "windows-1252")]
+fn test_unused_parameters() {
+    let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
+    compiler.read_script_data("unused.hsc", b"(script static short (a (short used) (short unused))\n    (+ used 1))\n(script stub short (b (short placeholder)) 0)\n(script static short (c (short x)) (if (> x 0) x 0))").unwrap();
+    let script_data = compiler.compile_script_data().unwrap();
+
+    // Stubs are placeholders, so their parameters don't need to be referenced
+    let warnings = script_data.get_warnings();
+    assert_eq!(1, warnings.len());
+    assert_eq!(CompileErrorCode::UnusedParameter, warnings[0].get_code());
+    assert_eq!("parameter 'unused' of script 'a' is never referenced, so the script may not be called the way it is defined", warnings[0].get_message());
+    assert_eq!((1, 45), warnings[0].get_position());
+}
+
+#[test]
+#[cfg(feature = "windows-1252")]
+fn test_set_target() {
+    let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
+    let mut error_for = |expression: &str| compiler.compile_expression(expression, ValueType::Void).err().map(|e| (e.get_message_key().to_owned(), e.get_position()));
+    assert_eq!(Some(("set_target_is_function".to_owned(), (1, 6))), error_for("(set sleep 1)"));
+    assert_eq!(Some(("set_target_is_literal".to_owned(), (1, 6))), error_for("(set 1.5 1)"));
+    assert_eq!(Some(("set_target_is_literal".to_owned(), (1, 6))), error_for("(set none 1)"));
+    assert_eq!(Some(("not_a_global_did_you_mean".to_owned(), (1, 6))), error_for("(set cheat_bottomless_clipp true)"));
+    assert_eq!(Some(("not_a_global".to_owned(), (1, 6))), error_for("(set not_anything_at_all 1)"));
+
+    // Parameters can be set, even if they share a name with a global
+    compiler.read_script_data("set.hsc", b"(global real a 1)\n(script static real (b (short a))\n    (set a 2)\n    a)").unwrap();
+    let script_data = compiler.compile_script_data().unwrap();
+    let set_name = script_data.get_nodes().iter().position(|n| n.get_string_data() == Some("set")).unwrap();
+    assert_eq!(ValueType::Short, script_data.get_nodes()[set_name + 1].get_value_type());
+}
+
+#[test]
+#[cfg(feature = "windows-1252")]
+fn test_startup_initialization() {
+    let script = b"(global short a b)\n(global short b 0)\n(global short c d)\n(global short d 0)\n(global short e f)\n(global short f 0)\n\
+        (script startup init\n    (set b (+ b 1))\n    (set d 1)\n    (if (= a 0) (set f 1)))";
+    let uninitialized = |startup_initialization: bool| {
+        let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
+        compiler.set_startup_initialization(startup_initialization);
+        compiler.read_script_data("startup.hsc", script).unwrap();
+        compiler.compile_script_data().unwrap().get_warnings().iter()
+            .filter(|w| w.get_code() == CompileErrorCode::UninitializedGlobal)
+            .map(|w| w.get_position().0)
+            .collect::<Vec<usize>>()
+    };
+
+    // b is read before it is set, and f is only set conditionally, so only d is initialized by the startup script
+    assert_eq!(vec![1, 3, 5], uninitialized(false));
+    assert_eq!(vec![1, 5], uninitialized(true));
+}
+
+#[test]
+#[cfg(feature = "windows-1252")]
+fn test_unset_globals() {
+    let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
+    compiler.set_check_unset_globals(true);
+    compiler.read_script_data("unset.hsc", b"(global short a 0)\n(global short b 0)\n(script static short get_a a)\n\
+        (script continuous early (if (> (get_a) 0) (sleep 1)))\n\
+        (script startup init (set a 5) (sleep 30) (set b 1))\n\
+        (script continuous late (if (= b 0) (sleep 1)))\n\
+        (script continuous safe (set b 2) (sleep b))").unwrap();
+    let script_data = compiler.compile_script_data().unwrap();
+    let warnings: Vec<&CompileError> = script_data.get_warnings().iter().filter(|w| w.get_code() == CompileErrorCode::UnsetGlobal).collect();
+    let related = |warning: &CompileError| warning.get_related_locations().iter().map(|r| (r.get_message().to_owned(), r.get_position())).collect::<Vec<_>>();
+
+    // 'early' runs before 'init' and reads 'a' through 'get_a', while 'late' runs after it but 'init' sleeps before setting 'b'
+    assert_eq!(2, warnings.len());
+    assert_eq!("global 'a' may be read by 'early' before startup script 'init' sets it", warnings[0].get_message());
+    assert_eq!((3, 28), warnings[0].get_position());
+    assert_eq!(vec![("'get_a' is called here".to_owned(), (4, 33)), ("'a' is set here".to_owned(), (5, 27))], related(warnings[0]));
+    assert_eq!("global 'b' may be read by 'late' before startup script 'init' sets it", warnings[1].get_message());
+    assert_eq!((6, 32), warnings[1].get_position());
+}
+
+#[test]
+#[cfg(feature = "windows-1252")]
+fn test_literal_names() {
+    let compile = |script: &[u8], strictness: Strictness| {
+        let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
+        compiler.set_strictness(strictness);
+        compiler.read_script_data("literal_names.hsc", script).unwrap();
+        compiler.compile_script_data()
+    };
+
+    let error = compile(b"(global short 1 2)", Strictness::Strict).err().unwrap();
+    assert_eq!("global name '1' looks like a literal, so it would be parsed as one instead of referring to the global", error.get_message());
+    assert_eq!((1, 15), error.get_position());
+    assert_eq!("script_name_is_literal", compile(b"(script static boolean true false)", Strictness::Pedantic).err().unwrap().get_message_key());
+    assert_eq!("parameter_name_is_literal", compile(b"(script static short (a (short -2.5)) 0)", Strictness::Strict).err().unwrap().get_message_key());
+    assert!(compile(b"(global short x1 2)\n(global boolean on_fire off)", Strictness::Strict).is_ok());
+
+    // Words that Rust parses as numbers are not numbers in script data, so they are names
+    assert_eq!("script_name_is_literal", compile(b"(script static short 1e3 0)", Strictness::Strict).err().unwrap().get_message_key());
+    let script_data = compile(b"(global real inf 1.5)\n(global real nan (+ inf 1))\n(script static real (infinity (real x)) x)", Strictness::Pedantic).unwrap();
+    assert_eq!(3, script_data.get_globals().len() + script_data.get_scripts().len());
+    assert_eq!("cannot_parse_token", compile(b"(global real a inf)", Strictness::Strict).err().unwrap().get_message_key());
+
+    // The original tools accepted these, so they are only warnings if lenient
+    let script_data = compile(b"(global boolean none true)\n(script static short (a (short 1)) 1)", Strictness::Lenient).unwrap();
+    let warnings: Vec<(&str, (usize, usize))> = script_data.get_warnings().iter()
+        .filter(|w| w.get_code() == CompileErrorCode::LiteralName)
+        .map(|w| (w.get_message_key(), w.get_position()))
+        .collect();
+    assert_eq!(vec![("global_name_is_literal", (1, 17)), ("parameter_name_is_literal", (2, 32))], warnings);
+}
+
+#[test]
+#[cfg(feature = "windows-1252")]
+fn test_quoted_names() {
+    let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
+    compiler.read_script_data("quoted.hsc", b"(global object_name \"door (left);1\" \"door;main\")\n\
+        (script static void \"open door\" (object_create \"door (left);1\"))\n\
+        (script static short (twice (short \"a;b\")) (* \"a;b\" 2))\n\
+        (script startup a (\"open door\") (set \"door (left);1\" \"door (right);2\") (sleep (twice 1)))").unwrap();
+    let script_data = compiler.compile_script_data().unwrap();
+
+    // Quoted tokens can be used anywhere a name is expected, both literals and references to scripts, globals, and parameters
+    let nodes = script_data.get_nodes();
+    let find = |node_type: NodeType, string_data: &str| nodes.iter().filter(|n| n.get_type() == node_type && n.get_string_data() == Some(string_data)).count();
+    assert_eq!("door (left);1", script_data.get_globals()[0].get_name());
+    assert_eq!("open door", script_data.get_scripts()[0].get_name());
+    assert_eq!(2, find(NodeType::Primitive(PrimitiveType::Global), "door (left);1"));
+    assert_eq!(1, find(NodeType::Primitive(PrimitiveType::Static), "door;main"));
+    assert_eq!(1, find(NodeType::Primitive(PrimitiveType::Static), "door (right);2"));
+    assert_eq!(1, find(NodeType::Primitive(PrimitiveType::Local), "a;b"));
+    assert!(nodes.iter().any(|n| n.get_type() == NodeType::FunctionCall(false) && n.get_index() == Some(0)));
+
+    // Completions quote names that would otherwise be split
+    let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
+    compiler.read_script_data("quoted.hsc", b"(global object_name \"door (left);1\" none)").unwrap();
+    let completions = compiler.completions(b"(script startup a (object_create do", (1, 100));
+    assert_eq!(vec!["\"door (left);1\""], completions.iter().map(|c| c.label.as_str()).collect::<Vec<_>>());
+}
+
+#[test]
+#[cfg(feature = "windows-1252")]
+fn test_global_usage() {
+    let script = b"(global short a 1)\n(global short b 2)\n(global short c 3)\n(global short d (+ b 1))\n(script startup e\n    (set a 5)\n    (set c (+ d c)))";
+
+    let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
+    compiler.read_script_data("usage.hsc", script).unwrap();
+    assert!(compiler.compile_script_data().unwrap().get_warnings().is_empty());
+
+    // 'a' is only written, 'b' and 'd' are only read, and 'c' is both
+    let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
+    compiler.set_check_global_usage(true);
+    compiler.read_script_data("usage.hsc", script).unwrap();
+    let script_data = compiler.compile_script_data().unwrap();
+    let warnings: Vec<(&str, (usize, usize))> = script_data.get_warnings().iter().map(|w| (w.get_message_key(), w.get_position())).collect();
+    assert_eq!(vec![("global_never_read", (1, 1)), ("global_never_set", (2, 1)), ("global_never_set", (4, 1))], warnings);
+    assert_eq!((6, 10), script_data.get_warnings()[0].get_related_locations()[0].get_position());
+}
+
+#[test]
+#[cfg(feature = "windows-1252")]
+fn test_propagate_constants() {
+    let script = b"(global short a 2)\n(global real b a)\n(global short c 3)\n(global boolean d true)\n(script static real e (+ a b c))\n(script startup f (set c 4) (sleep_until d))";
+    let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
+    compiler.set_propagate_constants(true);
+    compiler.read_script_data("constants.hsc", script).unwrap();
+    let script_data = compiler.compile_script_data().unwrap();
+
+    // 'c' is set, so it stays, and 'b' is initialized with 'a', so it is propagated too
+    let propagated: Vec<(&str, usize, bool)> = script_data.get_propagated_globals().iter().map(|p| (p.get_name(), p.get_reference_count(), p.is_removed())).collect();
+    assert_eq!(vec![("a", 2, true), ("b", 1, true), ("d", 1, true)], propagated);
+    let globals: Vec<&str> = script_data.get_globals().iter().map(|g| g.get_name()).collect();
+    assert_eq!(vec!["c"], globals);
+
+    // 'b' is a real, so 'a' is converted to one when initializing it
+    let reals: Vec<Option<NodeData>> = script_data.get_nodes().iter().filter(|n| n.get_type() == NodeType::Primitive(PrimitiveType::Static) && n.get_value_type() == ValueType::Real).map(|n| n.get_data()).collect();
+    assert_eq!(vec![Some(NodeData::Real(2.0)), Some(NodeData::Real(2.0))], reals);
+}
+
+#[test]
+#[cfg(feature = "windows-1252")]
+fn test_tick_costs() {
+    let script = b"(script static boolean a (objects_can_see_flag (players) none 45))\n(script continuous b (if (a) (sleep 1)))\n(script dormant c (if (a) (sleep 1)))";
+    let compile = |threshold: Option<usize>| {
+        let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
+        compiler.set_tick_cost_threshold(threshold);
+        compiler.read_script_data("cost.hsc", script).unwrap();
+        compiler.compile_script_data().unwrap()
+    };
+
+    assert!(compile(None).get_warnings().is_empty());
+    assert!(compile(Some(100)).get_warnings().is_empty());
+
+    // The expensive call is in the static script, but only the continuous script is warned about
+    let script_data = compile(Some(10));
+    let warnings = script_data.get_warnings();
+    assert_eq!(1, warnings.len());
+    assert_eq!((CompileErrorCode::TickCost, (2, 1)), (warnings[0].get_code(), warnings[0].get_position()));
+    assert_eq!((1, 26), warnings[0].get_related_locations()[0].get_position());
+}
+
+#[test]
+#[cfg(feature = "windows-1252")]
+fn test_time_units() {
+    let script = b"(global real a (minutes 0.5))\n(script dormant b\n    (sleep (seconds 2.5))\n    (sleep 0)\n    (sleep -1)\n    (sleep (+ (seconds 1) 2)))";
+
+    let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
+    compiler.read_script_data("time.hsc", script).unwrap();
+    assert_eq!("function 'minutes' is not defined", compiler.compile_script_data().err().unwrap().get_message());
+
+    let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
+    compiler.set_time_units(true);
+    compiler.read_script_data("time.hsc", script).unwrap();
+    let script_data = compiler.compile_script_data().unwrap();
+    let data: Vec<NodeData> = script_data.get_nodes().iter().filter(|n| n.get_type() == NodeType::Primitive(PrimitiveType::Static) && n.get_value_type() != ValueType::FunctionName).filter_map(|n| n.get_data()).collect();
+    assert_eq!(vec![NodeData::Short(75), NodeData::Short(0), NodeData::Short(-1), NodeData::Real(30.0), NodeData::Real(2.0), NodeData::Real(900.0)], data);
+
+    // Only sleeping for 0 ticks is suspicious here
+    let warnings: Vec<(CompileErrorCode, (usize, usize))> = script_data.get_warnings().iter().map(|w| (w.get_code(), w.get_position())).collect();
+    assert_eq!(vec![(CompileErrorCode::SuspiciousSleep, (4, 12))], warnings);
+
+    assert!(compiler.compile_expression("(sleep (seconds x))", ValueType::Void).is_err());
+}
+
+#[test]
+#[cfg(feature = "windows-1252")]
+fn test_sleep_until_timeouts() {
+    let script = b"(global short period 30)\n(script dormant a\n    (sleep_until false 0)\n    (sleep_until false 30 0)\n    (sleep_until false 60 30)\n    (sleep_until false 15 30)\n    (sleep_until false period 0)\n    (sleep_until false period 15))";
+
+    let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
+    compiler.read_script_data("sleep_until.hsc", script).unwrap();
+    let script_data = compiler.compile_script_data().unwrap();
+
+    // Only literals are checked, so a period from a global can't exceed the timeout
+    let warnings: Vec<(&str, (usize, usize))> = script_data.get_warnings().iter().map(|w| (w.get_message_key(), w.get_position())).collect();
+    assert_eq!(vec![
+        ("suspicious_sleep_until_period", (3, 24)),
+        ("suspicious_sleep_until_timeout", (4, 27)),
+        ("sleep_until_period_exceeds_timeout", (5, 27)),
+        ("suspicious_sleep_until_timeout", (7, 31))
+    ], warnings);
+    assert!(script_data.get_warnings().iter().all(|w| w.get_code() == CompileErrorCode::SuspiciousSleep));
+
+    // Parameter counts are checked against the definitions
+    assert!(compiler.compile_expression("(sleep_until false 30 60 90)", ValueType::Void).is_err());
+}
+
+#[test]
+fn test_known_names() {
+    let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
+    compiler.set_known_names(ValueType::HudMessage, Some(&["obj_escape", "Obj_Defend"]));
+    compiler.set_known_names(ValueType::CutsceneTitle, Some(&["title_1"]));
+
+    assert!(compiler.compile_expression("(hud_set_objective_text obj_defend)", ValueType::Void).is_ok());
+    assert!(compiler.compile_expression("(cinematic_set_title title_1)", ValueType::Void).is_ok());
+    let error = compiler.compile_expression("(hud_set_help_text obj_escpae)", ValueType::Void).err().unwrap();
+    assert_eq!("'obj_escpae' is not a known hud message; did you mean 'obj_escape'?", error.get_message());
+    assert_eq!((1, 20), error.get_position());
+    assert_eq!("unknown_name", compiler.compile_expression("(cinematic_set_title something_else)", ValueType::Void).err().unwrap().get_message_key());
+
+    // Clearing the names stops checking them
+    compiler.set_known_names(ValueType::HudMessage, None);
+    assert!(compiler.compile_expression("(hud_set_help_text obj_escpae)", ValueType::Void).is_ok());
+}
+
+#[test]
+#[cfg(feature = "windows-1252")]
+fn test_diff() {
+    let compile = |script: &[u8]| {
+        let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
+        compiler.read_script_data("diff.hsc", script).unwrap();
+        compiler.compile_script_data().unwrap()
+    };
+
+    let old = compile(b"(global short a 1)\n(global short b 2)\n(script static void c (print \"c\") (sleep 5))\n(script static void d (sleep a))");
+    let new = compile(b"(global short b 2)\n(global long a 1)\n(script static void e (sleep 1))\n\n(script static void c (print \"c\") (sleep 6) (sleep 1))\n(script static void d (sleep a))");
+    assert!(old.diff(&old).is_empty());
+
+    let diffs = old.diff(&new);
+    let summary: Vec<(SymbolType, &str, SymbolChange, bool)> = diffs.iter().map(|d| (d.symbol_type, d.name.as_str(), d.change, d.signature_changed)).collect();
+    assert_eq!(vec![
+        (SymbolType::Script, "e", SymbolChange::Added, false),
+        (SymbolType::Script, "c", SymbolChange::Changed, false),
+        (SymbolType::Global, "a", SymbolChange::Changed, true)
+    ], summary);
+
+    // The changed sleep and the added sleep
+    let changes = &diffs[1].node_changes;
+    assert_eq!(2, changes.len());
+    assert_eq!(Some(ValueType::Short), changes[0].new_node.and_then(|n| new.get_node(n)).map(|n| n.get_value_type()));
+    assert_eq!((Some(5), None), (changes[0].new_node.and_then(|n| new.get_node(n)).map(|n| n.get_line()), changes[1].old_node));
+}
+
+#[test]
+#[cfg(feature = "windows-1252")]
+fn test_check_all() {
+    let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
+    compiler.set_tolerant(true);
+    compiler.read_script_data("clean.hsc", b"(global short a 1)").unwrap();
+    compiler.read_script_data("broken.hsc", b"(global short b c)\n(script static void d (sleep \"x\"))\n(script static void e\n    (print \"e\")").unwrap_err();
+    compiler.read_script_data("warning.hsc", b"(global short f g)\n(global short g 1)").unwrap();
+
+    let results = compiler.check_all();
+    let files: Vec<(&str, Vec<(usize, usize)>)> = results.iter().map(|r| (r.file.as_str(), r.diagnostics.iter().map(|d| d.get_position()).collect())).collect();
+    assert_eq!(vec![
+        ("clean.hsc", vec![]),
+        ("broken.hsc", vec![(1, 17), (2, 30), (3, 1)]),
+        ("warning.hsc", vec![(1, 17)])
+    ], files);
+    assert!(matches!(results[2].diagnostics[0].get_error_type(), CompileErrorType::Warning));
+
+    // Everything was cleared
+    assert!(compiler.get_errors().is_empty());
+    assert!(compiler.check_all().is_empty());
+}
+
+#[test]
+fn test_parse_script_data() {
+    let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::UTF8);
+    compiler.set_tolerant(true);
+    compiler.read_script_data("loaded.hsc", b"(global short z 0)").unwrap();
+    let clean = compiler.parse_script_data("clean.hsc", b"(global short a\n    1)");
+    let broken = compiler.parse_script_data("broken.hsc", b"(global short b c)\n(script static void d (sleep \"x\"))\n(script static void e\n    (print \"e\")");
+    let warning = compiler.parse_script_data("warning.hsc", b"(global short f g)\n(global short g 1)");
+    assert_eq!("broken.hsc", broken.get_filename());
+    assert!(clean.get_result().is_ok());
+    assert_eq!((3, 1), broken.get_result().unwrap_err().get_position());
+
+    // Nothing was loaded by parsing
+    assert_eq!(vec!["loaded.hsc"], compiler.check_all().iter().map(|r| r.file.as_str()).collect::<Vec<&str>>());
+
+    // Parsed script data can be read any number of times, in any order, and is checked as if it had been read directly
+    for _ in 0..2 {
+        let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::UTF8);
+        compiler.set_tolerant(true);
+        compiler.read_parsed_script_data(&warning).unwrap();
+        assert_eq!((3, 1), compiler.read_parsed_script_data(&broken).unwrap_err().get_position());
+        compiler.read_parsed_script_data(&clean).unwrap();
+        assert_eq!(1, compiler.folding_ranges(2).len());
+
+        let files: Vec<(String, Vec<(usize, usize)>)> = compiler.check_all().iter().map(|r| (r.file.clone(), r.diagnostics.iter().map(|d| d.get_position()).collect())).collect();
+        assert_eq!(vec![
+            ("warning.hsc".to_owned(), vec![(1, 17)]),
+            ("broken.hsc".to_owned(), vec![(1, 17), (2, 30), (3, 1)]),
+            ("clean.hsc".to_owned(), vec![])
+        ], files);
+    }
+
+    // Script data that could not be read at all still gives its error
+    let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::UTF8);
+    let unreadable = compiler.parse_script_data("unreadable.hsc", b"(global short a");
+    compiler.read_parsed_script_data(&clean).unwrap();
+    assert!(compiler.read_parsed_script_data(&unreadable).is_err());
+    assert_eq!(1, compiler.compile_script_data().unwrap().get_globals().len());
+}
+
+#[test]
+#[cfg(feature = "windows-1252")]
+fn test_regional_targets() {
+    let compile = |target: CompileTarget| {
+        let mut compiler = Compiler::new(target, CompileEncoding::Windows1252);
+        compiler.read_script_data("hello_world.hsc", HELLO_WORLD_HSC).unwrap();
+        compiler.compile_script_data().unwrap()
+    };
+    let indices = |script_data: &CompiledScriptData| script_data.get_nodes().iter().map(|n| n.get_index()).collect::<Vec<_>>();
+
+    // Without their own definitions, these targets are aliases of the builds they are based on
+    let ntsc = indices(&compile(CompileTarget::HaloCEXboxNTSC));
+    assert_eq!(ntsc, indices(&compile(CompileTarget::HaloCEXboxPAL)));
+    assert_eq!(ntsc, indices(&compile(CompileTarget::HaloCEXboxJP)));
+    assert_eq!(indices(&compile(CompileTarget::HaloCEGBXDemo)), indices(&compile(CompileTarget::HaloCEGBXDemoMac)));
+
+    // The Mac demo has the Gearbox demo's indices, not those of the retail version
+    let mut compiler = Compiler::new(CompileTarget::HaloCEGBXDemoMac, CompileEncoding::Windows1252);
+    compiler.read_script_data("checkpoint.hsc", b"(script static void save (checkpoint_save))").unwrap();
+    assert!(compiler.compile_script_data().unwrap().get_nodes().iter().any(|n| n.get_index() == Some(507)));
+
+    let mut compiler = Compiler::new(CompileTarget::HaloCEGBXDemoMac, CompileEncoding::Windows1252);
+    compiler.read_script_data("test_compatibility_gbx_only.hsc", include_bytes!("script/test_compatibility_gbx_only.hsc")).unwrap();
+    assert!(compiler.compile_script_data().is_ok());
+
+    assert_eq!("Halo: Combat Evolved (Xbox, PAL)", CompileTarget::HaloCEXboxPAL.to_string());
+    assert_eq!(32, CompileTarget::HaloCEXboxJP.recommended_maximum_continuous_scripts());
+}
+
+#[test]
+#[cfg(feature = "windows-1252")]
+fn test_custom_target() {
+    struct PatchedCustomEdition;
+    impl Target for PatchedCustomEdition {
+        fn get_name(&self) -> String {
+            "Halo Custom Edition (patched)".to_owned()
+        }
+        fn get_base_target(&self) -> CompileTarget {
+            CompileTarget::HaloCustomEdition
+        }
+        fn maximum_script_parameters(&self) -> usize {
+            4
+        }
+        fn maximum_nodes(&self) -> usize {
+            30000
+        }
+        fn maximum_name_length(&self) -> usize {
+            63
+        }
+    }
+
+    let script = b"(script static short (add_with_a_name_that_is_too_long_for_the_engine (short a) (short b)) (+ a b))\n(script startup main (add_with_a_name_that_is_too_long_for_the_engine 1 2))";
+    let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
+    compiler.read_script_data("patched.hsc", script).unwrap();
+    let error = compiler.compile_script_data().err().unwrap();
+    assert_eq!("script name 'add_with_a_name_that_is_too_long_for_the_engine' exceeds 31 characters in length", error.get_message());
+
+    // Limits come from the target, and everything else from its base target
+    let mut compiler = Compiler::with_target(Box::new(PatchedCustomEdition), CompileEncoding::Windows1252);
+    compiler.read_script_data("patched.hsc", script).unwrap();
+    let script_data = compiler.compile_script_data().unwrap();
+    assert!(script_data.get_target() == CompileTarget::HaloCustomEdition);
+    assert_eq!(30000, script_data.get_statistics().maximum_node_count);
+
+    let syntax_data = script_data.to_syntax_data(&PatchedCustomEdition).unwrap();
+    assert_eq!(&30000u16.to_be_bytes(), &syntax_data.syntax_data[0x20..0x22]);
+    let errors = script_data.check_against_target(CompileTarget::HaloCustomEdition);
+    assert_eq!(1, errors.len());
+    assert!(errors[0].get_message().ends_with("Halo: Custom Edition"));
+
+    // The custom target's limits only apply to its base target when compiling for several targets
+    let mut compiler = Compiler::with_target(Box::new(PatchedCustomEdition), CompileEncoding::UTF8);
+    compiler.read_script_data("patched.hsc", b"(script static short (add (short a) (short b)) (+ a b))").unwrap();
+    let results = compiler.compile_for_targets(&[CompileTarget::HaloCustomEdition, CompileTarget::HaloCEA]).unwrap();
+    assert_eq!(30000, results[0].get_statistics().maximum_node_count);
+    assert_eq!(CompileTarget::HaloCEA.maximum_nodes(), results[1].get_statistics().maximum_node_count);
+    assert_eq!(30000, compiler.target_limits().maximum_nodes());
+
+    // Built-in targets are targets of their own
+    assert_eq!(CompileTarget::HaloCEA.maximum_nodes(), Target::maximum_nodes(&CompileTarget::HaloCEA));
+    assert_eq!("Halo: Combat Evolved (Xbox)", CompileTarget::HaloCEXboxNTSC.get_name());
+}
+
+#[test]
+#[cfg(feature = "windows-1252")]
+fn test_script_counts() {
+    let compile = |target: CompileTarget, count: usize| {
+        let script: String = (0..count).map(|i| format!("(script continuous c{i} (sleep 1))\n")).collect();
+        let mut compiler = Compiler::new(target, CompileEncoding::Windows1252);
+        compiler.set_check_script_counts(true);
+        compiler.read_script_data("continuous.hsc", script.as_bytes()).unwrap();
+        compiler.compile_script_data().unwrap()
+    };
+
+    // This is disabled by default
+    let script: String = (0..33).map(|i| format!("(script continuous c{i} (sleep 1))\n")).collect();
+    let mut compiler = Compiler::new(CompileTarget::HaloCEXboxNTSC, CompileEncoding::UTF8);
+    compiler.read_script_data("continuous.hsc", script.as_bytes()).unwrap();
+    assert!(compiler.compile_script_data().unwrap().get_warnings().is_empty());
+
+    assert!(compile(CompileTarget::HaloCEXboxNTSC, 32).get_warnings().is_empty());
+    assert!(compile(CompileTarget::HaloCustomEdition, 33).get_warnings().is_empty());
+
+    // The warning points to the first script past the limit and lists all of them
+    let script_data = compile(CompileTarget::HaloCEXboxNTSC, 33);
+    let warnings = script_data.get_warnings();
+    assert_eq!(1, warnings.len());
+    assert_eq!((33, 1), warnings[0].get_position());
+    assert!(warnings[0].get_message().starts_with("33 continuous scripts are defined, but no more than 32 are recommended for "));
+    assert!(warnings[0].get_message().ends_with(": 'c0', 'c1', 'c2', 'c3', 'c4', 'c5', 'c6', 'c7', 'c8', 'c9', 'c10', 'c11', 'c12', 'c13', 'c14', 'c15', 'c16', 'c17', 'c18', 'c19', 'c20', 'c21', 'c22', 'c23', 'c24', 'c25', 'c26', 'c27', 'c28', 'c29', 'c30', 'c31', 'c32'"));
+}
+
+#[test]
+#[cfg(feature = "windows-1252")]
+fn test_void_script_return_type() {
+    let compile = |script: &[u8]| {
+        let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
+        compiler.read_script_data("void.hsc", script).unwrap();
+        compiler.compile_script_data()
+    };
+
+    let error = compile(b"(script startup short foo (sleep 1))").err().unwrap();
+    assert_eq!("startup scripts always return void and cannot have a return type (remove 'short'; expected (script startup <name> <expression(s)>))", error.get_message());
+    assert_eq!((1, 17), error.get_position());
+    assert!(compile(b"(script dormant VOID foo (sleep 1))").is_err());
+
+    // A script can still be named after a type if its body is a block
+    assert!(compile(b"(script continuous ai (sleep 1))").is_ok());
+
+    let error = compile(b"(script static foo)").err().unwrap();
+    assert_eq!("incomplete script definition, expected (script static <return type> <name> <expression(s)>)", error.get_message());
+}
+
+#[test]
+#[cfg(feature = "windows-1252")]
+fn test_return_type_mismatch() {
+    let compile = |script: &[u8]| {
+        let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
+        compiler.read_script_data("return.hsc", script).unwrap();
+        compiler.compile_script_data()
+    };
+
+    let error = compile(b"(global boolean b false)\n(script static short x\n    (sleep 1)\n    (not b))").err().unwrap();
+    assert_eq!("script 'x' declared to return 'short' but its final expression is 'boolean'", error.get_message());
+    assert_eq!((4, 5), error.get_position());
+    assert_eq!((2, 16), error.get_related_locations()[0].get_position());
+
+    let error = compile(b"(global boolean b false)\n(script static short x b)").err().unwrap();
+    assert_eq!("script 'x' declared to return 'short' but its final expression is 'boolean'", error.get_message());
+    assert_eq!((2, 24), error.get_position());
+
+    // Errors elsewhere are left alone
+    let error = compile(b"(script static short x (sleep \"x\") 1)").err().unwrap();
+    assert!(error.get_message().starts_with("cannot parse token 'x' as short"));
+}
+
+#[test]
+#[cfg(feature = "windows-1252")]
+fn test_cast_suggestion() {
+    let compile = |script: &[u8]| {
+        let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
+        compiler.read_script_data("cast.hsc", script).unwrap();
+        compiler.compile_script_data()
+    };
+
+    let error = compile(b"(global object o none)\n(script static real x (unit_get_health o))").err().unwrap();
+    assert_eq!("global 'o' is 'object' which cannot convert to 'unit' (note: use '(unit <object>)' to cast it)", error.get_message());
+    assert_eq!("global_cannot_convert_without_cast", error.get_message_key());
+
+    let error = compile(b"(script static real x (unit_get_health (list_get (players) 0)))").err().unwrap();
+    assert_eq!("function 'list_get' returns 'object' which cannot convert to 'unit' (note: use '(unit <object>)' to cast it)", error.get_message());
+    assert_eq!("function_cannot_convert_without_cast", error.get_message_key());
+
+    // There is no function to cast to a vehicle
+    let error = compile(b"(global object o none)\n(script static void x (vehicle_test_seat o \"\" none))").err().unwrap();
+    assert_eq!("global 'o' is 'object' which cannot convert to 'vehicle'", error.get_message());
+    assert_eq!("global_cannot_convert", error.get_message_key());
+}
+
+#[test]
+fn test_conversion_chain() {
+    assert_eq!(Some(ConversionRule::Identity), ValueType::conversion_chain(ValueType::Short, ValueType::Short));
+    assert_eq!(Some(ConversionRule::VoidDiscard), ValueType::conversion_chain(ValueType::Unit, ValueType::Void));
+    assert_eq!(Some(ConversionRule::Passthrough), ValueType::conversion_chain(ValueType::Passthrough, ValueType::Team));
+    assert_eq!(Some(ConversionRule::NumericConversion), ValueType::conversion_chain(ValueType::Short, ValueType::Real));
+    assert_eq!(Some(ConversionRule::NarrowingNumericConversion), ValueType::conversion_chain(ValueType::Long, ValueType::Short));
+    assert_eq!(Some(ConversionRule::ObjectWidening), ValueType::conversion_chain(ValueType::Vehicle, ValueType::Unit));
+    assert_eq!(Some(ConversionRule::ObjectWidening), ValueType::conversion_chain(ValueType::Unit, ValueType::ObjectList));
+
+    // Shorts cannot become longs, and objects cannot be narrowed
+    assert_eq!(None, ValueType::conversion_chain(ValueType::Short, ValueType::Long));
+    assert_eq!(None, ValueType::conversion_chain(ValueType::Object, ValueType::Unit));
+    assert!(!ValueType::Short.can_convert_to(ValueType::Long));
+}
+
+#[test]
+#[cfg(feature = "windows-1252")]
+fn test_pedantic_conversions() {
+    let compile = |script: &[u8], strictness: Strictness| {
+        let mut compiler = Compiler::new(CompileTarget::HaloCEA, CompileEncoding::Windows1252);
+        compiler.set_strictness(strictness);
+        compiler.read_script_data("pedantic.hsc", script).unwrap();
+        compiler.compile_script_data()
+    };
+
+    let script = b"(global real r 1.5)\n(global short s r)\n(global long l (real_random_range 0 1))\n(global real r2 s)";
+    assert!(compile(script, Strictness::Strict).is_ok());
+
+    let error = compile(script, Strictness::Pedantic).err().unwrap();
+    assert_eq!("global 'r' is 'real' which may lose precision when converted to 'short' (note: this is only an error in pedantic mode)", error.get_message());
+
+    let error = compile(b"(global long l (real_random_range 0 1))", Strictness::Pedantic).err().unwrap();
+    assert_eq!("function 'real_random_range' returns 'real' which may lose precision when converted to 'long' (note: this is only an error in pedantic mode)", error.get_message());
+
+    // Shorts can always be represented as reals
+    assert!(compile(b"(global short s 1)\n(global real r s)", Strictness::Pedantic).is_ok());
+}
+
+#[test]
+#[cfg(feature = "windows-1252")]
+fn test_bug_compatibility() {
+    let compile = |script: &[u8], bug_compatibility: BugCompatibility| {
+        let mut compiler = Compiler::new(CompileTarget::HaloCustomEdition, CompileEncoding::Windows1252);
+        compiler.set_bug_compatibility(bug_compatibility);
+        compiler.read_script_data("bugs.hsc", script).unwrap();
+        compiler.compile_script_data()
+    };
+    let defaults = BugCompatibility::default();
+
+    // The variable being set gets an index of 0xFFFF
+    let script = b"(global short s 1)\n(script startup a (set s 2))";
+    let set_variable_index = |script_data: &CompiledScriptData| script_data.get_nodes().iter().find(|n| n.get_string_data() == Some("s") && n.get_value_type() == ValueType::Short).unwrap().get_index();
+    assert_eq!(Some(0xFFFF), set_variable_index(&compile(script, defaults).unwrap()));
+    assert_eq!(None, set_variable_index(&compile(script, BugCompatibility { set_variable_index: false }).unwrap()));
+}
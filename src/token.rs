@@ -7,14 +7,195 @@ pub(crate) struct Token {
     pub file: usize,
     pub string: String,
 
-    pub children: Option<Vec<Token>>
+    pub children: Option<Vec<Token>>,
+    pub synthetic: bool, // if true, the block was never terminated and was closed at the end of the script
+    pub end: (usize, usize) // line and column just past the token (or the right parenthesis of a block)
+}
+
+impl Token {
+    /// Set the file index of the token and each of its children.
+    fn set_file(&mut self, file: usize) {
+        self.file = file;
+        for c in self.children.iter_mut().flatten() {
+            c.set_file(file);
+        }
+    }
+}
+
+/// Script data that was tokenized with [`Compiler::parse_script_data`], which can be read by a compiler without tokenizing it again.
+#[derive(Clone)]
+pub struct ParsedScriptData {
+    filename: String,
+    result: Result<(), CompileError>,
+    read: bool,
+    tokens: Vec<Token>,
+    folding_ranges: Vec<FoldingRange>,
+    warnings: Vec<CompileError>,
+    errors: Vec<CompileError>
+}
+
+impl ParsedScriptData {
+    /// Get the name of the file.
+    pub fn get_filename(&self) -> &str {
+        &self.filename
+    }
+
+    /// Get the result of reading the script data, which is returned again each time it is read.
+    pub fn get_result(&self) -> Result<(), &CompileError> {
+        self.result.as_ref().map(|_| ())
+    }
+}
+
+/// Number of blocks that can be nested within each other, including the top level block, before the nesting is warned about.
+///
+/// This is not a limit of the engine. Blocks are compiled recursively, so nesting much deeper than this can overflow the stack.
+pub(crate) const MAXIMUM_BLOCK_DEPTH: usize = 64;
+
+/// How a flat list of tokens is made into a tree.
+#[derive(Copy, Clone, Default)]
+pub(crate) struct TreeOptions {
+    /// Keep an empty block that isn't at the top level as a block without any children
+    pub allow_empty_blocks: bool,
+
+    /// Close any block that is still open at the end of the script and mark it as synthetic
+    pub close_unterminated: bool,
+
+    /// Fail on any block nested deeper than this, including the top level block
+    pub maximum_depth: Option<usize>
+}
+
+/// Result of tokenizing a script.
+pub(crate) struct TokenizedScript {
+    /// Flat list of tokens
+    pub tokens: Vec<Token>,
+
+    /// Line and column of the end of the script
+    pub end: (usize, usize),
+
+    /// First and last line of each multi line comment
+    pub multiline_comments: Vec<(usize, usize)>,
+
+    /// Line and column of the start of each comment, and the line and column just past its end
+    pub comments: Vec<((usize, usize), (usize, usize))>,
+
+    /// Warnings for tokens with bytes that could not be decoded
+    pub warnings: Vec<CompileError>
 }
 
 impl Compiler {
+    pub(super) fn parse_script_data_separately(&mut self, filename: &str, script: &[u8]) -> ParsedScriptData {
+        // Read it without anything else that was loaded, so only what it adds is kept
+        let tokens = std::mem::take(&mut self.tokens);
+        let files = std::mem::take(&mut self.files);
+        let folding_ranges = std::mem::take(&mut self.folding_ranges);
+        let warnings = std::mem::take(&mut self.warnings);
+        let errors = std::mem::take(&mut self.errors);
+
+        let result = self.read_script_data(filename, script);
+        let parsed = ParsedScriptData {
+            filename: filename.to_owned(),
+            result,
+            read: !self.files.is_empty(),
+            tokens: std::mem::replace(&mut self.tokens, tokens),
+            folding_ranges: std::mem::replace(&mut self.folding_ranges, folding_ranges).pop().unwrap_or_default(),
+            warnings: std::mem::replace(&mut self.warnings, warnings),
+            errors: std::mem::replace(&mut self.errors, errors)
+        };
+        self.files = files;
+        parsed
+    }
+
+    pub(super) fn read_parsed_script_data_tokens(&mut self, parsed: &ParsedScriptData) -> Result<(), CompileError> {
+        if parsed.read {
+            let file = self.files.len();
+            self.files.push(parsed.filename.clone());
+            self.folding_ranges.push(parsed.folding_ranges.clone());
+            self.tokens.extend(parsed.tokens.iter().cloned().map(|mut t| {
+                t.set_file(file);
+                t
+            }));
+        }
+        self.warnings.extend(parsed.warnings.iter().cloned());
+        self.errors.extend(parsed.errors.iter().cloned());
+        self.check_diagnostic_limits()?;
+        parsed.result.clone()
+    }
+
     pub(super) fn tokenize_script_data(&mut self, filename: &str, script: &[u8]) -> Result<(), CompileError> {
+        let TokenizedScript { tokens, end, multiline_comments, warnings, .. } = self.tokenize(filename, self.files.len(), script, self.encoding)?;
+        if !warnings.is_empty() {
+            self.warnings.extend(warnings);
+            self.check_diagnostic_limits()?;
+        }
+        let folding_ranges = find_folding_ranges(&tokens, &multiline_comments);
+        let options = TreeOptions { allow_empty_blocks: self.strictness == Strictness::Lenient, close_unterminated: self.tolerant, maximum_depth: self.maximum_block_depth };
+
+        // Keep any well-formed blocks if recovering from errors
+        if self.error_recovery || self.tolerant {
+            let (token_tree, mut errors) = build_token_tree_recovering(filename, tokens, end, options);
+
+            // Blocks closed at the end of the script are kept, but they are still errors
+            for t in &token_tree {
+                find_synthetic_blocks(filename, t, end, &mut errors);
+            }
+            errors.sort_by_key(|e| e.get_position());
+
+            self.warn_deeply_nested_blocks(filename, &token_tree)?;
+            self.files.push(filename.to_owned());
+            self.folding_ranges.push(folding_ranges);
+            self.tokens.extend(token_tree);
+
+            return match errors.first().cloned() {
+                Some(e) => {
+                    self.errors.extend(errors);
+                    self.check_diagnostic_limits()?;
+                    Err(e)
+                },
+                None => Ok(())
+            }
+        }
+
+        let token_tree = build_token_tree(filename, tokens, end, options)?;
+
+        self.warn_deeply_nested_blocks(filename, &token_tree)?;
+        self.files.push(filename.to_owned());
+        self.folding_ranges.push(folding_ranges);
+        self.tokens.extend(token_tree);
+
+        Ok(())
+    }
+
+    /// Warn about the outermost block nested deeper than [`MAXIMUM_BLOCK_DEPTH`] in each path of the token tree.
+    fn warn_deeply_nested_blocks(&mut self, filename: &str, token_tree: &[Token]) -> Result<(), CompileError> {
+        let mut warnings = Vec::<CompileError>::new();
+        for t in token_tree {
+            find_deeply_nested_blocks(filename, t, 1, &mut warnings);
+        }
+        if !warnings.is_empty() {
+            self.warnings.extend(warnings);
+            self.check_diagnostic_limits()?;
+        }
+        Ok(())
+    }
+
+    /// Split the script into a flat list of tokens, with each parenthesis being its own token.
+    pub(super) fn tokenize(&self, filename: &str, file: usize, script: &[u8], encoding: CompileEncoding) -> Result<TokenizedScript, CompileError> {
         let mut tokens = Vec::<Token>::new();
+        let mut multiline_comments = Vec::<(usize, usize)>::new();
+        let mut comments = Vec::<((usize, usize), (usize, usize))>::new();
+        let mut warnings = Vec::<CompileError>::new();
+
+        // Fail before reading anything if the encoding was not compiled in
+        if !encoding.is_enabled() {
+            return Err(CompileError::from_message(filename, 1, 1, CompileErrorType::Error, message!("encoding_disabled", encoding.name(), encoding.feature().unwrap_or_default())))
+        }
+
+        // Anything after the first null terminator is ignored if requested
+        let script = match script.iter().position(|c| *c == 0) {
+            Some(n) if self.null_ends_input => &script[..n],
+            _ => script
+        };
 
-        let file = self.files.len();
         let mut line : usize = 1;
         let mut column : usize = 0;
 
@@ -23,6 +204,11 @@ impl Compiler {
         let mut current_token_column : usize = 1;
         let mut current_token_offset : usize = 0;
 
+        // Where the current multi line comment started
+        let mut comment_line : usize = 1;
+        let mut comment_column : usize = 1;
+        let mut comment_offset : usize = 0;
+
         enum CurrentlyIn {
             Whitespace,
             Token(bool), // if true, the token is terminated by a ". if false, the token is terminated by a whitespace or comment
@@ -31,14 +217,14 @@ impl Compiler {
 
         let mut currently_in = CurrentlyIn::Whitespace;
 
-        const ASTERISK : u8 = '*' as u8;
+        const ASTERISK : u8 = b'*';
 
         let script_file_length = script.len();
 
         // Go through every character
         for i in 0..script_file_length {
             // Increment the column
-            column = column + 1;
+            column += 1;
 
             let mut add_token = || {
                 // Check if quoted
@@ -48,15 +234,16 @@ impl Compiler {
                 };
 
                 // Add it!
+                let end = (line, column + if quoted { 1 } else { 0 });
+                let bytes = &script[current_token_offset + if quoted { 1 } else { 0 }..i];
                 tokens.push(Token {
                     line: current_token_line,
                     column: current_token_column,
-                    file: file,
-                    string: match std::str::from_utf8(&script[current_token_offset + if quoted { 1 } else { 0 }..i]) {
-                        Ok(n) => n.to_owned(),
-                        Err(e) => return Err(CompileError::from_message(filename, line, column, CompileErrorType::Error, &format!("failed to decode token - {e}")))
-                    },
-                    children: None
+                    file,
+                    string: self.decode_token(filename, bytes, (current_token_line, current_token_column), end, encoding, &mut warnings)?,
+                    children: None,
+                    synthetic: false,
+                    end
                 });
 
                 // Done!
@@ -72,7 +259,7 @@ impl Compiler {
                     break
                 }
                 else {
-                    return Err(CompileError::from_message(filename, line, column, CompileErrorType::Error, "unexpected null terminator"))
+                    return Err(CompileError::from_message(filename, line, column, CompileErrorType::Error, message!("unexpected_null_terminator", i)))
                 }
             }
 
@@ -85,11 +272,13 @@ impl Compiler {
 
                 if matches!(currently_in, CurrentlyIn::Whitespace) {
                     tokens.push(Token {
-                        line: line,
-                        column: column,
-                        file: file,
+                        line,
+                        column,
+                        file,
                         string: c.to_string(),
-                        children: None
+                        children: None,
+                        synthetic: false,
+                        end: (line, column + 1)
                     });
                 }
             }
@@ -102,15 +291,20 @@ impl Compiler {
                     currently_in = CurrentlyIn::Whitespace;
                 }
 
-                // If it's a newline, advance the line by 1 and reset the column
-                if c == '\n' {
-                    line += 1;
+                // If it's a newline (\n, \r\n, or \r), advance the line by 1 and reset the column
+                if c == '\n' && i > 0 && script[i - 1] == b'\r' {
+                    // The line was already advanced by the \r
                     column = 0;
-
+                }
+                else if c == '\n' || c == '\r' {
                     // And if it's a single line comment, we're done
                     if matches!(currently_in, CurrentlyIn::Comment(false)) {
                         currently_in = CurrentlyIn::Whitespace;
+                        comments.push(((comment_line, comment_column), (line, column)));
                     }
+
+                    line += 1;
+                    column = 0;
                 }
             }
 
@@ -125,11 +319,16 @@ impl Compiler {
                 // Starting a comment?
                 if matches!(currently_in, CurrentlyIn::Whitespace) {
                     currently_in = CurrentlyIn::Comment(matches!(&script.get(i + 1), Some(&ASTERISK))); // check if the next character is an asterisk. if so, it's terminated by a *;
+                    comment_line = line;
+                    comment_column = column;
+                    comment_offset = i;
                 }
 
-                // Ending a multi line comment?
-                else if matches!(currently_in, CurrentlyIn::Comment(true)) && matches!(&script.get(i - 1), Some(&ASTERISK)) {
+                // Ending a multi line comment? The asterisk of the ;* that started the comment doesn't count, so ;*; does not end it.
+                else if matches!(currently_in, CurrentlyIn::Comment(true)) && i >= comment_offset + 3 && script[i - 1] == ASTERISK {
                     currently_in = CurrentlyIn::Whitespace;
+                    multiline_comments.push((comment_line, line));
+                    comments.push(((comment_line, comment_column), (line, column + 1)));
                 }
             }
 
@@ -148,74 +347,267 @@ impl Compiler {
             }
         }
 
+        // Unquoted tokens can end at the end of the script
+        if let CurrentlyIn::Token(false) = currently_in {
+            let end = if script.last() == Some(&0) { script_file_length - 1 } else { script_file_length };
+            let token_end = (line, end - current_token_offset + current_token_column);
+            tokens.push(Token {
+                line: current_token_line,
+                column: current_token_column,
+                file,
+                string: self.decode_token(filename, &script[current_token_offset..end], (current_token_line, current_token_column), token_end, encoding, &mut warnings)?,
+                children: None,
+                synthetic: false,
+                end: token_end
+            });
+        }
+
         // Did the token end prematurely?
-        if let CurrentlyIn::Token(_) = currently_in {
-            return Err(CompileError::from_message(filename, line, column, CompileErrorType::Error, "unterminated token"));
+        else if let CurrentlyIn::Token(_) = currently_in {
+            return Err(CompileError::from_message(filename, line, column, CompileErrorType::Error, message!("unterminated_token")));
+        }
+
+        // Did a multi line comment never end?
+        else if let CurrentlyIn::Comment(true) = currently_in {
+            return Err(CompileError::from_message(filename, comment_line, comment_column, CompileErrorType::Error, message!("unterminated_multiline_comment"))
+                .with_related_location(filename, line, column + 1, message!("end_of_file_reached_here")));
         }
 
-        // Make the tokens into a tree
-        let mut token_tree = Vec::<Token>::new();
-        let mut token_iter = tokens.into_iter();
+        // Single line comments can end at the end of the script
+        else if let CurrentlyIn::Comment(false) = currently_in {
+            comments.push(((comment_line, comment_column), (line, column + 1)));
+        }
+
+        Ok(TokenizedScript { tokens, end: (line, column + 1), multiline_comments, comments, warnings })
+    }
+
+    /// Decode the bytes of a token with the decode error policy, adding a warning if any of them could not be decoded.
+    fn decode_token(&self, filename: &str, bytes: &[u8], start: (usize, usize), end: (usize, usize), encoding: CompileEncoding, warnings: &mut Vec<CompileError>) -> Result<String, CompileError> {
+        let (string, invalid) = encoding.decode_from_bytes_with_policy(bytes, self.decode_error_policy).map_err(|e| {
+            CompileError::from_message(filename, start.0, start.1, CompileErrorType::Error, message!("decode_token_failed", e)).with_end_position(end.0, end.1)
+        })?;
 
-        loop {
-            let mut next_token = match token_iter.next() {
-                Some(n) => n,
-                None => break
+        if invalid > 0 {
+            let message = match self.decode_error_policy {
+                DecodeErrorPolicy::Skip => message!("decode_token_skipped", invalid, encoding.name()),
+                _ => message!("decode_token_replaced", invalid, encoding.name())
             };
+            warnings.push(CompileError::from_message(filename, start.0, start.1, CompileErrorType::Warning, message)
+                .with_code(CompileErrorCode::UndecodableToken)
+                .with_end_position(end.0, end.1));
+        }
 
-            match next_token.string.as_str() {
-                "(" => {
-                    fn recursively_add_token(compiler: &Compiler, token: &mut Token, token_iter: &mut std::vec::IntoIter<Token>, filename: &str) -> Result<(), CompileError> {
-                        let mut children = Vec::<Token>::new();
-                        loop {
-                            // Check if we have another token
-                            let mut next_token = match token_iter.next() {
-                                Some(n) => n,
-                                None => return Err(CompileError::from_message(filename, token.line, token.column, CompileErrorType::Error, "unterminated block"))
-                            };
-
-                            // See if it's a parenthesis
-                            match next_token.string.as_str() {
-                                // It's another block!
-                                "(" => {
-                                    recursively_add_token(compiler, &mut next_token, token_iter, filename)?;
-                                },
-
-                                // We're closing the block
-                                ")" => {
-                                    // Error if a block is empty
-                                    if children.is_empty() {
-                                        return Err(CompileError::from_message(filename, token.line, token.column, CompileErrorType::Error, "empty block"))
-                                    }
-
-                                    // Move the token
-                                    token.children = Some(children);
-
-                                    // Done!
-                                    return Ok(())
-                                },
-
-                                // Just an ordinary token with no children
-                                _ => ()
-                            }
-
-                            // Okay, add it now
-                            children.push(next_token);
-                        }
-                    }
-                    recursively_add_token(self, &mut next_token, &mut token_iter, &filename)?;
-                    token_tree.push(next_token)
-                }
+        Ok(string)
+    }
+}
 
-                n => {
-                    return Err(CompileError::from_message(filename, next_token.line, next_token.column, CompileErrorType::Error, &format!("expected left parenthesis, got {n} instead")))
-                }
+/// Find the folding ranges of the blocks and multi line comments from a flat list of tokens.
+///
+/// Only ranges that span multiple lines are returned, and unmatched parenthesis are ignored.
+fn find_folding_ranges(tokens: &[Token], multiline_comments: &[(usize, usize)]) -> Vec<FoldingRange> {
+    let mut ranges = Vec::new();
+    let mut open_blocks = Vec::<&Token>::new();
+
+    for t in tokens {
+        match t.string.as_str() {
+            "(" => open_blocks.push(t),
+            ")" => if let Some(start) = open_blocks.pop() {
+                let kind = if open_blocks.is_empty() { FoldingRangeKind::Definition } else { FoldingRangeKind::Block };
+                ranges.push(FoldingRange { kind, start_line: start.line, end_line: t.line });
+            },
+            _ => ()
+        }
+    }
+
+    ranges.extend(multiline_comments.iter().map(|&(start_line, end_line)| FoldingRange { kind: FoldingRangeKind::Comment, start_line, end_line }));
+    ranges.retain(|r| r.start_line < r.end_line);
+    ranges.sort_by_key(|r| (r.start_line, std::cmp::Reverse(r.end_line)));
+    ranges
+}
+
+/// Make the flat list of tokens into a tree, where each block's tokens are its children.
+///
+/// The end position is the line and column of the end of the script, which is reported for unterminated blocks.
+pub(super) fn build_token_tree(filename: &str, tokens: Vec<Token>, end: (usize, usize), options: TreeOptions) -> Result<Vec<Token>, CompileError> {
+    let mut token_tree = Vec::<Token>::new();
+    let mut index = 0;
+
+    while index < tokens.len() {
+        let (block, next_index) = build_top_level_block(filename, &tokens, index, end, false, options)?;
+        token_tree.push(block);
+        index = next_index;
+    }
+
+    Ok(token_tree)
+}
+
+/// Quote the name if it is empty or has anything that would end an unquoted token, such as whitespace, parentheses, or semicolons.
+pub(super) fn quote_name(name: &str) -> String {
+    if name.is_empty() || name.chars().any(|c| c.is_whitespace() || matches!(c, '(' | ')' | ';')) {
+        format!("\"{name}\"")
+    }
+    else {
+        name.to_owned()
+    }
+}
+
+/// Make the flat list of tokens into a tree like [`build_token_tree`], but skip any malformed top level block and keep going.
+///
+/// A left parenthesis at the start of a line is assumed to begin a top level block, so if one is found inside of a block, the block is
+/// treated as unterminated. After an error, tokenizing resumes at the next such parenthesis. Returns all well-formed blocks along with
+/// every error.
+pub(super) fn build_token_tree_recovering(filename: &str, tokens: Vec<Token>, end: (usize, usize), options: TreeOptions) -> (Vec<Token>, Vec<CompileError>) {
+    let mut token_tree = Vec::<Token>::new();
+    let mut errors = Vec::<CompileError>::new();
+    let mut index = 0;
+
+    while index < tokens.len() {
+        match build_top_level_block(filename, &tokens, index, end, true, options) {
+            Ok((block, next_index)) => {
+                token_tree.push(block);
+                index = next_index;
+            },
+            Err(e) => {
+                errors.push(e);
+                index = (index + 1..tokens.len()).find(|i| tokens[*i].string == "(" && tokens[*i].column == 1).unwrap_or(tokens.len());
             }
         }
+    }
 
-        self.files.push(filename.to_owned());
-        self.tokens.extend(token_tree);
+    (token_tree, errors)
+}
 
-        Ok(())
+/// Add an error for the block and each of its children that was closed at the end of the script.
+fn find_synthetic_blocks(filename: &str, token: &Token, end: (usize, usize), errors: &mut Vec<CompileError>) {
+    if token.synthetic {
+        errors.push(unterminated_block_error(filename, token, end));
+    }
+    for c in token.children.iter().flatten() {
+        find_synthetic_blocks(filename, c, end, errors);
+    }
+}
+
+/// Add a warning for the block if it is nested deeper than [`MAXIMUM_BLOCK_DEPTH`], or else check each of its children.
+fn find_deeply_nested_blocks(filename: &str, token: &Token, depth: usize, warnings: &mut Vec<CompileError>) {
+    let children = match token.children.as_ref() {
+        Some(n) => n,
+        None => return
+    };
+    if depth > MAXIMUM_BLOCK_DEPTH {
+        warnings.push(CompileError::from_message(filename, token.line, token.column, CompileErrorType::Warning, message!("block_nested_deeply", MAXIMUM_BLOCK_DEPTH))
+            .with_code(CompileErrorCode::DeepNesting)
+            .with_end_position(token.end.0, token.end.1));
+        return
+    }
+    for c in children {
+        find_deeply_nested_blocks(filename, c, depth + 1, warnings);
+    }
+}
+
+/// Build the top level block starting at the given index, returning the block and the index of the token after it.
+fn build_top_level_block(filename: &str, tokens: &[Token], index: usize, end: (usize, usize), recovering: bool, options: TreeOptions) -> Result<(Token, usize), CompileError> {
+    let first_token = &tokens[index];
+    match first_token.string.as_str() {
+        "(" => {
+            let (block, next_index) = build_block(filename, tokens, index, end, recovering, options, 1).map_err(|e| match e {
+                // Point to the top level block if we found the start of the next one
+                BlockError::NextTopLevelBlock(next_block) => CompileError::from_message(filename, first_token.line, first_token.column, CompileErrorType::Error, message!("unterminated_block"))
+                    .with_related_location(filename, next_block.line, next_block.column, message!("next_block_starts_here")),
+                BlockError::Error(e) => e
+            })?;
+
+            // Top level blocks can never be empty
+            if block.children.as_ref().unwrap().is_empty() {
+                return Err(empty_block_error(filename, &block))
+            }
+
+            Ok((block, next_index))
+        },
+        n => Err(CompileError::from_message(filename, first_token.line, first_token.column, CompileErrorType::Error, message!("expected_left_parenthesis", n)))
+    }
+}
+
+/// Get the error for an empty block.
+fn empty_block_error(filename: &str, token: &Token) -> CompileError {
+    CompileError::from_message(filename, token.line, token.column, CompileErrorType::Error, message!("empty_block"))
+}
+
+/// Get the error for a block that was still open at the end of the script.
+fn unterminated_block_error(filename: &str, token: &Token, end: (usize, usize)) -> CompileError {
+    CompileError::from_message(filename, token.line, token.column, CompileErrorType::Error, message!("unterminated_block"))
+        .with_related_location(filename, end.0, end.1, message!("end_of_file_reached_here"))
+}
+
+/// Reason a block could not be built.
+enum BlockError {
+    /// A left parenthesis at the start of a line was found while recovering from errors.
+    NextTopLevelBlock(Token),
+
+    /// The block is malformed.
+    Error(CompileError)
+}
+
+/// Build the block whose left parenthesis is at the given index, returning the block and the index of the token after it.
+fn build_block(filename: &str, tokens: &[Token], index: usize, end: (usize, usize), recovering: bool, options: TreeOptions, depth: usize) -> Result<(Token, usize), BlockError> {
+    let mut token = tokens[index].clone();
+
+    // Blocks are compiled recursively, so don't let them nest deep enough to overflow the stack if asked to
+    if let Some(maximum_depth) = options.maximum_depth.filter(|m| depth > *m) {
+        return Err(BlockError::Error(CompileError::from_message(filename, token.line, token.column, CompileErrorType::Error, message!("block_nested_too_deeply", maximum_depth))))
+    }
+
+    let mut children = Vec::<Token>::new();
+    let mut next_index = index + 1;
+
+    loop {
+        // Check if we have another token
+        let next_token = match tokens.get(next_index) {
+            Some(n) => n,
+
+            // Close it here if requested
+            None if options.close_unterminated => {
+                if children.is_empty() && !options.allow_empty_blocks {
+                    return Err(BlockError::Error(empty_block_error(filename, &token)))
+                }
+                token.children = Some(children);
+                token.synthetic = true;
+                token.end = end;
+                return Ok((token, next_index))
+            },
+
+            None => return Err(BlockError::Error(unterminated_block_error(filename, &token, end)))
+        };
+
+        // See if it's a parenthesis
+        match next_token.string.as_str() {
+            // It's another block!
+            "(" if recovering && next_token.column == 1 => return Err(BlockError::NextTopLevelBlock(next_token.clone())),
+            "(" => {
+                let (block, after_block) = build_block(filename, tokens, next_index, end, recovering, options, depth + 1)?;
+                children.push(block);
+                next_index = after_block;
+            },
+
+            // We're closing the block
+            ")" => {
+                // Error if a block is empty
+                if children.is_empty() && !options.allow_empty_blocks {
+                    return Err(BlockError::Error(empty_block_error(filename, &token)))
+                }
+
+                // Move the tokens
+                token.children = Some(children);
+                token.end = next_token.end;
+
+                // Done!
+                return Ok((token, next_index + 1))
+            },
+
+            // Just an ordinary token with no children
+            _ => {
+                children.push(next_token.clone());
+                next_index += 1;
+            }
+        }
     }
 }
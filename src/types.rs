@@ -1,9 +1,16 @@
 use super::*;
 use std::fmt::Display;
+use std::ffi::{CString, CStr};
+
+#[cfg(feature = "windows-1252")]
+use encoding::{Encoding, EncoderTrap, DecoderTrap};
+#[cfg(feature = "windows-1252")]
+use encoding::all::WINDOWS_1252;
 
 /// Compile target to use. This determines available features, such as functions.
 #[derive(Copy, Clone, PartialEq)]
 #[repr(C)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum CompileTarget {
     /// Halo: Combat Evolved Anniversary as released by 343 Industries for Windows.
     HaloCEA,
@@ -13,18 +20,37 @@ pub enum CompileTarget {
 
     /// Halo: Combat Evolved as released by Gearbox and MacSoft on Windows and Mac OS X, respectively.
     ///
-    /// This also applies to the demo released by MacSoft.
+    /// The demo released by MacSoft has its own target, [`CompileTarget::HaloCEGBXDemoMac`].
     HaloCEGBX,
 
     /// Halo: Combat Evolved demo as released by Gearbox on Windows.
     ///
     /// This also applies to the un-updated CD version by Gearbox on Windows.
     ///
-    /// This does not apply to the demo released by MacSoft for Mac OS X, as it's based on a newer version.
+    /// The demo released by MacSoft for Mac OS X has its own target, [`CompileTarget::HaloCEGBXDemoMac`], though it is currently an alias
+    /// of this one.
     HaloCEGBXDemo,
 
     /// Halo Custom Edition as released by Gearbox on Windows.
     HaloCustomEdition,
+
+    /// Halo: Combat Evolved demo as released by MacSoft on Mac OS X.
+    ///
+    /// This is an alias of [`CompileTarget::HaloCEGBXDemo`]. The built-in definitions have no data for this build of its own, so its
+    /// functions and globals are those of the Gearbox demo unless added definitions say otherwise.
+    HaloCEGBXDemoMac,
+
+    /// PAL Xbox version.
+    ///
+    /// This is an alias of [`CompileTarget::HaloCEXboxNTSC`]. The built-in definitions have no data for this build of its own, so its
+    /// functions and globals are those of the NTSC version unless added definitions say otherwise.
+    HaloCEXboxPAL,
+
+    /// Japanese Xbox version.
+    ///
+    /// This is an alias of [`CompileTarget::HaloCEXboxNTSC`]. The built-in definitions have no data for this build of its own, so its
+    /// functions and globals are those of the NTSC version unless added definitions say otherwise.
+    HaloCEXboxJP,
 }
 
 impl CompileTarget {
@@ -35,45 +61,331 @@ impl CompileTarget {
             _ => 0
         }
     }
+
+    /// Get the dialect used to declare scripts and globals for the target engine.
+    pub(crate) fn dialect(&self) -> &'static dyn Dialect {
+        &HaloCEDialect
+    }
+
+    /// Get the maximum number of script nodes supported for the target engine.
+    pub fn maximum_nodes(&self) -> usize {
+        match *self {
+            CompileTarget::HaloCEA => i16::MAX as usize,
+            _ => 19001
+        }
+    }
+
+    /// Get the maximum length of script string data, in bytes, supported for the target engine.
+    pub fn maximum_string_data_length(&self) -> usize {
+        262144
+    }
+
+    /// Get the recommended maximum number of continuous scripts for the target engine.
+    ///
+    /// Continuous scripts are evaluated every tick, so having many of them can slow down the game. This is a rough guideline, not a
+    /// limit enforced by the engine.
+    pub fn recommended_maximum_continuous_scripts(&self) -> usize {
+        match *self {
+            CompileTarget::HaloCEXboxNTSC | CompileTarget::HaloCEXboxPAL | CompileTarget::HaloCEXboxJP => 32,
+            _ => 64
+        }
+    }
+
+    /// Get the recommended maximum number of dormant scripts for the target engine.
+    ///
+    /// Each dormant script holds onto a script thread. This is a rough guideline, not a limit enforced by the engine.
+    pub fn recommended_maximum_dormant_scripts(&self) -> usize {
+        128
+    }
+
+    /// Get the maximum length of the name of a script or global, in bytes, supported for the target engine.
+    pub fn maximum_name_length(&self) -> usize {
+        31
+    }
+}
+
+/// Engine that script data can be compiled for, such as one patched by a mod to raise its limits.
+///
+/// Each target is based on a built-in [`CompileTarget`], whose engine functions and globals, script syntax, and node layout are used.
+/// Every limit defaults to the limit of the base target, so only the ones that differ need to be implemented. Every [`CompileTarget`]
+/// is a target based on itself.
+#[allow(unused_variables)]
+pub trait Target: Send + Sync {
+    /// Get the name of the target, as shown in diagnostics.
+    fn get_name(&self) -> String;
+
+    /// Get the built-in target this target is based on.
+    ///
+    /// Engine functions and globals are available and have the same indices as on the base target, so a function added by a mod can
+    /// be loaded with [`Compiler::add_definitions`] using the base target's column.
+    fn get_base_target(&self) -> CompileTarget;
+
+    /// Get the maximum number of script parameters supported.
+    fn maximum_script_parameters(&self) -> usize {
+        self.get_base_target().maximum_script_parameters()
+    }
+
+    /// Get the maximum number of script nodes supported, which is also the size of the node table written to a scenario tag.
+    fn maximum_nodes(&self) -> usize {
+        self.get_base_target().maximum_nodes()
+    }
+
+    /// Get the maximum length of script string data, in bytes, supported.
+    fn maximum_string_data_length(&self) -> usize {
+        self.get_base_target().maximum_string_data_length()
+    }
+
+    /// Get the maximum length of the name of a script or global, in bytes, supported.
+    fn maximum_name_length(&self) -> usize {
+        self.get_base_target().maximum_name_length()
+    }
+
+    /// Get the recommended maximum number of continuous scripts.
+    fn recommended_maximum_continuous_scripts(&self) -> usize {
+        self.get_base_target().recommended_maximum_continuous_scripts()
+    }
+
+    /// Get the recommended maximum number of dormant scripts.
+    fn recommended_maximum_dormant_scripts(&self) -> usize {
+        self.get_base_target().recommended_maximum_dormant_scripts()
+    }
+}
+
+impl Target for CompileTarget {
+    fn get_name(&self) -> String {
+        self.to_string()
+    }
+
+    fn get_base_target(&self) -> CompileTarget {
+        *self
+    }
+}
+
+impl<T: Target + ?Sized> Target for &T {
+    fn get_name(&self) -> String {
+        (**self).get_name()
+    }
+    fn get_base_target(&self) -> CompileTarget {
+        (**self).get_base_target()
+    }
+    fn maximum_script_parameters(&self) -> usize {
+        (**self).maximum_script_parameters()
+    }
+    fn maximum_nodes(&self) -> usize {
+        (**self).maximum_nodes()
+    }
+    fn maximum_string_data_length(&self) -> usize {
+        (**self).maximum_string_data_length()
+    }
+    fn maximum_name_length(&self) -> usize {
+        (**self).maximum_name_length()
+    }
+    fn recommended_maximum_continuous_scripts(&self) -> usize {
+        (**self).recommended_maximum_continuous_scripts()
+    }
+    fn recommended_maximum_dormant_scripts(&self) -> usize {
+        (**self).recommended_maximum_dormant_scripts()
+    }
+}
+
+/// Kind of a [`FoldingRange`].
+#[derive(Copy, Clone, PartialEq, Debug)]
+#[repr(C)]
+pub enum FoldingRangeKind {
+    /// Top level block, such as a script or global definition.
+    Definition,
+
+    /// Block inside of another block.
+    Block,
+
+    /// Multi line comment.
+    Comment
+}
+
+/// Range of lines that an editor can fold.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct FoldingRange {
+    /// What is being folded.
+    pub kind: FoldingRangeKind,
+
+    /// First line of the range, starting at 1.
+    pub start_line: usize,
+
+    /// Last line of the range, starting at 1.
+    pub end_line: usize
+}
+
+/// Errors and warnings of a file, as returned by [`Compiler::check_all`](crate::Compiler::check_all).
+#[derive(Clone, Debug)]
+pub struct FileDiagnostics {
+    /// Name of the file.
+    pub file: String,
+
+    /// Errors and warnings in the file, sorted by position.
+    pub diagnostics: Vec<CompileError>
+}
+
+/// Known quirks of the original tools, which the compiler can either reproduce or leave out.
+///
+/// By default, every quirk is reproduced.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct BugCompatibility {
+    /// Set the index of the variable passed to `set` to 0xFFFF, like the original tools. If disabled, the index is left unset, like
+    /// any other variable reference.
+    pub set_variable_index: bool
+}
+
+impl Default for BugCompatibility {
+    fn default() -> BugCompatibility {
+        BugCompatibility {
+            set_variable_index: true
+        }
+    }
 }
 
+/// How strictly questionable constructs that the original tools accepted are handled.
+#[derive(Copy, Clone, PartialEq, Debug, Default)]
+#[repr(C)]
+pub enum Strictness {
+    /// Questionable constructs are errors.
+    #[default]
+    Strict,
+
+    /// Questionable constructs are compiled where possible with a warning, such as an empty `()` block being compiled as `(begin)`.
+    Lenient,
+
+    /// Questionable constructs are errors, and so are conversions that the engine allows but which may lose precision, such as a real
+    /// being converted to a short.
+    Pedantic
+}
+
+/// How compiled script data is laid out, as set with [`Compiler::set_output_mode`](crate::Compiler::set_output_mode).
+#[derive(Copy, Clone, PartialEq, Debug, Default)]
+#[repr(C)]
+pub enum OutputMode {
+    /// Save nodes where possible, such as by replacing `begin` blocks that only have one expression with that expression.
+    #[default]
+    Optimize,
+
+    /// Lay out nodes in source order without optimizing anything.
+    ///
+    /// The nodes of each script and global are emitted in the order they appear in the source rather than all scripts first, and
+    /// `begin` blocks are kept. This has not been checked against output from the original tools, so it is not known to match them.
+    SourceOrder
+}
+
+/// Soft limits, as a fraction of the target engine's maximums, at which compiling emits a warning.
+///
+/// For example, a threshold of `0.8` warns when 80% of the maximum is used. A threshold of `None` disables the warning.
+#[derive(Copy, Clone, PartialEq, Debug, Default)]
+pub struct BudgetThresholds {
+    /// Threshold for the number of script nodes.
+    pub nodes: Option<f64>,
+
+    /// Threshold for the length of script string data.
+    pub string_data: Option<f64>
+}
+
+/// Phase of compilation reported by [`Compiler::set_trace_callback`].
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum CompilePhase {
+    /// Script data is split into tokens and blocks. The count is the number of bytes read.
+    Tokenize,
+
+    /// Top level blocks are parsed into scripts and globals. The count is the number of scripts and globals.
+    Parse,
+
+    /// Expressions are type checked and made into nodes. The count is the number of scripts and globals.
+    TypeCheck,
+
+    /// Scripts and globals are checked for warnings, duplicates, and limits. The count is the number of scripts and globals.
+    Lint,
+
+    /// Nodes are resolved and emitted. The count is the number of nodes emitted, or the number of scripts and globals if emitted with
+    /// [`Compiler::compile_with_backend`].
+    Emit
+}
+
+/// Time taken by a phase of compilation, as reported by [`Compiler::set_trace_callback`].
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct PhaseTiming {
+    /// Phase that was completed.
+    pub phase: CompilePhase,
+
+    /// Time the phase took.
+    pub duration: std::time::Duration,
+
+    /// Number of items processed by the phase, which depends on the phase.
+    pub count: usize,
+
+    /// Memory held once the phase completed. This is the tokens read so far after tokenizing, the tokens and node trees of the
+    /// scripts and globals after parsing, type checking, and linting, and the compiled nodes after emitting.
+    pub memory: MemoryUsage
+}
+
+/// Approximate heap memory used by compilation, in bytes, as reported by [`PhaseTiming`] and [`ScriptDataStatistics`].
+///
+/// Only tokens, nodes, and their strings are counted, so this is meant for tuning limits such as the maximum block depth and budgets
+/// on memory-constrained hosts rather than being an exact figure.
+#[derive(Copy, Clone, PartialEq, Debug, Default)]
+#[repr(C)]
+pub struct MemoryUsage {
+    /// Bytes allocated for tokens, not including their strings.
+    pub tokens: usize,
+
+    /// Bytes allocated for nodes, not including their strings.
+    pub nodes: usize,
+
+    /// Bytes allocated for the strings of tokens and nodes.
+    pub strings: usize
+}
+
+/// Function called with the time taken by each phase of compilation, as set with [`Compiler::set_trace_callback`].
+pub type TraceCallback = Box<dyn FnMut(&PhaseTiming) + Send + Sync>;
+
 impl Display for CompileTarget {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
         let name = match *self {
             CompileTarget::HaloCEA => "Halo: Combat Evolved Anniversary",
             CompileTarget::HaloCustomEdition => "Halo: Custom Edition",
             CompileTarget::HaloCEGBX => "Halo: Combat Evolved (PC / Mac)",
-            CompileTarget::HaloCEGBXDemo => "Halo: Combat Evolved Demo (PC / Mac)",
+            CompileTarget::HaloCEGBXDemo => "Halo: Combat Evolved Demo (PC)",
+            CompileTarget::HaloCEGBXDemoMac => "Halo: Combat Evolved Demo (Mac)",
             CompileTarget::HaloCEXboxNTSC => "Halo: Combat Evolved (Xbox)",
+            CompileTarget::HaloCEXboxPAL => "Halo: Combat Evolved (Xbox, PAL)",
+            CompileTarget::HaloCEXboxJP => "Halo: Combat Evolved (Xbox, Japan)",
         };
         f.write_str(name)
     }
 }
 
 /// Script type which determines how a script is run and parsed.
+///
+/// The numeric values are stable, as they are used by the engine and exposed over the C API.
 #[derive(PartialEq, Copy, Clone)]
-#[repr(C)]
+#[repr(u16)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum ScriptType {
     /// Script called on startup.
     ///
     /// This script always returns void.
-    Startup,
+    Startup = 0,
 
     /// Continuous script that can be awoken later.
     ///
     /// This script always returns void.
-    Dormant,
+    Dormant = 1,
 
     /// Script called every tick.
     ///
     /// This script always returns void.
-    Continuous,
+    Continuous = 2,
 
     /// Script which can be called manually.
-    Static,
+    Static = 3,
 
     /// Static script that can be replaced by a non-stub script later.
-    Stub
+    Stub = 4
 }
 
 impl ScriptType {
@@ -88,6 +400,7 @@ impl ScriptType {
     }
 
     /// Get the script type from a string (as used in HSC)
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(input: &str) -> Option<ScriptType> {
         match input {
             "static" => Some(ScriptType::Static),
@@ -122,6 +435,9 @@ pub(crate) struct Script {
     /// Type of the script
     pub script_type: ScriptType,
 
+    /// Index of the first expression in the script's block
+    pub body_offset: usize,
+
     /// Token of the script (internal only)
     pub(crate) original_token: Token,
 
@@ -150,10 +466,7 @@ impl CallableFunction for Script {
     }
 
     fn get_type_of_parameter(&self, index: usize) -> Option<ValueType> {
-        match self.parameters.get(index) {
-            Some(n) => Some(n.value_type),
-            None => None
-        }
+        self.parameters.get(index).map(|n| n.value_type)
     }
 }
 
@@ -191,6 +504,9 @@ pub(crate) struct Global {
     /// Value type of the global
     pub value_type: ValueType,
 
+    /// Index of the expression in the global's block
+    pub body_offset: usize,
+
     /// Token of the global (internal only)
     pub(crate) original_token: Token,
 
@@ -212,6 +528,73 @@ impl CallableGlobal for Global {
     }
 }
 
+/// Script that already exists in the scenario being appended to, as declared with
+/// [`Compiler::declare_external_script`](crate::Compiler::declare_external_script).
+#[derive(Clone)]
+pub(crate) struct ExternalScript {
+    /// Name of the script
+    pub name: String,
+
+    /// Index of the script in the scenario, or `None` if it is resolved when linking
+    pub index: Option<usize>,
+
+    /// Return type of the script
+    pub return_type: ValueType,
+
+    /// Types of the parameters of the script
+    pub parameters: Vec<ValueType>
+}
+
+impl CallableFunction for ExternalScript {
+    fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    fn get_return_type(&self) -> ValueType {
+        self.return_type
+    }
+
+    fn supports_target(&self, _target: CompileTarget) -> bool {
+        true
+    }
+
+    fn get_total_parameter_count(&self) -> usize {
+        self.parameters.len()
+    }
+
+    fn get_type_of_parameter(&self, index: usize) -> Option<ValueType> {
+        self.parameters.get(index).copied()
+    }
+}
+
+/// Global that already exists in the scenario being appended to, as declared with
+/// [`Compiler::declare_external_global`](crate::Compiler::declare_external_global).
+#[derive(Clone)]
+pub(crate) struct ExternalGlobal {
+    /// Name of the global
+    pub name: String,
+
+    /// Index of the global in the scenario, or `None` if it is resolved when linking
+    pub index: Option<usize>,
+
+    /// Value type of the global
+    pub value_type: ValueType
+}
+
+impl CallableGlobal for ExternalGlobal {
+    fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    fn get_value_type(&self) -> ValueType {
+        self.value_type
+    }
+
+    fn supports_target(&self, _target: CompileTarget) -> bool {
+        true
+    }
+}
+
 /// Function that can be called in a script
 #[allow(unused_variables)]
 pub(crate) trait CallableFunction {
@@ -263,6 +646,11 @@ pub(crate) trait CallableFunction {
     fn is_engine_function(&self) -> bool {
         false
     }
+
+    /// Get whether or not the function is meant to be run from the console rather than from scripts
+    fn is_console_only(&self) -> bool {
+        false
+    }
 }
 
 /// Global that can be referenced in a script
@@ -277,13 +665,63 @@ pub(crate) trait CallableGlobal {
     fn supports_target(&self, target: CompileTarget) -> bool;
 
     /// Get whether or not it's an engine global
+    #[allow(dead_code)]
     fn is_engine_global(&self) -> bool {
         false
     }
 }
 
+/// Function supplied by the application, such as one added to the engine by a mod, which scripts can call once it is registered with
+/// [`Compiler::register_function`](crate::Compiler::register_function).
+///
+/// Calls to it are compiled as calls to an engine function, using the index it has in the engine's function table for the target.
+#[allow(unused_variables)]
+pub trait UserFunction: Send + Sync {
+    /// Get the name of the function.
+    ///
+    /// Names in scripts are lowercased, so this should be lowercase too.
+    fn get_name(&self) -> &str;
+
+    /// Get the value type the function returns.
+    ///
+    /// If this is [`ValueType::Passthrough`], the function returns the type of its passthrough parameters, like `begin` does.
+    fn get_return_type(&self) -> ValueType;
+
+    /// Get the index of the function in the engine's function table for the target, or `None` if the target does not have it.
+    fn get_index(&self, target: CompileTarget) -> Option<u16>;
+
+    /// Get the number of parameters that must be passed.
+    fn get_minimum_parameter_count(&self) -> usize {
+        0
+    }
+
+    /// Get the value type of the parameter at the index, or `None` if the function takes fewer parameters.
+    ///
+    /// This can be any rule, such as alternating between two types for functions taking pairs of parameters, as long as it returns
+    /// `None` for every index after the first index it returns `None` for.
+    fn get_type_of_parameter(&self, index: usize) -> Option<ValueType> {
+        None
+    }
+
+    /// Get whether the parameter at the index can be an uppercase literal, such as the string passed to `print`.
+    fn is_uppercase_allowed_for_parameter(&self, index: usize) -> bool {
+        false
+    }
+
+    /// Get whether the passthrough parameters must be numbers, like the parameters of `+` are.
+    fn is_number_passthrough(&self) -> bool {
+        false
+    }
+
+    /// Get whether the function is meant to be run from the console rather than from scripts, warning when it is called from scripts.
+    fn is_console_only(&self) -> bool {
+        false
+    }
+}
+
 /// Data of the node.
 #[derive(PartialEq, Copy, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum NodeData {
     Boolean(bool),
     Short(i16),
@@ -294,6 +732,7 @@ pub enum NodeData {
 
 /// Primitive type
 #[derive(PartialEq, Copy, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum PrimitiveType {
     /// Value is a static value.
     Static,
@@ -307,6 +746,7 @@ pub enum PrimitiveType {
 
 /// Type of the node.
 #[derive(PartialEq, Copy, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum NodeType {
     /// Node refers to a value.
     ///
@@ -357,18 +797,63 @@ impl NodeType {
     }
 }
 
-/*
-use std::ffi::{CString, CStr};
+/// Encoding to use when reading scripts and writing string data.
+#[derive(Copy, Clone, PartialEq, Debug)]
+#[repr(C)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum CompileEncoding {
+    /// UTF-8 encoding.
+    UTF8,
+
+    /// Windows-1252 encoding, as used by the original tools.
+    Windows1252
+}
 
 impl CompileEncoding {
+    /// Get the name of the encoding.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            CompileEncoding::UTF8 => "UTF-8",
+            CompileEncoding::Windows1252 => "Windows-1252"
+        }
+    }
+
+    /// Get the cargo feature that enables the encoding, or `None` if it is always available.
+    pub fn feature(&self) -> Option<&'static str> {
+        match *self {
+            CompileEncoding::UTF8 => None,
+            CompileEncoding::Windows1252 => Some("windows-1252")
+        }
+    }
+
+    /// Get whether or not support for the encoding was compiled in.
+    ///
+    /// UTF-8 is always enabled. Windows-1252 requires the `windows-1252` feature, which is enabled by default. Reading script data
+    /// with a disabled encoding fails.
+    pub fn is_enabled(&self) -> bool {
+        match *self {
+            CompileEncoding::UTF8 => true,
+            CompileEncoding::Windows1252 => cfg!(feature = "windows-1252")
+        }
+    }
+
     /// Encode to a null-terminated C string.
+    ///
+    /// If the encoding is disabled, characters outside of ASCII are replaced with `?`, as they would be if they could not be encoded.
     pub fn encode_to_cstring(&self, string: &str) -> CString {
         match *self {
             CompileEncoding::UTF8 => {
                 CString::new(string).unwrap()
             },
+
+            #[cfg(feature = "windows-1252")]
             CompileEncoding::Windows1252 => {
                 CString::new(WINDOWS_1252.encode(string, EncoderTrap::Replace).unwrap()).unwrap()
+            },
+
+            #[cfg(not(feature = "windows-1252"))]
+            CompileEncoding::Windows1252 => {
+                CString::new(string.chars().map(|c| if c.is_ascii() { c } else { '?' }).collect::<String>()).unwrap()
             }
         }
     }
@@ -377,7 +862,7 @@ impl CompileEncoding {
     ///
     /// # Errors
     ///
-    /// Errors if an error occurred on decoding.
+    /// Errors if an error occurred on decoding or if the encoding is disabled.
     pub fn decode_from_cstring(&self, string: &CStr) -> Result<String, String> {
         self.decode_from_bytes(string.to_bytes())
     }
@@ -386,7 +871,7 @@ impl CompileEncoding {
     ///
     /// # Errors
     ///
-    /// Errors if an error occurred on decoding.
+    /// Errors if an error occurred on decoding or if the encoding is disabled.
     pub fn decode_from_bytes(&self, string: &[u8]) -> Result<String, String> {
         match *self {
             CompileEncoding::UTF8 => {
@@ -395,13 +880,91 @@ impl CompileEncoding {
                     Err(e) => Err(format!("{e:?}"))
                 }
             },
+
+            #[cfg(feature = "windows-1252")]
             CompileEncoding::Windows1252 => {
                 match WINDOWS_1252.decode(string, DecoderTrap::Replace) {
                     Ok(n) => Ok(n),
                     Err(e) => Err(format!("{e:?}"))
                 }
+            },
+
+            #[cfg(not(feature = "windows-1252"))]
+            CompileEncoding::Windows1252 => {
+                Err(format!("{} support is disabled", self.name()))
+            }
+        }
+    }
+
+    /// Decode the string from an array of bytes, handling byte sequences that cannot be decoded with the policy.
+    ///
+    /// Returns the string and the number of byte sequences that could not be decoded. Invalid sequences are replaced with U+FFFD or
+    /// left out, depending on the policy.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the policy is [`DecodeErrorPolicy::Error`] and a byte sequence could not be decoded, or if the encoding is disabled.
+    pub fn decode_from_bytes_with_policy(&self, string: &[u8], policy: DecodeErrorPolicy) -> Result<(String, usize), String> {
+        let mut decoded = String::with_capacity(string.len());
+        let mut invalid = 0usize;
+        let mut handle_invalid = |decoded: &mut String, error: String| {
+            match policy {
+                DecodeErrorPolicy::Error => return Err(error),
+                DecodeErrorPolicy::Replace => decoded.push('\u{FFFD}'),
+                DecodeErrorPolicy::Skip => ()
+            }
+            invalid += 1;
+            Ok(())
+        };
+
+        match *self {
+            CompileEncoding::UTF8 => {
+                let mut remaining = string;
+                loop {
+                    match std::str::from_utf8(remaining) {
+                        Ok(n) => {
+                            decoded += n;
+                            break
+                        },
+                        Err(e) => {
+                            let (valid, rest) = remaining.split_at(e.valid_up_to());
+                            decoded += std::str::from_utf8(valid).unwrap();
+                            handle_invalid(&mut decoded, format!("{e:?}"))?;
+                            remaining = &rest[e.error_len().unwrap_or(rest.len())..];
+                        }
+                    }
+                }
+            },
+
+            #[cfg(feature = "windows-1252")]
+            CompileEncoding::Windows1252 => {
+                // Every byte can be decoded, as the bytes that aren't assigned to any character (0x81, 0x8D, 0x8F, 0x90, and 0x9D) are
+                // decoded as the control characters with the same value, so they are encoded back to the same bytes
+                decoded += &WINDOWS_1252.decode(string, DecoderTrap::Replace).unwrap();
+            },
+
+            #[cfg(not(feature = "windows-1252"))]
+            CompileEncoding::Windows1252 => {
+                return Err(format!("{} support is disabled", self.name()))
             }
         }
+
+        Ok((decoded, invalid))
     }
 }
-*/
+
+/// What is done with bytes in script data that cannot be decoded, as set with
+/// [`Compiler::set_decode_error_policy`](crate::Compiler::set_decode_error_policy).
+#[derive(Copy, Clone, PartialEq, Debug, Default)]
+#[repr(C)]
+pub enum DecodeErrorPolicy {
+    /// Fail to read the script data.
+    Error,
+
+    /// Replace each sequence of bytes that cannot be decoded with U+FFFD and warn.
+    #[default]
+    Replace,
+
+    /// Leave out each sequence of bytes that cannot be decoded and warn.
+    Skip
+}
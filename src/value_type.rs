@@ -1,105 +1,131 @@
 /// Value type, used for typing for parameters, return types, and globals
+///
+/// The numeric values are stable, as they are used by the engine and exposed over the C API. New value types must only be appended.
+#[derive(Copy, Clone, PartialEq, Debug, Default)]
+#[repr(u16)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ValueType {
+    #[default]
+    Unparsed = 0,
+    SpecialForm = 1,
+    FunctionName = 2,
+    Passthrough = 3,
+    Void = 4,
+    Boolean = 5,
+    Real = 6,
+    Short = 7,
+    Long = 8,
+    String = 9,
+    Script = 10,
+    TriggerVolume = 11,
+    CutsceneFlag = 12,
+    CutsceneCameraPoint = 13,
+    CutsceneTitle = 14,
+    CutsceneRecording = 15,
+    DeviceGroup = 16,
+    Ai = 17,
+    AiCommandList = 18,
+    StartingProfile = 19,
+    Conversation = 20,
+    Navpoint = 21,
+    HudMessage = 22,
+    ObjectList = 23,
+    Sound = 24,
+    Effect = 25,
+    Damage = 26,
+    LoopingSound = 27,
+    AnimationGraph = 28,
+    ActorVariant = 29,
+    DamageEffect = 30,
+    ObjectDefinition = 31,
+    GameDifficulty = 32,
+    Team = 33,
+    AiDefaultState = 34,
+    ActorType = 35,
+    HudCorner = 36,
+    Object = 37,
+    Unit = 38,
+    Vehicle = 39,
+    Weapon = 40,
+    Device = 41,
+    Scenery = 42,
+    ObjectName = 43,
+    UnitName = 44,
+    VehicleName = 45,
+    WeaponName = 46,
+    DeviceName = 47,
+    SceneryName = 48
+}
+
+/// Rule that allows a value of one type to convert to another, as returned by [`ValueType::conversion_chain`].
 #[derive(Copy, Clone, PartialEq, Debug)]
 #[repr(C)]
-pub enum ValueType {
-    Unparsed,
-    SpecialForm,
-    FunctionName,
+pub enum ConversionRule {
+    /// The types are the same.
+    Identity,
+
+    /// The value is discarded, as anything can be used where nothing is expected.
+    VoidDiscard,
+
+    /// The value is a passthrough value, which takes on the type it is converted to.
     Passthrough,
-    Void,
-    Boolean,
-    Real,
-    Short,
-    Long,
-    String,
-    Script,
-    TriggerVolume,
-    CutsceneFlag,
-    CutsceneCameraPoint,
-    CutsceneTitle,
-    CutsceneRecording,
-    DeviceGroup,
-    Ai,
-    AiCommandList,
-    StartingProfile,
-    Conversation,
-    Navpoint,
-    HudMessage,
-    ObjectList,
-    Sound,
-    Effect,
-    Damage,
-    LoopingSound,
-    AnimationGraph,
-    ActorVariant,
-    DamageEffect,
-    ObjectDefinition,
-    GameDifficulty,
-    Team,
-    AiDefaultState,
-    ActorType,
-    HudCorner,
-    Object,
-    Unit,
-    Vehicle,
-    Weapon,
-    Device,
-    Scenery,
-    ObjectName,
-    UnitName,
-    VehicleName,
-    WeaponName,
-    DeviceName,
-    SceneryName
-}
 
-impl Default for ValueType {
-    fn default() -> ValueType {
-        ValueType::Unparsed
-    }
+    /// The value is converted to a numeric type that can represent every value of its type, such as a short to a real.
+    NumericConversion,
+
+    /// The value is converted to a numeric type that cannot represent every value of its type, such as a real to a short.
+    NarrowingNumericConversion,
+
+    /// The value is an object used as a more general object type, such as a vehicle as a unit or a unit as an object list.
+    ObjectWidening
 }
 
-impl ToString for ValueType {
-    fn to_string(&self) -> String {
-        self.as_str().to_owned()
+impl std::fmt::Display for ValueType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
     }
 }
 
 impl ValueType {
     /// Return true if the type can convert to the supplied type.
     pub fn can_convert_to(&self, to: ValueType) -> bool {
-        match *self {
+        ValueType::conversion_chain(*self, to).is_some()
+    }
+
+    /// Get the rule that allows converting a value from one type to another, or `None` if it cannot convert.
+    pub fn conversion_chain(from: ValueType, to: ValueType) -> Option<ConversionRule> {
+        match from {
             // Anything matches itself
-            n if n == to => true,
+            n if n == to => Some(ConversionRule::Identity),
 
-            // Anything can convert into a void or passthrough
-            _ if to == ValueType::Void => true,
+            // Anything can convert into a void
+            _ if to == ValueType::Void => Some(ConversionRule::VoidDiscard),
 
 
             // Passthrough can become anything else
-            ValueType::Passthrough => true,
+            ValueType::Passthrough => Some(ConversionRule::Passthrough),
 
 
             // Reals can convert into any integer type
-            ValueType::Real => to == ValueType::Long || to == ValueType::Short,
+            ValueType::Real if to == ValueType::Long || to == ValueType::Short => Some(ConversionRule::NarrowingNumericConversion),
 
             // Shorts can ONLY convert into reals but NOT longs. This is probably a bug in Halo
-            ValueType::Short => to == ValueType::Real,
+            ValueType::Short if to == ValueType::Real => Some(ConversionRule::NumericConversion),
 
-            // Longs can be demoted into shorts or converted into a real number
-            ValueType::Long => to == ValueType::Short || to == ValueType::Real,
+            // Longs can be demoted into shorts or converted into a real number (which cannot represent every long)
+            ValueType::Long if to == ValueType::Short || to == ValueType::Real => Some(ConversionRule::NarrowingNumericConversion),
 
 
             // Vehicles can be converted into units
-            ValueType::Vehicle if to == ValueType::Unit => true,
+            ValueType::Vehicle if to == ValueType::Unit => Some(ConversionRule::ObjectWidening),
 
 
             // Objects can be converted into object lists and objects
-            ValueType::ObjectName | ValueType::Object | ValueType::Unit | ValueType::Weapon | ValueType::Scenery | ValueType::Vehicle | ValueType::Device => to == ValueType::Object || to == ValueType::ObjectList,
+            ValueType::ObjectName | ValueType::Object | ValueType::Unit | ValueType::Weapon | ValueType::Scenery | ValueType::Vehicle | ValueType::Device if to == ValueType::Object || to == ValueType::ObjectList => Some(ConversionRule::ObjectWidening),
 
 
             // Anything not covered is false
-            _ => false
+            _ => None
         }
     }
 
@@ -158,6 +184,17 @@ impl ValueType {
         }
     }
 
+    /// Get the names accepted for an enumerated value type, in order of their value.
+    ///
+    /// This is empty for value types that are not enumerated.
+    pub fn enum_values(&self) -> &'static [&'static str] {
+        match *self {
+            ValueType::GameDifficulty => &["easy", "normal", "hard", "impossible"],
+            ValueType::Team => &["default", "player", "human", "covenant", "flood", "sentinel", "unused6", "unused7", "unused8", "unused9"],
+            _ => &[]
+        }
+    }
+
     /// Convert a string to a ValueType.
     ///
     /// This will not match the result of [`as_str`](ValueType::as_str), as this is used for parsing HSC scripts where [`as_str`](ValueType::as_str) is used for diagnostic messages.
@@ -221,4 +258,60 @@ impl ValueType {
     pub fn as_u16(&self) -> u16 {
         *self as u16
     }
+
+    /// Get the value type from its numeric representation, or `None` if it is not a valid value type.
+    pub fn from_u16(value: u16) -> Option<ValueType> {
+        Some(match value {
+            0 => ValueType::Unparsed,
+            1 => ValueType::SpecialForm,
+            2 => ValueType::FunctionName,
+            3 => ValueType::Passthrough,
+            4 => ValueType::Void,
+            5 => ValueType::Boolean,
+            6 => ValueType::Real,
+            7 => ValueType::Short,
+            8 => ValueType::Long,
+            9 => ValueType::String,
+            10 => ValueType::Script,
+            11 => ValueType::TriggerVolume,
+            12 => ValueType::CutsceneFlag,
+            13 => ValueType::CutsceneCameraPoint,
+            14 => ValueType::CutsceneTitle,
+            15 => ValueType::CutsceneRecording,
+            16 => ValueType::DeviceGroup,
+            17 => ValueType::Ai,
+            18 => ValueType::AiCommandList,
+            19 => ValueType::StartingProfile,
+            20 => ValueType::Conversation,
+            21 => ValueType::Navpoint,
+            22 => ValueType::HudMessage,
+            23 => ValueType::ObjectList,
+            24 => ValueType::Sound,
+            25 => ValueType::Effect,
+            26 => ValueType::Damage,
+            27 => ValueType::LoopingSound,
+            28 => ValueType::AnimationGraph,
+            29 => ValueType::ActorVariant,
+            30 => ValueType::DamageEffect,
+            31 => ValueType::ObjectDefinition,
+            32 => ValueType::GameDifficulty,
+            33 => ValueType::Team,
+            34 => ValueType::AiDefaultState,
+            35 => ValueType::ActorType,
+            36 => ValueType::HudCorner,
+            37 => ValueType::Object,
+            38 => ValueType::Unit,
+            39 => ValueType::Vehicle,
+            40 => ValueType::Weapon,
+            41 => ValueType::Device,
+            42 => ValueType::Scenery,
+            43 => ValueType::ObjectName,
+            44 => ValueType::UnitName,
+            45 => ValueType::VehicleName,
+            46 => ValueType::WeaponName,
+            47 => ValueType::DeviceName,
+            48 => ValueType::SceneryName,
+            _ => return None
+        })
+    }
 }